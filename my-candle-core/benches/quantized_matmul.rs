@@ -0,0 +1,68 @@
+// Compares the simd and multithreaded quantized matmul with the scalar single threaded path.
+//
+// cargo bench --bench quantized_matmul
+// RUSTFLAGS="-C target-cpu=native" cargo bench --bench quantized_matmul
+use my_candle_core::quantized::k_quants::{self, BlockQ4K, BlockQ4_0, BlockQ6K, GgmlType};
+use std::time::Instant;
+
+const ITERS: usize = 5;
+
+fn time<F: FnMut() -> anyhow::Result<()>>(mut f: F) -> anyhow::Result<f64> {
+    f()?;
+    let start = Instant::now();
+    for _ in 0..ITERS {
+        f()?
+    }
+    Ok(start.elapsed().as_secs_f64() / ITERS as f64)
+}
+
+fn bench<T: GgmlType>(name: &str, (m, k, n): (usize, usize, usize)) -> anyhow::Result<()> {
+    let lhs: Vec<f32> = (0..m * k).map(|i| (i as f32 * 0.37).sin()).collect();
+    let rhs: Vec<f32> = (0..n * k).map(|i| (i as f32 * 0.11).cos()).collect();
+    let rhs_t = k_quants::quantize::<T>(&rhs)?;
+    let mut dst = vec![0f32; m * n];
+
+    let k_in_rhs_blocks = k / T::BLCK_SIZE;
+    let k_in_lhs_blocks = k / T::VecDotType::BLCK_SIZE;
+    let scalar = time(|| {
+        let lhs_b = k_quants::quantize::<T::VecDotType>(&lhs)?;
+        for (row_idx, dst_row) in dst.chunks_mut(n).enumerate() {
+            let lhs_row = &lhs_b[row_idx * k_in_lhs_blocks..(row_idx + 1) * k_in_lhs_blocks];
+            for (col_idx, dst) in dst_row.iter_mut().enumerate() {
+                let rhs_col = &rhs_t[col_idx * k_in_rhs_blocks..(col_idx + 1) * k_in_rhs_blocks];
+                *dst = T::vec_dot_unopt(k, rhs_col, lhs_row)?;
+            }
+        }
+        Ok(())
+    })?;
+    let expected = dst.clone();
+
+    let optimized = time(|| Ok(k_quants::matmul((m, k, n), &lhs, &rhs_t, &mut dst)?))?;
+    let max_diff = dst
+        .iter()
+        .zip(expected.iter())
+        .map(|(a, b)| (a - b).abs())
+        .fold(0f32, f32::max);
+
+    let gflops = |secs: f64| 2. * (m * k * n) as f64 / secs / 1e9;
+    println!(
+        "{name:>5} {m:>4}x{k}x{n}: scalar {:8.3}ms ({:6.2} GFLOPS), optimized {:8.3}ms ({:6.2} GFLOPS), speedup {:5.1}x, max diff {max_diff:.2e}",
+        scalar * 1e3,
+        gflops(scalar),
+        optimized * 1e3,
+        gflops(optimized),
+        scalar / optimized,
+    );
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    // A single token and a short prompt through a 4096x4096 projection.
+    for m in [1, 16] {
+        let mkn = (m, 4096, 4096);
+        bench::<BlockQ4_0>("q4_0", mkn)?;
+        bench::<BlockQ4K>("q4k", mkn)?;
+        bench::<BlockQ6K>("q6k", mkn)?;
+    }
+    Ok(())
+}
@@ -50,6 +50,11 @@ mod ffi {
         pub fn vvcos(dst: *mut c_double, src: *const c_double, len: *const c_int);
         pub fn vvlogf(dst: *mut c_float, src: *const c_float, len: *const c_int);
         pub fn vvlog(dst: *mut c_double, src: *const c_double, len: *const c_int);
+        pub fn vvtanhf(dst: *mut c_float, src: *const c_float, len: *const c_int);
+        pub fn vvtanh(dst: *mut c_double, src: *const c_double, len: *const c_int);
+        // Computes x^y, note that the exponent comes before the base.
+        pub fn vvpowf(dst: *mut c_float, y: *const c_float, x: *const c_float, len: *const c_int);
+        pub fn vvpow(dst: *mut c_double, y: *const c_double, x: *const c_double, len: *const c_int);
 
         pub fn vDSP_vaddD(
             _: *const c_double,
@@ -123,6 +128,42 @@ mod ffi {
             _: c_long,
             _: c_ulong,
         );
+        pub fn vDSP_vmaxD(
+            _: *const c_double,
+            _: c_long,
+            _: *const c_double,
+            _: c_long,
+            _: *mut c_double,
+            _: c_long,
+            _: c_ulong,
+        );
+        pub fn vDSP_vmax(
+            _: *const c_float,
+            _: c_long,
+            _: *const c_float,
+            _: c_long,
+            _: *mut c_float,
+            _: c_long,
+            _: c_ulong,
+        );
+        pub fn vDSP_vminD(
+            _: *const c_double,
+            _: c_long,
+            _: *const c_double,
+            _: c_long,
+            _: *mut c_double,
+            _: c_long,
+            _: c_ulong,
+        );
+        pub fn vDSP_vmin(
+            _: *const c_float,
+            _: c_long,
+            _: *const c_float,
+            _: c_long,
+            _: *mut c_float,
+            _: c_long,
+            _: c_ulong,
+        );
     }
 }
 
@@ -312,6 +353,86 @@ pub fn vd_sqr(a: &[f64], y: &mut [f64]) {
     y.iter_mut().zip(a.iter()).for_each(|(y, a)| *y = *a * *a)
 }
 
+#[inline]
+pub fn vs_tanh(a: &[f32], y: &mut [f32]) {
+    let a_len = a.len();
+    let y_len = y.len();
+    if a_len != y_len {
+        panic!("a and y have different lengths {a_len} <> {y_len}")
+    }
+    unsafe { ffi::vvtanhf(y.as_mut_ptr(), a.as_ptr(), &(a_len as i32)) }
+}
+
+#[inline]
+pub fn vd_tanh(a: &[f64], y: &mut [f64]) {
+    let a_len = a.len();
+    let y_len = y.len();
+    if a_len != y_len {
+        panic!("a and y have different lengths {a_len} <> {y_len}")
+    }
+    unsafe { ffi::vvtanh(y.as_mut_ptr(), a.as_ptr(), &(a_len as i32)) }
+}
+
+#[inline]
+pub fn vs_sigmoid(vs: &[f32], ys: &mut [f32]) {
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y = -v
+    }
+    unsafe { ffi::vvexpf(ys.as_mut_ptr(), ys.as_ptr(), &(ys.len() as i32)) }
+    for y in ys.iter_mut() {
+        *y = 1.0 / (1.0 + *y)
+    }
+}
+
+#[inline]
+pub fn vd_sigmoid(vs: &[f64], ys: &mut [f64]) {
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y = -v
+    }
+    unsafe { ffi::vvexp(ys.as_mut_ptr(), ys.as_ptr(), &(ys.len() as i32)) }
+    for y in ys.iter_mut() {
+        *y = 1.0 / (1.0 + *y)
+    }
+}
+
+#[inline]
+pub fn vs_silu(vs: &[f32], ys: &mut [f32]) {
+    vs_sigmoid(vs, ys);
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y *= v
+    }
+}
+
+#[inline]
+pub fn vd_silu(vs: &[f64], ys: &mut [f64]) {
+    vd_sigmoid(vs, ys);
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y *= v
+    }
+}
+
+#[inline]
+pub fn vs_pow(a: &[f32], b: &[f32], y: &mut [f32]) {
+    let a_len = a.len();
+    let b_len = b.len();
+    let y_len = y.len();
+    if a_len != y_len || b_len != y_len {
+        panic!("vs_pow a,b,y len mismatch {a_len} {b_len} {y_len}");
+    }
+    unsafe { ffi::vvpowf(y.as_mut_ptr(), b.as_ptr(), a.as_ptr(), &(a_len as i32)) }
+}
+
+#[inline]
+pub fn vd_pow(a: &[f64], b: &[f64], y: &mut [f64]) {
+    let a_len = a.len();
+    let b_len = b.len();
+    let y_len = y.len();
+    if a_len != y_len || b_len != y_len {
+        panic!("vd_pow a,b,y len mismatch {a_len} {b_len} {y_len}");
+    }
+    unsafe { ffi::vvpow(y.as_mut_ptr(), b.as_ptr(), a.as_ptr(), &(a_len as i32)) }
+}
+
 macro_rules! binary_op {
     ($fn_name:ident, $ty:ty, $accelerate_name:ident) => {
         #[inline]
@@ -348,3 +469,27 @@ binary_op!(vs_mul, f32, vDSP_vmul);
 binary_op!(vd_mul, f64, vDSP_vmulD);
 binary_op!(vs_div, f32, vDSP_vdiv);
 binary_op!(vd_div, f64, vDSP_vdivD);
+binary_op!(vs_vendor_max, f32, vDSP_vmax);
+binary_op!(vd_vendor_max, f64, vDSP_vmaxD);
+binary_op!(vs_vendor_min, f32, vDSP_vmin);
+binary_op!(vd_vendor_min, f64, vDSP_vminD);
+
+// vDSP_vmax and vDSP_vmin skip NaN operands, the NaNs are written back afterwards so that the
+// results are the same as without accelerate.
+macro_rules! binary_op_nan {
+    ($fn_name:ident, $ty:ty, $vendor_fn_name:ident) => {
+        #[inline]
+        pub fn $fn_name(a: &[$ty], b: &[$ty], y: &mut [$ty]) {
+            $vendor_fn_name(a, b, y);
+            for ((y, a), b) in y.iter_mut().zip(a.iter()).zip(b.iter()) {
+                if a.is_nan() || b.is_nan() {
+                    *y = <$ty>::NAN
+                }
+            }
+        }
+    };
+}
+binary_op_nan!(vs_max, f32, vs_vendor_max);
+binary_op_nan!(vd_max, f64, vd_vendor_max);
+binary_op_nan!(vs_min, f32, vs_vendor_min);
+binary_op_nan!(vd_min, f64, vd_vendor_min);
@@ -21,8 +21,21 @@ pub trait BackendStorage:Sized {
 
     fn elu(&self, _: &Layout, _:f64) -> Result<Self>;
 
+    fn powf(&self, _: &Layout, _: f64) -> Result<Self>;
+
+    /// Clamps the values between the min and max bounds.
+    fn clamp(&self, _: &Layout, _: f64, _: f64) -> Result<Self>;
+
+    /// Softmax (or log-softmax when the flag is set) over the last dimension, the layout has to
+    /// be contiguous.
+    fn softmax_last_dim(&self, _: &Layout, _: bool) -> Result<Self>;
+
     fn reduce_op(&self, _: ReduceOp, _: &Layout, _: &[usize]) -> Result<Self>;
 
+    /// Cumulative sum or product along a dimension, only `ReduceOp::Sum` and `ReduceOp::Prod`
+    /// are supported.
+    fn cumulative(&self, _: ReduceOp, _: &Layout, _: usize) -> Result<Self>;
+
     fn cmp(&self, _: CmpOp, _: &Self, _: &Layout, _: &Layout) -> Result<Self>;
 
     fn to_dtype(&self, _: &Layout, _: DType) -> Result<Self>;
@@ -49,6 +62,22 @@ pub trait BackendStorage:Sized {
         _params: &crate::conv::ParamsConv2D,
     ) -> Result<Self>;
 
+    fn conv_transpose1d(
+        &self,
+        _l: &Layout,
+        _kernel: &Self,
+        _kernel_l: &Layout,
+        _params: &crate::conv::ParamsConvTranspose1D,
+    ) -> Result<Self>;
+
+    fn conv_transpose2d(
+        &self,
+        _l: &Layout,
+        _kernel: &Self,
+        _kernel_l: &Layout,
+        _params: &crate::conv::ParamsConvTranspose2D,
+    ) -> Result<Self>;
+
     fn avg_pool2d(&self, _: &Layout, _: (usize, usize), _: (usize, usize)) -> Result<Self>;
     fn max_pool2d(&self, _: &Layout, _: (usize, usize), _: (usize, usize)) -> Result<Self>;
     fn upsample_nearest2d(&self, _: &Layout, _: usize, _: usize) -> Result<Self>;
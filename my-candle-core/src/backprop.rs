@@ -1,5 +1,6 @@
 use crate::op::{BinaryOp, Op, ReduceOp, UnaryOp};
-use crate::{Error, Result, Tensor, TensorId};
+use crate::{Error, Result, Tensor, TensorId, Var};
+use std::cell::Cell;
 use std::collections::HashMap;
 use num_traits::real::Real;
 
@@ -16,6 +17,216 @@ fn broadcast_back(arg: &Tensor, node: &Tensor, reduced_dims: &[usize]) -> Result
     }
 }
 
+// The output padding for the transposed convolution of the output gradient to recover the input
+// size `l_in`. This is computed in i64 as the padding can be larger than the kernel.
+fn conv_bwd_output_padding(
+    l_in: usize,
+    k_size: usize,
+    l_grad: usize,
+    padding: usize,
+    stride: usize,
+    dilation: usize,
+) -> Result<usize> {
+    // The output length for conv_transpose is:
+    // (l_in - 1) * stride - 2 * padding + dilation * (k_size - 1) + output_padding + 1
+    let out_size = (l_grad as i64 - 1) * stride as i64 + dilation as i64 * (k_size as i64 - 1) + 1
+        - 2 * padding as i64;
+    match usize::try_from(l_in as i64 - out_size) {
+        Ok(output_padding) => Ok(output_padding),
+        Err(_) => crate::bail!(
+            "conv backward: gradient length {l_grad} does not match the input length {l_in}"
+        ),
+    }
+}
+
+// Gradients of a single group conv1d with respect to its input and kernel. The input gradient
+// is the transposed convolution of the output gradient, the kernel gradient is a convolution of
+// the input by the output gradient where the batch dimension plays the role of the channels.
+fn conv1d_single_group_bwd(
+    arg: &Tensor,
+    kernel: &Tensor,
+    grad: &Tensor,
+    padding: usize,
+    stride: usize,
+    dilation: usize,
+) -> Result<(Tensor, Tensor)> {
+    let l_in = arg.dim(2)?;
+    let k_size = kernel.dim(2)?;
+    let l_grad = grad.dim(2)?;
+    let output_padding =
+        conv_bwd_output_padding(l_in, k_size, l_grad, padding, stride, dilation)?;
+    let grad_arg = grad.conv_transpose1d(kernel, padding, output_padding, stride, dilation)?;
+    let grad_kernel = arg
+        .transpose(0, 1)?
+        .conv1d(&grad.transpose(0, 1)?, padding, dilation, stride, 1)?
+        .transpose(0, 1)?;
+    let grad_kernel = if grad_kernel.dim(2)? != k_size {
+        grad_kernel.narrow(2, 0, k_size)?
+    } else {
+        grad_kernel
+    };
+    Ok((grad_arg, grad_kernel))
+}
+
+fn conv2d_single_group_bwd(
+    arg: &Tensor,
+    kernel: &Tensor,
+    grad: &Tensor,
+    padding: usize,
+    stride: usize,
+    dilation: usize,
+) -> Result<(Tensor, Tensor)> {
+    let (_, _, i_h, i_w) = arg.dims4()?;
+    let (_, _, k_h, k_w) = kernel.dims4()?;
+    let (_, _, g_h, g_w) = grad.dims4()?;
+    // conv_transpose2d only supports a single output padding value, use the largest one and
+    // trim the extra rows or columns.
+    let output_padding = usize::max(
+        conv_bwd_output_padding(i_h, k_h, g_h, padding, stride, dilation)?,
+        conv_bwd_output_padding(i_w, k_w, g_w, padding, stride, dilation)?,
+    );
+    let grad_arg = grad
+        .conv_transpose2d(kernel, padding, output_padding, stride, dilation)?
+        .narrow(2, 0, i_h)?
+        .narrow(3, 0, i_w)?;
+    let grad_kernel = arg
+        .transpose(0, 1)?
+        .conv2d(&grad.transpose(0, 1)?, padding, dilation, stride, 1)?
+        .transpose(0, 1)?;
+    let (_, _, gk_h, gk_w) = grad_kernel.dims4()?;
+    let grad_kernel = if gk_h != k_h || gk_w != k_w {
+        grad_kernel.narrow(2, 0, k_h)?.narrow(3, 0, k_w)?
+    } else {
+        grad_kernel
+    };
+    Ok((grad_arg, grad_kernel))
+}
+
+type ConvBwdFn = fn(&Tensor, &Tensor, &Tensor, usize, usize, usize) -> Result<(Tensor, Tensor)>;
+
+// Grouped convolutions are differentiated group by group, the per group gradients are then
+// concatenated on the channel dimensions.
+#[allow(clippy::too_many_arguments)]
+fn conv_bwd(
+    arg: &Tensor,
+    kernel: &Tensor,
+    grad: &Tensor,
+    padding: usize,
+    stride: usize,
+    dilation: usize,
+    groups: usize,
+    single_group_bwd: ConvBwdFn,
+) -> Result<(Tensor, Tensor)> {
+    if groups == 1 {
+        return single_group_bwd(arg, kernel, grad, padding, stride, dilation);
+    }
+    let c_in_per_group = arg.dim(1)? / groups;
+    let c_out_per_group = kernel.dim(0)? / groups;
+    let mut grad_args = Vec::with_capacity(groups);
+    let mut grad_kernels = Vec::with_capacity(groups);
+    for g in 0..groups {
+        let arg = arg.narrow(1, g * c_in_per_group, c_in_per_group)?;
+        let kernel = kernel.narrow(0, g * c_out_per_group, c_out_per_group)?;
+        let grad = grad.narrow(1, g * c_out_per_group, c_out_per_group)?;
+        let (grad_arg, grad_kernel) =
+            single_group_bwd(&arg, &kernel, &grad, padding, stride, dilation)?;
+        grad_args.push(grad_arg);
+        grad_kernels.push(grad_kernel);
+    }
+    Ok((Tensor::cat(&grad_args, 1)?, Tensor::cat(&grad_kernels, 0)?))
+}
+
+// The rows and columns of the element at `offset` in each window of a 2d pooling whose output
+// is `node`.
+fn pool2d_window_ids(
+    node: &Tensor,
+    offset: (usize, usize),
+    stride: (usize, usize),
+) -> Result<(Tensor, Tensor)> {
+    let (_n, _c, h_out, w_out) = node.dims4()?;
+    let ids = |offset: usize, len: usize, stride: usize| {
+        let (start, end) = (offset as u32, (offset + len * stride) as u32);
+        Tensor::arange_step(start, end, stride as u32, node.device())
+    };
+    Ok((
+        ids(offset.0, h_out, stride.0)?,
+        ids(offset.1, w_out, stride.1)?,
+    ))
+}
+
+// Adds the per window values `src` to `acc` at the positions given by the rows and columns
+// returned by `pool2d_window_ids`.
+fn pool2d_scatter_add(acc: &Tensor, rows: &Tensor, cols: &Tensor, src: &Tensor) -> Result<Tensor> {
+    let (n, c, h, _w) = acc.dims4()?;
+    let (_n, _c, _h_out, w_out) = src.dims4()?;
+    let src =
+        Tensor::zeros((n, c, h, w_out), src.dtype(), src.device())?.index_add(rows, src, 2)?;
+    acc.index_add(cols, &src, 3)
+}
+
+thread_local! {
+    // `None` when the ops are recorded. While a checkpointed function runs, this is set to
+    // whether some tracked tensor has been used by the function.
+    static UNTRACKED: Cell<Option<bool>> = const { Cell::new(None) };
+    // Set while `graph::record` runs, all the ops are then recorded so that the graph can be
+    // exported even when no variable is involved.
+    static RECORD_ALL: Cell<bool> = const { Cell::new(false) };
+}
+
+// Whether an op should be recorded, `tracked` tells if one of its arguments is a variable or
+// depends on one.
+pub(crate) fn record_op(tracked: bool) -> bool {
+    UNTRACKED.with(|untracked| match untracked.get() {
+        None => tracked || RECORD_ALL.with(|record_all| record_all.get()),
+        Some(used) => {
+            if tracked && !used {
+                untracked.set(Some(true))
+            }
+            false
+        }
+    })
+}
+
+// Restores the previous recording state, also when the function panics.
+struct UntrackedGuard(Option<bool>);
+
+impl Drop for UntrackedGuard {
+    fn drop(&mut self) {
+        UNTRACKED.with(|untracked| untracked.set(self.0))
+    }
+}
+
+// Runs `f` without recording ops, also returns whether `f` used some tracked tensor.
+pub(crate) fn run_untracked<T>(f: impl FnOnce() -> T) -> (T, bool) {
+    let mut guard = UntrackedGuard(UNTRACKED.with(|untracked| untracked.replace(Some(false))));
+    let res = f();
+    let used = UNTRACKED.with(|untracked| untracked.get() == Some(true));
+    // Nested checkpoints make the enclosing function use tracked tensors too.
+    guard.0 = guard.0.map(|outer_used| outer_used || used);
+    (res, used)
+}
+
+// Restores the previous value of `RECORD_ALL`, also when the function panics.
+struct RecordAllGuard(bool);
+
+impl Drop for RecordAllGuard {
+    fn drop(&mut self) {
+        RECORD_ALL.with(|record_all| record_all.set(self.0))
+    }
+}
+
+// Runs `f` recording all the ops, see `graph::record`.
+pub(crate) fn run_record_all<T>(f: impl FnOnce() -> T) -> T {
+    let _guard = RecordAllGuard(RECORD_ALL.with(|record_all| record_all.replace(true)));
+    f()
+}
+
+// Sum over the indexes `i..` along `dim` for each index `i`.
+fn reverse_cumsum(xs: &Tensor, dim: usize) -> Result<Tensor> {
+    let total = xs.sum_keepdim(dim)?;
+    total.broadcast_sub(&xs.cumsum(dim)?)?.add(xs)
+}
+
 impl Tensor {
     /// Return all the nodes that lead to this value in a topologically sorted vec, the first
     /// elements having dependencies on the latter ones, e.g. the first element if any is the
@@ -37,6 +248,12 @@ impl Tensor {
                 // Do not call recursively on the "leaf" nodes.
                 track_grad = true;
                 nodes
+            } else if !node.dtype().is_float() {
+                // Integer and boolean values such as indexes or masks are not differentiable.
+                // Ops like gather or index-select never produce a gradient for their index
+                // operand so tracking these nodes, e.g. the output of argsort on a variable,
+                // would leave them without any gradient when running the backward pass.
+                nodes
             } else if let Some(op) = node.op() {
                 match op {
                     Op::IndexAdd(t1, t2, t3, _)
@@ -61,6 +278,16 @@ impl Tensor {
                         kernel: rhs,
                         ..
                     }
+                    | Op::ConvTranspose1D {
+                        arg: lhs,
+                        kernel: rhs,
+                        ..
+                    }
+                    | Op::ConvTranspose2D {
+                        arg: lhs,
+                        kernel: rhs,
+                        ..
+                    }
                     | Op::CustomOp2(lhs, rhs, _)
                     | Op::Binary(lhs, rhs, _)
                     | Op::Gather(lhs, rhs, _)
@@ -77,6 +304,12 @@ impl Tensor {
                         track_grad |= tg;
                         nodes
                     }),
+                    Op::Checkpoint(args, _) => {
+                        // The function can use variables that are not part of its arguments.
+                        track_grad = true;
+                        args.iter()
+                            .fold(nodes, |nodes, arg| walk(arg, nodes, already_seen).1)
+                    }
                     Op::Affine { arg, mul, .. } => {
                         if *mul == 0. {
                             nodes
@@ -97,9 +330,16 @@ impl Tensor {
                     | Op::ToDType(node)
                     | Op::ToDevice(node)
                     | Op::Transpose(node, _, _)
+                    | Op::Permute(node, _)
+                    | Op::Unfold(node, _, _, _)
                     | Op::Narrow(node, _, _, _)
                     | Op::Unary(node, _)
                     | Op::Elu(node, _)
+                    | Op::Powf(node, _)
+                    | Op::Clamp(node, _, _)
+                    | Op::Cumulative(node, _, _)
+                    | Op::SoftmaxLastDim(node)
+                    | Op::LogSoftmaxLastDim(node)
                     | Op::CustomOp1(node, _) => {
                         let (tg, nodes) = walk(node, nodes, already_seen);
                         track_grad |= tg;
@@ -121,9 +361,14 @@ impl Tensor {
     }
 
     pub fn backward(&self) -> Result<GradStore> {
+        self.backward_from(self.ones_like()?.contiguous()?)
+    }
+
+    // Backpropagates `grad`, the gradient of the final value with respect to this tensor.
+    fn backward_from(&self, grad: Tensor) -> Result<GradStore> {
         let sorted_nodes = self.sorted_nodes();
         let mut grads = GradStore::new();
-        grads.insert(self, self.ones_like()?.contiguous()?);
+        grads.insert(self, grad);
         for node in sorted_nodes.iter() {
             if node.is_variable() {
                 continue;
@@ -162,6 +407,27 @@ impl Tensor {
                         let rhs_sum_grad = grads.or_insert(rhs)?;
                         *rhs_sum_grad = rhs_sum_grad.sub(&rhs_grad)?;
                     }
+                    Op::Binary(lhs, rhs, BinaryOp::Pow) => {
+                        // d/dl l^r = r * l^(r - 1), d/dr l^r = l^r * ln(l)
+                        let lhs_grad = grad.mul(rhs)?.mul(&lhs.pow(&rhs.affine(1., -1.)?)?)?;
+                        let lhs_sum_grad = grads.or_insert(lhs)?;
+                        *lhs_sum_grad = lhs_sum_grad.add(&lhs_grad)?;
+                        let rhs_grad = grad.mul(node)?.mul(&lhs.log()?)?;
+                        let rhs_sum_grad = grads.or_insert(rhs)?;
+                        *rhs_sum_grad = rhs_sum_grad.add(&rhs_grad)?;
+                    }
+                    Op::Binary(lhs, rhs, BinaryOp::Minimum)
+                    | Op::Binary(lhs, rhs, BinaryOp::Maximum) => {
+                        let mask_lhs = node.eq(lhs)?.to_dtype(grad.dtype())?;
+                        let mask_rhs = node.eq(rhs)?.to_dtype(grad.dtype())?;
+                        // On ties the gradient is split evenly between both sides.
+                        let lhs_grad = mask_lhs.mul(&grad)?.div(&(&mask_rhs + 1.)?)?;
+                        let lhs_sum_grad = grads.or_insert(lhs)?;
+                        *lhs_sum_grad = lhs_sum_grad.add(&lhs_grad)?;
+                        let rhs_grad = mask_rhs.mul(&grad)?.div(&(&mask_lhs + 1.)?)?;
+                        let rhs_sum_grad = grads.or_insert(rhs)?;
+                        *rhs_sum_grad = rhs_sum_grad.add(&rhs_grad)?;
+                    }
                     Op::WhereCond(pred, t, f) => {
                         let zeros = grad.zeros_like()?;
                         let t_sum_grad = grads.or_insert(t)?;
@@ -171,13 +437,204 @@ impl Tensor {
                         let f_grad = pred.where_cond(&zeros, &grad)?;
                         *f_sum_grad = f_sum_grad.add(&f_grad)?;
                     }
-                    Op::Conv1D { .. } => Err(Error::BackwardNotSupported { op: "conv1d" })?,
-                    Op::Conv2D { .. } => Err(Error::BackwardNotSupported { op: "conv2d" })?,
-                    Op::AvgPool2D { .. } => Err(Error::BackwardNotSupported { op: "avg-pool2d" })?,
-                    Op::MaxPool2D { .. } => Err(Error::BackwardNotSupported { op: "max-pool2d" })?,
-                    Op::UpsampleNearest2D { .. } => Err(Error::BackwardNotSupported {
-                        op: "upsample-nearest2d",
-                    })?,
+                    Op::Conv1D {
+                        arg,
+                        kernel,
+                        padding,
+                        stride,
+                        dilation,
+                        groups,
+                    } => {
+                        let (grad_arg, grad_kernel) = conv_bwd(
+                            arg,
+                            kernel,
+                            &grad,
+                            *padding,
+                            *stride,
+                            *dilation,
+                            *groups,
+                            conv1d_single_group_bwd,
+                        )?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+                        let sum_grad = grads.or_insert(kernel)?;
+                        *sum_grad = sum_grad.add(&grad_kernel)?;
+                    }
+                    Op::Conv2D {
+                        arg,
+                        kernel,
+                        padding,
+                        stride,
+                        dilation,
+                        groups,
+                    } => {
+                        let (grad_arg, grad_kernel) = conv_bwd(
+                            arg,
+                            kernel,
+                            &grad,
+                            *padding,
+                            *stride,
+                            *dilation,
+                            *groups,
+                            conv2d_single_group_bwd,
+                        )?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+                        let sum_grad = grads.or_insert(kernel)?;
+                        *sum_grad = sum_grad.add(&grad_kernel)?;
+                    }
+                    Op::ConvTranspose1D {
+                        arg,
+                        kernel,
+                        padding,
+                        output_padding: _,
+                        stride,
+                        dilation,
+                    } => {
+                        // The transposed convolution is the adjoint of the convolution so the
+                        // input gradient is a plain convolution of the output gradient.
+                        let grad_arg = grad
+                            .conv1d(kernel, *padding, *stride, *dilation, 1)?
+                            .narrow(2, 0, arg.dim(2)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+
+                        let k_size = kernel.dim(2)?;
+                        let grad_kernel = grad
+                            .transpose(0, 1)?
+                            .conv1d(&arg.transpose(0, 1)?, *padding, *dilation, *stride, 1)?
+                            .transpose(0, 1)?
+                            .narrow(2, 0, k_size)?;
+                        let sum_grad = grads.or_insert(kernel)?;
+                        *sum_grad = sum_grad.add(&grad_kernel)?;
+                    }
+                    Op::ConvTranspose2D {
+                        arg,
+                        kernel,
+                        padding,
+                        output_padding: _,
+                        stride,
+                        dilation,
+                    } => {
+                        let (_, _, i_h, i_w) = arg.dims4()?;
+                        let grad_arg = grad
+                            .conv2d(kernel, *padding, *stride, *dilation, 1)?
+                            .narrow(2, 0, i_h)?
+                            .narrow(3, 0, i_w)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+
+                        let (_, _, k_h, k_w) = kernel.dims4()?;
+                        let grad_kernel = grad
+                            .transpose(0, 1)?
+                            .conv2d(&arg.transpose(0, 1)?, *padding, *dilation, *stride, 1)?
+                            .transpose(0, 1)?
+                            .narrow(2, 0, k_h)?
+                            .narrow(3, 0, k_w)?;
+                        let sum_grad = grads.or_insert(kernel)?;
+                        *sum_grad = sum_grad.add(&grad_kernel)?;
+                    }
+                    Op::AvgPool2D {
+                        arg,
+                        kernel_size,
+                        stride,
+                    } => {
+                        let (_n, _c, h, w) = arg.dims4()?;
+                        let (_n, _c, h_out, w_out) = grad.dims4()?;
+                        let (k_h, k_w) = *kernel_size;
+                        // Each element of a window gets an equal share of the gradient, the
+                        // trailing rows and columns that are not covered by a window get zeros.
+                        let grad_arg = if kernel_size == stride {
+                            grad
+                                .upsample_nearest2d(h_out * k_h, w_out * k_w)?
+                                .affine(1. / (k_h * k_w) as f64, 0.)?
+                                .pad_with_zeros(2, 0, h - h_out * k_h)?
+                                .pad_with_zeros(3, 0, w - w_out * k_w)?
+                        } else {
+                            // The windows may overlap, the share of each offset in the window
+                            // is added to the corresponding input elements.
+                            let grad = grad.affine(1. / (k_h * k_w) as f64, 0.)?;
+                            let mut grad_arg = arg.zeros_like()?;
+                            for offset_h in 0..k_h {
+                                for offset_w in 0..k_w {
+                                    let (rows, cols) =
+                                        pool2d_window_ids(&grad, (offset_h, offset_w), *stride)?;
+                                    grad_arg = pool2d_scatter_add(&grad_arg, &rows, &cols, &grad)?;
+                                }
+                            }
+                            grad_arg
+                        };
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+                    }
+                    Op::MaxPool2D {
+                        arg,
+                        kernel_size,
+                        stride,
+                    } => {
+                        let (_n, _c, h, w) = arg.dims4()?;
+                        let (_n, _c, h_out, w_out) = node.dims4()?;
+                        let (k_h, k_w) = *kernel_size;
+                        // The gradient is routed to the argmax positions of each window. In case
+                        // of ties, the gradient is split evenly between the maximum elements.
+                        let grad_arg = if kernel_size == stride {
+                            // The argmax positions are found by comparing the input with the
+                            // upsampled output.
+                            let (c_h, c_w) = (h_out * k_h, w_out * k_w);
+                            let arg_c = arg.narrow(2, 0, c_h)?.narrow(3, 0, c_w)?;
+                            let node_upsampled = node.upsample_nearest2d(c_h, c_w)?;
+                            let mask = arg_c.eq(&node_upsampled)?.to_dtype(arg.dtype())?;
+                            let count = mask
+                                .avg_pool2d(*kernel_size, *stride)?
+                                .affine((k_h * k_w) as f64, 0.)?;
+                            (grad.div(&count)?.upsample_nearest2d(c_h, c_w)? * mask)?
+                                .pad_with_zeros(2, 0, h - c_h)?
+                                .pad_with_zeros(3, 0, w - c_w)?
+                        } else {
+                            // The windows may overlap, each offset in the window is compared
+                            // with the output separately and its share of the gradient is added
+                            // to the corresponding input elements.
+                            let mut count = node.zeros_like()?;
+                            let mut masks = Vec::with_capacity(k_h * k_w);
+                            for offset_h in 0..k_h {
+                                for offset_w in 0..k_w {
+                                    let (rows, cols) =
+                                        pool2d_window_ids(node, (offset_h, offset_w), *stride)?;
+                                    let mask = arg
+                                        .index_select(&rows, 2)?
+                                        .index_select(&cols, 3)?
+                                        .eq(*node)?
+                                        .to_dtype(arg.dtype())?;
+                                    count = count.add(&mask)?;
+                                    masks.push((rows, cols, mask));
+                                }
+                            }
+                            let grad = grad.div(&count)?;
+                            let mut grad_arg = arg.zeros_like()?;
+                            for (rows, cols, mask) in masks.iter() {
+                                let src = grad.mul(mask)?;
+                                grad_arg = pool2d_scatter_add(&grad_arg, rows, cols, &src)?;
+                            }
+                            grad_arg
+                        };
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+                    }
+                    Op::UpsampleNearest2D(arg) => {
+                        let (_n, _c, h, w) = arg.dims4()?;
+                        let (_n, _c, target_h, target_w) = node.dims4()?;
+                        if target_h % h != 0 || target_w % w != 0 {
+                            crate::bail!("backward not supported for upsample-nearest2d with non integer upscaling factors")
+                        }
+                        let (scale_h, scale_w) = (target_h / h, target_w / w);
+                        // Each input element has been copied to a scale_h x scale_w window,
+                        // its gradient is the sum over this window.
+                        let grad_arg = grad
+                            .avg_pool2d((scale_h, scale_w), (scale_h, scale_w))?
+                            .affine((scale_h * scale_w) as f64, 0.)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&grad_arg)?;
+                    }
                     Op::Gather(arg, indexes, dim) => {
                         let sum_grad = grads.or_insert(arg)?;
                         *sum_grad = sum_grad.scatter_add(indexes, &grad, *dim)?;
@@ -252,20 +709,77 @@ impl Tensor {
                         let sum_grad = grads.or_insert(arg)?;
                         *sum_grad = sum_grad.add(&grad)?;
                     }
-                    Op::Cmp(_args, _) => {}
-                    Op::Reduce(arg, ReduceOp::Max, reduced_dims) => {
-                        let node = broadcast_back(arg, node, reduced_dims)?;
+                    Op::Reduce(arg, ReduceOp::Prod, reduced_dims) => {
+                        // The gradient for x_i is the product of the other values, this avoids
+                        // dividing by x_i so that zeros are handled properly.
+                        let prod_dims: Vec<usize> = arg
+                            .dims()
+                            .iter()
+                            .zip(reduced_dims.iter())
+                            .enumerate()
+                            .filter_map(|(i, (d, r))| if d != r { Some(i) } else { None })
+                            .collect();
+                        let zeros = arg.eq(0.)?.to_dtype(arg.dtype())?;
+                        let non_zeros = (arg + &zeros)?;
+                        let prod_non_zeros = non_zeros.prod_keepdim(prod_dims.as_slice())?;
+                        let n_zeros = zeros.sum_keepdim(prod_dims.as_slice())?;
+                        let no_zero = n_zeros.eq(0.)?.to_dtype(arg.dtype())?;
+                        let one_zero = n_zeros.eq(1.)?.to_dtype(arg.dtype())?;
+                        let prod_grad = (non_zeros.recip()?.broadcast_mul(&no_zero)?
+                            + zeros.broadcast_mul(&one_zero)?)?
+                            .broadcast_mul(&prod_non_zeros)?;
                         let grad = broadcast_back(arg, &grad, reduced_dims)?;
-                        let grad = node.eq(arg)?.to_dtype(grad.dtype())?.mul(&grad)?;
                         let sum_grad = grads.or_insert(arg)?;
-                        *sum_grad = sum_grad.add(&grad.broadcast_as(sum_grad.dims())?)?;
+                        *sum_grad = sum_grad.add(&(grad * prod_grad)?)?;
+                    }
+                    &Op::Cumulative(ref arg, ReduceOp::Sum, dim) => {
+                        let arg_grad = reverse_cumsum(&grad, dim)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&arg_grad)?
+                    }
+                    &Op::Cumulative(ref arg, ReduceOp::Prod, dim) => {
+                        // Before the first zero of a slice, the gradient for x_i is
+                        // sum_{k >= i} g_k y_k / x_i. After it, all the products that depend on
+                        // x_i are zero. For the first zero itself, the products are recomputed
+                        // with this value replaced by one.
+                        let dtype = arg.dtype();
+                        let zeros = arg.eq(0.)?.to_dtype(dtype)?;
+                        let n_zeros = zeros.cumsum(dim)?;
+                        let before = n_zeros.eq(0.)?.to_dtype(dtype)?;
+                        let first_zero = (&zeros * n_zeros.eq(1.)?.to_dtype(dtype)?)?;
+                        let non_zeros = (arg + &zeros)?;
+                        let grad_before = reverse_cumsum(&(&grad * *node)?, dim)?
+                            .div(&non_zeros)?
+                            .mul(&before)?;
+                        let prod_without_first = (arg + &first_zero)?.cumprod(dim)?;
+                        let after = before.affine(-1., 1.)?;
+                        let weighted = (&grad * prod_without_first)?.mul(&after)?;
+                        let grad_first = reverse_cumsum(&weighted, dim)?.mul(&first_zero)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(grad_before + grad_first)?)?
+                    }
+                    Op::Cumulative(_, op, _) => {
+                        Err(Error::BackwardNotSupported { op: op.name() })?
                     }
-                    Op::Reduce(arg, ReduceOp::Min, reduced_dims) => {
+                    Op::Cmp(_args, _) => {}
+                    Op::Reduce(arg, ReduceOp::Max, reduced_dims)
+                    | Op::Reduce(arg, ReduceOp::Min, reduced_dims) => {
+                        // The gradient goes to the positions where the extremum is reached, it
+                        // is split evenly between them on ties.
                         let node = broadcast_back(arg, node, reduced_dims)?;
-                        let grad = broadcast_back(arg, &grad, reduced_dims)?;
-                        let grad = node.eq(arg)?.to_dtype(grad.dtype())?.mul(&grad)?;
+                        let mask = node.eq(arg)?.to_dtype(grad.dtype())?;
+                        let reduce_dims: Vec<usize> = arg
+                            .dims()
+                            .iter()
+                            .zip(reduced_dims.iter())
+                            .enumerate()
+                            .filter_map(|(i, (d, r))| if d != r { Some(i) } else { None })
+                            .collect();
+                        let count = mask.sum_keepdim(reduce_dims)?;
+                        let grad = grad.reshape(reduced_dims.as_slice())?.div(&count)?;
+                        let grad = mask.broadcast_mul(&grad)?;
                         let sum_grad = grads.or_insert(arg)?;
-                        *sum_grad = sum_grad.add(&grad.broadcast_as(sum_grad.dims())?)?;
+                        *sum_grad = sum_grad.add(&grad)?;
                     }
                     Op::ToDType(arg) => {
                         let sum_grad = grads.or_insert(arg)?;
@@ -344,13 +858,94 @@ impl Tensor {
                         let sum_grad = grads.or_insert(arg)?;
                         *sum_grad = sum_grad.add(&arg_grad)?
                     }
-                    Op::Unary(_, UnaryOp::Gelu) => Err(Error::BackwardNotSupported { op: "gelu" })?,
+                    Op::Unary(arg, UnaryOp::Gelu) => {
+                        // gelu(x) = 0.5 * x * (1 + tanh(c * (x + 0.044715 * x^3))), c = sqrt(2/pi)
+                        let c = (2. / std::f64::consts::PI).sqrt();
+                        let x2 = arg.sqr()?;
+                        let tanh = (arg * x2.affine(0.044715 * c, c)?)?.tanh()?;
+                        let dtanh =
+                            (tanh.sqr()?.affine(-1., 1.)? * x2.affine(3. * 0.044715 * c, c)?)?;
+                        let gelu_grad =
+                            (tanh.affine(0.5, 0.5)? + (arg * dtanh)?.affine(0.5, 0.)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * gelu_grad)?)?
+                    }
+                    Op::Unary(arg, UnaryOp::GeluErf) => {
+                        // d/dx x * cdf(x) = cdf(x) + x * pdf(x)
+                        use std::f64::consts::{FRAC_1_SQRT_2, FRAC_2_SQRT_PI};
+                        let cdf = arg.affine(FRAC_1_SQRT_2, 0.)?.erf()?.affine(0.5, 0.5)?;
+                        let pdf = arg
+                            .sqr()?
+                            .affine(-0.5, 0.)?
+                            .exp()?
+                            .affine(0.5 * FRAC_2_SQRT_PI * FRAC_1_SQRT_2, 0.)?;
+                        let gelu_grad = (cdf + (arg * pdf)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * gelu_grad)?)?
+                    }
+                    Op::Unary(arg, UnaryOp::Tanh) => {
+                        let tanh_grad = node.sqr()?.affine(-1., 1.)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * tanh_grad)?)?
+                    }
+                    Op::Unary(arg, UnaryOp::Sigmoid) => {
+                        let sigmoid_grad = node.mul(&node.affine(-1., 1.)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * sigmoid_grad)?)?
+                    }
+                    Op::Unary(arg, UnaryOp::Silu) => {
+                        // d/dx x * s(x) = s(x) * (1 + x * (1 - s(x)))
+                        let sigmoid = arg.sigmoid()?;
+                        let silu_grad =
+                            sigmoid.mul(&(arg * sigmoid.affine(-1., 1.)?)?.affine(1., 1.)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * silu_grad)?)?
+                    }
+                    Op::Unary(arg, UnaryOp::Erf) => {
+                        // d/dx erf(x) = 2/sqrt(pi) * exp(-x^2)
+                        let erf_grad = arg
+                            .sqr()?
+                            .neg()?
+                            .exp()?
+                            .affine(std::f64::consts::FRAC_2_SQRT_PI, 0.)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * erf_grad)?)?
+                    }
+                    Op::Unary(_, UnaryOp::Floor)
+                    | Op::Unary(_, UnaryOp::Ceil)
+                    | Op::Unary(_, UnaryOp::Round)
+                    | Op::Unary(_, UnaryOp::Sign) => {}
                     Op::Unary(arg, UnaryOp::Relu) => {
                         let sum_grad = grads.or_insert(arg)?;
                         let relu_grad = arg.ge(&arg.zeros_like()?)?.to_dtype(arg.dtype())?;
                         *sum_grad = sum_grad.add(&(&grad * relu_grad)?)?
                     }
                     Op::Elu(..) => Err(Error::BackwardNotSupported { op: "elu" })?,
+                    &Op::Powf(ref arg, e) => {
+                        let arg_grad = (&grad * arg.powf(e - 1.)?.affine(e, 0.)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&arg_grad)?
+                    }
+                    Op::Clamp(arg, _, _) => {
+                        // The gradient only flows where the value has not been clamped.
+                        let clamp_grad = node.eq(arg)?.to_dtype(arg.dtype())?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&(&grad * clamp_grad)?)?
+                    }
+                    Op::SoftmaxLastDim(arg) => {
+                        // d/dx_i = s_i * (g_i - sum_j g_j s_j)
+                        let sum_gs = (&grad * *node)?.sum_keepdim(crate::D::Minus1)?;
+                        let arg_grad = node.mul(&grad.broadcast_sub(&sum_gs)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&arg_grad)?
+                    }
+                    Op::LogSoftmaxLastDim(arg) => {
+                        // d/dx_i = g_i - exp(ls_i) * sum_j g_j
+                        let sum_g = grad.sum_keepdim(crate::D::Minus1)?;
+                        let arg_grad = grad.sub(&node.exp()?.broadcast_mul(&sum_g)?)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&arg_grad)?
+                    }
                     Op::CustomOp1(arg, c) => {
                         if let Some(arg_grad) = c.bwd(arg, node, &grad)? {
                             let sum_grad = grads.or_insert(arg)?;
@@ -384,6 +979,45 @@ impl Tensor {
                             *sum_grad = sum_grad.add(&arg_grad3)?
                         }
                     }
+                    Op::Checkpoint(args, f) => {
+                        // Run the function again with the ops being recorded, the arguments that
+                        // need a gradient are replaced by variables.
+                        let inputs = args
+                            .iter()
+                            .map(|arg| {
+                                if arg.track_op() && arg.dtype().is_float() {
+                                    Ok(Var::from_tensor(arg)?.into_inner())
+                                } else {
+                                    arg.detach()
+                                }
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        let ys = f(&inputs)?;
+                        if !ys.track_op() {
+                            continue;
+                        }
+                        // Only the gradients of the arguments and of the variables used by the
+                        // function are kept, the tracked values captured by the function have
+                        // already been backpropagated through by the inner pass.
+                        let var_ids: Vec<TensorId> = ys
+                            .sorted_nodes()
+                            .iter()
+                            .filter(|node| node.is_variable())
+                            .map(|node| node.id())
+                            .collect();
+                        let mut ys_grads = ys.backward_from(grad)?;
+                        for (arg, input) in args.iter().zip(inputs.iter()) {
+                            if let Some(arg_grad) = ys_grads.remove(input) {
+                                let sum_grad = grads.or_insert(arg)?;
+                                *sum_grad = sum_grad.add(&arg_grad)?
+                            }
+                        }
+                        for id in var_ids {
+                            if let Some(var_grad) = ys_grads.0.remove(&id) {
+                                grads.add_id(id, var_grad)?
+                            }
+                        }
+                    }
                     Op::Unary(arg, UnaryOp::Sqr) => {
                         let arg_grad = arg.mul(&grad)?.affine(2., 0.)?;
                         let sum_grad = grads.or_insert(arg)?;
@@ -404,6 +1038,34 @@ impl Tensor {
                         let sum_grad = grads.or_insert(arg)?;
                         *sum_grad = sum_grad.add(&arg_grad)?
                     }
+                    Op::Permute(arg, dims) => {
+                        let mut inv_dims = vec![0; dims.len()];
+                        for (i, &dim_idx) in dims.iter().enumerate() {
+                            inv_dims[dim_idx] = i
+                        }
+                        let arg_grad = grad.permute(inv_dims)?;
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&arg_grad)?
+                    }
+                    Op::Unfold(arg, dim, size, step) => {
+                        // Each element of a window is added back to its position, windows
+                        // overlap when the step is smaller than the size.
+                        let n_windows = node.dims()[*dim];
+                        let last_dim = node.rank() - 1;
+                        let mut arg_grad = arg.zeros_like()?;
+                        for k in 0..*size {
+                            let ids = Tensor::arange_step(
+                                k as u32,
+                                (k + n_windows * step) as u32,
+                                *step as u32,
+                                arg.device(),
+                            )?;
+                            let src = grad.narrow(last_dim, k, 1)?.squeeze(last_dim)?;
+                            arg_grad = arg_grad.index_add(&ids, &src, *dim)?;
+                        }
+                        let sum_grad = grads.or_insert(arg)?;
+                        *sum_grad = sum_grad.add(&arg_grad)?
+                    }
                 };
             }
         }
@@ -434,6 +1096,20 @@ impl GradStore {
         self.0.insert(tensor.id(), grad)
     }
 
+    fn add_id(&mut self, id: TensorId, grad: Tensor) -> Result<()> {
+        use std::collections::hash_map::Entry;
+        match self.0.entry(id) {
+            Entry::Occupied(mut entry) => {
+                let sum_grad = entry.get().add(&grad)?;
+                entry.insert(sum_grad);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(grad);
+            }
+        }
+        Ok(())
+    }
+
     fn or_insert(&mut self, tensor: &Tensor) -> Result<&mut Tensor> {
         use std::collections::hash_map::Entry;
         let grad = match self.0.entry(tensor.id()) {
@@ -446,3 +1122,382 @@ impl GradStore {
         Ok(grad)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{DType, Device, IndexOp, Result, Tensor, Var};
+
+    // Deterministic, distinct values in [-1, 1). Softmax does not saturate on this range so the
+    // finite differences stay accurate, and distinct inputs give each element its own gradient,
+    // which also means that max-pooling and the max/min reductions have no ties.
+    fn values(n: usize) -> Vec<f64> {
+        (0..n).map(|i| (i * 37 % 101) as f64 / 50. - 1.).collect()
+    }
+
+    // Compares the gradient of `sum(f(x) * w)` with respect to `x` against central finite
+    // differences, `w` being some fixed weights so that all the outputs contribute differently.
+    fn check_grad<F: Fn(&Tensor) -> Result<Tensor>>(shape: &[usize], f: F) -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = values(shape.iter().product());
+        let x = Var::from_slice(&xs, shape, dev)?;
+        let ys = f(x.as_tensor())?;
+        let w = Tensor::arange(0u32, ys.elem_count() as u32, dev)?
+            .to_dtype(DType::F64)?
+            .affine(0.7, 0.3)?
+            .sin()?
+            .reshape(ys.shape())?;
+        let loss = |xs: &[f64]| -> Result<f64> {
+            let x = Tensor::from_slice(xs, shape, dev)?;
+            f(&x)?.mul(&w)?.sum_all()?.to_scalar::<f64>()
+        };
+        let grads = ys.mul(&w)?.sum_all()?.backward()?;
+        let grad = grads.get(&x).unwrap().flatten_all()?.to_vec1::<f64>()?;
+        let eps = 1e-4;
+        for i in 0..xs.len() {
+            let mut xs_p = xs.clone();
+            xs_p[i] += eps;
+            let mut xs_m = xs.clone();
+            xs_m[i] -= eps;
+            let fd = (loss(&xs_p)? - loss(&xs_m)?) / (2. * eps);
+            assert!((fd - grad[i]).abs() < 1e-6, "{i}: finite-diff {fd} grad {}", grad[i]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn conv1d_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let k = Tensor::from_slice(&values(4 * 3 * 3), (4, 3, 3), dev)?;
+        let x = Tensor::from_slice(&values(2 * 6 * 9), (2, 6, 9), dev)?;
+        check_grad(&[2, 3, 9], |x| x.conv1d(&k, 1, 2, 1, 1))?;
+        check_grad(&[2, 3, 10], |x| x.conv1d(&k, 0, 1, 2, 1))?;
+        check_grad(&[4, 3, 3], |k| x.narrow(1, 0, 3)?.conv1d(k, 1, 2, 1, 1))?;
+        check_grad(&[4, 3, 3], |k| x.narrow(1, 0, 3)?.conv1d(k, 2, 1, 2, 1))?;
+        check_grad(&[2, 6, 9], |x| x.conv1d(&k, 1, 1, 1, 2))?;
+        check_grad(&[4, 3, 3], |k| x.conv1d(k, 1, 1, 1, 2))?;
+        // The padding can be larger than the kernel, here the input length is 1.
+        let k1 = Tensor::from_slice(&values(2 * 3), (2, 3, 1), dev)?;
+        let x1 = Tensor::from_slice(&values(2 * 3), (2, 3, 1), dev)?;
+        check_grad(&[2, 3, 1], |x| x.conv1d(&k1, 3, 4, 1, 1))?;
+        check_grad(&[2, 3, 1], |k| x1.conv1d(k, 3, 4, 1, 1))?;
+        check_grad(&[2, 3, 4], |x| x.conv1d(&k, 5, 3, 2, 1))?;
+        Ok(())
+    }
+
+    #[test]
+    fn conv2d_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let k = Tensor::from_slice(&values(4 * 2 * 3 * 2), (4, 2, 3, 2), dev)?;
+        let x = Tensor::from_slice(&values(2 * 4 * 6 * 5), (2, 4, 6, 5), dev)?;
+        check_grad(&[2, 2, 6, 5], |x| x.conv2d(&k, 1, 2, 1, 1))?;
+        check_grad(&[2, 2, 7, 6], |x| x.conv2d(&k, 0, 1, 2, 1))?;
+        check_grad(&[4, 2, 3, 2], |k| x.narrow(1, 0, 2)?.conv2d(k, 1, 2, 1, 1))?;
+        check_grad(&[2, 4, 6, 5], |x| x.conv2d(&k, 1, 1, 1, 2))?;
+        check_grad(&[4, 2, 3, 2], |k| x.conv2d(k, 1, 1, 1, 2))?;
+        let k1 = Tensor::from_slice(&values(2 * 2), (2, 2, 1, 1), dev)?;
+        let x1 = Tensor::from_slice(&values(2 * 2 * 2), (1, 2, 1, 2), dev)?;
+        check_grad(&[1, 2, 1, 2], |x| x.conv2d(&k1, 3, 4, 1, 1))?;
+        check_grad(&[2, 2, 1, 1], |k| x1.conv2d(k, 3, 4, 1, 1))?;
+        Ok(())
+    }
+
+    #[test]
+    fn conv_transpose_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let k1 = Tensor::from_slice(&values(3 * 2 * 3), (3, 2, 3), dev)?;
+        let x1 = Tensor::from_slice(&values(2 * 3 * 5), (2, 3, 5), dev)?;
+        check_grad(&[2, 3, 5], |x| x.conv_transpose1d(&k1, 1, 1, 2, 1))?;
+        check_grad(&[2, 3, 5], |x| x.conv_transpose1d(&k1, 0, 0, 1, 2))?;
+        check_grad(&[3, 2, 3], |k| x1.conv_transpose1d(k, 1, 1, 2, 1))?;
+        let k2 = Tensor::from_slice(&values(3 * 2 * 2 * 3), (3, 2, 2, 3), dev)?;
+        let x2 = Tensor::from_slice(&values(2 * 3 * 4 * 3), (2, 3, 4, 3), dev)?;
+        check_grad(&[2, 3, 4, 3], |x| x.conv_transpose2d(&k2, 1, 1, 2, 1))?;
+        check_grad(&[3, 2, 2, 3], |k| x2.conv_transpose2d(k, 0, 0, 1, 2))?;
+        Ok(())
+    }
+
+    #[test]
+    fn softmax_last_dim() -> Result<()> {
+        let dev = &Device::Cpu;
+        // 19 columns so that the simd reductions have some leftovers.
+        let xs: Vec<f32> = values(3 * 19).iter().map(|&v| (v * 7.) as f32).collect();
+        let xs = Tensor::from_vec(xs, (3, 19), dev)?;
+        for xs in [xs.clone(), xs.t()?] {
+            let max = xs.max_keepdim(1)?;
+            let diff = xs.broadcast_sub(&max)?;
+            let sum = diff.exp()?.sum_keepdim(1)?;
+            let expected = diff.exp()?.broadcast_div(&sum)?;
+            let err = (xs.softmax_last_dim()? - expected)?.abs()?.max_keepdim(1)?;
+            assert!(err.flatten_all()?.to_vec1::<f32>()?.iter().all(|&d| d < 1e-6));
+            let expected = diff.broadcast_sub(&sum.log()?)?;
+            let err = (xs.log_softmax_last_dim()? - expected)?.abs()?.max_keepdim(1)?;
+            assert!(err.flatten_all()?.to_vec1::<f32>()?.iter().all(|&d| d < 1e-5));
+        }
+        let ys = Tensor::new(&[[f32::NEG_INFINITY, 0., 0.]], dev)?.softmax_last_dim()?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[0., 0.5, 0.5]]);
+
+        check_grad(&[3, 5], |x| x.softmax_last_dim())?;
+        check_grad(&[2, 3, 4], |x| x.log_softmax_last_dim())?;
+        check_grad(&[4, 3], |x| x.t()?.softmax_last_dim())?;
+        Ok(())
+    }
+
+    #[test]
+    fn pool_and_upsample_grad() -> Result<()> {
+        check_grad(&[1, 2, 5, 4], |x| x.avg_pool2d((2, 2), (2, 2)))?;
+        check_grad(&[1, 2, 5, 4], |x| x.max_pool2d((2, 2), (2, 2)))?;
+        check_grad(&[2, 1, 6, 6], |x| x.max_pool2d((3, 2), (3, 2)))?;
+        // Overlapping windows as in the resnet stem, and windows with gaps between them.
+        check_grad(&[1, 2, 7, 7], |x| x.max_pool2d((3, 3), (2, 2)))?;
+        check_grad(&[1, 2, 7, 7], |x| x.avg_pool2d((3, 3), (2, 2)))?;
+        check_grad(&[2, 1, 6, 5], |x| x.max_pool2d((3, 2), (1, 2)))?;
+        check_grad(&[2, 1, 6, 5], |x| x.avg_pool2d((2, 1), (3, 2)))?;
+        check_grad(&[1, 2, 2, 3], |x| x.upsample_nearest2d(4, 6))?;
+        check_grad(&[1, 2, 2, 3], |x| x.upsample_nearest2d(6, 3))?;
+        Ok(())
+    }
+
+    #[test]
+    fn elementwise_grad() -> Result<()> {
+        check_grad(&[2, 5], |x| x.tanh())?;
+        check_grad(&[2, 5], |x| x.sigmoid())?;
+        check_grad(&[2, 5], |x| x.silu())?;
+        check_grad(&[2, 5], |x| x.erf())?;
+        check_grad(&[2, 5], |x| x.gelu())?;
+        check_grad(&[2, 5], |x| x.gelu_erf())?;
+        check_grad(&[2, 5], |x| x.affine(1., 1.5)?.powf(2.5))?;
+        check_grad(&[2, 5], |x| x.clamp(-0.45, 0.45))?;
+        let e = Tensor::new(&[[0.5f64, 2., -1., 3., 1.5], [1., 0.3, 2.2, -0.7, 4.]], &Device::Cpu)?;
+        check_grad(&[2, 5], |x| x.affine(1., 1.5)?.pow(&e))?;
+        check_grad(&[2, 5], |x| e.affine(0.1, 1.5)?.pow(x))?;
+        Ok(())
+    }
+
+    #[test]
+    fn reduce_grad() -> Result<()> {
+        check_grad(&[3, 4], |x| x.mean_keepdim(1))?;
+        check_grad(&[2, 3, 4], |x| x.mean((0, 2)))?;
+        check_grad(&[3, 4], |x| x.var(1, true))?;
+        check_grad(&[2, 3, 4], |x| x.std_keepdim((0, 2), false))?;
+        check_grad(&[3, 4], |x| x.logsumexp(1))?;
+        check_grad(&[2, 3, 4], |x| x.logsumexp_keepdim((0, 2)))?;
+        // A fully masked row gives -inf rather than NaN, an infinite value gives infinity.
+        let inf = f32::INFINITY;
+        let xs = Tensor::new(&[[-inf, -inf, -inf], [0., -inf, 0.], [1., inf, 2.]], &Device::Cpu)?;
+        let lse = xs.logsumexp(1)?.to_vec1::<f32>()?;
+        assert_eq!(lse, [-inf, 2f32.ln(), inf]);
+        // The masked values get a zero gradient, including on the fully masked row.
+        let xs = Var::new(&[[-inf, -inf, -inf], [0., -inf, 0.]], &Device::Cpu)?;
+        let grads = xs.logsumexp(1)?.sum_all()?.backward()?;
+        let grad = grads.get(&xs).unwrap().to_vec2::<f32>()?;
+        assert_eq!(grad, [[0., 0., 0.], [0.5, 0., 0.5]]);
+        check_grad(&[3, 4], |x| x.prod(1))?;
+        check_grad(&[2, 3, 4], |x| x.prod_keepdim((0, 2)))?;
+        // Products containing one or more zeros.
+        let x = Var::new(&[[2f64, 0., 3.], [0., 4., 0.]], &Device::Cpu)?;
+        let grads = x.prod(1)?.sum_all()?.backward()?;
+        let grad = grads.get(&x).unwrap().to_vec2::<f64>()?;
+        assert_eq!(grad, [[0., 6., 0.], [0., 0., 0.]]);
+        Ok(())
+    }
+
+    #[test]
+    fn max_min_grad() -> Result<()> {
+        check_grad(&[3, 4], |x| x.max(1))?;
+        check_grad(&[2, 3, 4], |x| x.max_keepdim((0, 2)))?;
+        check_grad(&[2, 3, 4], |x| x.min((2, 0)))?;
+        check_grad(&[2, 3, 4], |x| x.max_all())?;
+        // Ties split the gradient between the maximal positions.
+        let x = Var::new(&[[1f64, 3., 3.], [2., 2., 2.]], &Device::Cpu)?;
+        let grads = x.max(1)?.sum_all()?.backward()?;
+        let grad = grads.get(&x).unwrap().to_vec2::<f64>()?;
+        assert_eq!(grad, [[0., 0.5, 0.5], [1. / 3., 1. / 3., 1. / 3.]]);
+        // There is nothing to reduce on rank 0 tensors.
+        let x = Var::new(3f64, &Device::Cpu)?;
+        assert_eq!(x.max_all()?.to_scalar::<f64>()?, 3.);
+        assert_eq!(x.min_all()?.to_scalar::<f64>()?, 3.);
+        let grads = x.min_all()?.affine(2., 0.)?.backward()?;
+        assert_eq!(grads.get(&x).unwrap().to_scalar::<f64>()?, 2.);
+        Ok(())
+    }
+
+    #[test]
+    fn integer_nodes_grad() -> Result<()> {
+        check_grad(&[2, 5], |x| Ok(x.sort_last_dim(false)?.0))?;
+        check_grad(&[2, 5], |x| Ok(x.topk(2, 1)?.0))?;
+        // The indexes computed from the variable are not tracked, the gradient only flows
+        // through the gathered values.
+        let x = Var::new(&[[1f64, 3., 2.], [5., 4., 6.]], &Device::Cpu)?;
+        let idx = x.argmax_keepdim(1)?;
+        let ys = x.gather(&idx, 1)?.add(&idx.to_dtype(DType::F64)?)?;
+        let grads = ys.sum_all()?.backward()?;
+        assert!(grads.get(&idx).is_none());
+        let grad = grads.get(&x).unwrap().to_vec2::<f64>()?;
+        assert_eq!(grad, [[0., 1., 0.], [0., 0., 1.]]);
+        Ok(())
+    }
+
+    #[test]
+    fn cumulative_grad() -> Result<()> {
+        check_grad(&[3, 4], |x| x.cumsum(1))?;
+        check_grad(&[2, 3, 4], |x| x.cumsum(1))?;
+        check_grad(&[3, 4], |x| x.cumprod(1))?;
+        check_grad(&[2, 3, 4], |x| x.cumprod(0))?;
+        check_grad(&[2, 3, 3], |x| x.tril(0))?;
+        check_grad(&[3, 4], |x| x.triu(1))?;
+        // Zeros in cumulative products.
+        let x = Var::new(&[2f64, 0., 3., 0., 5.], &Device::Cpu)?;
+        let w = Tensor::new(&[1f64, 2., 3., 4., 5.], &Device::Cpu)?;
+        let grads = x.cumprod(0)?.mul(&w)?.sum_all()?.backward()?;
+        let grad = grads.get(&x).unwrap().to_vec1::<f64>()?;
+        assert_eq!(grad, [1., 2. * 2. + 3. * 6., 0., 0., 0.]);
+        Ok(())
+    }
+
+    #[test]
+    fn maximum_minimum_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let y = Tensor::from_slice(&values(10), (2, 5), dev)?.affine(-0.5, 0.1)?;
+        check_grad(&[2, 5], |x| x.maximum(&y))?;
+        check_grad(&[2, 5], |x| y.minimum(x))?;
+        check_grad(&[2, 5], |x| x.maximum(0.)?.minimum(0.5))?;
+        // Ties split the gradient between both arguments.
+        let x = Var::new(&[1f64, 2., 3.], dev)?;
+        let z = Tensor::new(&[0f64, 2., 4.], dev)?;
+        let grads = x.maximum(&z)?.sum_all()?.backward()?;
+        assert_eq!(grads.get(&x).unwrap().to_vec1::<f64>()?, [1., 0.5, 0.]);
+        Ok(())
+    }
+
+    #[test]
+    fn slice_assign_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let src = Tensor::from_slice(&values(4), (2, 2), dev)?.affine(2., 1.)?;
+        check_grad(&[3, 4], |x| x.slice_assign(&[1..3, 1..3], &src))?;
+        let dst = Tensor::from_slice(&values(12), (3, 4), dev)?;
+        check_grad(&[2, 2], |x| dst.slice_assign(&[0..2, 2..], x))?;
+        let ids = Tensor::new(&[2u32, 0], dev)?;
+        check_grad(&[3, 4], |x| x.index_put(&ids, &src.pad_with_zeros(1, 1, 1)?, 0))?;
+        check_grad(&[2, 4], |x| dst.index_put(&ids, x, 0))?;
+
+        // The values are written exactly, whatever the dtype and the values being replaced.
+        let xs = Tensor::new(&[[1u8, 2], [3, 4], [5, 6]], dev)?;
+        let src = Tensor::new(&[[0u8, 255]], dev)?;
+        let ys = xs.index_put(&Tensor::new(&[1u32], dev)?, &src, 0)?;
+        assert_eq!(ys.to_vec2::<u8>()?, [[1, 2], [0, 255], [5, 6]]);
+        let xs = Tensor::new(&[f32::INFINITY, f32::NAN, 0.1, f32::NEG_INFINITY], dev)?;
+        let src = Tensor::new(&[0.3f32, f32::INFINITY], dev)?;
+        let ys = xs.index_put(&Tensor::new(&[1u32, 3], dev)?, &src, 0)?;
+        let expected = [f32::INFINITY, 0.3, 0.1, f32::INFINITY];
+        assert_eq!(ys.to_vec1::<f32>()?, expected);
+        let src = Tensor::new(&[-0f32], dev)?;
+        let ys = xs.index_put(&Tensor::new(&[2u32], dev)?, &src, 0)?;
+        assert!(ys.to_vec1::<f32>()?[2].is_sign_negative());
+        // Duplicate indexes are rejected, including when there are more than 255 of them.
+        let ids = Tensor::new(&[1u32, 0, 1], dev)?;
+        let src = Tensor::new(&[1f32, 2., 3.], dev)?;
+        assert!(xs.index_put(&ids, &src, 0).is_err());
+        let ids = Tensor::zeros(256, DType::U32, dev)?;
+        let src = Tensor::ones((2, 256), DType::F32, dev)?;
+        let xs = Tensor::zeros((2, 3), DType::F32, dev)?;
+        assert!(xs.index_put(&ids, &src, 1).is_err());
+
+        // In place update of a variable.
+        let cache = Var::zeros((2, 3, 2), DType::F32, dev)?;
+        let src = Tensor::new(&[[[1f32, 2.]], [[3., 4.]]], dev)?;
+        cache.slice_set(&src, 1, 2)?;
+        cache.slice_set(&src.affine(1., 4.)?, 1, 0)?;
+        assert_eq!(
+            cache.to_vec3::<f32>()?,
+            [
+                [[5., 6.], [0., 0.], [1., 2.]],
+                [[7., 8.], [0., 0.], [3., 4.]]
+            ]
+        );
+        assert!(cache.slice_set(&src, 1, 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn strided_views_grad() -> Result<()> {
+        check_grad(&[2, 3, 4], |x| x.permute((2, 0, 1)))?;
+        check_grad(&[2, 3, 4], |x| x.movedim(0, 2)?.sqr())?;
+        check_grad(&[3, 4], |x| x.flip((0, 1)))?;
+        check_grad(&[3, 4], |x| x.roll(&[2, -1], (0, 1)))?;
+        check_grad(&[2, 3], |x| x.repeat((2, 1, 3)))?;
+        check_grad(&[2, 7], |x| x.unfold(1, 3, 2))?;
+        check_grad(&[5, 2], |x| x.unfold(0, 2, 1)?.sqr())?;
+
+        let x = Tensor::arange(0u32, 24, &Device::Cpu)?.reshape((2, 3, 4))?;
+        let y = x.permute((2, 0, 1))?;
+        assert_eq!(y.dims(), [4, 2, 3]);
+        assert_eq!(y.contiguous()?.i((1, 1))?.to_vec1::<u32>()?, [13, 17, 21]);
+        assert_eq!(x.movedim(2, 0)?.dims(), [4, 2, 3]);
+        assert_eq!(
+            x.i(0)?.flip(1)?.to_vec2::<u32>()?,
+            [[3, 2, 1, 0], [7, 6, 5, 4], [11, 10, 9, 8]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn broadcast_matmul_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let w = Tensor::from_slice(&values(12), (4, 3), dev)?;
+        check_grad(&[2, 3, 2, 4], |x| x.matmul(&w))?;
+        let xs = Tensor::from_slice(&values(24), (2, 3, 4), dev)?;
+        check_grad(&[4, 2], |w| xs.matmul(w))?;
+        check_grad(&[3, 1, 4, 2], |w| xs.matmul(w))?;
+        check_grad(&[4], |v| xs.matmul(v))?;
+        check_grad(&[4], |v| v.matmul(&w))?;
+        check_grad(&[3], |v| v.matmul(v))?;
+        assert_eq!(xs.matmul(&w)?.dims(), [2, 3, 3]);
+        assert!(xs.matmul(&Tensor::zeros((3, 4, 2), DType::F64, dev)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint_grad() -> Result<()> {
+        let dev = &Device::Cpu;
+        let w = Var::from_slice(&values(12), (4, 3), dev)?;
+        let block = {
+            let w = w.as_tensor().clone();
+            move |xs: &[Tensor]| xs[0].matmul(&w)?.tanh()?.sqr()
+        };
+        check_grad(&[2, 4], |x| Tensor::checkpoint(&[x], block.clone()))?;
+
+        // Only the inputs are kept alive, the weights get their gradients through the
+        // recomputation, including for nested checkpoints.
+        let x = Var::from_slice(&values(8), (2, 4), dev)?;
+        let ys = Tensor::checkpoint(&[&x], block.clone())?;
+        assert_eq!(crate::graph::Graph::from_tensors(&[&ys])?.nodes().len(), 2);
+        let nested = {
+            let block = block.clone();
+            move |xs: &[Tensor]| Tensor::checkpoint(&[&xs[0]], block.clone())?.exp()
+        };
+        let ys = Tensor::checkpoint(&[&x], nested)?;
+        let grads = ys.sum_all()?.backward()?;
+        let expected = block(&[x.as_tensor().clone()])?.exp()?.sum_all()?.backward()?;
+        for t in [x.as_tensor(), w.as_tensor()] {
+            let diff = (grads.get(t).unwrap() - expected.get(t).unwrap())?;
+            assert!(diff.abs()?.max_all()?.to_scalar::<f64>()? < 1e-12);
+        }
+
+        // A tracked value captured by the function and also used outside of the checkpoint
+        // gets a single share of the gradient from each use.
+        let v = Var::from_slice(&values(12), (4, 3), dev)?;
+        let w = (v.as_tensor() * 2.)?;
+        let block = {
+            let w = w.clone();
+            move |xs: &[Tensor]| xs[0].matmul(&w)?.tanh()
+        };
+        let x = Tensor::from_slice(&values(8), (2, 4), dev)?;
+        let ys = (Tensor::checkpoint(&[&x], block.clone())? + x.matmul(&w)?)?;
+        let grads = ys.sum_all()?.backward()?;
+        let expected = (block(&[x.clone()])? + x.matmul(&w)?)?.sum_all()?.backward()?;
+        assert!(grads.get(&w).is_none());
+        let diff = (grads.get(&v).unwrap() - expected.get(&v).unwrap())?;
+        assert!(diff.abs()?.max_all()?.to_scalar::<f64>()? < 1e-12);
+        Ok(())
+    }
+}
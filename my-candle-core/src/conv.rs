@@ -7,12 +7,22 @@ pub struct ParamsConv1D {
     pub(crate) k_size: usize,
     pub(crate) padding: usize,
     pub(crate) stride: usize,
+    pub(crate) dilation: usize,
+    pub(crate) groups: usize,
 }
 
 impl ParamsConv1D {
     pub(crate) fn l_out(&self) -> usize {
-        let dilation = 1;
-        (self.l_in + 2 * self.padding - dilation * (self.k_size -1) -1) /self.stride + 1
+        (self.l_in + 2 * self.padding - self.dilation * (self.k_size - 1) - 1) / self.stride + 1
+    }
+
+    /// Number of input channels seen by each output channel, i.e. the kernel in-channels.
+    pub(crate) fn c_in_per_group(&self) -> usize {
+        self.c_in / self.groups
+    }
+
+    pub(crate) fn c_out_per_group(&self) -> usize {
+        self.c_out / self.groups
     }
 
     pub(crate) fn out_dims(&self) -> Vec<usize> {
@@ -32,17 +42,82 @@ pub struct ParamsConv2D {
     pub(crate) c_in: usize,
     pub(crate) padding: usize,
     pub(crate) stride: usize,
+    pub(crate) dilation: usize,
+    pub(crate) groups: usize,
 }
 
 impl ParamsConv2D {
     pub(crate) fn out_h(&self) -> usize {
-        let dilation = 1;
-        (self.i_h + 2 * self.padding - dilation * (self.k_h - 1) - 1) / self.stride + 1
+        (self.i_h + 2 * self.padding - self.dilation * (self.k_h - 1) - 1) / self.stride + 1
+    }
+
+    pub(crate) fn out_w(&self) -> usize {
+        (self.i_w + 2 * self.padding - self.dilation * (self.k_w - 1) - 1) / self.stride + 1
+    }
+
+    /// Number of input channels seen by each output channel, i.e. the kernel in-channels.
+    pub(crate) fn c_in_per_group(&self) -> usize {
+        self.c_in / self.groups
+    }
+
+    pub(crate) fn c_out_per_group(&self) -> usize {
+        self.c_out / self.groups
+    }
+
+    pub(crate) fn out_dims(&self) -> Vec<usize> {
+        vec![self.b_size, self.c_out, self.out_h(), self.out_w()]
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamsConvTranspose1D {
+    pub(crate) b_size: usize,
+    pub(crate) l_in: usize,
+    pub(crate) c_out: usize,
+    pub(crate) c_in: usize,
+    pub(crate) k_size: usize,
+    pub(crate) padding: usize,
+    pub(crate) output_padding: usize,
+    pub(crate) stride: usize,
+    pub(crate) dilation: usize,
+}
+
+impl ParamsConvTranspose1D {
+    pub(crate) fn l_out(&self) -> usize {
+        (self.l_in - 1) * self.stride + self.dilation * (self.k_size - 1) + self.output_padding + 1
+            - 2 * self.padding
+    }
+
+    pub(crate) fn out_dims(&self) -> Vec<usize> {
+        vec![self.b_size, self.c_out, self.l_out()]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamsConvTranspose2D {
+    pub(crate) b_size: usize,
+    pub(crate) i_h: usize,
+    pub(crate) i_w: usize,
+    pub(crate) k_h: usize,
+    pub(crate) k_w: usize,
+    pub(crate) c_out: usize,
+    pub(crate) c_in: usize,
+    pub(crate) padding: usize,
+    pub(crate) output_padding: usize,
+    pub(crate) stride: usize,
+    pub(crate) dilation: usize,
+}
+
+impl ParamsConvTranspose2D {
+    pub(crate) fn out_h(&self) -> usize {
+        (self.i_h - 1) * self.stride + self.dilation * (self.k_h - 1) + self.output_padding + 1
+            - 2 * self.padding
     }
 
     pub(crate) fn out_w(&self) -> usize {
-        let dilation = 1;
-        (self.i_w + 2 * self.padding - dilation * (self.k_w - 1) - 1) / self.stride + 1
+        (self.i_w - 1) * self.stride + self.dilation * (self.k_w - 1) + self.output_padding + 1
+            - 2 * self.padding
     }
 
     pub(crate) fn out_dims(&self) -> Vec<usize> {
@@ -50,3 +125,129 @@ impl ParamsConv2D {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::{Device, Result, Tensor};
+
+    fn values(n: usize) -> Vec<f32> {
+        (0..n).map(|i| (i * 37 % 101) as f32 / 50. - 1.).collect()
+    }
+
+    // Inserts `dilation - 1` zeros between the kernel elements along the last dim.
+    fn dilate_last_dim(k: &Tensor, dilation: usize) -> Result<Tensor> {
+        let k_size = k.dim(k.rank() - 1)?;
+        let mut dims = k.dims().to_vec();
+        dims[k.rank() - 1] = dilation * (k_size - 1) + 1;
+        let zeros = k.narrow(k.rank() - 1, 0, 1)?.zeros_like()?;
+        let mut parts = vec![];
+        for i in 0..k_size {
+            if i > 0 {
+                parts.extend(std::iter::repeat(zeros.clone()).take(dilation - 1))
+            }
+            parts.push(k.narrow(k.rank() - 1, i, 1)?)
+        }
+        let k = Tensor::cat(&parts, k.rank() - 1)?;
+        assert_eq!(k.dims(), dims);
+        Ok(k)
+    }
+
+    fn max_abs_diff(a: &Tensor, b: &Tensor) -> Result<f32> {
+        (a - b)?.abs()?.flatten_all()?.max(0)?.to_scalar::<f32>()
+    }
+
+    #[test]
+    fn conv1d_dilation_groups() -> Result<()> {
+        let dev = &Device::Cpu;
+        let x = Tensor::from_vec(values(2 * 4 * 9), (2, 4, 9), dev)?;
+        let k = Tensor::from_vec(values(6 * 2 * 3), (6, 2, 3), dev)?;
+
+        // A dilated kernel is the same as a kernel with zeros between its elements.
+        let k4 = Tensor::from_vec(values(6 * 4 * 3), (6, 4, 3), dev)?;
+        let ys = x.conv1d(&k4, 1, 2, 2, 1)?;
+        assert_eq!(ys.dims(), [2, 6, 4]);
+        let expected = x.conv1d(&dilate_last_dim(&k4, 2)?, 1, 2, 1, 1)?;
+        assert!(max_abs_diff(&ys, &expected)? < 1e-5);
+
+        // Each group convolves its own slice of the channels.
+        let ys = x.conv1d(&k, 1, 1, 2, 2)?;
+        let y0 = x.narrow(1, 0, 2)?.conv1d(&k.narrow(0, 0, 3)?, 1, 1, 2, 1)?;
+        let y1 = x.narrow(1, 2, 2)?.conv1d(&k.narrow(0, 3, 3)?, 1, 1, 2, 1)?;
+        let expected = Tensor::cat(&[y0, y1], 1)?;
+        assert!(max_abs_diff(&ys, &expected)? < 1e-5);
+
+        let err = x.conv1d(&k4, 0, 1, 5, 1).unwrap_err();
+        assert!(err.to_string().contains("larger than the padded input"));
+        Ok(())
+    }
+
+    #[test]
+    fn conv2d_dilation_groups() -> Result<()> {
+        let dev = &Device::Cpu;
+        let x = Tensor::from_vec(values(2 * 4 * 7 * 6), (2, 4, 7, 6), dev)?;
+        let k = Tensor::from_vec(values(4 * 2 * 3 * 2), (4, 2, 3, 2), dev)?;
+
+        let k4 = Tensor::from_vec(values(4 * 4 * 3 * 2), (4, 4, 3, 2), dev)?;
+        let ys = x.conv2d(&k4, 1, 1, 2, 1)?;
+        assert_eq!(ys.dims(), [2, 4, 5, 6]);
+        let dilated = dilate_last_dim(&k4, 2)?;
+        let dilated = dilate_last_dim(&dilated.transpose(2, 3)?, 2)?.transpose(2, 3)?;
+        let expected = x.conv2d(&dilated.contiguous()?, 1, 1, 1, 1)?;
+        assert!(max_abs_diff(&ys, &expected)? < 1e-5);
+
+        let ys = x.conv2d(&k, 0, 2, 1, 2)?;
+        let y0 = x.narrow(1, 0, 2)?.conv2d(&k.narrow(0, 0, 2)?, 0, 2, 1, 1)?;
+        let y1 = x.narrow(1, 2, 2)?.conv2d(&k.narrow(0, 2, 2)?, 0, 2, 1, 1)?;
+        let expected = Tensor::cat(&[y0, y1], 1)?;
+        assert!(max_abs_diff(&ys, &expected)? < 1e-5);
+
+        let err = x.conv2d(&k4, 0, 1, 4, 1).unwrap_err();
+        assert!(err.to_string().contains("larger than the padded input"));
+        Ok(())
+    }
+
+    // The expected values are computed by hand: each input element adds the kernel scaled by
+    // its value at `i * stride` in the full output which is then cropped by `padding`.
+    #[test]
+    fn conv_transpose_values() -> Result<()> {
+        let dev = &Device::Cpu;
+        let x = Tensor::new(&[[[1f32, 2., 3.]]], dev)?;
+        let k = Tensor::new(&[[[1f32, -1.], [2., 0.5]]], dev)?;
+        let ys = x.conv_transpose1d(&k, 1, 1, 2, 1)?;
+        assert_eq!(
+            ys.to_vec3::<f32>()?,
+            [[[-1., 2., -2., 3., -3.], [0.5, 4., 1., 6., 1.5]]]
+        );
+        let ys = x.conv_transpose1d(&k.narrow(1, 0, 1)?, 0, 0, 1, 2)?;
+        assert_eq!(ys.to_vec3::<f32>()?, [[[1., 2., 2., -2., -3.]]]);
+
+        let x = Tensor::new(&[[[[1f32, 2.], [3., 4.]]]], dev)?;
+        let k = Tensor::new(&[[[[1f32, 0.], [0., -1.]]]], dev)?;
+        let ys = x.conv_transpose2d(&k, 1, 1, 2, 1)?;
+        assert_eq!(
+            ys.squeeze(0)?.squeeze(0)?.to_vec2::<f32>()?,
+            [[-1., 0., -2.], [0., 4., 0.], [-3., 0., -4.]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn conv_transpose_invalid_args() -> Result<()> {
+        let dev = &Device::Cpu;
+        let k1 = Tensor::from_vec(values(3 * 2 * 3), (3, 2, 3), dev)?;
+        let x1 = Tensor::from_vec(values(2 * 3 * 5), (2, 3, 5), dev)?;
+        assert_eq!(x1.conv_transpose1d(&k1, 1, 1, 2, 1)?.dims(), [2, 2, 10]);
+        let err = x1.narrow(2, 0, 0)?.conv_transpose1d(&k1, 0, 0, 1, 1).unwrap_err();
+        assert!(err.to_string().contains("empty input"));
+        let err = x1.conv_transpose1d(&k1, 0, 2, 2, 1).unwrap_err();
+        assert!(err.to_string().contains("has to be smaller than stride"));
+
+        let k2 = Tensor::from_vec(values(3 * 2 * 2 * 3), (3, 2, 2, 3), dev)?;
+        let x2 = Tensor::from_vec(values(2 * 3 * 4 * 3), (2, 3, 4, 3), dev)?;
+        assert_eq!(x2.conv_transpose2d(&k2, 0, 1, 1, 2)?.dims(), [2, 2, 7, 8]);
+        let err = x2.narrow(3, 0, 0)?.conv_transpose2d(&k2, 0, 0, 1, 1).unwrap_err();
+        assert!(err.to_string().contains("empty input"));
+        let err = x2.conv_transpose2d(&k2, 0, 1, 1, 1).unwrap_err();
+        assert!(err.to_string().contains("has to be smaller than stride"));
+        Ok(())
+    }
+}
@@ -95,6 +95,10 @@ from_tensor!(f32);
 from_tensor!(f16);
 from_tensor!(bf16);
 from_tensor!(u32);
+from_tensor!(i8);
+from_tensor!(i16);
+from_tensor!(i32);
+from_tensor!(i64);
 from_tensor!(u8);
 
 impl Tensor {
@@ -131,10 +135,34 @@ impl Tensor {
                     f.write_u32::<LittleEndian>(v)?
                 }
             }
+            DType::I8 => {
+                for v in vs.to_vec1::<i8>()? {
+                    f.write_i8(v)?
+                }
+            }
+            DType::I16 => {
+                for v in vs.to_vec1::<i16>()? {
+                    f.write_i16::<LittleEndian>(v)?
+                }
+            }
+            DType::I32 => {
+                for v in vs.to_vec1::<i32>()? {
+                    f.write_i32::<LittleEndian>(v)?
+                }
+            }
+            DType::I64 => {
+                for v in vs.to_vec1::<i64>()? {
+                    f.write_i64::<LittleEndian>(v)?
+                }
+            }
             DType::U8 => {
                 let vs = vs.to_vec1::<u8>()?;
                 f.write_all(&vs)?;
             }
+            DType::Bool => {
+                let vs = vs.to_dtype(DType::U8)?.to_vec1::<u8>()?;
+                f.write_all(&vs)?;
+            }
         }
         Ok(())
     }
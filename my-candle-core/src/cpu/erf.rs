@@ -0,0 +1,149 @@
+// The error function, this follows the implementation from fdlibm
+// https://www.netlib.org/fdlibm/s_erf.c
+// The approximation uses rational functions on four intervals: [0, 0.84375), [0.84375, 1.25),
+// [1.25, 1/0.35) and [1/0.35, 6), erf is rounded to +/-1 above 6.
+
+const ERX: f64 = 8.45062911510467529297e-01;
+const EFX: f64 = 1.28379167095512586316e-01;
+
+// |x| < 0.84375
+const PP: [f64; 5] = [
+    1.28379167095512558561e-01,
+    -3.25042107247001499370e-01,
+    -2.84817495755985104766e-02,
+    -5.77027029648944159157e-03,
+    -2.37630166566501626084e-05,
+];
+const QQ: [f64; 6] = [
+    1.0,
+    3.97917223959155352819e-01,
+    6.50222499887672944485e-02,
+    5.08130628187576562776e-03,
+    1.32494738004321644526e-04,
+    -3.96022827877536812320e-06,
+];
+
+// 0.84375 <= |x| < 1.25
+const PA: [f64; 7] = [
+    -2.36211856075265944077e-03,
+    4.14856118683748331666e-01,
+    -3.72207876035701323847e-01,
+    3.18346619901161753674e-01,
+    -1.10894694282396677476e-01,
+    3.54783043256182359371e-02,
+    -2.16637559486879084300e-03,
+];
+const QA: [f64; 7] = [
+    1.0,
+    1.06420880400844228286e-01,
+    5.40397917702171048937e-01,
+    7.18286544141962662868e-02,
+    1.26171219808761642112e-01,
+    1.36370839120290507362e-02,
+    1.19844998467991074170e-02,
+];
+
+// 1.25 <= |x| < 1/0.35
+const RA: [f64; 8] = [
+    -9.86494403484714822705e-03,
+    -6.93858572707181764372e-01,
+    -1.05586262253232909814e+01,
+    -6.23753324503260060396e+01,
+    -1.62396669462573470355e+02,
+    -1.84605092906711035994e+02,
+    -8.12874355063065934246e+01,
+    -9.81432934416914548592e+00,
+];
+const SA: [f64; 9] = [
+    1.0,
+    1.96512716674392571292e+01,
+    1.37657754143519042600e+02,
+    4.34565877475229228821e+02,
+    6.45387271733267880336e+02,
+    4.29008140027567833386e+02,
+    1.08635005541779435134e+02,
+    6.57024977031928170135e+00,
+    -6.04244152148580987438e-02,
+];
+
+// 1/0.35 <= |x| < 6
+const RB: [f64; 7] = [
+    -9.86494292470009928597e-03,
+    -7.99283237680523006574e-01,
+    -1.77579549177547519889e+01,
+    -1.60636384855821916062e+02,
+    -6.37566443368389627722e+02,
+    -1.02509513161107724954e+03,
+    -4.83519191608651397019e+02,
+];
+const SB: [f64; 8] = [
+    1.0,
+    3.03380607434824582924e+01,
+    3.25792512996573918826e+02,
+    1.53672958608443695994e+03,
+    3.19985821950859553908e+03,
+    2.55305040643316442583e+03,
+    4.74528541206955367215e+02,
+    -2.24409524465858183362e+01,
+];
+
+#[inline(always)]
+fn poly(cs: &[f64], x: f64) -> f64 {
+    cs.iter().rev().fold(0., |acc, &c| acc * x + c)
+}
+
+/// The error function `erf(x) = 2/sqrt(pi) * integral(exp(-t^2), t=0..x)`.
+pub fn erf(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    let ax = x.abs();
+    if ax < 0.84375 {
+        if ax < f64::powi(2., -28) {
+            return x + EFX * x;
+        }
+        let z = x * x;
+        return x + x * poly(&PP, z) / poly(&QQ, z);
+    }
+    if ax < 1.25 {
+        let s = ax - 1.;
+        return (ERX + poly(&PA, s) / poly(&QA, s)).copysign(x);
+    }
+    if ax >= 6. {
+        return 1f64.copysign(x);
+    }
+    let s = 1. / (ax * ax);
+    let (r, s) = if ax < 1. / 0.35 {
+        (poly(&RA, s), poly(&SA, s))
+    } else {
+        (poly(&RB, s), poly(&SB, s))
+    };
+    // Dropping the low bits of x makes z * z exact.
+    let z = f64::from_bits(ax.to_bits() & 0xffff_ffff_0000_0000);
+    let r = (-z * z - 0.5625).exp() * ((z - ax) * (z + ax) + r / s).exp();
+    (1. - r / ax).copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::erf;
+
+    #[test]
+    fn erf_values() {
+        // Reference values from the python math.erf function.
+        let expected = [
+            (0.0, 0.0),
+            (1e-10, 1.1283791670955126e-10),
+            (0.5, 0.5204998778130465),
+            (1.0, 0.8427007929497149),
+            (-1.0, -0.8427007929497149),
+            (2.0, 0.9953222650189527),
+            (3.5, 0.9999992569016276),
+            (-4.0, -0.9999999845827421),
+            (7.0, 1.0),
+        ];
+        for (x, e) in expected {
+            assert!((erf(x) - e).abs() < 1e-15, "erf({x}) = {} <> {e}", erf(x))
+        }
+    }
+}
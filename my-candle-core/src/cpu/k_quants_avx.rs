@@ -0,0 +1,186 @@
+// AVX2 versions of the quantized dot products, these follow the implementations from
+// https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c
+use crate::error::Result;
+use crate::quantized::k_quants::{
+    q4k_scales_mins, BlockQ4K, BlockQ4_0, BlockQ6K, BlockQ8K, BlockQ8_0, QK8_0, QK_K,
+};
+use half::f16;
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+#[inline(always)]
+unsafe fn sum_i16_pairs_float(x: __m256i) -> __m256 {
+    let ones = _mm256_set1_epi16(1);
+    let summed_pairs = _mm256_madd_epi16(ones, x);
+    _mm256_cvtepi32_ps(summed_pairs)
+}
+
+#[inline(always)]
+unsafe fn mul_sum_us8_pairs_float(ax: __m256i, sy: __m256i) -> __m256 {
+    let dot = _mm256_maddubs_epi16(ax, sy);
+    sum_i16_pairs_float(dot)
+}
+
+// maddubs needs its first argument to be unsigned so the sign of x is moved to y.
+#[inline(always)]
+unsafe fn mul_sum_i8_pairs_float(x: __m256i, y: __m256i) -> __m256 {
+    let ax = _mm256_sign_epi8(x, x);
+    let sy = _mm256_sign_epi8(y, x);
+    mul_sum_us8_pairs_float(ax, sy)
+}
+
+#[inline(always)]
+unsafe fn hsum_float_8(x: __m256) -> f32 {
+    let res = _mm256_extractf128_ps(x, 1);
+    let res = _mm_add_ps(res, _mm256_castps256_ps128(x));
+    let res = _mm_add_ps(res, _mm_movehl_ps(res, res));
+    let res = _mm_add_ss(res, _mm_movehdup_ps(res));
+    _mm_cvtss_f32(res)
+}
+
+// Unpacks 32 4-bit values, the low nibbles end up in the first 16 bytes.
+#[inline(always)]
+unsafe fn bytes_from_nibbles_32(rsi: *const u8) -> __m256i {
+    let tmp = _mm_loadu_si128(rsi as *const __m128i);
+    let bytes = _mm256_insertf128_si256::<1>(_mm256_castsi128_si256(tmp), _mm_srli_epi16(tmp, 4));
+    let low_mask = _mm256_set1_epi8(0xF);
+    _mm256_and_si256(low_mask, bytes)
+}
+
+// Broadcasts s0 on the low 8 16-bit lanes and s1 on the high ones.
+#[inline(always)]
+unsafe fn scale_pair(s0: i16, s1: i16) -> __m256i {
+    _mm256_set_m128i(_mm_set1_epi16(s1), _mm_set1_epi16(s0))
+}
+
+#[inline(always)]
+pub(crate) fn vec_dot_q4_0_q8_0(n: usize, xs: &[BlockQ4_0], ys: &[BlockQ8_0]) -> Result<f32> {
+    let qk = QK8_0;
+    if n % qk != 0 {
+        crate::bail!("vec_dot_q4_0_q8_0: {n} is not divisible by {qk}")
+    }
+    let nb = n / qk;
+    unsafe {
+        let off = _mm256_set1_epi8(8);
+        let mut acc = _mm256_setzero_ps();
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let d = _mm256_set1_ps(f16::to_f32(x.d) * f16::to_f32(y.d));
+            let bx = _mm256_sub_epi8(bytes_from_nibbles_32(x.qs.as_ptr()), off);
+            let by = _mm256_loadu_si256(y.qs.as_ptr() as *const __m256i);
+            let q = mul_sum_i8_pairs_float(bx, by);
+            acc = _mm256_add_ps(_mm256_mul_ps(d, q), acc);
+        }
+        Ok(hsum_float_8(acc))
+    }
+}
+
+#[inline(always)]
+pub(crate) fn vec_dot_q4k_q8k(n: usize, xs: &[BlockQ4K], ys: &[BlockQ8K]) -> Result<f32> {
+    if n % QK_K != 0 {
+        crate::bail!("vec_dot_q4k_q8k: {n} is not divisible by {QK_K}")
+    }
+    let nb = n / QK_K;
+    let mut sum_mins = 0f32;
+    unsafe {
+        let m4 = _mm256_set1_epi8(0xF);
+        let mut acc = _mm256_setzero_ps();
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let (scales, mins) = q4k_scales_mins(&x.scales);
+            let mins_dot = mins
+                .iter()
+                .enumerate()
+                .map(|(j, &m)| m as i32 * (y.bsums[2 * j] as i32 + y.bsums[2 * j + 1] as i32))
+                .sum::<i32>();
+            sum_mins += y.d * x.dmin.to_f32() * mins_dot as f32;
+
+            let mut q4 = x.qs.as_ptr();
+            let mut q8 = y.qs.as_ptr();
+            let mut sumi = _mm256_setzero_si256();
+            for j in 0..QK_K / 64 {
+                let scale_l = _mm256_set1_epi16(scales[2 * j] as i16);
+                let scale_h = _mm256_set1_epi16(scales[2 * j + 1] as i16);
+
+                let q4bits = _mm256_loadu_si256(q4 as *const __m256i);
+                q4 = q4.add(32);
+                let q4l = _mm256_and_si256(q4bits, m4);
+                let q4h = _mm256_and_si256(_mm256_srli_epi16(q4bits, 4), m4);
+
+                let q8l = _mm256_loadu_si256(q8 as *const __m256i);
+                q8 = q8.add(32);
+                let p16l = _mm256_madd_epi16(scale_l, _mm256_maddubs_epi16(q4l, q8l));
+                sumi = _mm256_add_epi32(sumi, p16l);
+
+                let q8h = _mm256_loadu_si256(q8 as *const __m256i);
+                q8 = q8.add(32);
+                let p16h = _mm256_madd_epi16(scale_h, _mm256_maddubs_epi16(q4h, q8h));
+                sumi = _mm256_add_epi32(sumi, p16h);
+            }
+            let d = _mm256_set1_ps(y.d * x.d.to_f32());
+            acc = _mm256_add_ps(_mm256_mul_ps(d, _mm256_cvtepi32_ps(sumi)), acc);
+        }
+        Ok(hsum_float_8(acc) - sum_mins)
+    }
+}
+
+#[inline(always)]
+pub(crate) fn vec_dot_q6k_q8k(n: usize, xs: &[BlockQ6K], ys: &[BlockQ8K]) -> Result<f32> {
+    if n % QK_K != 0 {
+        crate::bail!("vec_dot_q6k_q8k: {n} is not divisible by {QK_K}")
+    }
+    let nb = n / QK_K;
+    unsafe {
+        let m4 = _mm256_set1_epi8(0xF);
+        let m2 = _mm256_set1_epi8(3);
+        let m32s = _mm256_set1_epi8(32);
+        let mut acc = _mm256_setzero_ps();
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let mut q4 = x.ql.as_ptr();
+            let mut qh = x.qh.as_ptr();
+            let mut q8 = y.qs.as_ptr();
+            let sc = &x.scales;
+            let mut sumi = _mm256_setzero_si256();
+
+            for j in 0..QK_K / 128 {
+                let is = j * 8;
+                let q4bits1 = _mm256_loadu_si256(q4 as *const __m256i);
+                q4 = q4.add(32);
+                let q4bits2 = _mm256_loadu_si256(q4 as *const __m256i);
+                q4 = q4.add(32);
+                let q4bits_h = _mm256_loadu_si256(qh as *const __m256i);
+                qh = qh.add(32);
+
+                let q4h_0 = _mm256_slli_epi16(_mm256_and_si256(q4bits_h, m2), 4);
+                let q4h_1 =
+                    _mm256_slli_epi16(_mm256_and_si256(_mm256_srli_epi16(q4bits_h, 2), m2), 4);
+                let q4h_2 =
+                    _mm256_slli_epi16(_mm256_and_si256(_mm256_srli_epi16(q4bits_h, 4), m2), 4);
+                let q4h_3 =
+                    _mm256_slli_epi16(_mm256_and_si256(_mm256_srli_epi16(q4bits_h, 6), m2), 4);
+
+                let q4_0 = _mm256_or_si256(_mm256_and_si256(q4bits1, m4), q4h_0);
+                let q4_1 = _mm256_or_si256(_mm256_and_si256(q4bits2, m4), q4h_1);
+                let q4_2 =
+                    _mm256_or_si256(_mm256_and_si256(_mm256_srli_epi16(q4bits1, 4), m4), q4h_2);
+                let q4_3 =
+                    _mm256_or_si256(_mm256_and_si256(_mm256_srli_epi16(q4bits2, 4), m4), q4h_3);
+
+                for (idx, q4) in [q4_0, q4_1, q4_2, q4_3].into_iter().enumerate() {
+                    let q8v = _mm256_loadu_si256(q8 as *const __m256i);
+                    q8 = q8.add(32);
+                    // The values are stored with an offset of 32 which is removed using the
+                    // sums of the q8 values.
+                    let q8s = _mm256_maddubs_epi16(m32s, q8v);
+                    let p16 = _mm256_sub_epi16(_mm256_maddubs_epi16(q4, q8v), q8s);
+                    let scale = scale_pair(sc[is + 2 * idx] as i16, sc[is + 2 * idx + 1] as i16);
+                    sumi = _mm256_add_epi32(sumi, _mm256_madd_epi16(scale, p16));
+                }
+            }
+            let d = _mm256_set1_ps(y.d * x.d.to_f32());
+            acc = _mm256_add_ps(_mm256_mul_ps(d, _mm256_cvtepi32_ps(sumi)), acc);
+        }
+        Ok(hsum_float_8(acc))
+    }
+}
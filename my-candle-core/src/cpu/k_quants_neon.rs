@@ -0,0 +1,161 @@
+// NEON versions of the quantized dot products, these follow the implementations from
+// https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c
+use crate::error::Result;
+use crate::quantized::k_quants::{
+    q4k_scales_mins, BlockQ4K, BlockQ4_0, BlockQ6K, BlockQ8K, BlockQ8_0, QK8_0, QK_K,
+};
+
+use core::arch::aarch64::*;
+
+// Dot product of 16 i8 values, accumulated in 4 i32 lanes.
+#[inline(always)]
+unsafe fn vdotq_s32(a: int8x16_t, b: int8x16_t) -> int32x4_t {
+    let p0 = vmull_s8(vget_low_s8(a), vget_low_s8(b));
+    let p1 = vmull_s8(vget_high_s8(a), vget_high_s8(b));
+    vaddq_s32(vpaddlq_s16(p0), vpaddlq_s16(p1))
+}
+
+#[inline(always)]
+pub(crate) fn vec_dot_q4_0_q8_0(n: usize, xs: &[BlockQ4_0], ys: &[BlockQ8_0]) -> Result<f32> {
+    let qk = QK8_0;
+    if n % qk != 0 {
+        crate::bail!("vec_dot_q4_0_q8_0: {n} is not divisible by {qk}")
+    }
+    let nb = n / qk;
+    unsafe {
+        let m4b = vdupq_n_u8(0x0F);
+        let s8b = vdupq_n_s8(0x8);
+        let mut sumv = vdupq_n_f32(0f32);
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let v0 = vld1q_u8(x.qs.as_ptr());
+            // 4-bit -> 8-bit, then remove the offset.
+            let v0l = vsubq_s8(vreinterpretq_s8_u8(vandq_u8(v0, m4b)), s8b);
+            let v0h = vsubq_s8(vreinterpretq_s8_u8(vshrq_n_u8(v0, 4)), s8b);
+            let v1l = vld1q_s8(y.qs.as_ptr());
+            let v1h = vld1q_s8(y.qs.as_ptr().add(16));
+            let p = vaddq_s32(vdotq_s32(v0l, v1l), vdotq_s32(v0h, v1h));
+            sumv = vmlaq_n_f32(sumv, vcvtq_f32_s32(p), x.d.to_f32() * y.d.to_f32());
+        }
+        Ok(vaddvq_f32(sumv))
+    }
+}
+
+#[inline(always)]
+pub(crate) fn vec_dot_q4k_q8k(n: usize, xs: &[BlockQ4K], ys: &[BlockQ8K]) -> Result<f32> {
+    if n % QK_K != 0 {
+        crate::bail!("vec_dot_q4k_q8k: {n} is not divisible by {QK_K}")
+    }
+    let nb = n / QK_K;
+    let mut sumf = 0f32;
+    unsafe {
+        let m4b = vdupq_n_u8(0xF);
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let (scales, mins) = q4k_scales_mins(&x.scales);
+            let mins_dot = mins
+                .iter()
+                .enumerate()
+                .map(|(j, &m)| m as i32 * (y.bsums[2 * j] as i32 + y.bsums[2 * j + 1] as i32))
+                .sum::<i32>();
+
+            let mut q4 = x.qs.as_ptr();
+            let mut q8 = y.qs.as_ptr();
+            let mut sumi = 0i32;
+            for j in 0..QK_K / 64 {
+                let q4bits_0 = vld1q_u8(q4);
+                let q4bits_1 = vld1q_u8(q4.add(16));
+                q4 = q4.add(32);
+
+                let p0 = vdotq_s32(
+                    vreinterpretq_s8_u8(vandq_u8(q4bits_0, m4b)),
+                    vld1q_s8(q8),
+                );
+                let p1 = vdotq_s32(
+                    vreinterpretq_s8_u8(vandq_u8(q4bits_1, m4b)),
+                    vld1q_s8(q8.add(16)),
+                );
+                q8 = q8.add(32);
+                sumi += vaddvq_s32(vaddq_s32(p0, p1)) * scales[2 * j] as i32;
+
+                let p2 = vdotq_s32(vreinterpretq_s8_u8(vshrq_n_u8(q4bits_0, 4)), vld1q_s8(q8));
+                let p3 = vdotq_s32(
+                    vreinterpretq_s8_u8(vshrq_n_u8(q4bits_1, 4)),
+                    vld1q_s8(q8.add(16)),
+                );
+                q8 = q8.add(32);
+                sumi += vaddvq_s32(vaddq_s32(p2, p3)) * scales[2 * j + 1] as i32;
+            }
+            sumf += y.d * (x.d.to_f32() * sumi as f32 - x.dmin.to_f32() * mins_dot as f32);
+        }
+    }
+    Ok(sumf)
+}
+
+#[inline(always)]
+pub(crate) fn vec_dot_q6k_q8k(n: usize, xs: &[BlockQ6K], ys: &[BlockQ8K]) -> Result<f32> {
+    if n % QK_K != 0 {
+        crate::bail!("vec_dot_q6k_q8k: {n} is not divisible by {QK_K}")
+    }
+    let nb = n / QK_K;
+    let mut sumf = 0f32;
+    unsafe {
+        let m4b = vdupq_n_u8(0xF);
+        let mone = vdupq_n_u8(3);
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            // The values are stored with an offset of 32 which is removed using the sums of
+            // the q8 values.
+            let isum_mins = x
+                .scales
+                .iter()
+                .zip(y.bsums.iter())
+                .map(|(&s, &b)| s as i32 * b as i32)
+                .sum::<i32>();
+
+            let mut q6 = x.ql.as_ptr();
+            let mut qh = x.qh.as_ptr();
+            let mut q8 = y.qs.as_ptr();
+            let mut scale = x.scales.as_ptr();
+            let mut isum = 0i32;
+            for _j in 0..QK_K / 128 {
+                let qhbits_0 = vld1q_u8(qh);
+                let qhbits_1 = vld1q_u8(qh.add(16));
+                qh = qh.add(32);
+                let q6bits = [
+                    vld1q_u8(q6),
+                    vld1q_u8(q6.add(16)),
+                    vld1q_u8(q6.add(32)),
+                    vld1q_u8(q6.add(48)),
+                ];
+                q6 = q6.add(64);
+
+                let high = |bits: uint8x16_t, shift: i32| -> uint8x16_t {
+                    let bits = match shift {
+                        0 => bits,
+                        2 => vshrq_n_u8(bits, 2),
+                        4 => vshrq_n_u8(bits, 4),
+                        _ => vshrq_n_u8(bits, 6),
+                    };
+                    vshlq_n_u8(vandq_u8(mone, bits), 4)
+                };
+
+                let q6bytes = [
+                    vorrq_u8(vandq_u8(q6bits[0], m4b), high(qhbits_0, 0)),
+                    vorrq_u8(vandq_u8(q6bits[1], m4b), high(qhbits_1, 0)),
+                    vorrq_u8(vandq_u8(q6bits[2], m4b), high(qhbits_0, 2)),
+                    vorrq_u8(vandq_u8(q6bits[3], m4b), high(qhbits_1, 2)),
+                    vorrq_u8(vshrq_n_u8(q6bits[0], 4), high(qhbits_0, 4)),
+                    vorrq_u8(vshrq_n_u8(q6bits[1], 4), high(qhbits_1, 4)),
+                    vorrq_u8(vshrq_n_u8(q6bits[2], 4), high(qhbits_0, 6)),
+                    vorrq_u8(vshrq_n_u8(q6bits[3], 4), high(qhbits_1, 6)),
+                ];
+                for q6bytes in q6bytes {
+                    let p = vdotq_s32(vreinterpretq_s8_u8(q6bytes), vld1q_s8(q8));
+                    q8 = q8.add(16);
+                    isum += vaddvq_s32(p) * *scale as i32;
+                    scale = scale.add(1);
+                }
+            }
+            sumf += x.d.to_f32() * y.d * (isum - 32 * isum_mins) as f32;
+        }
+    }
+    Ok(sumf)
+}
@@ -1,4 +1,4 @@
-pub trait VecOps: num_traits::NumAssign + Copy {
+pub trait VecOps: num_traits::NumAssign + PartialOrd + Copy {
     /// Dot-product of two vectors.
     ///
     /// # Safety
@@ -26,6 +26,38 @@ pub trait VecOps: num_traits::NumAssign + Copy {
             *res += *xs.add(i)
         }
     }
+
+    /// Maximum element in a non-empty vector.
+    ///
+    /// # Safety
+    /// The length of `xs` must be at least `len` and positive. `res` has to point to a valid
+    /// element.
+    #[inline(always)]
+    unsafe fn vec_reduce_max(xs: *const Self, res: *mut Self, len: usize) {
+        *res = *xs;
+        for i in 1..len {
+            let x = *xs.add(i);
+            if x > *res {
+                *res = x
+            }
+        }
+    }
+
+    /// Minimum element in a non-empty vector.
+    ///
+    /// # Safety
+    /// The length of `xs` must be at least `len` and positive. `res` has to point to a valid
+    /// element.
+    #[inline(always)]
+    unsafe fn vec_reduce_min(xs: *const Self, res: *mut Self, len: usize) {
+        *res = *xs;
+        for i in 1..len {
+            let x = *xs.add(i);
+            if x < *res {
+                *res = x
+            }
+        }
+    }
 }
 
 impl VecOps for f32 {
@@ -39,6 +71,11 @@ impl VecOps for f32 {
     unsafe fn vec_reduce_sum(xs: *const Self, res: *mut Self, len: usize) {
         super::vec_sum(xs, res, len)
     }
+
+    #[inline(always)]
+    unsafe fn vec_reduce_max(xs: *const Self, res: *mut Self, len: usize) {
+        super::vec_max(xs, res, len)
+    }
 }
 
 impl VecOps for half::f16 {
@@ -58,6 +95,14 @@ impl VecOps for u8 {}
 
 impl VecOps for u32 {}
 
+impl VecOps for i8 {}
+
+impl VecOps for i16 {}
+
+impl VecOps for i32 {}
+
+impl VecOps for i64 {}
+
 
 /**
 这段代码定义了一个函数 par_for_each，用于并行地执行一个函数。具体来说：
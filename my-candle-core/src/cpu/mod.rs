@@ -1,4 +1,5 @@
 pub mod kernels;
+pub mod erf;
 
 trait Cpu<const ARR:usize> {
     type Unit;
@@ -60,6 +61,9 @@ pub mod avx;
 #[cfg(target_feature = "avx")]
 pub use avx::{CurrentCpu, CurrentCpuF16};
 
+#[cfg(target_feature = "avx2")]
+pub(crate) mod k_quants_avx;
+
 #[cfg(target_arch = "wasm32")]
 #[cfg(target_feature = "simd128")]
 pub mod simd128;
@@ -74,6 +78,10 @@ pub mod neon;
 #[cfg(target_feature = "neon")]
 pub use neon::CurrentCpu;
 
+#[cfg(target_arch = "aarch64")]
+#[cfg(target_feature = "neon")]
+pub(crate) mod k_quants_neon;
+
 #[cfg(any(
 target_feature = "neon",
 target_feature = "avx",
@@ -156,6 +164,30 @@ pub(crate) unsafe fn vec_sum(row: *const f32, b: *mut f32, k: usize) {
     }
 }
 
+// The maximum is tracked over independent lanes so that the compiler can vectorize the main
+// loop with the simd max instructions of the target.
+#[inline(always)]
+pub(crate) unsafe fn vec_max(row: *const f32, b: *mut f32, k: usize) {
+    const LANES: usize = 8;
+    let xs = std::slice::from_raw_parts(row, k);
+    let mut lanes = [xs[0]; LANES];
+    let mut chunks = xs.chunks_exact(LANES);
+    for chunk in &mut chunks {
+        for (l, &x) in lanes.iter_mut().zip(chunk.iter()) {
+            if x > *l {
+                *l = x
+            }
+        }
+    }
+    let mut max = lanes[0];
+    for &x in lanes[1..].iter().chain(chunks.remainder().iter()) {
+        if x > max {
+            max = x
+        }
+    }
+    *b = max
+}
+
 #[cfg(target_feature = "avx")]
 #[inline(always)]
 pub(crate) unsafe fn vec_dot_f16(a_row: *const f16, b_row: *const f16, c: *mut f32, k: usize) {
@@ -10,8 +10,13 @@ use crate::device::DeviceLocation;
 
 #[derive(Debug, Clone)]
 pub  enum CpuStorage {
+    Bool(Vec<bool>),
     U8(Vec<u8>),
     U32(Vec<u32>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
     BF16(Vec<bf16>),
     F16(Vec<f16>),
     F32(Vec<f32>),
@@ -23,12 +28,18 @@ pub  enum CpuStorage {
 pub struct CpuDevice;
 
 pub trait Map1 {
+    const OP: &'static str;
     fn f<T: WithDType>(&self, vs: &[T], layout: &Layout) -> Result<Vec<T>>;
 
     fn map(&self, vs: &CpuStorage, layout: &Layout) -> Result<CpuStorage> {
         match vs {
+            CpuStorage::Bool(_) => Err(Error::UnsupportedDTypeForOp(DType::Bool, Self::OP).bt()),
             CpuStorage::U8(vs) => Ok(CpuStorage::U8(self.f(vs, layout)?)),
             CpuStorage::U32(vs) => Ok(CpuStorage::U32(self.f(vs, layout)?)),
+            CpuStorage::I8(vs) => Ok(CpuStorage::I8(self.f(vs, layout)?)),
+            CpuStorage::I16(vs) => Ok(CpuStorage::I16(self.f(vs, layout)?)),
+            CpuStorage::I32(vs) => Ok(CpuStorage::I32(self.f(vs, layout)?)),
+            CpuStorage::I64(vs) => Ok(CpuStorage::I64(self.f(vs, layout)?)),
             CpuStorage::BF16(vs) => Ok(CpuStorage::BF16(self.f(vs, layout)?)),
             CpuStorage::F16(vs) => Ok(CpuStorage::F16(self.f(vs, layout)?)),
             CpuStorage::F32(vs) => Ok(CpuStorage::F32(self.f(vs, layout)?)),
@@ -38,6 +49,7 @@ pub trait Map1 {
 }
 
 pub trait Map1Any {
+    const OP: &'static str;
     fn f<T: WithDType, W: Fn(Vec<T>) -> CpuStorage>(
         &self,
         vs: &[T],
@@ -47,8 +59,13 @@ pub trait Map1Any {
 
     fn map(&self, vs: &CpuStorage, layout: &Layout) -> Result<CpuStorage> {
         match vs {
+            CpuStorage::Bool(_) => Err(Error::UnsupportedDTypeForOp(DType::Bool, Self::OP).bt()),
             CpuStorage::U8(vs) => Ok(self.f(vs, layout, CpuStorage::U8)?),
             CpuStorage::U32(vs) => Ok(self.f(vs, layout, CpuStorage::U32)?),
+            CpuStorage::I8(vs) => Ok(self.f(vs, layout, CpuStorage::I8)?),
+            CpuStorage::I16(vs) => Ok(self.f(vs, layout, CpuStorage::I16)?),
+            CpuStorage::I32(vs) => Ok(self.f(vs, layout, CpuStorage::I32)?),
+            CpuStorage::I64(vs) => Ok(self.f(vs, layout, CpuStorage::I64)?),
             CpuStorage::BF16(vs) => Ok(self.f(vs, layout, CpuStorage::BF16)?),
             CpuStorage::F16(vs) => Ok(self.f(vs, layout, CpuStorage::F16)?),
             CpuStorage::F32(vs) => Ok(self.f(vs, layout, CpuStorage::F32)?),
@@ -71,8 +88,15 @@ pub trait Map2 {
         l2: &Layout,
     ) -> Result<CpuStorage> {
         match (v1, v2) {
+            (C::Bool(_), C::Bool(_)) => {
+                Err(Error::UnsupportedDTypeForOp(DType::Bool, Self::OP).bt())
+            }
             (C::U8(v1), C::U8(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
             (C::U32(v1), C::U32(v2)) => Ok(C::U32(self.f(v1, l1, v2, l2)?)),
+            (C::I8(v1), C::I8(v2)) => Ok(C::I8(self.f(v1, l1, v2, l2)?)),
+            (C::I16(v1), C::I16(v2)) => Ok(C::I16(self.f(v1, l1, v2, l2)?)),
+            (C::I32(v1), C::I32(v2)) => Ok(C::I32(self.f(v1, l1, v2, l2)?)),
+            (C::I64(v1), C::I64(v2)) => Ok(C::I64(self.f(v1, l1, v2, l2)?)),
             (C::BF16(v1), C::BF16(v2)) => Ok(C::BF16(self.f(v1, l1, v2, l2)?)),
             (C::F16(v1), C::F16(v2)) => Ok(C::F16(self.f(v1, l1, v2, l2)?)),
             (C::F32(v1), C::F32(v2)) => Ok(C::F32(self.f(v1, l1, v2, l2)?)),
@@ -99,8 +123,15 @@ pub trait Map2U8 {
         l2: &Layout,
     ) -> Result<CpuStorage> {
         match (v1, v2) {
+            (C::Bool(_), C::Bool(_)) => {
+                Err(Error::UnsupportedDTypeForOp(DType::Bool, Self::OP).bt())
+            }
             (C::U8(v1), C::U8(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
             (C::U32(v1), C::U32(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
+            (C::I8(v1), C::I8(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
+            (C::I16(v1), C::I16(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
+            (C::I32(v1), C::I32(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
+            (C::I64(v1), C::I64(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
             (C::BF16(v1), C::BF16(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
             (C::F16(v1), C::F16(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
             (C::F32(v1), C::F32(v2)) => Ok(C::U8(self.f(v1, l1, v2, l2)?)),
@@ -259,6 +290,7 @@ impl ReduceIndex {
 }
 
 impl Map1Any for ReduceIndex {
+    const OP: &'static str = "reduce-index";
     #[inline(always)]
     fn f<T: WithDType, W: Fn(Vec<T>) -> CpuStorage>(
         &self,
@@ -291,10 +323,18 @@ struct ReduceSum<'a> {
 
 impl<'a> ReduceSum<'a> {
     #[inline(always)]
-    fn fold_impl<T, F>(&self, src: &[T], src_l: &Layout, start_elt: T, f: F) -> Result<Vec<T>>
+    fn fold_impl<T, F, FV>(
+        &self,
+        src: &[T],
+        src_l: &Layout,
+        start_elt: T,
+        f: F,
+        f_vec: FV,
+    ) -> Result<Vec<T>>
         where
             T: WithDType,
             F: Fn(T, T) -> T,
+            FV: Fn(&[T], &mut T),
     {
         let mut dst = vec![start_elt; self.dst_shape.elem_count()];
         match src_l.contiguous_offsets() {
@@ -317,13 +357,7 @@ impl<'a> ReduceSum<'a> {
                         .product::<usize>();
                     for (dst_i, dst_v) in dst.iter_mut().enumerate() {
                         let src_i = dst_i * reduce_sz;
-                        unsafe {
-                            T::vec_reduce_sum(
-                                src[src_i..src_i + reduce_sz].as_ptr(),
-                                dst_v,
-                                reduce_sz,
-                            )
-                        };
+                        f_vec(&src[src_i..src_i + reduce_sz], dst_v)
                     }
                     return Ok(dst);
                 };
@@ -356,12 +390,32 @@ impl<'a> ReduceSum<'a> {
 }
 
 impl<'a> Map1 for ReduceSum<'a> {
+    const OP: &'static str = "reduce-sum";
+    #[inline(always)]
+    fn f<T: WithDType>(&self, src: &[T], src_l: &Layout) -> Result<Vec<T>> {
+        self.fold_impl(src, src_l, T::zero(), |x, y| x + y, |xs, dst| unsafe {
+            T::vec_reduce_sum(xs.as_ptr(), dst, xs.len())
+        })
+    }
+}
+
+// Uses the same index computations as the sum, only the accumulation differs.
+struct ReduceProd<'a>(ReduceSum<'a>);
+
+impl<'a> Map1 for ReduceProd<'a> {
+    const OP: &'static str = "reduce-prod";
     #[inline(always)]
     fn f<T: WithDType>(&self, src: &[T], src_l: &Layout) -> Result<Vec<T>> {
-        self.fold_impl(src, src_l, T::zero(), |x, y| x + y)
+        self.0.fold_impl(src, src_l, T::one(), |x, y| x * y, |xs, dst| {
+            *dst = xs.iter().fold(T::one(), |acc, &x| acc * x)
+        })
     }
 }
 
+fn nonzero<T: WithDType>(vs: &[T], layout: &Layout) -> Vec<bool> {
+    unary_map(vs, layout, |v| v != T::zero())
+}
+
 pub fn unary_map<T: Copy, U: Copy, F: FnMut(T) -> U>(
     vs: &[T],
     layout: &Layout,
@@ -634,6 +688,7 @@ fn binary_map_vec<T: Copy, F: FnMut(T, T) -> T, FV: FnMut(&[T], &[T], &mut [T])>
 struct Affine(f64, f64);
 
 impl Map1 for Affine {
+    const OP: &'static str = "affine";
     fn f<T: WithDType>(&self, vs: &[T], layout: &Layout) -> Result<Vec<T>> {
         let mul = T::from_f64(self.0);
         let add = T::from_f64(self.1);
@@ -641,9 +696,59 @@ impl Map1 for Affine {
     }
 }
 
+struct Clamp(f64, f64);
+
+impl Map1 for Clamp {
+    const OP: &'static str = "clamp";
+    fn f<T: WithDType>(&self, vs: &[T], layout: &Layout) -> Result<Vec<T>> {
+        let min = T::from_f64(self.0);
+        let max = T::from_f64(self.1);
+        Ok(unary_map(vs, layout, |v| {
+            if v < min {
+                min
+            } else if v > max {
+                max
+            } else {
+                v
+            }
+        }))
+    }
+}
+
+struct Cumulative {
+    dim: usize,
+    prod: bool,
+}
+
+impl Map1 for Cumulative {
+    const OP: &'static str = "cumulative";
+    fn f<T: WithDType>(&self, vs: &[T], layout: &Layout) -> Result<Vec<T>> {
+        let dims = layout.dims();
+        let dim_size = dims[self.dim];
+        let inner: usize = dims[self.dim + 1..].iter().product();
+        // Start from a contiguous copy and accumulate in place along the dimension.
+        let mut dst = unary_map(vs, layout, |v| v);
+        if dim_size == 0 || inner == 0 {
+            return Ok(dst);
+        }
+        for block in dst.chunks_exact_mut(dim_size * inner) {
+            for idx in inner..dim_size * inner {
+                let prev = block[idx - inner];
+                if self.prod {
+                    block[idx] *= prev
+                } else {
+                    block[idx] += prev
+                }
+            }
+        }
+        Ok(dst)
+    }
+}
+
 struct AvgPool2D((usize, usize), (usize, usize));
 
 impl Map1 for AvgPool2D {
+    const OP: &'static str = "avg-pool2d";
     fn f<T: WithDType>(&self, src: &[T], layout: &Layout) -> Result<Vec<T>> {
         // https://pytorch.org/docs/stable/generated/torch.nn.AvgPool2d.html
         let (k_h, k_w) = self.0;
@@ -685,6 +790,7 @@ impl Map1 for AvgPool2D {
 struct MaxPool2D((usize, usize), (usize, usize));
 
 impl Map1 for MaxPool2D {
+    const OP: &'static str = "max-pool2d";
     fn f<T: WithDType>(&self, src: &[T], layout: &Layout) -> Result<Vec<T>> {
         // https://pytorch.org/docs/stable/generated/torch.nn.MaxPool2d.html
         let (k_h, k_w) = self.0;
@@ -727,6 +833,7 @@ impl Map1 for MaxPool2D {
 struct UpsampleNearest2D(usize, usize);
 
 impl Map1 for UpsampleNearest2D {
+    const OP: &'static str = "upsample-nearest2d";
     fn f<T: WithDType>(&self, src: &[T], layout: &Layout) -> Result<Vec<T>> {
         // TODO: Specialized implementation for the case 2*h, 2*w?
         let (dst_h, dst_w) = (self.0, self.1);
@@ -768,6 +875,7 @@ struct Gather<'a, I: IntDType> {
 }
 
 impl<'a, I: IntDType> Map1 for Gather<'a, I> {
+    const OP: &'static str = "gather";
     fn f<T: WithDType>(&self, src: &[T], src_l: &Layout) -> Result<Vec<T>> {
         let ids = match self.ids_l.contiguous_offsets() {
             Some((a, b)) => &self.ids[a..b],
@@ -796,7 +904,7 @@ impl<'a, I: IntDType> Map1 for Gather<'a, I> {
                 let start_dst_idx = start_dst_idx + i * dst_right_len;
                 for right_i in 0..dst_right_len {
                     let dst_idx = start_dst_idx + right_i;
-                    let index = ids[dst_idx].as_usize();
+                    let index = ids[dst_idx].as_usize()?;
                     if index >= src_dim_len {
                         Err(Error::InvalidIndex {
                             index,
@@ -821,6 +929,7 @@ struct IndexSelect<'a, T: IntDType> {
 }
 
 impl<'a, I: IntDType> Map1 for IndexSelect<'a, I> {
+    const OP: &'static str = "index-select";
     fn f<T: WithDType>(&self, src: &[T], layout: &Layout) -> Result<Vec<T>> {
         let src = match layout.contiguous_offsets() {
             Some((a, b)) => &src[a..b],
@@ -848,7 +957,7 @@ impl<'a, I: IntDType> Map1 for IndexSelect<'a, I> {
             let start_src_idx = left_i * right_len * src_dim;
             let start_dst_idx = left_i * right_len * n_ids;
             for i in 0..n_ids {
-                let index = self.ids[self.ids_l.start_offset() + stride_ids * i].as_usize();
+                let index = self.ids[self.ids_l.start_offset() + stride_ids * i].as_usize()?;
                 if index >= src_dim {
                     Err(Error::InvalidIndex {
                         index,
@@ -905,7 +1014,7 @@ impl<'a, I: IntDType> Map2 for ScatterAdd<'a, I> {
                 let start_ids_idx = start_ids_idx + i * ids_right_len;
                 for right_i in 0..dst_right_len {
                     let ids_idx = start_ids_idx + right_i;
-                    let index = ids[ids_idx].as_usize();
+                    let index = ids[ids_idx].as_usize()?;
                     if index >= dst_dim_len {
                         Err(Error::InvalidIndex {
                             index,
@@ -948,7 +1057,7 @@ impl<'a, I: IntDType> Map2 for IndexAdd<'a, I> {
         let post_dim = src_l.dims()[dim + 1..].iter().product::<usize>();
         if dim == 0 {
             for (src_idx, dst_idx) in self.ids.iter().enumerate() {
-                let dst_idx = dst_idx.as_usize();
+                let dst_idx = dst_idx.as_usize()?;
                 if dst_idx >= max_idx {
                     Err(Error::InvalidIndex {
                         index: dst_idx,
@@ -966,7 +1075,7 @@ impl<'a, I: IntDType> Map2 for IndexAdd<'a, I> {
             }
         } else {
             for (src_idx, dst_idx) in self.ids.iter().enumerate() {
-                let dst_idx = dst_idx.as_usize();
+                let dst_idx = dst_idx.as_usize()?;
                 if dst_idx >= max_idx {
                     Err(Error::InvalidIndex {
                         index: dst_idx,
@@ -1055,26 +1164,34 @@ impl<'a> Map2 for Conv1D<'a> {
 
         let num_threads = crate::utils::get_num_threads();
 
+        // With groups, each output channel only sees the `c_in_per_group` input channels of its
+        // group, the kernel in-channel dimension has this size.
+        let c_in_per_group = p.c_in_per_group();
+        let c_out_per_group = p.c_out_per_group();
         for offset in 0..p.k_size {
             crate::cpu::kernels::par_range(0, p.c_out, num_threads, |dst_c_idx| {
                 let dst_idx = dst_c_idx * l_out;
-                let k_cont = (0..p.c_in)
+                let src_c_offset = (dst_c_idx / c_out_per_group) * c_in_per_group;
+                let k_cont = (0..c_in_per_group)
                     .map(|c_in_idx| k[dst_c_idx * k_s0 + c_in_idx * k_s1 + offset * k_s2])
                     .collect::<Vec<_>>();
                 for b_idx in 0..p.b_size {
                     let dst_idx = dst_idx + b_idx * p.c_out * l_out;
                     for dst_l in 0..l_out {
                         let dst_idx = dst_idx + dst_l;
-                        let src_l = p.stride * dst_l + offset;
+                        let src_l = p.stride * dst_l + offset * p.dilation;
                         if src_l < p.padding || src_l >= p.padding + p.l_in {
                             continue;
                         }
                         let src_l = src_l - p.padding;
-                        let inp_cont = &inp_cont[b_idx * p.l_in * p.c_in + src_l * p.c_in..];
-                        assert!(inp_cont.len() >= p.c_in);
-                        assert!(k_cont.len() >= p.c_in);
+                        let inp_cont = &inp_cont
+                            [b_idx * p.l_in * p.c_in + src_l * p.c_in + src_c_offset..];
+                        assert!(inp_cont.len() >= c_in_per_group);
+                        assert!(k_cont.len() >= c_in_per_group);
                         let mut d = T::zero();
-                        unsafe { T::vec_dot(inp_cont.as_ptr(), k_cont.as_ptr(), &mut d, p.c_in) }
+                        unsafe {
+                            T::vec_dot(inp_cont.as_ptr(), k_cont.as_ptr(), &mut d, c_in_per_group)
+                        }
                         let dst_p = dst.as_ptr();
                         // Safety: dst_idx are uniques per dst_c_idx which is used to parallelise
                         // the different tasks so no two threads can try to write at the same
@@ -1126,11 +1243,16 @@ impl<'a> Map2 for Conv2D<'a> {
 
         let num_threads = crate::utils::get_num_threads();
 
+        // With groups, each output channel only sees the `c_in_per_group` input channels of its
+        // group, the kernel in-channel dimension has this size.
+        let c_in_per_group = p.c_in_per_group();
+        let c_out_per_group = p.c_out_per_group();
         for offset_h in 0..p.k_h {
             for offset_w in 0..p.k_w {
                 crate::cpu::kernels::par_range(0, p.c_out, num_threads, |dst_c_idx| {
                     let dst_idx = dst_c_idx * out_w * out_h;
-                    let k_cont = (0..p.c_in)
+                    let src_c_offset = (dst_c_idx / c_out_per_group) * c_in_per_group;
+                    let k_cont = (0..c_in_per_group)
                         .map(|c_in_idx| {
                             k[dst_c_idx * k_s0
                                 + c_in_idx * k_s1
@@ -1142,26 +1264,191 @@ impl<'a> Map2 for Conv2D<'a> {
                         let dst_idx = dst_idx + b_idx * p.c_out * out_h * out_w;
                         for dst_h in 0..out_h {
                             let dst_idx = dst_idx + dst_h * out_w;
-                            let src_h = p.stride * dst_h + offset_h;
+                            let src_h = p.stride * dst_h + offset_h * p.dilation;
                             if src_h < p.padding || src_h >= p.i_h + p.padding {
                                 continue;
                             }
                             let src_h = src_h - p.padding;
                             for dst_w in 0..out_w {
                                 let dst_idx = dst_idx + dst_w;
-                                let src_w = p.stride * dst_w + offset_w;
+                                let src_w = p.stride * dst_w + offset_w * p.dilation;
                                 if src_w < p.padding || src_w >= p.i_w + p.padding {
                                     continue;
                                 }
                                 let src_w = src_w - p.padding;
+                                let inp_cont = &inp_cont[b_idx * cont_s0
+                                    + src_h * cont_s1
+                                    + src_w * cont_s2
+                                    + src_c_offset..];
+                                assert!(inp_cont.len() >= c_in_per_group);
+                                assert!(k_cont.len() >= c_in_per_group);
+                                let mut d = T::zero();
+                                unsafe {
+                                    T::vec_dot(
+                                        inp_cont.as_ptr(),
+                                        k_cont.as_ptr(),
+                                        &mut d,
+                                        c_in_per_group,
+                                    )
+                                }
+                                let dst_p = dst.as_ptr();
+                                // Safety: dst_idx are uniques per dst_c_idx which is used to parallelise
+                                // the different tasks so no two threads can try to write at the same
+                                // location.
+                                unsafe {
+                                    let ptr = dst_p.add(dst_idx) as *mut T;
+                                    *ptr += d
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
+        Ok(dst)
+    }
+}
+
+struct ConvTranspose1D<'a>(&'a crate::conv::ParamsConvTranspose1D);
+
+impl<'a> Map2 for ConvTranspose1D<'a> {
+    const OP: &'static str = "conv_transpose1d";
+    fn f<T: WithDType>(&self, inp: &[T], inp_l: &Layout, k: &[T], k_l: &Layout) -> Result<Vec<T>> {
+        let p = self.0;
+        let inp = &inp[inp_l.start_offset()..];
+        let k = &k[k_l.start_offset()..];
+        let (inp_s0, inp_s1, inp_s2) = crate::shape::dims3(inp_l.stride())?;
+        let (k_s0, k_s1, k_s2) = crate::shape::dims3(k_l.stride())?;
+        let l_out = p.l_out();
+
+        // Output shape: [b_size, c_out, l_out].
+        let dst = vec![T::zero(); p.b_size * p.c_out * l_out];
+
+        // TODO: Avoid making this copy if `inp` already has the appropriate layout.
+        let mut inp_cont = vec![T::zero(); p.b_size * p.c_in * p.l_in];
+        let cont_s0 = p.l_in * p.c_in;
+        let cont_s1 = p.c_in;
+        for b_idx in 0..p.b_size {
+            for l_idx in 0..p.l_in {
+                for c_idx in 0..p.c_in {
+                    let src_idx = b_idx * inp_s0 + c_idx * inp_s1 + l_idx * inp_s2;
+                    let dst_idx = b_idx * cont_s0 + l_idx * cont_s1 + c_idx;
+                    inp_cont[dst_idx] = inp[src_idx]
+                }
+            }
+        }
+
+        let num_threads = crate::utils::get_num_threads();
+
+        // Each input position is scattered to the output positions covered by the kernel, the
+        // kernel shape is [c_in, c_out, k_size].
+        for k_idx in 0..p.k_size {
+            crate::cpu::kernels::par_range(0, p.c_out, num_threads, |dst_c_idx| {
+                let k_cont = (0..p.c_in)
+                    .map(|c_in_idx| k[c_in_idx * k_s0 + dst_c_idx * k_s1 + k_idx * k_s2])
+                    .collect::<Vec<_>>();
+                for b_idx in 0..p.b_size {
+                    for l_idx in 0..p.l_in {
+                        let out_idx = l_idx * p.stride + k_idx * p.dilation;
+                        if out_idx < p.padding || out_idx - p.padding >= l_out {
+                            continue;
+                        }
+                        let out_idx = out_idx - p.padding;
+                        let inp_cont = &inp_cont[b_idx * cont_s0 + l_idx * cont_s1..];
+                        assert!(inp_cont.len() >= p.c_in);
+                        assert!(k_cont.len() >= p.c_in);
+                        let mut d = T::zero();
+                        unsafe { T::vec_dot(inp_cont.as_ptr(), k_cont.as_ptr(), &mut d, p.c_in) }
+                        let dst_idx = b_idx * p.c_out * l_out + dst_c_idx * l_out + out_idx;
+                        let dst_p = dst.as_ptr();
+                        // Safety: dst_idx are uniques per dst_c_idx which is used to parallelise
+                        // the different tasks so no two threads can try to write at the same
+                        // location.
+                        unsafe {
+                            let ptr = dst_p.add(dst_idx) as *mut T;
+                            *ptr += d
+                        }
+                    }
+                }
+            })
+        }
+        Ok(dst)
+    }
+}
+
+struct ConvTranspose2D<'a>(&'a crate::conv::ParamsConvTranspose2D);
+
+impl<'a> Map2 for ConvTranspose2D<'a> {
+    const OP: &'static str = "conv_transpose2d";
+    fn f<T: WithDType>(&self, inp: &[T], inp_l: &Layout, k: &[T], k_l: &Layout) -> Result<Vec<T>> {
+        let p = self.0;
+        let inp = &inp[inp_l.start_offset()..];
+        let (inp_s0, inp_s1, inp_s2, inp_s3) = crate::shape::dims4(inp_l.stride())?;
+        let k = &k[k_l.start_offset()..];
+        let (k_s0, k_s1, k_s2, k_s3) = crate::shape::dims4(k_l.stride())?;
+        let (out_h, out_w) = (p.out_h(), p.out_w());
+
+        // Output shape: [b_size, c_out, out_h, out_w].
+        let dst = vec![T::zero(); p.b_size * p.c_out * out_h * out_w];
+        let dst_s0 = p.c_out * out_h * out_w;
+        let dst_s1 = out_h * out_w;
+        let dst_s2 = out_w;
+
+        // TODO: Avoid making this copy if `inp` already has the appropriate layout.
+        let mut inp_cont = vec![T::zero(); p.b_size * p.c_in * p.i_h * p.i_w];
+        let cont_s0 = p.i_h * p.i_w * p.c_in;
+        let cont_s1 = p.i_w * p.c_in;
+        let cont_s2 = p.c_in;
+        for b_idx in 0..p.b_size {
+            for h_idx in 0..p.i_h {
+                for w_idx in 0..p.i_w {
+                    for c_idx in 0..p.c_in {
+                        let src_idx =
+                            b_idx * inp_s0 + c_idx * inp_s1 + h_idx * inp_s2 + w_idx * inp_s3;
+                        let dst_idx = b_idx * cont_s0 + h_idx * cont_s1 + w_idx * cont_s2 + c_idx;
+                        inp_cont[dst_idx] = inp[src_idx]
+                    }
+                }
+            }
+        }
+
+        let num_threads = crate::utils::get_num_threads();
+
+        // The kernel shape is [c_in, c_out, k_h, k_w].
+        for k_y in 0..p.k_h {
+            for k_x in 0..p.k_w {
+                crate::cpu::kernels::par_range(0, p.c_out, num_threads, |dst_c_idx| {
+                    let k_cont = (0..p.c_in)
+                        .map(|c_in_idx| {
+                            k[c_in_idx * k_s0 + dst_c_idx * k_s1 + k_y * k_s2 + k_x * k_s3]
+                        })
+                        .collect::<Vec<_>>();
+                    for b_idx in 0..p.b_size {
+                        for inp_y in 0..p.i_h {
+                            let out_y = inp_y * p.stride + k_y * p.dilation;
+                            if out_y < p.padding || out_y - p.padding >= out_h {
+                                continue;
+                            }
+                            let out_y = out_y - p.padding;
+                            for inp_x in 0..p.i_w {
+                                let out_x = inp_x * p.stride + k_x * p.dilation;
+                                if out_x < p.padding || out_x - p.padding >= out_w {
+                                    continue;
+                                }
+                                let out_x = out_x - p.padding;
                                 let inp_cont = &inp_cont
-                                    [b_idx * cont_s0 + src_h * cont_s1 + src_w * cont_s2..];
+                                    [b_idx * cont_s0 + inp_y * cont_s1 + inp_x * cont_s2..];
                                 assert!(inp_cont.len() >= p.c_in);
                                 assert!(k_cont.len() >= p.c_in);
                                 let mut d = T::zero();
                                 unsafe {
                                     T::vec_dot(inp_cont.as_ptr(), k_cont.as_ptr(), &mut d, p.c_in)
                                 }
+                                let dst_idx = b_idx * dst_s0
+                                    + dst_c_idx * dst_s1
+                                    + out_y * dst_s2
+                                    + out_x;
                                 let dst_p = dst.as_ptr();
                                 // Safety: dst_idx are uniques per dst_c_idx which is used to parallelise
                                 // the different tasks so no two threads can try to write at the same
@@ -1176,7 +1463,6 @@ impl<'a> Map2 for Conv2D<'a> {
                 });
             }
         }
-
         Ok(dst)
     }
 }
@@ -1220,17 +1506,13 @@ impl Map2 for MatMul {
         let rhs_cs = rhs_stride[rank - 1];
         let rhs_rs = rhs_stride[rank - 2];
 
-        let a_skip: usize = match lhs_stride[..rank - 2] {
-            [s1, stride] if s1 == stride * lhs_l.dims()[1] => stride,
-            [stride] => stride,
-            [] => m * k,
-            _ => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous lhs"))?,
+        let a_skip = match lhs_l.matmul_batch_stride() {
+            Some(stride) => stride,
+            None => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous lhs"))?,
         };
-        let b_skip: usize = match rhs_stride[..rank - 2] {
-            [s1, stride] if s1 == stride * rhs_l.dims()[1] => stride,
-            [stride] => stride,
-            [] => n * k,
-            _ => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous rhs"))?,
+        let b_skip = match rhs_l.matmul_batch_stride() {
+            Some(stride) => stride,
+            None => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous rhs"))?,
         };
         let c_skip: usize = m * n;
 
@@ -1291,19 +1573,14 @@ impl Map2 for MatMul {
 
         let lhs_stride = lhs_l.stride();
         let rhs_stride = rhs_l.stride();
-        let rank = lhs_stride.len();
 
-        let a_skip: usize = match lhs_stride[..rank - 2] {
-            [s1, stride] if s1 == stride * lhs_l.dims()[1] => stride,
-            [stride] => stride,
-            [] => m * k,
-            _ => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous lhs"))?,
+        let a_skip = match lhs_l.matmul_batch_stride() {
+            Some(stride) => stride,
+            None => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous lhs"))?,
         };
-        let b_skip: usize = match rhs_stride[..rank - 2] {
-            [s1, stride] if s1 == stride * rhs_l.dims()[1] => stride,
-            [stride] => stride,
-            [] => n * k,
-            _ => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous rhs"))?,
+        let b_skip = match rhs_l.matmul_batch_stride() {
+            Some(stride) => stride,
+            None => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous rhs"))?,
         };
         let c_skip: usize = m * n;
 
@@ -1394,19 +1671,14 @@ impl Map2 for MatMul {
 
         let lhs_stride = lhs_l.stride();
         let rhs_stride = rhs_l.stride();
-        let rank = lhs_stride.len();
 
-        let a_skip: usize = match lhs_stride[..rank - 2] {
-            [s1, stride] if s1 == stride * lhs_l.dims()[1] => stride,
-            [stride] => stride,
-            [] => m * k,
-            _ => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous lhs"))?,
+        let a_skip = match lhs_l.matmul_batch_stride() {
+            Some(stride) => stride,
+            None => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous lhs"))?,
         };
-        let b_skip: usize = match rhs_stride[..rank - 2] {
-            [s1, stride] if s1 == stride * rhs_l.dims()[1] => stride,
-            [stride] => stride,
-            [] => n * k,
-            _ => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous rhs"))?,
+        let b_skip = match rhs_l.matmul_batch_stride() {
+            Some(stride) => stride,
+            None => Err(self.striding_error(lhs_l, rhs_l, "non-contiguous rhs"))?,
         };
         let c_skip: usize = m * n;
 
@@ -1519,6 +1791,58 @@ fn elu<T: num_traits::Float>(v: T, alpha: T) -> T {
     }
 }
 
+struct SoftmaxLastDim {
+    log: bool,
+}
+
+impl SoftmaxLastDim {
+    fn f<T: WithDType + num_traits::Float>(&self, src: &[T], layout: &Layout) -> Result<Vec<T>> {
+        use rayon::prelude::*;
+
+        let src = match layout.contiguous_offsets() {
+            None => Err(Error::RequiresContiguous {
+                op: "softmax-last-dim",
+            }
+            .bt())?,
+            Some((o1, o2)) => &src[o1..o2],
+        };
+        let dim_m1 = match layout.dims().last() {
+            Some(&dim_m1) => dim_m1,
+            None => 1,
+        };
+        let mut dst = vec![T::zero(); src.len()];
+        if dim_m1 == 0 {
+            return Ok(dst);
+        }
+        // Each row is processed independently: subtract the max for numerical stability,
+        // exponentiate in place in the destination buffer and then normalize. The max and sum
+        // reductions use the simd `VecOps` kernels for f32, the exponentials are computed with
+        // the scalar `exp`.
+        src.par_chunks(dim_m1)
+            .zip(dst.par_chunks_mut(dim_m1))
+            .for_each(|(src, dst)| {
+                let mut max = T::neg_infinity();
+                unsafe { T::vec_reduce_max(src.as_ptr(), &mut max, dim_m1) };
+                for (s, d) in src.iter().zip(dst.iter_mut()) {
+                    *d = (*s - max).exp();
+                }
+                let mut sum_exp = T::zero();
+                unsafe { T::vec_reduce_sum(dst.as_ptr(), &mut sum_exp, dim_m1) };
+                if self.log {
+                    let log_sum_exp = sum_exp.ln();
+                    for (s, d) in src.iter().zip(dst.iter_mut()) {
+                        *d = *s - max - log_sum_exp
+                    }
+                } else {
+                    for d in dst.iter_mut() {
+                        *d /= sum_exp
+                    }
+                }
+            });
+        Ok(dst)
+    }
+}
+
 impl CpuStorage {
     pub fn as_slice<D: WithDType>(&self) -> Result<&[D]> {
         D::cpu_storage_as_slice(self)
@@ -1530,8 +1854,13 @@ impl BackendStorage for CpuStorage {
 
     fn dtype(&self) -> DType {
         match self {
+            Self::Bool(_) => DType::Bool,
             Self::U8(_) => DType::U8,
             Self::U32(_) => DType::U32,
+            Self::I8(_) => DType::I8,
+            Self::I16(_) => DType::I16,
+            Self::I32(_) => DType::I32,
+            Self::I64(_) => DType::I64,
             Self::BF16(_) => DType::BF16,
             Self::F16(_) => DType::F16,
             Self::F32(_) => DType::F32,
@@ -1542,6 +1871,25 @@ impl BackendStorage for CpuStorage {
     fn to_dtype(&self, layout: &Layout, dtype: DType) -> Result<Self> {
         // TODO: find a way around the quadratic number of cases below.
         match (self, dtype) {
+            (Self::Bool(storage), DType::Bool) => {
+                let data = unary_map(storage, layout, |v| v);
+                Ok(Self::Bool(data))
+            }
+            // Booleans are converted through u8, true maps to one and false to zero.
+            (Self::Bool(storage), dtype) => {
+                let data = unary_map(storage, layout, u8::from);
+                Self::U8(data).to_dtype(&Layout::contiguous(layout.shape()), dtype)
+            }
+            (Self::U8(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::U32(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::I8(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::I16(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::I32(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::I64(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::BF16(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::F16(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::F32(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
+            (Self::F64(storage), DType::Bool) => Ok(Self::Bool(nonzero(storage, layout))),
             (Self::U8(storage), DType::BF16) => {
                 let data = unary_map(storage, layout, |v| bf16::from_f32(v as f32));
                 Ok(Self::BF16(data))
@@ -1686,12 +2034,268 @@ impl BackendStorage for CpuStorage {
                 let data = unary_map(storage, layout, |v| v);
                 Ok(Self::F64(data))
             }
+            (Self::I8(storage), DType::U8) => {
+                let data = unary_map(storage, layout, |v| v as u8);
+                Ok(Self::U8(data))
+            }
+            (Self::I16(storage), DType::U8) => {
+                let data = unary_map(storage, layout, |v| v as u8);
+                Ok(Self::U8(data))
+            }
+            (Self::I32(storage), DType::U8) => {
+                let data = unary_map(storage, layout, |v| v as u8);
+                Ok(Self::U8(data))
+            }
+            (Self::I64(storage), DType::U8) => {
+                let data = unary_map(storage, layout, |v| v as u8);
+                Ok(Self::U8(data))
+            }
+            (Self::I8(storage), DType::U32) => {
+                let data = unary_map(storage, layout, |v| v as u32);
+                Ok(Self::U32(data))
+            }
+            (Self::I16(storage), DType::U32) => {
+                let data = unary_map(storage, layout, |v| v as u32);
+                Ok(Self::U32(data))
+            }
+            (Self::I32(storage), DType::U32) => {
+                let data = unary_map(storage, layout, |v| v as u32);
+                Ok(Self::U32(data))
+            }
+            (Self::I64(storage), DType::U32) => {
+                let data = unary_map(storage, layout, |v| v as u32);
+                Ok(Self::U32(data))
+            }
+            (Self::U8(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::U32(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::I8(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v);
+                Ok(Self::I8(data))
+            }
+            (Self::I16(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::I32(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::I64(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::BF16(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::F16(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::F32(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::F64(storage), DType::I8) => {
+                let data = unary_map(storage, layout, |v| v as i8);
+                Ok(Self::I8(data))
+            }
+            (Self::U8(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::U32(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::I8(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::I16(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v);
+                Ok(Self::I16(data))
+            }
+            (Self::I32(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::I64(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::BF16(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::F16(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::F32(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::F64(storage), DType::I16) => {
+                let data = unary_map(storage, layout, |v| v as i16);
+                Ok(Self::I16(data))
+            }
+            (Self::U8(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::U32(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::I8(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::I16(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::I32(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v);
+                Ok(Self::I32(data))
+            }
+            (Self::I64(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::BF16(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::F16(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::F32(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::F64(storage), DType::I32) => {
+                let data = unary_map(storage, layout, |v| v as i32);
+                Ok(Self::I32(data))
+            }
+            (Self::U8(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::U32(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::I8(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::I16(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::I32(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::I64(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v);
+                Ok(Self::I64(data))
+            }
+            (Self::BF16(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::F16(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v.to_f32() as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::F32(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::F64(storage), DType::I64) => {
+                let data = unary_map(storage, layout, |v| v as i64);
+                Ok(Self::I64(data))
+            }
+            (Self::I8(storage), DType::BF16) => {
+                let data = unary_map(storage, layout, |v| bf16::from_f32(v as f32));
+                Ok(Self::BF16(data))
+            }
+            (Self::I16(storage), DType::BF16) => {
+                let data = unary_map(storage, layout, |v| bf16::from_f32(v as f32));
+                Ok(Self::BF16(data))
+            }
+            (Self::I32(storage), DType::BF16) => {
+                let data = unary_map(storage, layout, |v| bf16::from_f32(v as f32));
+                Ok(Self::BF16(data))
+            }
+            (Self::I64(storage), DType::BF16) => {
+                let data = unary_map(storage, layout, |v| bf16::from_f32(v as f32));
+                Ok(Self::BF16(data))
+            }
+            (Self::I8(storage), DType::F16) => {
+                let data = unary_map(storage, layout, |v| f16::from_f32(v as f32));
+                Ok(Self::F16(data))
+            }
+            (Self::I16(storage), DType::F16) => {
+                let data = unary_map(storage, layout, |v| f16::from_f32(v as f32));
+                Ok(Self::F16(data))
+            }
+            (Self::I32(storage), DType::F16) => {
+                let data = unary_map(storage, layout, |v| f16::from_f32(v as f32));
+                Ok(Self::F16(data))
+            }
+            (Self::I64(storage), DType::F16) => {
+                let data = unary_map(storage, layout, |v| f16::from_f32(v as f32));
+                Ok(Self::F16(data))
+            }
+            (Self::I8(storage), DType::F32) => {
+                let data = unary_map(storage, layout, |v| v as f32);
+                Ok(Self::F32(data))
+            }
+            (Self::I16(storage), DType::F32) => {
+                let data = unary_map(storage, layout, |v| v as f32);
+                Ok(Self::F32(data))
+            }
+            (Self::I32(storage), DType::F32) => {
+                let data = unary_map(storage, layout, |v| v as f32);
+                Ok(Self::F32(data))
+            }
+            (Self::I64(storage), DType::F32) => {
+                let data = unary_map(storage, layout, |v| v as f32);
+                Ok(Self::F32(data))
+            }
+            (Self::I8(storage), DType::F64) => {
+                let data = unary_map(storage, layout, |v| v as f64);
+                Ok(Self::F64(data))
+            }
+            (Self::I16(storage), DType::F64) => {
+                let data = unary_map(storage, layout, |v| v as f64);
+                Ok(Self::F64(data))
+            }
+            (Self::I32(storage), DType::F64) => {
+                let data = unary_map(storage, layout, |v| v as f64);
+                Ok(Self::F64(data))
+            }
+            (Self::I64(storage), DType::F64) => {
+                let data = unary_map(storage, layout, |v| v as f64);
+                Ok(Self::F64(data))
+            }
         }
     }
 
     fn reduce_op(&self, op: ReduceOp, layout: &Layout, reduce_dims: &[usize]) -> Result<Self> {
         match op {
-            ReduceOp::Sum => {
+            ReduceOp::Sum | ReduceOp::Prod => {
                 let src_dims = layout.dims();
                 let mut dst_dims = src_dims.to_vec();
                 for &dim in reduce_dims.iter() {
@@ -1706,24 +2310,33 @@ impl BackendStorage for CpuStorage {
                     .iter()
                     .map(|&d| (src_dims[d], src_dims[d + 1..].iter().product::<usize>()))
                     .collect();
-                ReduceSum {
+                let reduce_sum = ReduceSum {
                     dst_shape: &dst_shape,
                     reduce_dims: &reduce_dims,
                     reduce_dims_and_stride,
+                };
+                if op == ReduceOp::Prod {
+                    ReduceProd(reduce_sum).map(self, layout)
+                } else {
+                    reduce_sum.map(self, layout)
                 }
-                    .map(self, layout)
+            }
+            ReduceOp::Min | ReduceOp::Max if reduce_dims.len() > 1 => {
+                // Reduce over one dimension at a time, the intermediate results are contiguous.
+                let mut dims = layout.dims().to_vec();
+                let mut res = self.reduce_op(op, layout, &reduce_dims[..1])?;
+                dims[reduce_dims[0]] = 1;
+                for &dim in reduce_dims[1..].iter() {
+                    res = res.reduce_op(op, &Layout::contiguous(dims.clone()), &[dim])?;
+                    dims[dim] = 1;
+                }
+                Ok(res)
             }
             ReduceOp::Min | ReduceOp::ArgMin | ReduceOp::Max | ReduceOp::ArgMax => {
                 let reduce_dim_index = match reduce_dims {
                     [reduce_dim_index] => *reduce_dim_index,
                     _ => {
-                        let op = match op {
-                            ReduceOp::Min => "min",
-                            ReduceOp::ArgMin => "argmin",
-                            ReduceOp::Max => "max",
-                            ReduceOp::ArgMax => "argmax",
-                            _ => unreachable!(),
-                        };
+                        let op = op.name();
                         let dims = reduce_dims.to_vec();
                         Err(Error::OnlySingleDimension { op, dims })?
                     }
@@ -1745,6 +2358,15 @@ impl BackendStorage for CpuStorage {
         }
     }
 
+    fn cumulative(&self, op: ReduceOp, layout: &Layout, dim: usize) -> Result<Self> {
+        let prod = match op {
+            ReduceOp::Sum => false,
+            ReduceOp::Prod => true,
+            _ => crate::bail!("cumulative op not supported for {}", op.name()),
+        };
+        Cumulative { dim, prod }.map(self, layout)
+    }
+
     fn cmp(&self, op: CmpOp, rhs: &Self, lhs_l: &Layout, rhs_l: &Layout) -> Result<Self> {
         Cmp(op).map(self, lhs_l, rhs, rhs_l)
     }
@@ -1794,13 +2416,71 @@ impl BackendStorage for CpuStorage {
                 let data = unary_map(storage, layout, |v| elu(v, alpha));
                 Ok(Self::F64(data))
             }
+            Self::Bool(_) => Err(Error::UnsupportedDTypeForOp(DType::Bool, "elu").bt()),
             Self::U8(_) => Err(Error::UnsupportedDTypeForOp(DType::U8, "elu").bt()),
             Self::U32(_) => Err(Error::UnsupportedDTypeForOp(DType::U32, "elu").bt()),
+            Self::I8(_) => Err(Error::UnsupportedDTypeForOp(DType::I8, "elu").bt()),
+            Self::I16(_) => Err(Error::UnsupportedDTypeForOp(DType::I16, "elu").bt()),
+            Self::I32(_) => Err(Error::UnsupportedDTypeForOp(DType::I32, "elu").bt()),
+            Self::I64(_) => Err(Error::UnsupportedDTypeForOp(DType::I64, "elu").bt()),
+        }
+    }
+
+    fn powf(&self, layout: &Layout, e: f64) -> Result<Self> {
+        match self {
+            Self::BF16(storage) => {
+                let e = bf16::from_f64(e);
+                let data = unary_map(storage, layout, |v| num_traits::Float::powf(v, e));
+                Ok(Self::BF16(data))
+            }
+            Self::F16(storage) => {
+                let e = f16::from_f64(e);
+                let data = unary_map(storage, layout, |v| num_traits::Float::powf(v, e));
+                Ok(Self::F16(data))
+            }
+            Self::F32(storage) => {
+                let e = e as f32;
+                let data = unary_map(storage, layout, |v| v.powf(e));
+                Ok(Self::F32(data))
+            }
+            Self::F64(storage) => {
+                let data = unary_map(storage, layout, |v| v.powf(e));
+                Ok(Self::F64(data))
+            }
+            Self::Bool(_) => Err(Error::UnsupportedDTypeForOp(DType::Bool, "powf").bt()),
+            Self::U8(_) => Err(Error::UnsupportedDTypeForOp(DType::U8, "powf").bt()),
+            Self::U32(_) => Err(Error::UnsupportedDTypeForOp(DType::U32, "powf").bt()),
+            Self::I8(_) => Err(Error::UnsupportedDTypeForOp(DType::I8, "powf").bt()),
+            Self::I16(_) => Err(Error::UnsupportedDTypeForOp(DType::I16, "powf").bt()),
+            Self::I32(_) => Err(Error::UnsupportedDTypeForOp(DType::I32, "powf").bt()),
+            Self::I64(_) => Err(Error::UnsupportedDTypeForOp(DType::I64, "powf").bt()),
+        }
+    }
+
+    fn clamp(&self, layout: &Layout, min: f64, max: f64) -> Result<Self> {
+        Clamp(min, max).map(self, layout)
+    }
+
+    fn softmax_last_dim(&self, layout: &Layout, log: bool) -> Result<Self> {
+        let op = SoftmaxLastDim { log };
+        match self {
+            Self::BF16(storage) => Ok(Self::BF16(op.f(storage, layout)?)),
+            Self::F16(storage) => Ok(Self::F16(op.f(storage, layout)?)),
+            Self::F32(storage) => Ok(Self::F32(op.f(storage, layout)?)),
+            Self::F64(storage) => Ok(Self::F64(op.f(storage, layout)?)),
+            Self::Bool(_) => Err(Error::UnsupportedDTypeForOp(DType::Bool, "softmax").bt()),
+            Self::U8(_) => Err(Error::UnsupportedDTypeForOp(DType::U8, "softmax").bt()),
+            Self::U32(_) => Err(Error::UnsupportedDTypeForOp(DType::U32, "softmax").bt()),
+            Self::I8(_) => Err(Error::UnsupportedDTypeForOp(DType::I8, "softmax").bt()),
+            Self::I16(_) => Err(Error::UnsupportedDTypeForOp(DType::I16, "softmax").bt()),
+            Self::I32(_) => Err(Error::UnsupportedDTypeForOp(DType::I32, "softmax").bt()),
+            Self::I64(_) => Err(Error::UnsupportedDTypeForOp(DType::I64, "softmax").bt()),
         }
     }
 
     fn unary_impl<B: UnaryOpT>(&self, layout: &Layout) -> Result<Self> {
         match self {
+            Self::Bool(_) => Err(Error::UnsupportedDTypeForOp(DType::Bool, B::NAME).bt()),
             Self::BF16(storage) => {
                 if B::BF16_VEC {
                     let data = unary_map_vec(storage, layout, B::bf16, B::bf16_vec);
@@ -1845,6 +2525,22 @@ impl BackendStorage for CpuStorage {
                 let data = unary_map(storage, layout, B::u32);
                 Ok(Self::U32(data))
             }
+            Self::I8(storage) => {
+                let data = unary_map(storage, layout, B::i8);
+                Ok(Self::I8(data))
+            }
+            Self::I16(storage) => {
+                let data = unary_map(storage, layout, B::i16);
+                Ok(Self::I16(data))
+            }
+            Self::I32(storage) => {
+                let data = unary_map(storage, layout, B::i32);
+                Ok(Self::I32(data))
+            }
+            Self::I64(storage) => {
+                let data = unary_map(storage, layout, B::i64);
+                Ok(Self::I64(data))
+            }
         }
     }
 
@@ -1895,6 +2591,38 @@ impl BackendStorage for CpuStorage {
                 };
                 Ok(Self::U32(data))
             }
+            (Self::I8(lhs), Self::I8(rhs)) => {
+                let data = if B::I8_VEC {
+                    binary_map_vec(lhs_l, rhs_l, lhs, rhs, B::i8, B::i8_vec)
+                } else {
+                    binary_map(lhs_l, rhs_l, lhs, rhs, B::i8)
+                };
+                Ok(Self::I8(data))
+            }
+            (Self::I16(lhs), Self::I16(rhs)) => {
+                let data = if B::I16_VEC {
+                    binary_map_vec(lhs_l, rhs_l, lhs, rhs, B::i16, B::i16_vec)
+                } else {
+                    binary_map(lhs_l, rhs_l, lhs, rhs, B::i16)
+                };
+                Ok(Self::I16(data))
+            }
+            (Self::I32(lhs), Self::I32(rhs)) => {
+                let data = if B::I32_VEC {
+                    binary_map_vec(lhs_l, rhs_l, lhs, rhs, B::i32, B::i32_vec)
+                } else {
+                    binary_map(lhs_l, rhs_l, lhs, rhs, B::i32)
+                };
+                Ok(Self::I32(data))
+            }
+            (Self::I64(lhs), Self::I64(rhs)) => {
+                let data = if B::I64_VEC {
+                    binary_map_vec(lhs_l, rhs_l, lhs, rhs, B::i64, B::i64_vec)
+                } else {
+                    binary_map(lhs_l, rhs_l, lhs, rhs, B::i64)
+                };
+                Ok(Self::I64(data))
+            }
             (Self::U8(lhs), Self::U8(rhs)) => {
                 let data = if B::U8_VEC {
                     binary_map_vec(lhs_l, rhs_l, lhs, rhs, B::u8, B::u8_vec)
@@ -1903,6 +2631,9 @@ impl BackendStorage for CpuStorage {
                 };
                 Ok(Self::U8(data))
             }
+            (Self::Bool(_), Self::Bool(_)) => {
+                Err(Error::UnsupportedDTypeForOp(DType::Bool, B::NAME).bt())
+            }
             _ => {
                 // This should be covered by the dtype check above.
                 Err(Error::DTypeMismatchBinaryOp {
@@ -1917,8 +2648,13 @@ impl BackendStorage for CpuStorage {
 
     fn copy_strided_src(&self, dst: &mut Self, dst_offset: usize, src_l: &Layout) -> Result<()> {
         match (self, dst) {
+            (Self::Bool(src), Self::Bool(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
             (Self::U8(src), Self::U8(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
             (Self::U32(src), Self::U32(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
+            (Self::I8(src), Self::I8(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
+            (Self::I16(src), Self::I16(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
+            (Self::I32(src), Self::I32(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
+            (Self::I64(src), Self::I64(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
             (Self::BF16(src), Self::BF16(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
             (Self::F16(src), Self::F16(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
             (Self::F32(src), Self::F32(dst)) => copy_strided_src_(src, dst, dst_offset, src_l),
@@ -1945,8 +2681,16 @@ impl BackendStorage for CpuStorage {
         f_l: &Layout,
     ) -> Result<Self> {
         match self {
+            Self::Bool(pred) => {
+                let pred = pred.iter().map(|&p| u8::from(p)).collect::<Vec<_>>();
+                WCond(&pred, layout).map(t, t_l, f, f_l)
+            }
             Self::U8(pred) => WCond(pred, layout).map(t, t_l, f, f_l),
             Self::U32(pred) => WCond(pred, layout).map(t, t_l, f, f_l),
+            Self::I8(pred) => WCond(pred, layout).map(t, t_l, f, f_l),
+            Self::I16(pred) => WCond(pred, layout).map(t, t_l, f, f_l),
+            Self::I32(pred) => WCond(pred, layout).map(t, t_l, f, f_l),
+            Self::I64(pred) => WCond(pred, layout).map(t, t_l, f, f_l),
             _ => Err(Error::UnsupportedDTypeForOp(self.dtype(), "where-cond")),
         }
     }
@@ -1971,10 +2715,34 @@ impl BackendStorage for CpuStorage {
         Conv2D(params).map(self, l, kernel, kernel_l)
     }
 
+    fn conv_transpose1d(
+        &self,
+        l: &Layout,
+        kernel: &Self,
+        kernel_l: &Layout,
+        params: &crate::conv::ParamsConvTranspose1D,
+    ) -> Result<Self> {
+        ConvTranspose1D(params).map(self, l, kernel, kernel_l)
+    }
+
+    fn conv_transpose2d(
+        &self,
+        l: &Layout,
+        kernel: &Self,
+        kernel_l: &Layout,
+        params: &crate::conv::ParamsConvTranspose2D,
+    ) -> Result<Self> {
+        ConvTranspose2D(params).map(self, l, kernel, kernel_l)
+    }
+
     fn index_select(&self, ids: &Self, l: &Layout, ids_l: &Layout, dim: usize) -> Result<Self> {
         match ids {
             Self::U8(ids) => IndexSelect { ids, ids_l, dim }.map(self, l),
             Self::U32(ids) => IndexSelect { ids, ids_l, dim }.map(self, l),
+            Self::I8(ids) => IndexSelect { ids, ids_l, dim }.map(self, l),
+            Self::I16(ids) => IndexSelect { ids, ids_l, dim }.map(self, l),
+            Self::I32(ids) => IndexSelect { ids, ids_l, dim }.map(self, l),
+            Self::I64(ids) => IndexSelect { ids, ids_l, dim }.map(self, l),
             _ => Err(Error::UnsupportedDTypeForOp(self.dtype(), "index-select")),
         }
     }
@@ -1983,6 +2751,10 @@ impl BackendStorage for CpuStorage {
         match ids {
             Self::U8(ids) => Gather { ids, ids_l, dim }.map(self, l),
             Self::U32(ids) => Gather { ids, ids_l, dim }.map(self, l),
+            Self::I8(ids) => Gather { ids, ids_l, dim }.map(self, l),
+            Self::I16(ids) => Gather { ids, ids_l, dim }.map(self, l),
+            Self::I32(ids) => Gather { ids, ids_l, dim }.map(self, l),
+            Self::I64(ids) => Gather { ids, ids_l, dim }.map(self, l),
             _ => Err(Error::UnsupportedDTypeForOp(self.dtype(), "gather")),
         }
     }
@@ -1999,6 +2771,10 @@ impl BackendStorage for CpuStorage {
         match ids {
             Self::U8(ids) => ScatterAdd { ids, ids_l, dim }.map(self, l, src, src_l),
             Self::U32(ids) => ScatterAdd { ids, ids_l, dim }.map(self, l, src, src_l),
+            Self::I8(ids) => ScatterAdd { ids, ids_l, dim }.map(self, l, src, src_l),
+            Self::I16(ids) => ScatterAdd { ids, ids_l, dim }.map(self, l, src, src_l),
+            Self::I32(ids) => ScatterAdd { ids, ids_l, dim }.map(self, l, src, src_l),
+            Self::I64(ids) => ScatterAdd { ids, ids_l, dim }.map(self, l, src, src_l),
             _ => Err(Error::UnsupportedDTypeForOp(self.dtype(), "scatter-add")),
         }
     }
@@ -2027,6 +2803,34 @@ impl BackendStorage for CpuStorage {
                 };
                 IndexAdd { ids, dim }.map(self, l, src, src_l)
             }
+            Self::I8(ids) => {
+                let ids = match ids_l.contiguous_offsets() {
+                    Some((a, b)) => &ids[a..b],
+                    None => Err(Error::RequiresContiguous { op: "index-add" })?,
+                };
+                IndexAdd { ids, dim }.map(self, l, src, src_l)
+            }
+            Self::I16(ids) => {
+                let ids = match ids_l.contiguous_offsets() {
+                    Some((a, b)) => &ids[a..b],
+                    None => Err(Error::RequiresContiguous { op: "index-add" })?,
+                };
+                IndexAdd { ids, dim }.map(self, l, src, src_l)
+            }
+            Self::I32(ids) => {
+                let ids = match ids_l.contiguous_offsets() {
+                    Some((a, b)) => &ids[a..b],
+                    None => Err(Error::RequiresContiguous { op: "index-add" })?,
+                };
+                IndexAdd { ids, dim }.map(self, l, src, src_l)
+            }
+            Self::I64(ids) => {
+                let ids = match ids_l.contiguous_offsets() {
+                    Some((a, b)) => &ids[a..b],
+                    None => Err(Error::RequiresContiguous { op: "index-add" })?,
+                };
+                IndexAdd { ids, dim }.map(self, l, src, src_l)
+            }
             _ => Err(Error::UnsupportedDTypeForOp(self.dtype(), "index-add")),
         }
     }
@@ -2079,7 +2883,13 @@ impl BackendDevice for CpuDevice {
         let elem_count = shape.elem_count();
         let mut rng = rand::thread_rng();
         match dtype {
-            DType::U8 | DType::U32 => Err(Error::UnsupportedDTypeForOp(dtype, "rand_uniform").bt()),
+            DType::Bool
+            | DType::U8
+            | DType::U32
+            | DType::I8
+            | DType::I16
+            | DType::I32
+            | DType::I64 => Err(Error::UnsupportedDTypeForOp(dtype, "rand_uniform").bt()),
             DType::BF16 => {
                 let mut data = Vec::with_capacity(elem_count);
                 let uniform =
@@ -2123,7 +2933,13 @@ impl BackendDevice for CpuDevice {
         let elem_count = shape.elem_count();
         let mut rng = rand::thread_rng();
         match dtype {
-            DType::U8 | DType::U32 => Err(Error::UnsupportedDTypeForOp(dtype, "rand_normal").bt()),
+            DType::Bool
+            | DType::U8
+            | DType::U32
+            | DType::I8
+            | DType::I16
+            | DType::I32
+            | DType::I64 => Err(Error::UnsupportedDTypeForOp(dtype, "rand_normal").bt()),
             DType::BF16 => {
                 let mut data = Vec::with_capacity(elem_count);
                 let normal = rand_distr::Normal::new(bf16::from_f64(mean), bf16::from_f64(std))
@@ -2165,8 +2981,13 @@ impl BackendDevice for CpuDevice {
     fn ones_impl(&self, shape: &Shape, dtype: DType) -> Result<CpuStorage> {
         let elem_count = shape.elem_count();
         let storage = match dtype {
+            DType::Bool => CpuStorage::Bool(vec![true; elem_count]),
             DType::U8 => CpuStorage::U8(vec![1u8; elem_count]),
             DType::U32 => CpuStorage::U32(vec![1u32; elem_count]),
+            DType::I8 => CpuStorage::I8(vec![1i8; elem_count]),
+            DType::I16 => CpuStorage::I16(vec![1i16; elem_count]),
+            DType::I32 => CpuStorage::I32(vec![1i32; elem_count]),
+            DType::I64 => CpuStorage::I64(vec![1i64; elem_count]),
             DType::BF16 => CpuStorage::BF16(vec![bf16::ONE; elem_count]),
             DType::F16 => CpuStorage::F16(vec![f16::ONE; elem_count]),
             DType::F32 => CpuStorage::F32(vec![1f32; elem_count]),
@@ -2178,8 +2999,13 @@ impl BackendDevice for CpuDevice {
     fn zeros_impl(&self, shape: &Shape, dtype: DType) -> Result<CpuStorage> {
         let elem_count = shape.elem_count();
         let storage = match dtype {
+            DType::Bool => CpuStorage::Bool(vec![false; elem_count]),
             DType::U8 => CpuStorage::U8(vec![0u8; elem_count]),
             DType::U32 => CpuStorage::U32(vec![0u32; elem_count]),
+            DType::I8 => CpuStorage::I8(vec![0i8; elem_count]),
+            DType::I16 => CpuStorage::I16(vec![0i16; elem_count]),
+            DType::I32 => CpuStorage::I32(vec![0i32; elem_count]),
+            DType::I64 => CpuStorage::I64(vec![0i64; elem_count]),
             DType::BF16 => CpuStorage::BF16(vec![bf16::ZERO; elem_count]),
             DType::F16 => CpuStorage::F16(vec![f16::ZERO; elem_count]),
             DType::F32 => CpuStorage::F32(vec![0f32; elem_count]),
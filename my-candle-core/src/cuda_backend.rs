@@ -148,6 +148,9 @@ impl CudaDevice {
                 unsafe { func.launch(cfg, params) }.w()?;
                 CudaStorageSlice::U32(data)
             }
+            DType::Bool | DType::I8 | DType::I16 | DType::I32 | DType::I64 => {
+                Err(CudaError::UnsupportedDtype { dtype, op: "fill" }).w()?
+            }
             DType::BF16 => {
                 // SAFETY: Set later by running the fill kernel.
                 let data = unsafe { self.alloc::<bf16>(elem_count) }.w()?;
@@ -245,6 +248,9 @@ impl BackendDevice for CudaDevice {
                 let data = self.alloc_zeros::<u32>(elem_count).w()?;
                 CudaStorageSlice::U32(data)
             }
+            DType::Bool | DType::I8 | DType::I16 | DType::I32 | DType::I64 => {
+                Err(CudaError::UnsupportedDtype { dtype, op: "zeros" }).w()?
+            }
             DType::BF16 => {
                 let data = self.alloc_zeros::<bf16>(elem_count).w()?;
                 CudaStorageSlice::BF16(data)
@@ -274,7 +280,15 @@ impl BackendDevice for CudaDevice {
         let slice = match dtype {
             // TODO: Add support for F16 and BF16 though this is likely to require some upstream
             // cudarc changes.
-            DType::U8 | DType::U32 | DType::F16 | DType::BF16 => Err(CudaError::UnsupportedDtype {
+            DType::Bool
+            | DType::U8
+            | DType::U32
+            | DType::I8
+            | DType::I16
+            | DType::I32
+            | DType::I64
+            | DType::F16
+            | DType::BF16 => Err(CudaError::UnsupportedDtype {
                 dtype,
                 op: "rand_uniform",
             })
@@ -306,7 +320,15 @@ impl BackendDevice for CudaDevice {
         let elem_count = shape.elem_count();
         let curand = self.curand.lock().unwrap();
         let slice = match dtype {
-            DType::U8 | DType::U32 | DType::F16 | DType::BF16 => Err(CudaError::UnsupportedDtype {
+            DType::Bool
+            | DType::U8
+            | DType::U32
+            | DType::I8
+            | DType::I16
+            | DType::I32
+            | DType::I64
+            | DType::F16
+            | DType::BF16 => Err(CudaError::UnsupportedDtype {
                 dtype,
                 op: "rand_normal",
             })
@@ -345,6 +367,17 @@ impl BackendDevice for CudaDevice {
                 let data = self.htod_sync_copy(storage).w()?;
                 CudaStorageSlice::U32(data)
             }
+            CpuStorage::Bool(_)
+            | CpuStorage::I8(_)
+            | CpuStorage::I16(_)
+            | CpuStorage::I32(_)
+            | CpuStorage::I64(_) => {
+                Err(CudaError::UnsupportedDtype {
+                    dtype: storage.dtype(),
+                    op: "storage_from_cpu_storage",
+                })
+                .w()?
+            }
             CpuStorage::BF16(storage) => {
                 let data = self.htod_sync_copy(storage).w()?;
                 CudaStorageSlice::BF16(data)
@@ -575,6 +608,62 @@ impl Map1 for Elu {
     }
 }
 
+struct Powf(f64);
+impl Map1 for Powf {
+    fn f<T: DeviceRepr + WithDType>(
+        &self,
+        src: &CudaSlice<T>,
+        dev: &CudaDevice,
+        layout: &Layout,
+    ) -> Result<CudaSlice<T>> {
+        let shape = layout.shape();
+        let dims = shape.dims();
+        let el = shape.elem_count();
+        let cfg = LaunchConfig::for_num_elems(el as u32);
+        let ds = dev.htod_copy([dims, layout.stride()].concat()).w()?;
+        let src = &src.slice(layout.start_offset()..);
+        let func = dev.get_or_load_func(&kernel_name::<T>("upowf"), kernels::UNARY)?;
+        // SAFETY: Set later by running the kernel.
+        let out = unsafe { dev.alloc::<T>(el) }.w()?;
+        let params = (el, dims.len(), &ds, T::from_f64(self.0), src, &out);
+        // SAFETY: ffi.
+        unsafe { func.launch(cfg, params) }.w()?;
+        Ok(out)
+    }
+}
+
+struct Clamp(f64, f64);
+impl Map1 for Clamp {
+    fn f<T: DeviceRepr + WithDType>(
+        &self,
+        src: &CudaSlice<T>,
+        dev: &CudaDevice,
+        layout: &Layout,
+    ) -> Result<CudaSlice<T>> {
+        let shape = layout.shape();
+        let dims = shape.dims();
+        let el = shape.elem_count();
+        let cfg = LaunchConfig::for_num_elems(el as u32);
+        let ds = dev.htod_copy([dims, layout.stride()].concat()).w()?;
+        let src = &src.slice(layout.start_offset()..);
+        let func = dev.get_or_load_func(&kernel_name::<T>("uclamp"), kernels::UNARY)?;
+        // SAFETY: Set later by running the kernel.
+        let out = unsafe { dev.alloc::<T>(el) }.w()?;
+        let params = (
+            el,
+            dims.len(),
+            &ds,
+            T::from_f64(self.0),
+            T::from_f64(self.1),
+            src,
+            &out,
+        );
+        // SAFETY: ffi.
+        unsafe { func.launch(cfg, params) }.w()?;
+        Ok(out)
+    }
+}
+
 struct Sum<'a>(&'a [usize]);
 impl<'a> Map1 for Sum<'a> {
     fn f<T: DeviceRepr + WithDType + ValidAsZeroBits>(
@@ -658,6 +747,7 @@ impl<'a> Map1Any for FastReduce<'a> {
         let src = &src.slice(layout.start_offset()..);
         let (name, check_empty, return_index) = match self.1 {
             ReduceOp::Sum => ("fast_sum", false, false),
+            ReduceOp::Prod => ("fast_prod", false, false),
             ReduceOp::Min => ("fast_min", true, false),
             ReduceOp::Max => ("fast_max", true, false),
             ReduceOp::ArgMin => ("fast_argmin", true, true),
@@ -1305,21 +1395,19 @@ fn gemm_config<T>(
         transb,
     };
 
-    let stride_b: usize = match lhs_stride[..lhs_stride.len() - 2] {
-        [s1, stride] if s1 == stride * lhs_l.dims()[1] => stride,
-        [stride] => stride,
-        [] => m * k,
-        _ => Err(CudaError::MatMulNonContiguous {
+    // Broadcasted batch dimensions have a stride of 0 and are not materialized.
+    let stride_b: usize = match lhs_l.matmul_batch_stride() {
+        Some(stride) => stride,
+        None => Err(CudaError::MatMulNonContiguous {
             lhs_stride: lhs_stride.to_vec(),
             rhs_stride: rhs_stride.to_vec(),
             mnk: (m, n, k),
         })?,
     };
-    let stride_a: usize = match rhs_stride[..rhs_stride.len() - 2] {
-        [s1, stride] if s1 == stride * rhs_l.dims()[1] => stride,
-        [stride] => stride,
-        [] => n * k,
-        _ => Err(CudaError::MatMulNonContiguous {
+    // Broadcasted batch dimensions have a stride of 0 and are not materialized.
+    let stride_a: usize = match rhs_l.matmul_batch_stride() {
+        Some(stride) => stride,
+        None => Err(CudaError::MatMulNonContiguous {
             lhs_stride: lhs_stride.to_vec(),
             rhs_stride: rhs_stride.to_vec(),
             mnk: (m, n, k),
@@ -1395,6 +1483,9 @@ impl BackendStorage for CudaStorage {
                 unsafe { func.launch(cfg, params) }.w()?;
                 CudaStorageSlice::U32(out)
             }
+            DType::Bool | DType::I8 | DType::I16 | DType::I32 | DType::I64 => {
+                Err(CudaError::UnsupportedDtype { dtype, op: "to_dtype" }).w()?
+            }
             DType::BF16 => {
                 let out = unsafe { dev.alloc::<bf16>(el) }.w()?;
                 let params = (el, dims.len(), &ds, *inp, &out);
@@ -1438,12 +1529,36 @@ impl BackendStorage for CudaStorage {
         Ok(Self { slice, device })
     }
 
+    fn powf(&self, layout: &Layout, e: f64) -> Result<Self> {
+        let device = self.device().clone();
+        let slice = Powf(e).map(&self.slice, &device, layout)?;
+        Ok(Self { slice, device })
+    }
+
+    fn clamp(&self, layout: &Layout, min: f64, max: f64) -> Result<Self> {
+        let device = self.device().clone();
+        let slice = Clamp(min, max).map(&self.slice, &device, layout)?;
+        Ok(Self { slice, device })
+    }
+
+    fn softmax_last_dim(&self, layout: &Layout, log: bool) -> Result<Self> {
+        // TODO: add a dedicated kernel, for now this round-trips through the cpu backend.
+        let cpu_storage = self.to_cpu_storage()?.softmax_last_dim(layout, log)?;
+        self.device().storage_from_cpu_storage(&cpu_storage)
+    }
+
     fn reduce_op(&self, op: ReduceOp, layout: &Layout, sum_dims: &[usize]) -> Result<Self> {
         let device = self.device().clone();
         let slice = FastReduce(sum_dims, op).map(&self.slice, &device, layout)?;
         Ok(Self { slice, device })
     }
 
+    fn cumulative(&self, op: ReduceOp, layout: &Layout, dim: usize) -> Result<Self> {
+        // TODO: add a dedicated kernel, for now this round-trips through the cpu backend.
+        let cpu_storage = self.to_cpu_storage()?.cumulative(op, layout, dim)?;
+        self.device().storage_from_cpu_storage(&cpu_storage)
+    }
+
     fn cmp(&self, op: CmpOp, rhs: &Self, lhs_l: &Layout, rhs_l: &Layout) -> Result<Self> {
         let device = self.device().clone();
         let slice = Cmp(op).map(&self.slice, lhs_l, &rhs.slice, rhs_l, &device)?;
@@ -1522,6 +1637,11 @@ impl BackendStorage for CudaStorage {
         kernel_l: &Layout,
         params: &crate::conv::ParamsConv1D,
     ) -> Result<Self> {
+        if params.dilation != 1 || params.groups != 1 {
+            Err(CudaError::InternalError(
+                "conv1d with dilation or groups is not supported on cuda",
+            ))?
+        }
         let device = self.device().clone();
         let slice = Conv1D(params).map(&self.slice, l, &kernel.slice, kernel_l, &device)?;
         Ok(Self { slice, device })
@@ -1535,6 +1655,11 @@ impl BackendStorage for CudaStorage {
         kernel_l: &Layout,
         params: &crate::conv::ParamsConv2D,
     ) -> Result<Self> {
+        if params.dilation != 1 || params.groups != 1 {
+            Err(CudaError::InternalError(
+                "conv2d with dilation or groups is not supported on cuda",
+            ))?
+        }
         let device = self.device().clone();
         let slice = Conv2D(params).map(&self.slice, l, &kernel.slice, kernel_l, &device)?;
         Ok(Self { slice, device })
@@ -1548,6 +1673,11 @@ impl BackendStorage for CudaStorage {
         kernel_l: &Layout,
         params: &crate::conv::ParamsConv2D,
     ) -> Result<Self> {
+        if params.dilation != 1 || params.groups != 1 {
+            Err(CudaError::InternalError(
+                "conv2d with dilation or groups is not supported on cuda",
+            ))?
+        }
         let device = self.device().clone();
         if !kernel_l.is_contiguous() {
             let slice = Conv2D(params).map(&self.slice, inp_l, &kernel.slice, kernel_l, &device)?;
@@ -1603,6 +1733,26 @@ impl BackendStorage for CudaStorage {
         Ok(Self { slice, device })
     }
 
+    fn conv_transpose1d(
+        &self,
+        _: &Layout,
+        _: &Self,
+        _: &Layout,
+        _: &crate::conv::ParamsConvTranspose1D,
+    ) -> Result<Self> {
+        Err(CudaError::InternalError("conv_transpose1d is not supported on cuda"))?
+    }
+
+    fn conv_transpose2d(
+        &self,
+        _: &Layout,
+        _: &Self,
+        _: &Layout,
+        _: &crate::conv::ParamsConvTranspose2D,
+    ) -> Result<Self> {
+        Err(CudaError::InternalError("conv_transpose2d is not supported on cuda"))?
+    }
+
     fn avg_pool2d(&self, l: &Layout, k: (usize, usize), stride: (usize, usize)) -> Result<Self> {
         let device = self.device().clone();
         let slice = Pool2D {
@@ -12,15 +12,20 @@ impl Tensor {
             crate::Device::Cpu => "Cpu",
             crate::Device::Cuda(_) => "Cuda",
         };
+        // Booleans have no `WithDType` implementation, their values are read back as u8.
+        let values = match self.dtype() {
+            DType::Bool => self.to_dtype(DType::U8).map_err(|_| std::fmt::Error)?,
+            _ => self.clone(),
+        };
         write!(f, "{prefix}Tensor[")?;
         match self.dims() {
             [] => {
-                if let Ok(v) = self.to_scalar::<T>() {
+                if let Ok(v) = values.to_scalar::<T>() {
                     write!(f, "{v}")?
                 }
             }
             [s] if *s < 10 => {
-                if let Ok(vs) = self.to_vec1::<T>() {
+                if let Ok(vs) = values.to_vec1::<T>() {
                     for (i, v) in vs.iter().enumerate() {
                         if i > 0 {
                             write!(f, ", ")?;
@@ -46,8 +51,12 @@ impl Tensor {
 impl std::fmt::Debug for Tensor {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self.dtype() {
-            DType::U8 => self.fmt_dt::<u8>(f),
+            DType::Bool | DType::U8 => self.fmt_dt::<u8>(f),
             DType::U32 => self.fmt_dt::<u32>(f),
+            DType::I8 => self.fmt_dt::<i8>(f),
+            DType::I16 => self.fmt_dt::<i16>(f),
+            DType::I32 => self.fmt_dt::<i32>(f),
+            DType::I64 => self.fmt_dt::<i64>(f),
             DType::BF16 => self.fmt_dt::<bf16>(f),
             DType::F16 => self.fmt_dt::<f16>(f),
             DType::F32 => self.fmt_dt::<f32>(f),
@@ -418,6 +427,18 @@ impl std::fmt::Display for Tensor {
             self.clone()
         };
         match self.dtype() {
+            DType::Bool => {
+                // Booleans are displayed as zeros and ones.
+                let (t, to_display) =
+                    match (self.to_dtype(DType::U8), to_display.to_dtype(DType::U8)) {
+                        (Ok(t), Ok(to_display)) => (t, to_display),
+                        (Err(err), _) | (_, Err(err)) => return write!(f, "{err:?}"),
+                    };
+                let tf: IntFormatter<u8> = IntFormatter::new();
+                let max_w = tf.max_width(&to_display);
+                tf.fmt_tensor(&t, 1, max_w, summarize, &po, f)?;
+                writeln!(f)?;
+            }
             DType::U8 => {
                 let tf: IntFormatter<u8> = IntFormatter::new();
                 let max_w = tf.max_width(&to_display);
@@ -430,6 +451,30 @@ impl std::fmt::Display for Tensor {
                 tf.fmt_tensor(self, 1, max_w, summarize, &po, f)?;
                 writeln!(f)?;
             }
+            DType::I8 => {
+                let tf: IntFormatter<i8> = IntFormatter::new();
+                let max_w = tf.max_width(&to_display);
+                tf.fmt_tensor(self, 1, max_w, summarize, &po, f)?;
+                writeln!(f)?;
+            }
+            DType::I16 => {
+                let tf: IntFormatter<i16> = IntFormatter::new();
+                let max_w = tf.max_width(&to_display);
+                tf.fmt_tensor(self, 1, max_w, summarize, &po, f)?;
+                writeln!(f)?;
+            }
+            DType::I32 => {
+                let tf: IntFormatter<i32> = IntFormatter::new();
+                let max_w = tf.max_width(&to_display);
+                tf.fmt_tensor(self, 1, max_w, summarize, &po, f)?;
+                writeln!(f)?;
+            }
+            DType::I64 => {
+                let tf: IntFormatter<i64> = IntFormatter::new();
+                let max_w = tf.max_width(&to_display);
+                tf.fmt_tensor(self, 1, max_w, summarize, &po, f)?;
+                writeln!(f)?;
+            }
             DType::BF16 => {
                 if let Ok(tf) = FloatFormatter::<bf16>::new(&to_display, &po) {
                     let max_w = tf.max_width(&to_display);
@@ -7,8 +7,13 @@ use crate::cpu_backend::CpuStorage;
 use Error::{Error,Result};
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DType {
+    Bool,
     U8,
     U32,
+    I8,
+    I16,
+    I32,
+    I64,
     BF16,
     F16,
     F32,
@@ -23,8 +28,13 @@ impl std::str::FromStr for DType {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s {
             // core::result::Result::Ok
+            "bool" => Ok(Self::Bool),
             "u8" => Ok(Self::U8),
             "u32" => Ok(Self::U32),
+            "i8" => Ok(Self::I8),
+            "i16" => Ok(Self::I16),
+            "i32" => Ok(Self::I32),
+            "i64" => Ok(Self::I64),
             "bf16" => Ok(Self::BF16),
             "f16" => Ok(Self::F16),
             "f32" => Ok(Self::F32),
@@ -38,8 +48,13 @@ impl std::str::FromStr for DType {
 impl DType {
     pub fn as_str(&self) -> &'static str {
         match self {
+            Self::Bool => "bool",
             Self::U8 => "u8",
             Self::U32 => "u32",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
             Self::BF16 => "bf16",
             Self::F16 => "f16",
             Self::F32 => "f32",
@@ -47,10 +62,29 @@ impl DType {
         }
     }
 
+    pub fn is_int(&self) -> bool {
+        match self {
+            Self::U8 | Self::U32 | Self::I8 | Self::I16 | Self::I32 | Self::I64 => true,
+            Self::Bool | Self::BF16 | Self::F16 | Self::F32 | Self::F64 => false,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        match self {
+            Self::BF16 | Self::F16 | Self::F32 | Self::F64 => true,
+            Self::Bool | Self::U8 | Self::U32 | Self::I8 | Self::I16 | Self::I32 | Self::I64 => false,
+        }
+    }
+
     pub fn size_in_bytes(&self) -> usize {
         match self {
+            DType::Bool => 1,
             DType::U8 => 1,
             DType::U32 => 4,
+            DType::I8 => 1,
+            DType::I16 => 2,
+            DType::I32 => 4,
+            DType::I64 => 8,
             DType::BF16 => 2,
             DType::F16 => 2,
             DType::F32 => 4,
@@ -156,6 +190,10 @@ macro_rules! with_dtype {
 
 with_dtype!(u8, U8, |v: f64| v as u8, |v: u8| v as f64);
 with_dtype!(u32, U32, |v: f64| v as u32, |v: u32| v as f64);
+with_dtype!(i8, I8, |v: f64| v as i8, |v: i8| v as f64);
+with_dtype!(i16, I16, |v: f64| v as i16, |v: i16| v as f64);
+with_dtype!(i32, I32, |v: f64| v as i32, |v: i32| v as f64);
+with_dtype!(i64, I64, |v: f64| v as i64, |v: i64| v as f64);
 with_dtype!(f16, F16, f16::from_f64, f16::to_f64);
 with_dtype!(bf16, BF16, bf16::from_f64, bf16::to_f64);
 with_dtype!(f32, F32, |v: f64| v as f32, |v: f32| v as f64);
@@ -163,8 +201,9 @@ with_dtype!(f64, F64, |v: f64| v, |v: f64| v);
 
 pub trait IntDType: WithDType {
     fn is_true(&self) -> bool;
-    
-    fn as_usize(&self) -> usize;
+
+    /// Converts an index to `usize`, negative indexes are rejected with an error.
+    fn as_usize(&self) -> Result<usize>;
 }
 
 impl IntDType for u32 {
@@ -172,14 +211,91 @@ impl IntDType for u32 {
         *self != 0
     }
 
-    fn as_usize(&self) -> usize {
-        *self as usize
+    fn as_usize(&self) -> Result<usize> {
+        Ok(*self as usize)
     }
 }
 
+macro_rules! signed_int_dtype {
+    ($ty:ty) => {
+        impl IntDType for $ty {
+            fn is_true(&self) -> bool {
+                *self != 0
+            }
+
+            fn as_usize(&self) -> Result<usize> {
+                usize::try_from(*self)
+                    .map_err(|_| Error::Msg(format!("negative index {self}")).bt())
+            }
+        }
+    };
+}
+
+signed_int_dtype!(i8);
+signed_int_dtype!(i16);
+signed_int_dtype!(i32);
+signed_int_dtype!(i64);
+
 pub trait FloatDType: WithDType {}
 
 impl FloatDType for f16 {}
 impl FloatDType for bf16 {}
 impl FloatDType for f32 {}
 impl FloatDType for f64 {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DType, Device, Result, Tensor};
+
+    #[test]
+    fn signed_int_ops() -> Result<()> {
+        let dev = &Device::Cpu;
+        let t = Tensor::new(&[-3i64, 0, 5], dev)?;
+        assert_eq!(t.neg()?.to_vec1::<i64>()?, [3, 0, -5]);
+        assert_eq!(t.abs()?.to_vec1::<i64>()?, [3, 0, 5]);
+        assert_eq!(t.sqr()?.to_vec1::<i64>()?, [9, 0, 25]);
+        assert_eq!(t.to_dtype(DType::F32)?.to_vec1::<f32>()?, [-3., 0., 5.]);
+        let t = Tensor::new(&[i8::MIN, 7], dev)?;
+        assert_eq!(t.abs()?.to_vec1::<i8>()?, [i8::MIN, 7]);
+
+        // Float only ops and neg on unsigned ints return an error rather than panicking.
+        let err = t.exp().unwrap_err().to_string();
+        assert!(err.contains("unsupported dtype I8 for op exp"), "{err}");
+        assert!(Tensor::new(&[1u32], dev)?.neg().is_err());
+
+        // Negative indexes are rejected rather than wrapped around.
+        let xs = Tensor::new(&[1f32, 2., 3.], dev)?;
+        let ids = Tensor::new(&[2i32, 0], dev)?;
+        assert_eq!(xs.index_select(&ids, 0)?.to_vec1::<f32>()?, [3., 1.]);
+        let ids = Tensor::new(&[-1i32], dev)?;
+        assert!(xs.index_select(&ids, 0).is_err());
+
+        for xs in [Tensor::new(&[4i64, 6], dev)?, Tensor::new(&[4u32, 6], dev)?] {
+            let err = xs.mean(0).unwrap_err().to_string();
+            assert!(err.contains("for op mean"), "{err}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bool_dtype() -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = Tensor::new(&[[0f32, -1.5], [f32::NAN, 2.]], dev)?;
+        let mask = xs.to_dtype(DType::Bool)?;
+        assert_eq!(mask.dtype(), DType::Bool);
+        assert_eq!(mask.to_dtype(DType::U8)?.to_vec2::<u8>()?, [[0, 1], [1, 1]]);
+        assert_eq!(mask.to_dtype(DType::F32)?.to_vec2::<f32>()?, [[0., 1.], [1., 1.]]);
+
+        // Booleans can be copied around and used as masks but not in arithmetic ops.
+        let mask = mask.t()?.contiguous()?;
+        assert_eq!(mask.to_dtype(DType::I64)?.to_vec2::<i64>()?, [[0, 1], [1, 1]]);
+        let zeros = Tensor::zeros((2, 2), DType::F32, dev)?;
+        let ones = Tensor::ones((2, 2), DType::F32, dev)?;
+        let ys = mask.where_cond(&ones, &zeros)?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[0., 1.], [1., 1.]]);
+        assert!(mask.neg().is_err());
+        assert!((&mask + &mask).is_err());
+        assert_eq!(format!("{mask:?}"), "CpuTensor[dims 2, 2; bool]");
+        Ok(())
+    }
+}
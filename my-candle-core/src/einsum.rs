@@ -0,0 +1,347 @@
+//! Einstein summation, the contractions are lowered to `permute`, `reshape`, `sum` and batched
+//! `matmul` so the gradients are handled by these ops.
+use crate::{Result, Tensor};
+use std::collections::HashMap;
+
+fn parse_labels(spec: &str) -> Result<Vec<char>> {
+    let mut labels = Vec::with_capacity(spec.len());
+    for c in spec.chars() {
+        if c.is_ascii_alphabetic() {
+            labels.push(c)
+        } else if !c.is_whitespace() {
+            crate::bail!("einsum: unexpected character '{c}', ellipsis are not supported")
+        }
+    }
+    Ok(labels)
+}
+
+// Sums out the labels of `xs` that are not in `keep`.
+fn sum_unused(xs: Tensor, labels: &[char], keep: &[char]) -> Result<(Tensor, Vec<char>)> {
+    let sum_dims: Vec<usize> = (0..labels.len())
+        .filter(|&i| !keep.contains(&labels[i]))
+        .collect();
+    if sum_dims.is_empty() {
+        return Ok((xs, labels.to_vec()));
+    }
+    let xs = xs.sum(sum_dims.as_slice())?;
+    let labels = labels
+        .iter()
+        .copied()
+        .filter(|l| keep.contains(l))
+        .collect();
+    Ok((xs, labels))
+}
+
+// Permutes `xs` so that its labels are in the order given by `target`.
+fn permute_to(xs: &Tensor, labels: &[char], target: &[char]) -> Result<Tensor> {
+    if labels == target {
+        return Ok(xs.clone());
+    }
+    let dims: Vec<usize> = target
+        .iter()
+        .map(|t| labels.iter().position(|l| l == t).unwrap())
+        .collect();
+    xs.permute(dims)
+}
+
+// Contracts two operands, the labels from `keep` are preserved and the other ones are summed
+// over. The labels of the result are the batch labels followed by the free labels of `lhs` and
+// of `rhs`.
+fn contract(
+    lhs: &Tensor,
+    lhs_labels: &[char],
+    rhs: &Tensor,
+    rhs_labels: &[char],
+    keep: &[char],
+    sizes: &HashMap<char, usize>,
+) -> Result<(Tensor, Vec<char>)> {
+    // Labels that only appear in one operand and not in the output can be summed right away.
+    let lhs_keep: Vec<char> = lhs_labels
+        .iter()
+        .copied()
+        .filter(|l| keep.contains(l) || rhs_labels.contains(l))
+        .collect();
+    let rhs_keep: Vec<char> = rhs_labels
+        .iter()
+        .copied()
+        .filter(|l| keep.contains(l) || lhs_labels.contains(l))
+        .collect();
+    let (lhs, lhs_labels) = sum_unused(lhs.clone(), lhs_labels, &lhs_keep)?;
+    let (rhs, rhs_labels) = sum_unused(rhs.clone(), rhs_labels, &rhs_keep)?;
+
+    let (mut batch, mut contracted) = (vec![], vec![]);
+    for l in lhs_labels
+        .iter()
+        .copied()
+        .filter(|l| rhs_labels.contains(l))
+    {
+        if keep.contains(&l) {
+            batch.push(l)
+        } else {
+            contracted.push(l)
+        }
+    }
+    let lhs_free: Vec<char> = lhs_labels
+        .iter()
+        .copied()
+        .filter(|l| !rhs_labels.contains(l))
+        .collect();
+    let rhs_free: Vec<char> = rhs_labels
+        .iter()
+        .copied()
+        .filter(|l| !lhs_labels.contains(l))
+        .collect();
+    let size = |ls: &[char]| -> usize { ls.iter().map(|l| sizes[l]).product() };
+    let (b, m, k, n) = (
+        size(&batch),
+        size(&lhs_free),
+        size(&contracted),
+        size(&rhs_free),
+    );
+
+    let lhs_target = [batch.as_slice(), &lhs_free, &contracted].concat();
+    let lhs = permute_to(&lhs, &lhs_labels, &lhs_target)?.reshape((b, m, k))?;
+    let rhs_target = [batch.as_slice(), &contracted, &rhs_free].concat();
+    let rhs = permute_to(&rhs, &rhs_labels, &rhs_target)?.reshape((b, k, n))?;
+    let labels = [batch, lhs_free, rhs_free].concat();
+    let dims: Vec<usize> = labels.iter().map(|l| sizes[l]).collect();
+    let xs = lhs
+        .contiguous()?
+        .matmul(&rhs.contiguous()?)?
+        .reshape(dims)?;
+    Ok((xs, labels))
+}
+
+impl Tensor {
+    /// Einstein summation over the operands, `spec` uses one letter per dimension for each
+    /// operand, e.g. `"bhqd,bhkd->bhqk"` computes attention scores. The labels that do not
+    /// appear on the right of `->` are summed over. Without `->`, the output uses the labels
+    /// that appear exactly once in alphabetical order as in NumPy. As in NumPy, a dimension of
+    /// size 1 is broadcast to the size that the other operands use for the same label.
+    ///
+    /// Operands are contracted from left to right, each contraction being a batched matmul.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+    /// let b = Tensor::new(&[[1f32, 0.], [1., 1.]], &Device::Cpu)?;
+    /// let c = Tensor::einsum("ij,jk->ik", &[&a, &b])?;
+    /// assert_eq!(c.to_vec2::<f32>()?, &[[3., 2.], [7., 4.]]);
+    /// let c = Tensor::einsum("ij->j", &[&a])?;
+    /// assert_eq!(c.to_vec1::<f32>()?, &[4., 6.]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn einsum(spec: &str, operands: &[&Tensor]) -> Result<Tensor> {
+        let (inputs, output) = match spec.split_once("->") {
+            Some((inputs, output)) => (inputs, Some(output)),
+            None => (spec, None),
+        };
+        let input_labels = inputs
+            .split(',')
+            .map(parse_labels)
+            .collect::<Result<Vec<_>>>()?;
+        if input_labels.len() != operands.len() {
+            crate::bail!(
+                "einsum: {spec} has {} inputs but {} operands were provided",
+                input_labels.len(),
+                operands.len()
+            )
+        }
+        let mut sizes = HashMap::new();
+        let mut counts = HashMap::new();
+        for (labels, operand) in input_labels.iter().zip(operands.iter()) {
+            if labels.len() != operand.rank() {
+                crate::bail!(
+                    "einsum: subscripts {labels:?} do not match the operand shape {:?}",
+                    operand.shape()
+                )
+            }
+            for (i, &l) in labels.iter().enumerate() {
+                if labels[..i].contains(&l) {
+                    crate::bail!("einsum: repeated label '{l}' in an operand is not supported")
+                }
+                // Dimensions of size 1 are broadcast to the size used by the other operands.
+                let size = operand.dims()[i];
+                let known = sizes.entry(l).or_insert(size);
+                if *known == 1 {
+                    *known = size
+                } else if size != 1 && *known != size {
+                    crate::bail!(
+                        "einsum: label '{l}' is used for dimensions of size {known} and {size}"
+                    )
+                }
+                *counts.entry(l).or_insert(0usize) += 1;
+            }
+        }
+        let operands = input_labels
+            .iter()
+            .zip(operands.iter())
+            .map(|(labels, operand)| {
+                let dims: Vec<usize> = labels.iter().map(|l| sizes[l]).collect();
+                if operand.dims() == dims.as_slice() {
+                    Ok((*operand).clone())
+                } else {
+                    operand.broadcast_as(dims)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let output = match output {
+            Some(output) => parse_labels(output)?,
+            None => {
+                let mut output: Vec<char> = counts
+                    .iter()
+                    .filter(|(_, c)| **c == 1)
+                    .map(|(&l, _)| l)
+                    .collect();
+                output.sort();
+                output
+            }
+        };
+        for (i, l) in output.iter().enumerate() {
+            if output[..i].contains(l) || !sizes.contains_key(l) {
+                crate::bail!("einsum: invalid output label '{l}' in {spec}")
+            }
+        }
+
+        let mut xs = operands[0].clone();
+        let mut labels = input_labels[0].clone();
+        for i in 1..operands.len() {
+            // The labels used by the output or by the operands still to be contracted.
+            let keep = [output.clone(), input_labels[i + 1..].concat()].concat();
+            let rhs_labels = &input_labels[i];
+            (xs, labels) = contract(&xs, &labels, &operands[i], rhs_labels, &keep, &sizes)?;
+        }
+        let (xs, labels) = sum_unused(xs, &labels, &output)?;
+        permute_to(&xs, &labels, &output)
+    }
+
+    /// Sums the products of the elements of `self` and `rhs` over the dimensions `lhs_dims` and
+    /// `rhs_dims` respectively, as `numpy.tensordot`. The result has the remaining dimensions of
+    /// `self` followed by the remaining dimensions of `rhs`.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::arange(0f32, 24., &Device::Cpu)?.reshape((2, 3, 4))?;
+    /// let b = Tensor::ones((4, 3, 5), my_candle_core::DType::F32, &Device::Cpu)?;
+    /// let c = a.tensordot(&b, &[1, 2], &[1, 0])?;
+    /// assert_eq!(c.dims(), &[2, 5]);
+    /// assert_eq!(c.to_vec2::<f32>()?[1], &[210.; 5]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn tensordot(
+        &self,
+        rhs: &Tensor,
+        lhs_dims: &[usize],
+        rhs_dims: &[usize],
+    ) -> Result<Tensor> {
+        self.batched_tensordot(rhs, (&[], &[]), (lhs_dims, rhs_dims))
+    }
+
+    /// Same as [`Tensor::tensordot`] with some batch dimensions, `batch_dims.0` of `self` being
+    /// paired with `batch_dims.1` of `rhs` without being summed over. The result has the batch
+    /// dimensions first, followed by the remaining dimensions of `self` and of `rhs`.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::arange(0f32, 24., &Device::Cpu)?.reshape((2, 3, 4))?;
+    /// let b = Tensor::ones((4, 5, 2), my_candle_core::DType::F32, &Device::Cpu)?;
+    /// let c = a.batched_tensordot(&b, (&[0], &[2]), (&[2], &[0]))?;
+    /// assert_eq!(c.dims(), &[2, 3, 5]);
+    /// assert_eq!(c.to_vec3::<f32>()?[1][0], &[54.; 5]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn batched_tensordot(
+        &self,
+        rhs: &Tensor,
+        batch_dims: (&[usize], &[usize]),
+        dims: (&[usize], &[usize]),
+    ) -> Result<Tensor> {
+        for (name, (l, r)) in [("batch", batch_dims), ("contracted", dims)] {
+            if l.len() != r.len() {
+                crate::bail!(
+                    "tensordot: {} lhs {name} dims and {} rhs {name} dims",
+                    l.len(),
+                    r.len()
+                )
+            }
+        }
+        if self.rank() + rhs.rank() > 52 {
+            crate::bail!("tensordot: too many dimensions")
+        }
+        let mut letters = ('a'..='z').chain('A'..='Z');
+        let lhs_labels: Vec<char> = letters.by_ref().take(self.rank()).collect();
+        let mut rhs_labels: Vec<char> = letters.take(rhs.rank()).collect();
+        let pairs = batch_dims.0.iter().zip(batch_dims.1.iter());
+        for (&l, &r) in pairs.chain(dims.0.iter().zip(dims.1.iter())) {
+            if l >= self.rank() || r >= rhs.rank() {
+                crate::bail!("tensordot: invalid dims {batch_dims:?} {dims:?}")
+            }
+            rhs_labels[r] = lhs_labels[l]
+        }
+        let batch: Vec<char> = batch_dims.0.iter().map(|&d| lhs_labels[d]).collect();
+        let free = lhs_labels
+            .iter()
+            .chain(rhs_labels.iter())
+            .filter(|l| !batch.contains(l) && !dims.0.iter().any(|&d| lhs_labels[d] == **l));
+        let output: String = batch.iter().chain(free).collect();
+        let lhs_labels: String = lhs_labels.into_iter().collect();
+        let rhs_labels: String = rhs_labels.into_iter().collect();
+        Tensor::einsum(
+            &format!("{lhs_labels},{rhs_labels}->{output}"),
+            &[self, rhs],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, Result, Tensor};
+
+    #[test]
+    fn einsum_matches_matmul() -> Result<()> {
+        let dev = &Device::Cpu;
+        let q = Tensor::arange(0f32, 24., dev)?
+            .reshape((1, 2, 3, 4))?
+            .affine(0.1, -1.)?;
+        let k = Tensor::arange(0f32, 40., dev)?
+            .reshape((1, 2, 5, 4))?
+            .cos()?;
+        let scores = Tensor::einsum("bhqd,bhkd->bhqk", &[&q, &k])?;
+        let expected = q.matmul(&k.t()?.contiguous()?)?;
+        let diff = (scores - expected)?.abs()?.max_all()?.to_scalar::<f32>()?;
+        assert!(diff < 1e-5);
+
+        // Outer product, trace-free reductions and implicit outputs.
+        let a = Tensor::new(&[1f32, 2., 3.], dev)?;
+        let b = Tensor::new(&[1f32, -1.], dev)?;
+        let outer = Tensor::einsum("i,j->ij", &[&a, &b])?;
+        assert_eq!(outer.to_vec2::<f32>()?, [[1., -1.], [2., -2.], [3., -3.]]);
+        let t = Tensor::einsum("ij,i,j", &[&outer, &a, &b])?;
+        assert_eq!(t.to_scalar::<f32>()?, 28.);
+        assert_eq!(Tensor::einsum("ij->ji", &[&outer])?.dims(), [2, 3]);
+        assert!(Tensor::einsum("ij,jk->ik", &[&outer, &outer]).is_err());
+        assert!(Tensor::einsum("ii->i", &[&outer]).is_err());
+
+        // Size 1 dimensions are broadcast.
+        let col = Tensor::new(&[[1f32], [2.], [3.]], dev)?;
+        let row = Tensor::new(&[[1f32, -1.]], dev)?;
+        let c = Tensor::einsum("ij,ij->ij", &[&col, &row])?;
+        assert_eq!(c.to_vec2::<f32>()?, outer.to_vec2::<f32>()?);
+        assert_eq!(
+            Tensor::einsum("ij,jk->ik", &[&row, &col.t()?])?.dims(),
+            [1, 3]
+        );
+        assert_eq!(
+            Tensor::einsum("ij,ij->i", &[&col, &row])?.to_vec1::<f32>()?,
+            [0., 0., 0.]
+        );
+
+        let x = Tensor::arange(0f32, 24., dev)?.reshape((2, 3, 4))?;
+        let y = Tensor::arange(0f32, 40., dev)?.reshape((2, 4, 5))?;
+        let c = x.batched_tensordot(&y, (&[0], &[0]), (&[2], &[1]))?;
+        let diff = (c - x.matmul(&y)?)?.abs()?.max_all()?.to_scalar::<f32>()?;
+        assert_eq!(diff, 0.);
+        assert!(x.batched_tensordot(&y, (&[0], &[]), (&[2], &[1])).is_err());
+        Ok(())
+    }
+}
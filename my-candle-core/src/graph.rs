@@ -0,0 +1,448 @@
+//! Export of the computation graph recorded on tensors.
+//!
+//! The graph is the one used for backpropagation so by default only the ops that depend on a
+//! variable are recorded, the other tensors appear as `constant` nodes. The ops of a model that
+//! does not use variables, e.g. for inference, are recorded when it runs within [`record`].
+//! Nodes are numbered in topological order starting from the inputs, so that the graphs built by
+//! two runs of the same model can be compared. The graph can be serialized to JSON or to the
+//! Graphviz DOT format.
+//!
+//! ```rust
+//! use my_candle_core::{graph::Graph, Device, Tensor, Var};
+//! let x = Var::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+//! let y = (x.matmul(&x)? + 1.)?.sum_keepdim(1)?;
+//! let graph = Graph::from_tensors(&[&y])?;
+//! assert_eq!(graph.nodes().len(), 4);
+//! assert!(graph.to_dot().starts_with("digraph"));
+//!
+//! // Without variables the ops have to be recorded explicitly.
+//! let x = Tensor::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+//! assert!(Graph::from_tensors(&[&x.exp()?]).is_err());
+//! let y = my_candle_core::graph::record(|| x.exp())?;
+//! assert_eq!(Graph::from_tensors(&[&y])?.nodes().len(), 2);
+//! # Ok::<(), my_candle_core::Error>(())
+//! ```
+use crate::op::Op;
+use crate::{DType, Result, Tensor, TensorId};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// A parameter of an op, e.g. the dimension used by a reduction. The non-finite values have no
+/// JSON representation and are serialized as `null`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Param {
+    Usize(usize),
+    Usizes(Vec<usize>),
+    F64(f64),
+    Str(&'static str),
+}
+
+impl std::fmt::Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usize(v) => write!(f, "{v}"),
+            Self::Usizes(vs) => write!(f, "{vs:?}"),
+            Self::F64(v) => write!(f, "{v:?}"),
+            Self::Str(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Node {
+    /// The position of the node in the graph, inputs come before the nodes using them.
+    pub id: usize,
+    /// The op name, leaves are either `variable` or `constant`.
+    pub op: &'static str,
+    /// The ids of the op arguments.
+    pub inputs: Vec<usize>,
+    pub shape: Vec<usize>,
+    #[serde(serialize_with = "serialize_dtype")]
+    pub dtype: DType,
+    /// The op parameters, serialized as an object.
+    #[serde(serialize_with = "serialize_params")]
+    pub params: Vec<(&'static str, Param)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Graph {
+    nodes: Vec<Node>,
+    outputs: Vec<usize>,
+}
+
+// The op name, arguments and parameters.
+fn op_info(op: &Op) -> (&'static str, Vec<&Tensor>, Vec<(&'static str, Param)>) {
+    use Param::{Str, Usize, Usizes, F64};
+    match op {
+        Op::Binary(lhs, rhs, b) => (b.name(), vec![lhs, rhs], vec![]),
+        Op::Unary(arg, u) => (u.name(), vec![arg], vec![]),
+        Op::Cmp(arg, c) => ("cmp", vec![arg], vec![("op", Str(c.name()))]),
+        Op::Reduce(arg, r, dims) => (r.name(), vec![arg], vec![("dims", Usizes(dims.clone()))]),
+        Op::Matmul(lhs, rhs) => ("matmul", vec![lhs, rhs], vec![]),
+        Op::Gather(arg, ids, dim) => ("gather", vec![arg, ids], vec![("dim", Usize(*dim))]),
+        Op::ScatterAdd(init, ids, src, dim) => (
+            "scatter_add",
+            vec![init, ids, src],
+            vec![("dim", Usize(*dim))],
+        ),
+        Op::IndexSelect(arg, ids, dim) => {
+            ("index_select", vec![arg, ids], vec![("dim", Usize(*dim))])
+        }
+        Op::IndexAdd(init, ids, src, dim) => (
+            "index_add",
+            vec![init, ids, src],
+            vec![("dim", Usize(*dim))],
+        ),
+        Op::WhereCond(pred, t, f) => ("where_cond", vec![pred, t, f], vec![]),
+        Op::Conv1D {
+            arg,
+            kernel,
+            padding,
+            stride,
+            dilation,
+            groups,
+        } => (
+            "conv1d",
+            vec![arg, kernel],
+            vec![
+                ("padding", Usize(*padding)),
+                ("stride", Usize(*stride)),
+                ("dilation", Usize(*dilation)),
+                ("groups", Usize(*groups)),
+            ],
+        ),
+        Op::Conv2D {
+            arg,
+            kernel,
+            padding,
+            stride,
+            dilation,
+            groups,
+        } => (
+            "conv2d",
+            vec![arg, kernel],
+            vec![
+                ("padding", Usize(*padding)),
+                ("stride", Usize(*stride)),
+                ("dilation", Usize(*dilation)),
+                ("groups", Usize(*groups)),
+            ],
+        ),
+        Op::ConvTranspose1D {
+            arg,
+            kernel,
+            padding,
+            output_padding,
+            stride,
+            dilation,
+        } => (
+            "conv_transpose1d",
+            vec![arg, kernel],
+            vec![
+                ("padding", Usize(*padding)),
+                ("output_padding", Usize(*output_padding)),
+                ("stride", Usize(*stride)),
+                ("dilation", Usize(*dilation)),
+            ],
+        ),
+        Op::ConvTranspose2D {
+            arg,
+            kernel,
+            padding,
+            output_padding,
+            stride,
+            dilation,
+        } => (
+            "conv_transpose2d",
+            vec![arg, kernel],
+            vec![
+                ("padding", Usize(*padding)),
+                ("output_padding", Usize(*output_padding)),
+                ("stride", Usize(*stride)),
+                ("dilation", Usize(*dilation)),
+            ],
+        ),
+        Op::AvgPool2D {
+            arg,
+            kernel_size,
+            stride,
+        } => (
+            "avg_pool2d",
+            vec![arg],
+            vec![
+                ("kernel_size", Usizes(vec![kernel_size.0, kernel_size.1])),
+                ("stride", Usizes(vec![stride.0, stride.1])),
+            ],
+        ),
+        Op::MaxPool2D {
+            arg,
+            kernel_size,
+            stride,
+        } => (
+            "max_pool2d",
+            vec![arg],
+            vec![
+                ("kernel_size", Usizes(vec![kernel_size.0, kernel_size.1])),
+                ("stride", Usizes(vec![stride.0, stride.1])),
+            ],
+        ),
+        Op::UpsampleNearest2D(arg) => ("upsample_nearest2d", vec![arg], vec![]),
+        Op::Cat(args, dim) => ("cat", args.iter().collect(), vec![("dim", Usize(*dim))]),
+        Op::Affine { arg, mul, add } => (
+            "affine",
+            vec![arg],
+            vec![("mul", F64(*mul)), ("add", F64(*add))],
+        ),
+        Op::ToDType(arg) => ("to_dtype", vec![arg], vec![]),
+        Op::Copy(arg) => ("copy", vec![arg], vec![]),
+        Op::Broadcast(arg) => ("broadcast", vec![arg], vec![]),
+        Op::Narrow(arg, dim, start, len) => (
+            "narrow",
+            vec![arg],
+            vec![
+                ("dim", Usize(*dim)),
+                ("start", Usize(*start)),
+                ("len", Usize(*len)),
+            ],
+        ),
+        Op::Reshape(arg) => ("reshape", vec![arg], vec![]),
+        Op::ToDevice(arg) => ("to_device", vec![arg], vec![]),
+        Op::Transpose(arg, dim1, dim2) => (
+            "transpose",
+            vec![arg],
+            vec![("dim1", Usize(*dim1)), ("dim2", Usize(*dim2))],
+        ),
+        Op::Permute(arg, dims) => ("permute", vec![arg], vec![("dims", Usizes(dims.clone()))]),
+        Op::Unfold(arg, dim, size, step) => (
+            "unfold",
+            vec![arg],
+            vec![
+                ("dim", Usize(*dim)),
+                ("size", Usize(*size)),
+                ("step", Usize(*step)),
+            ],
+        ),
+        Op::Elu(arg, alpha) => ("elu", vec![arg], vec![("alpha", F64(*alpha))]),
+        Op::Powf(arg, e) => ("powf", vec![arg], vec![("exponent", F64(*e))]),
+        Op::Clamp(arg, min, max) => (
+            "clamp",
+            vec![arg],
+            vec![("min", F64(*min)), ("max", F64(*max))],
+        ),
+        Op::Cumulative(arg, r, dim) => (
+            "cumulative",
+            vec![arg],
+            vec![("op", Str(r.name())), ("dim", Usize(*dim))],
+        ),
+        Op::SoftmaxLastDim(arg) => ("softmax_last_dim", vec![arg], vec![]),
+        Op::LogSoftmaxLastDim(arg) => ("log_softmax_last_dim", vec![arg], vec![]),
+        Op::CustomOp1(arg, c) => ("custom_op1", vec![arg], vec![("name", Str(c.name()))]),
+        Op::CustomOp2(arg1, arg2, c) => (
+            "custom_op2",
+            vec![arg1, arg2],
+            vec![("name", Str(c.name()))],
+        ),
+        Op::CustomOp3(arg1, arg2, arg3, c) => (
+            "custom_op3",
+            vec![arg1, arg2, arg3],
+            vec![("name", Str(c.name()))],
+        ),
+        Op::Checkpoint(args, _) => ("checkpoint", args.iter().collect(), vec![]),
+    }
+}
+
+fn serialize_dtype<S: Serializer>(dtype: &DType, s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(dtype.as_str())
+}
+
+fn serialize_params<S: Serializer>(
+    params: &[(&'static str, Param)],
+    s: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    s.collect_map(params.iter().map(|(name, param)| (name, param)))
+}
+
+// Escapes a string to be used in a quoted DOT label, backslashes first so that the ones added
+// for the quotes are not escaped again.
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Runs `f` recording all the ops, including the ones that do not depend on a variable, so that
+/// [`Graph::from_tensors`] can export the graph of the tensors it returns. The recording only
+/// applies to the current thread and the recorded tensors keep their inputs alive.
+pub fn record<T>(f: impl FnOnce() -> T) -> T {
+    crate::backprop::run_record_all(f)
+}
+
+impl Graph {
+    /// Builds the graph of the ops leading to the given tensors.
+    ///
+    /// An error is returned if one of the outputs is neither a variable nor the result of a
+    /// recorded op, the ops that do not depend on a variable are only recorded within
+    /// [`record`].
+    pub fn from_tensors(outputs: &[&Tensor]) -> Result<Self> {
+        // The walk is recursive like `sorted_nodes` in backprop.rs.
+        fn visit(t: &Tensor, nodes: &mut Vec<Node>, ids: &mut HashMap<TensorId, usize>) -> usize {
+            if let Some(&id) = ids.get(&t.id()) {
+                return id;
+            }
+            let (op, inputs, params) = match t.op() {
+                Some(op) => op_info(op),
+                None if t.is_variable() => ("variable", vec![], vec![]),
+                None => ("constant", vec![], vec![]),
+            };
+            let inputs = inputs
+                .into_iter()
+                .map(|input| visit(input, nodes, ids))
+                .collect();
+            let id = nodes.len();
+            nodes.push(Node {
+                id,
+                op,
+                inputs,
+                shape: t.dims().to_vec(),
+                dtype: t.dtype(),
+                params,
+            });
+            ids.insert(t.id(), id);
+            id
+        }
+        if let Some(t) = outputs
+            .iter()
+            .find(|t| t.op().is_none() && !t.is_variable())
+        {
+            crate::bail!(
+                "no op recorded for output {:?} {:?}, use graph::record to record the ops that \
+                 do not depend on a variable",
+                t.dims(),
+                t.dtype()
+            )
+        }
+        let mut nodes = vec![];
+        let mut ids = HashMap::new();
+        let outputs = outputs
+            .iter()
+            .map(|t| visit(t, &mut nodes, &mut ids))
+            .collect();
+        Ok(Self { nodes, outputs })
+    }
+
+    /// The nodes in topological order, the id of a node is its position in this slice.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// The ids of the nodes for the tensors used to build the graph.
+    pub fn outputs(&self) -> &[usize] {
+        &self.outputs
+    }
+
+    /// Serializes the graph to JSON, nodes are written as objects with the `id`, `op`,
+    /// `inputs`, `shape`, `dtype` and `params` fields.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(crate::Error::wrap)
+    }
+
+    /// Serializes the graph to the Graphviz DOT format, e.g. to be rendered with
+    /// `dot -Tsvg graph.dot -o graph.svg`. The outputs are drawn with a double border.
+    pub fn to_dot(&self) -> String {
+        let mut dst = String::new();
+        dst.push_str("digraph {\n  node [shape=box];\n");
+        for node in self.nodes.iter() {
+            // The lines are escaped separately so that the `\n` separators are kept.
+            let mut lines = vec![escape_dot(node.op)];
+            if !node.params.is_empty() {
+                let params: Vec<String> = node
+                    .params
+                    .iter()
+                    .map(|(name, param)| format!("{name}={param}"))
+                    .collect();
+                lines.push(escape_dot(&params.join(", ")));
+            }
+            lines.push(escape_dot(&format!(
+                "{:?} {}",
+                node.shape,
+                node.dtype.as_str()
+            )));
+            let label = lines.join("\\n");
+            let peripheries = if self.outputs.contains(&node.id) {
+                2
+            } else {
+                1
+            };
+            let _ = writeln!(
+                dst,
+                "  n{} [label=\"{label}\", peripheries={peripheries}];",
+                node.id
+            );
+            for input in node.inputs.iter() {
+                let _ = writeln!(dst, "  n{input} -> n{};", node.id);
+            }
+        }
+        dst.push_str("}\n");
+        dst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Graph, Param};
+    use crate::{Device, Result, Tensor, Var};
+
+    #[test]
+    fn graph_export() -> Result<()> {
+        let dev = &Device::Cpu;
+        let x = Var::new(&[[1f32, -2.], [3., 4.]], dev)?;
+        let w = Tensor::new(&[0.5f32, 2.], dev)?;
+        let y = x.broadcast_mul(&w)?.relu()?.transpose(0, 1)?;
+        let graph = Graph::from_tensors(&[&y, &x])?;
+        // The broadcast of `w` does not depend on a variable so it is not recorded.
+        let ops: Vec<&str> = graph.nodes().iter().map(|n| n.op).collect();
+        assert_eq!(ops, ["variable", "constant", "mul", "relu", "transpose"]);
+        assert_eq!(graph.outputs(), [4, 0]);
+        assert_eq!(graph.nodes()[2].inputs, [0, 1]);
+        assert_eq!(graph.nodes()[1].shape, [2, 2]);
+        let params = [("dim1", Param::Usize(0)), ("dim2", Param::Usize(1))];
+        assert_eq!(graph.nodes()[4].params, params);
+
+        let json = graph.to_json()?;
+        let node = r#"{"id":4,"op":"transpose","inputs":[3],"shape":[2,2],"dtype":"f32","params":{"dim1":0,"dim2":1}}"#;
+        assert!(json.contains(node));
+        assert!(json.starts_with(r#"{"nodes":[{"id":0,"op":"variable","#));
+        assert!(json.ends_with(r#"}],"outputs":[4,0]}"#));
+        let dot = graph.to_dot();
+        let node = r#"  n4 [label="transpose\ndim1=0, dim2=1\n[2, 2] f32", peripheries=2];"#;
+        assert!(dot.contains(node));
+        assert!(dot.contains("  n3 -> n4;"));
+        assert_eq!(super::escape_dot(r#"a\"b"#), r#"a\\\"b"#);
+        Ok(())
+    }
+
+    #[test]
+    fn graph_export_recorded() -> Result<()> {
+        let dev = &Device::Cpu;
+        let x = Tensor::new(&[[1f32, -2.], [3., 4.]], dev)?;
+        let w = Tensor::new(&[0.5f32, 2.], dev)?;
+        let forward = || x.broadcast_mul(&w)?.relu()?.sum_keepdim(1);
+        // Without variables nothing is recorded and the export fails rather than returning a
+        // graph with a single node.
+        let y = forward()?;
+        assert!(y.op().is_none());
+        assert!(Graph::from_tensors(&[&y]).is_err());
+
+        let y = super::record(forward)?;
+        let graph = Graph::from_tensors(&[&y])?;
+        let ops: Vec<&str> = graph.nodes().iter().map(|n| n.op).collect();
+        assert_eq!(
+            ops,
+            ["constant", "constant", "broadcast", "mul", "relu", "sum"]
+        );
+        assert_eq!(graph.outputs(), [5]);
+        // The recording stops with the closure.
+        assert!(x.exp()?.op().is_none());
+        Ok(())
+    }
+}
@@ -3,12 +3,82 @@ use std::ops::{
 };
 use crate::error::{Error,Result};
 use crate::tensor::Tensor;
+use crate::DType;
+
+// Converts a possibly negative index to a position in a dimension of size `dim_size`, negative
+// values are counted from the end.
+fn resolve_index(index: isize, dim_size: usize) -> Option<usize> {
+    let index = if index < 0 {
+        index + dim_size as isize
+    } else {
+        index
+    };
+    if index < 0 {
+        None
+    } else {
+        Some(index as usize)
+    }
+}
+
+// Returns the `[start, stop)` interval for the given bounds, out of range values are clamped as
+// in NumPy.
+fn resolve_bounds(start: &Bound<isize>, stop: &Bound<isize>, dim_size: usize) -> (usize, usize) {
+    let clamp = |i: isize| resolve_index(i, dim_size).unwrap_or(0).min(dim_size);
+    let start = match start {
+        Bound::Included(n) => clamp(*n),
+        Bound::Excluded(n) => clamp(*n).saturating_add(1).min(dim_size),
+        Bound::Unbounded => 0,
+    };
+    let stop = match stop {
+        Bound::Included(n) => clamp(*n).saturating_add(1).min(dim_size),
+        Bound::Excluded(n) => clamp(*n),
+        Bound::Unbounded => dim_size,
+    };
+    (start, stop.max(start))
+}
+
+// Pairs the index tensors elementwise as NumPy does, the result holds `x[a[i], b[i], ...]` for
+// each `i`. The indexed dimensions are moved to the front and flattened so that a single
+// index_select on the flattened index gathers the elements, the paired dimension is the first
+// one of the result.
+fn index_paired(x: &Tensor, index_tensors: &[(usize, &Tensor)]) -> Result<Tensor> {
+    let any = |mask: Tensor| -> Result<bool> {
+        Ok(mask.to_dtype(DType::U32)?.sum_all()?.to_scalar::<u32>()? > 0)
+    };
+    let len = index_tensors[0].1.dims1()?;
+    let mut flat: Option<Tensor> = None;
+    for &(dim, ids) in index_tensors.iter() {
+        if ids.dims1()? != len {
+            crate::bail!(
+                "index tensors cannot be paired, got lengths {len} and {}",
+                ids.dims1()?
+            )
+        }
+        let dim_size = x.dim(dim)?;
+        let ids = ids.to_dtype(DType::I64)?;
+        if any(ids.lt(0i64)?)? || any(ids.ge(dim_size as i64)?)? {
+            crate::bail!("index tensor is out of bounds for dim {dim} with size {dim_size}")
+        }
+        flat = Some(match flat {
+            None => ids,
+            Some(flat) => flat.affine(dim_size as f64, 0.)?.add(&ids)?,
+        });
+    }
+    let indexed: Vec<usize> = index_tensors.iter().map(|(dim, _)| *dim).collect();
+    let mut dims = indexed.clone();
+    dims.extend((0..x.rank()).filter(|dim| !indexed.contains(dim)));
+    let x = x.permute(dims)?.flatten_to(indexed.len() - 1)?;
+    match flat {
+        Some(flat) => x.index_select(&flat, 0),
+        None => crate::bail!("index_paired requires at least one index tensor"),
+    }
+}
 
 impl Tensor {
     /// Intended to be use by the trait `.i()`
     ///
     /// ```
-    /// # use candle_core::{Tensor, DType, Device, IndexOp};
+    /// # use my_candle_core::{Tensor, DType, Device, IndexOp, NewAxis, TensorIndexer};
     /// let a = Tensor::zeros((2, 3), DType::F32, &Device::Cpu)?;
     ///
     /// let c = a.i(0..1)?;
@@ -23,66 +93,212 @@ impl Tensor {
     /// let c = a.i((.., ..=2))?;
     /// assert_eq!(c.shape().dims(), &[2, 3]);
     ///
-    /// # Ok::<(), candle_core::Error>(())
+    /// // Negative indexes are counted from the end, `a[-1, :-1]` in NumPy.
+    /// let c = a.i((-1, ..-1))?;
+    /// assert_eq!(c.shape().dims(), &[2]);
+    ///
+    /// // Stepped ranges, `a[:, ::2]` in NumPy.
+    /// let c = a.i((.., TensorIndexer::step_by(.., 2)))?;
+    /// assert_eq!(c.shape().dims(), &[2, 2]);
+    ///
+    /// // Index arrays and new axis, `a[[1, 0, 1], None]` in NumPy.
+    /// let ids = Tensor::new(&[1u32, 0, 1], &Device::Cpu)?;
+    /// let c = a.i((&ids, NewAxis))?;
+    /// assert_eq!(c.shape().dims(), &[3, 1, 3]);
+    ///
+    /// // Several index arrays are paired elementwise, `a[[1, 0, 1], [2, 2, 0]]` in NumPy.
+    /// let cols = Tensor::new(&[2u32, 2, 0], &Device::Cpu)?;
+    /// let c = a.i((&ids, &cols))?;
+    /// assert_eq!(c.shape().dims(), &[3]);
+    ///
+    /// # Ok::<(), my_candle_core::Error>(())
     /// ```
     fn index(&self, indexers: &[TensorIndexer]) -> Result<Self, Error> {
         let mut x = self.clone();
-        let dims = self.shape().dims();
         let mut current_dim = 0;
-        for (i, indexer) in indexers.iter().enumerate() {
+        // Index tensors are applied once the other indexers are, together with the dimension
+        // they index. `advanced` holds the positions of the scalar and tensor indexers, NumPy
+        // uses them to decide where the indexed dimension goes.
+        let mut index_tensors = vec![];
+        let mut advanced = vec![];
+        for (pos, indexer) in indexers.iter().enumerate() {
             x = match indexer {
-                TensorIndexer::Select(n) => x.narrow(current_dim, *n, 1)?.squeeze(current_dim)?,
+                TensorIndexer::Select(n) => {
+                    advanced.push(pos);
+                    let dim_size = x.dim(current_dim)?;
+                    match resolve_index(*n, dim_size) {
+                        Some(i) if i < dim_size => {
+                            x.narrow(current_dim, i, 1)?.squeeze(current_dim)?
+                        }
+                        _ => crate::bail!(
+                            "index {n} is out of bounds for dim {current_dim} with size {dim_size}"
+                        ),
+                    }
+                }
                 TensorIndexer::Narrow(left_bound, right_bound) => {
-                    let start = match left_bound {
-                        Bound::Included(n) => *n,
-                        Bound::Excluded(n) => *n + 1,
-                        Bound::Unbounded => 0,
+                    let dim_size = x.dim(current_dim)?;
+                    let (start, stop) = resolve_bounds(left_bound, right_bound, dim_size);
+                    let out = x.narrow(current_dim, start, stop - start)?;
+                    current_dim += 1;
+                    out
+                }
+                TensorIndexer::StepBy(range, step) => {
+                    let (left_bound, right_bound) = match range.as_ref() {
+                        TensorIndexer::Narrow(l, r) => (l, r),
+                        indexer => {
+                            crate::bail!("step_by can only be applied to ranges, got {indexer:?}")
+                        }
                     };
-                    let stop = match right_bound {
-                        Bound::Included(n) => *n + 1,
-                        Bound::Excluded(n) => *n,
-                        Bound::Unbounded => dims[i],
+                    if *step == 0 {
+                        crate::bail!("step_by requires a positive step")
+                    }
+                    let dim_size = x.dim(current_dim)?;
+                    let (start, stop) = resolve_bounds(left_bound, right_bound, dim_size);
+                    let out = x.narrow(current_dim, start, stop - start)?;
+                    let out = if *step == 1 {
+                        out
+                    } else {
+                        let len = stop - start;
+                        let (len, step) = match (u32::try_from(len), u32::try_from(*step)) {
+                            (Ok(len), Ok(step)) => (len, step),
+                            _ => crate::bail!(
+                                "step_by only supports u32 ranges and steps, got {len} and {step}"
+                            ),
+                        };
+                        let ids = Tensor::arange_step(0u32, len, step, x.device())?;
+                        out.index_select(&ids, current_dim)?
                     };
-                    let out = x.narrow(current_dim, start, stop.saturating_sub(start))?;
+                    current_dim += 1;
+                    out
+                }
+                TensorIndexer::IndexSelect(ids) => {
+                    advanced.push(pos);
+                    index_tensors.push((current_dim, ids));
+                    current_dim += 1;
+                    x
+                }
+                TensorIndexer::NewAxis => {
+                    let out = x.unsqueeze(current_dim)?;
                     current_dim += 1;
                     out
                 }
             };
         }
-        Ok(x)
+        // As in NumPy, the indexed dimension replaces the indexed ones when the scalar and
+        // tensor indexers are next to each other and goes first otherwise.
+        let adjacent = advanced.windows(2).all(|w| w[1] == w[0] + 1);
+        match index_tensors.as_slice() {
+            [] => Ok(x),
+            [(dim, ids)] => {
+                let x = x.index_select(ids, *dim)?;
+                if adjacent {
+                    Ok(x)
+                } else {
+                    x.movedim(*dim, 0)
+                }
+            }
+            [(dim, _), ..] => {
+                let x = index_paired(&x, &index_tensors)?;
+                if adjacent {
+                    x.movedim(0, *dim)
+                } else {
+                    Ok(x)
+                }
+            }
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 /// Generic structure used to index a slice of the tensor
 pub enum TensorIndexer {
-    /// This selects the elemnts for which an index has some specific value.
-    Select(usize),
-    /// This is a regular slice, purely indexing a chunk of the tensor
-    Narrow(Bound<usize>, Bound<usize>),
+    /// This selects the elemnts for which an index has some specific value, negative values are
+    /// counted from the end.
+    Select(isize),
+    /// This is a regular slice, purely indexing a chunk of the tensor. Negative bounds are
+    /// counted from the end and out of range bounds are clamped.
+    Narrow(Bound<isize>, Bound<isize>),
+    /// A range where only one element every `step` is kept, see [`TensorIndexer::step_by`].
+    StepBy(Box<TensorIndexer>, usize),
+    /// Selects the elements at the positions given by a 1D tensor of indexes.
+    IndexSelect(Tensor),
+    /// Inserts a new dimension of size one.
+    NewAxis,
 }
 
-impl From<usize> for TensorIndexer {
-    fn from(index: usize) -> Self {
-        TensorIndexer::Select(index)
+impl TensorIndexer {
+    /// Keeps one element every `step` in `range`, `TensorIndexer::step_by(1.., 2)` is the
+    /// equivalent of `1::2` in NumPy.
+    pub fn step_by<R: Into<TensorIndexer>>(range: R, step: usize) -> Self {
+        TensorIndexer::StepBy(Box::new(range.into()), step)
+    }
+}
+
+/// Marker inserting a new dimension of size one when indexing, the equivalent of `None` or
+/// `np.newaxis` in NumPy.
+#[derive(Debug, Clone, Copy)]
+pub struct NewAxis;
+
+impl From<NewAxis> for TensorIndexer {
+    fn from(_: NewAxis) -> Self {
+        TensorIndexer::NewAxis
+    }
+}
+
+impl From<&Tensor> for TensorIndexer {
+    fn from(ids: &Tensor) -> Self {
+        TensorIndexer::IndexSelect(ids.clone())
+    }
+}
+
+impl From<Tensor> for TensorIndexer {
+    fn from(ids: Tensor) -> Self {
+        TensorIndexer::IndexSelect(ids)
+    }
+}
+
+/// Integer types that can be used as indexes.
+pub trait IndexInt: Copy {
+    fn to_isize(self) -> isize;
+}
+
+macro_rules! index_int {
+    ($ty:ty) => {
+        impl IndexInt for $ty {
+            fn to_isize(self) -> isize {
+                self as isize
+            }
+        }
+    };
+}
+
+index_int!(usize);
+index_int!(isize);
+index_int!(u32);
+index_int!(i32);
+index_int!(i64);
+
+impl<I: IndexInt> From<I> for TensorIndexer {
+    fn from(index: I) -> Self {
+        TensorIndexer::Select(index.to_isize())
     }
 }
 
 macro_rules! impl_from_range {
     ($range_type:ty) => {
-        impl From<$range_type> for TensorIndexer {
+        impl<I: IndexInt> From<$range_type> for TensorIndexer {
             fn from(range: $range_type) -> Self {
                 use std::ops::Bound::*;
 
                 let start = match range.start_bound() {
-                    Included(idx) => Included(*idx),
-                    Excluded(idx) => Excluded(*idx),
+                    Included(idx) => Included(idx.to_isize()),
+                    Excluded(idx) => Excluded(idx.to_isize()),
                     Unbounded => Unbounded,
                 };
 
                 let end = match range.end_bound() {
-                    Included(idx) => Included(*idx),
-                    Excluded(idx) => Excluded(*idx),
+                    Included(idx) => Included(idx.to_isize()),
+                    Excluded(idx) => Excluded(idx.to_isize()),
                     Unbounded => Unbounded,
                 };
 
@@ -92,18 +308,27 @@ macro_rules! impl_from_range {
     };
 }
 
-impl_from_range!(Range<usize>);
-impl_from_range!(RangeFrom<usize>);
-impl_from_range!(RangeFull);
-impl_from_range!(RangeInclusive<usize>);
-impl_from_range!(RangeTo<usize>);
-impl_from_range!(RangeToInclusive<usize>);
+impl_from_range!(Range<I>);
+impl_from_range!(RangeFrom<I>);
+impl_from_range!(RangeInclusive<I>);
+impl_from_range!(RangeTo<I>);
+impl_from_range!(RangeToInclusive<I>);
+
+impl From<RangeFull> for TensorIndexer {
+    fn from(_: RangeFull) -> Self {
+        TensorIndexer::Narrow(Bound::Unbounded, Bound::Unbounded)
+    }
+}
 
 /// Trait used to implement multiple signatures for ease of use of the slicing
 /// of a tensor
 pub trait IndexOp<T> {
     /// Returns a slicing iterator which are the chunks of data necessary to
     /// reconstruct the desired tensor.
+    ///
+    /// Several index tensors are paired elementwise as in NumPy advanced indexing: `t.i((&a, &b))`
+    /// has shape `(a.len(),)` and holds `t[a[i], b[i]]`, the index tensors must have the same
+    /// length. Use successive calls to `.i()` to get the outer product of the selections.
     fn i(&self, index: T) -> Result<Tensor, Error>;
 }
 
@@ -136,3 +361,64 @@ index_op_tuple!(A, B, C, D);
 index_op_tuple!(A, B, C, D, E);
 index_op_tuple!(A, B, C, D, E, F);
 index_op_tuple!(A, B, C, D, E, F, G);
+
+#[cfg(test)]
+mod tests {
+    use super::{NewAxis, TensorIndexer};
+    use crate::{Device, IndexOp, Result, Tensor};
+
+    #[test]
+    fn numpy_like_indexing() -> Result<()> {
+        let dev = &Device::Cpu;
+        let t = Tensor::arange(0u32, 12, dev)?.reshape((3, 4))?;
+        assert_eq!(t.i(-1)?.to_vec1::<u32>()?, [8, 9, 10, 11]);
+        assert_eq!(t.i((.., -2))?.to_vec1::<u32>()?, [2, 6, 10]);
+        assert_eq!(t.i((-2.., 1..-1))?.to_vec2::<u32>()?, [[5, 6], [9, 10]]);
+        // Out of range slices are clamped, out of range indexes are errors.
+        assert_eq!(t.i((1..10, 3..))?.to_vec2::<u32>()?, [[7], [11]]);
+        assert!(t.i(3).is_err());
+        assert!(t.i(-4).is_err());
+        let c = t.i((
+            TensorIndexer::step_by(.., 2),
+            TensorIndexer::step_by(1.., 2),
+        ))?;
+        assert_eq!(c.to_vec2::<u32>()?, [[1, 3], [9, 11]]);
+        let ids = Tensor::new(&[2u32, 0], dev)?;
+        assert_eq!(t.i((1, &ids))?.to_vec1::<u32>()?, [6, 4]);
+        let c = t.i((NewAxis, .., NewAxis, 1..3))?;
+        assert_eq!(c.dims(), [1, 3, 1, 2]);
+        assert!(t.i(TensorIndexer::step_by(0, 2)).is_err());
+        if let Ok(step) = usize::try_from(1u64 << 40) {
+            assert!(t.i(TensorIndexer::step_by(.., step)).is_err());
+        }
+        // Index tensors on several dimensions are paired elementwise.
+        let rows = Tensor::new(&[0u32, 2], dev)?;
+        let cols = Tensor::new(&[1u32, 3], dev)?;
+        assert_eq!(t.i((&rows, &cols))?.to_vec1::<u32>()?, [1, 11]);
+        let c = t.i(&rows)?.i((.., &cols))?;
+        assert_eq!(c.to_vec2::<u32>()?, [[1, 3], [9, 11]]);
+        assert!(t.i((&rows, &Tensor::new(&[1u32, 2, 3], dev)?)).is_err());
+        assert!(t.i((&rows, &Tensor::new(&[1u32, 4], dev)?)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn numpy_like_advanced_indexing_placement() -> Result<()> {
+        let dev = &Device::Cpu;
+        let t = Tensor::arange(0u32, 24, dev)?.reshape((2, 3, 4))?;
+        let a = Tensor::new(&[2u32, 0], dev)?;
+        let b = Tensor::new(&[3u32, 0], dev)?;
+        // Adjacent indexers, `t[:, [2, 0], [3, 0]]` has shape (2, 2).
+        let c = t.i((.., &a, &b))?;
+        assert_eq!(c.to_vec2::<u32>()?, [[11, 0], [23, 12]]);
+        // Non adjacent indexers, `t[[1, 0], :, [3, 0]]` has the paired dim first.
+        let c = t.i((&Tensor::new(&[1u32, 0], dev)?, .., &b))?;
+        assert_eq!(c.to_vec2::<u32>()?, [[15, 19, 23], [0, 4, 8]]);
+        // Scalars count as advanced indexes, `t[0, :, [3, 0]]` has shape (2, 3).
+        let c = t.i((0, .., &b))?;
+        assert_eq!(c.to_vec2::<u32>()?, [[3, 7, 11], [0, 4, 8]]);
+        let c = t.i((.., 1, &b))?;
+        assert_eq!(c.to_vec2::<u32>()?, [[7, 4], [19, 16]]);
+        Ok(())
+    }
+}
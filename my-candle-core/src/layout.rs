@@ -112,6 +112,90 @@ impl Layout {
         })
     }
 
+    /// The offset between two consecutive matrices when flattening the batch dimensions of a
+    /// matmul operand, i.e. all the dimensions but the last two. Returns `None` if these
+    /// dimensions cannot be flattened without a copy, broadcasted dimensions have a stride of 0
+    /// so the batch of a broadcasted operand can be flattened.
+    pub(crate) fn matmul_batch_stride(&self) -> Option<usize> {
+        let batch_rank = self.dims().len().saturating_sub(2);
+        let mut batch_stride = None;
+        let mut expected_stride = None;
+        for (&dim, &stride) in self.dims()[..batch_rank]
+            .iter()
+            .zip(self.stride[..batch_rank].iter())
+            .rev()
+        {
+            // The stride of a dimension of size 1 is never used.
+            if dim == 1 {
+                continue;
+            }
+            match expected_stride {
+                None => batch_stride = Some(stride),
+                Some(expected_stride) if expected_stride != stride => return None,
+                Some(_) => {}
+            }
+            expected_stride = Some(stride * dim);
+        }
+        Some(batch_stride.unwrap_or(0))
+    }
+
+    pub(crate) fn permute(&self, idxs: &[usize]) -> Result<Self> {
+        let is_permutation =
+            idxs.len() == self.shape.rank() && (0..idxs.len()).all(|i| idxs.contains(&i));
+        if !is_permutation {
+            crate::bail!(
+                "dimension mismatch in permute, tensor {:?}, dims: {:?}",
+                self.dims(),
+                idxs
+            )
+        }
+        let stride = self.stride();
+        let dims = self.shape().dims();
+        let mut perm_stride = stride.to_vec();
+        let mut perm_dims = dims.to_vec();
+        for (i, &idx) in idxs.iter().enumerate() {
+            perm_stride[i] = stride[idx];
+            perm_dims[i] = dims[idx];
+        }
+        Ok(Self {
+            shape: Shape::from(perm_dims),
+            stride: perm_stride,
+            start_offset: self.start_offset,
+        })
+    }
+
+    /// The sliding windows of size `size` along `dim`, the windows are indexed by `dim` and their
+    /// elements by a new last dimension. Windows overlap when `step < size` in which case the
+    /// same storage element is used by multiple positions of the layout.
+    pub(crate) fn unfold(&self, dim: usize, size: usize, step: usize) -> Result<Self> {
+        let dims = self.shape().dims();
+        if dim >= dims.len() {
+            Err(Error::DimOutOfRange {
+                shape: self.shape().clone(),
+                dim: dim as i32,
+                op: "unfold",
+            }
+                .bt())?
+        }
+        if step == 0 || size > dims[dim] {
+            crate::bail!(
+                "unfold: invalid window size {size} and step {step} for dim {dim} of {:?}",
+                dims
+            )
+        }
+        let mut dims = dims.to_vec();
+        let mut stride = self.stride.clone();
+        dims[dim] = (dims[dim] - size) / step + 1;
+        dims.push(size);
+        stride.push(stride[dim]);
+        stride[dim] *= step;
+        Ok(Self {
+            shape: Shape::from(dims),
+            stride,
+            start_offset: self.start_offset,
+        })
+    }
+
     pub fn broadcast_as<S: Into<Shape>>(&self, shape: S) -> Result<Self> {
         let shape = shape.into();
         if shape.rank() < self.shape().rank() {
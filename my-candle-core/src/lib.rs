@@ -49,16 +49,21 @@ mod device;
 pub mod display;
 mod dtype;
 mod dummy_cuda_backend;
+mod einsum;
 pub mod error;
+pub mod graph;
 mod indexer;
 pub mod layout;
 #[cfg(feature = "mkl")]
 mod mkl;
 pub mod npy;
+pub mod onnx;
 mod op;
 pub mod quantized;
 pub mod safetensors;
+mod scalar;
 pub mod shape;
+mod sort;
 mod storage;
 mod strided_index;
 mod tensor;
@@ -69,8 +74,9 @@ pub use cpu_backend::CpuStorage;
 pub use device::{Device, DeviceLocation};
 pub use dtype::{DType, FloatDType, IntDType, WithDType};
 pub use error::{Error, Result};
-pub use indexer::IndexOp;
+pub use indexer::{IndexOp, NewAxis, TensorIndexer};
 pub use layout::Layout;
+pub use scalar::{TensorOrScalar, TensorScalar};
 pub use op::{CustomOp1, CustomOp2, CustomOp3};
 pub use shape::{Shape, D};
 pub use storage::Storage;
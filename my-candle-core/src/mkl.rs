@@ -25,6 +25,12 @@ mod ffi {
         pub fn vdMul(n: c_int, a: *const c_double, b: *const c_double, y: *mut c_double);
         pub fn vsDiv(n: c_int, a: *const c_float, b: *const c_float, y: *mut c_float);
         pub fn vdDiv(n: c_int, a: *const c_double, b: *const c_double, y: *mut c_double);
+        pub fn vsPow(n: c_int, a: *const c_float, b: *const c_float, y: *mut c_float);
+        pub fn vdPow(n: c_int, a: *const c_double, b: *const c_double, y: *mut c_double);
+        pub fn vsFmax(n: c_int, a: *const c_float, b: *const c_float, y: *mut c_float);
+        pub fn vdFmax(n: c_int, a: *const c_double, b: *const c_double, y: *mut c_double);
+        pub fn vsFmin(n: c_int, a: *const c_float, b: *const c_float, y: *mut c_float);
+        pub fn vdFmin(n: c_int, a: *const c_double, b: *const c_double, y: *mut c_double);
 
         pub fn sgemm_(
             transa: *const c_char,
@@ -298,7 +304,7 @@ pub fn vd_sqr(a: &[f64], y: &mut [f64]) {
 }
 
 #[inline]
-fn vs_tanh(a: &[f32], y: &mut [f32]) {
+pub fn vs_tanh(a: &[f32], y: &mut [f32]) {
     let a_len = a.len();
     let y_len = y.len();
     if a_len != y_len {
@@ -308,7 +314,7 @@ fn vs_tanh(a: &[f32], y: &mut [f32]) {
 }
 
 #[inline]
-fn vd_tanh(a: &[f64], y: &mut [f64]) {
+pub fn vd_tanh(a: &[f64], y: &mut [f64]) {
     let a_len = a.len();
     let y_len = y.len();
     if a_len != y_len {
@@ -352,6 +358,44 @@ pub fn vd_gelu(vs: &[f64], ys: &mut [f64]) {
     }
 }
 
+#[inline]
+pub fn vs_sigmoid(vs: &[f32], ys: &mut [f32]) {
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y = -v
+    }
+    unsafe { ffi::vsExp(ys.len() as i32, ys.as_ptr(), ys.as_mut_ptr()) }
+    for y in ys.iter_mut() {
+        *y = 1.0 / (1.0 + *y)
+    }
+}
+
+#[inline]
+pub fn vd_sigmoid(vs: &[f64], ys: &mut [f64]) {
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y = -v
+    }
+    unsafe { ffi::vdExp(ys.len() as i32, ys.as_ptr(), ys.as_mut_ptr()) }
+    for y in ys.iter_mut() {
+        *y = 1.0 / (1.0 + *y)
+    }
+}
+
+#[inline]
+pub fn vs_silu(vs: &[f32], ys: &mut [f32]) {
+    vs_sigmoid(vs, ys);
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y *= v
+    }
+}
+
+#[inline]
+pub fn vd_silu(vs: &[f64], ys: &mut [f64]) {
+    vd_sigmoid(vs, ys);
+    for (&v, y) in vs.iter().zip(ys.iter_mut()) {
+        *y *= v
+    }
+}
+
 macro_rules! binary_op {
     ($fn_name:ident, $ty:ty, $mkl_name:ident) => {
         #[inline]
@@ -376,4 +420,30 @@ binary_op!(vd_sub, f64, vdSub);
 binary_op!(vs_mul, f32, vsMul);
 binary_op!(vd_mul, f64, vdMul);
 binary_op!(vs_div, f32, vsDiv);
-binary_op!(vd_div, f64, vdDiv);
\ No newline at end of file
+binary_op!(vd_div, f64, vdDiv);
+binary_op!(vs_pow, f32, vsPow);
+binary_op!(vd_pow, f64, vdPow);
+binary_op!(vs_vendor_max, f32, vsFmax);
+binary_op!(vd_vendor_max, f64, vdFmax);
+binary_op!(vs_vendor_min, f32, vsFmin);
+binary_op!(vd_vendor_min, f64, vdFmin);
+
+// vsFmax and friends return the other operand when one of them is NaN, the NaNs are propagated
+// afterwards to match the non-vectorized kernels.
+macro_rules! binary_op_nan {
+    ($fn_name:ident, $ty:ty, $vendor_fn_name:ident) => {
+        #[inline]
+        pub fn $fn_name(a: &[$ty], b: &[$ty], y: &mut [$ty]) {
+            $vendor_fn_name(a, b, y);
+            for ((y, a), b) in y.iter_mut().zip(a.iter()).zip(b.iter()) {
+                if a.is_nan() || b.is_nan() {
+                    *y = <$ty>::NAN
+                }
+            }
+        }
+    };
+}
+binary_op_nan!(vs_max, f32, vs_vendor_max);
+binary_op_nan!(vd_max, f64, vd_vendor_max);
+binary_op_nan!(vs_min, f32, vs_vendor_min);
+binary_op_nan!(vd_min, f64, vd_vendor_min);
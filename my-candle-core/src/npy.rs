@@ -64,6 +64,11 @@ impl Header {
             DType::F64 => "f8",
             DType::U32 => "u4",
             DType::U8 => "u1",
+            DType::Bool => "b1",
+            DType::I8 => "i1",
+            DType::I16 => "i2",
+            DType::I32 => "i4",
+            DType::I64 => "i8",
         };
         if !shape.is_empty() {
             shape.push(',')
@@ -136,13 +141,13 @@ impl Header {
                     "e" | "f2" => DType::F16,
                     "f" | "f4" => DType::F32,
                     "d" | "f8" => DType::F64,
-                    // "i" | "i4" => DType::S32,
-                    // "q" | "i8" => DType::S64,
-                    // "h" | "i2" => DType::S16,
-                    // "b" | "i1" => DType::S8,
+                    "i" | "i4" => DType::I32,
+                    "q" | "i8" => DType::I64,
+                    "h" | "i2" => DType::I16,
+                    "b" | "i1" => DType::I8,
                     "B" | "u1" => DType::U8,
                     "I" | "u4" => DType::U32,
-                    "?" | "b1" => DType::U8,
+                    "?" | "b1" => DType::Bool,
                     // "F" | "F4" => DType::C64,
                     // "D" | "F8" => DType::C128,
                     descr => return Err(Error::Npy(format!("unrecognized descr {descr}"))),
@@ -201,11 +206,36 @@ impl Tensor {
                 reader.read_exact(&mut data_t)?;
                 Tensor::from_vec(data_t, shape, &Device::Cpu)
             }
+            DType::Bool => {
+                let mut data_t = vec![0u8; elem_count];
+                reader.read_exact(&mut data_t)?;
+                Tensor::from_vec(data_t, shape, &Device::Cpu)?.to_dtype(DType::Bool)
+            }
             DType::U32 => {
                 let mut data_t = vec![0u32; elem_count];
                 reader.read_u32_into::<LittleEndian>(&mut data_t)?;
                 Tensor::from_vec(data_t, shape, &Device::Cpu)
             }
+            DType::I8 => {
+                let mut data_t = vec![0i8; elem_count];
+                reader.read_i8_into(&mut data_t)?;
+                Tensor::from_vec(data_t, shape, &Device::Cpu)
+            }
+            DType::I16 => {
+                let mut data_t = vec![0i16; elem_count];
+                reader.read_i16_into::<LittleEndian>(&mut data_t)?;
+                Tensor::from_vec(data_t, shape, &Device::Cpu)
+            }
+            DType::I32 => {
+                let mut data_t = vec![0i32; elem_count];
+                reader.read_i32_into::<LittleEndian>(&mut data_t)?;
+                Tensor::from_vec(data_t, shape, &Device::Cpu)
+            }
+            DType::I64 => {
+                let mut data_t = vec![0i64; elem_count];
+                reader.read_i64_into::<LittleEndian>(&mut data_t)?;
+                Tensor::from_vec(data_t, shape, &Device::Cpu)
+            }
         }
     }
 
@@ -217,8 +247,6 @@ impl Tensor {
         if header.fortran_order {
             return Err(Error::Npy("fortran order not supported".to_string()));
         }
-        let mut data: Vec<u8> = vec![];
-        reader.read_to_end(&mut data)?;
         Self::from_reader(header.shape(), header.descr, &mut reader)
     }
 
@@ -397,5 +425,16 @@ mod tests {
             h.to_string().unwrap(),
             "{'descr': '<u4', 'fortran_order': False, 'shape': (), }"
         );
+
+        let h = "{'descr': '<i8', 'fortran_order': False, 'shape': (3,), }";
+        let h = Header::parse(h).unwrap();
+        assert_eq!(h.descr, DType::I64);
+        assert_eq!(
+            h.to_string().unwrap(),
+            "{'descr': '<i8', 'fortran_order': False, 'shape': (3,), }"
+        );
+
+        let h = "{'descr': '|b1', 'fortran_order': False, 'shape': (2, 2), }";
+        assert_eq!(Header::parse(h).unwrap().descr, DType::Bool);
     }
 }
@@ -0,0 +1,635 @@
+use super::proto::{self, AttributeProto, ModelProto, NodeProto};
+use crate::error::Result;
+use crate::{DType, Device, Tensor};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+/// The operators from the default `ai.onnx` domain that can be evaluated.
+pub const SUPPORTED_OPS: &[&str] = &[
+    "Abs",
+    "Add",
+    "Cast",
+    "Ceil",
+    "Clip",
+    "Concat",
+    "Constant",
+    "Conv",
+    "Cos",
+    "Div",
+    "Dropout",
+    "Equal",
+    "Erf",
+    "Exp",
+    "Flatten",
+    "Floor",
+    "Gather",
+    "Gelu",
+    "Gemm",
+    "Greater",
+    "GreaterOrEqual",
+    "Identity",
+    "LayerNormalization",
+    "Less",
+    "LessOrEqual",
+    "Log",
+    "LogSoftmax",
+    "MatMul",
+    "Mul",
+    "Neg",
+    "Pow",
+    "Reciprocal",
+    "ReduceMean",
+    "ReduceSum",
+    "Relu",
+    "Reshape",
+    "Shape",
+    "Sigmoid",
+    "Sin",
+    "Slice",
+    "Softmax",
+    "Sqrt",
+    "Squeeze",
+    "Sub",
+    "Tanh",
+    "Transpose",
+    "Unsqueeze",
+    "Where",
+];
+
+// The attributes that can hold the value of a Constant node, the node may also carry unrelated
+// ones such as a doc string.
+const CONSTANT_VALUE_ATTRS: [&str; 5] = [
+    "value",
+    "value_float",
+    "value_int",
+    "value_floats",
+    "value_ints",
+];
+
+fn is_supported(node: &NodeProto) -> bool {
+    (node.domain.is_empty() || node.domain == "ai.onnx")
+        && SUPPORTED_OPS.contains(&node.op_type.as_str())
+}
+
+fn get_attr<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute.iter().find(|attr| attr.name == name)
+}
+
+fn attr_i(node: &NodeProto, name: &str, default: i64) -> i64 {
+    get_attr(node, name).map_or(default, |attr| attr.i)
+}
+
+fn attr_f(node: &NodeProto, name: &str, default: f32) -> f32 {
+    get_attr(node, name).map_or(default, |attr| attr.f)
+}
+
+fn attr_ints<'a>(node: &'a NodeProto, name: &str) -> Option<&'a [i64]> {
+    get_attr(node, name).map(|attr| attr.ints.as_slice())
+}
+
+fn attr_s<'a>(node: &'a NodeProto, name: &str) -> Option<&'a [u8]> {
+    get_attr(node, name).map(|attr| attr.s.as_slice())
+}
+
+fn normalize_axis(axis: i64, rank: usize) -> Result<usize> {
+    let rank = rank as i64;
+    let axis = if axis < 0 { axis + rank } else { axis };
+    if axis < 0 || axis >= rank {
+        crate::bail!("onnx: axis {axis} is out of range for rank {rank}")
+    }
+    Ok(axis as usize)
+}
+
+fn to_i64s(t: &Tensor) -> Result<Vec<i64>> {
+    t.flatten_all()?.to_dtype(DType::I64)?.to_vec1::<i64>()
+}
+
+/// The dims normalized by `normalize_axis` and sorted in increasing order.
+fn normalize_axes(axes: &[i64], rank: usize) -> Result<Vec<usize>> {
+    let mut axes = axes
+        .iter()
+        .map(|&axis| normalize_axis(axis, rank))
+        .collect::<Result<Vec<_>>>()?;
+    axes.sort();
+    Ok(axes)
+}
+
+fn broadcast_shapes(ts: &[&Tensor]) -> Result<Vec<usize>> {
+    let rank = ts.iter().map(|t| t.rank()).max().unwrap_or(0);
+    let mut dims = vec![1; rank];
+    for t in ts.iter() {
+        let offset = rank - t.rank();
+        for (i, &d) in t.dims().iter().enumerate() {
+            let dim = &mut dims[offset + i];
+            if *dim == 1 {
+                *dim = d
+            } else if d != 1 && d != *dim {
+                let shapes: Vec<_> = ts.iter().map(|t| t.dims()).collect();
+                crate::bail!("onnx: cannot broadcast shapes {shapes:?}")
+            }
+        }
+    }
+    Ok(dims)
+}
+
+fn softmax(xs: &Tensor, dim: usize, log: bool) -> Result<Tensor> {
+    let max = xs.max_keepdim(dim)?;
+    let diff = xs.broadcast_sub(&max)?;
+    let sum = diff.exp()?.sum_keepdim(dim)?;
+    if log {
+        diff.broadcast_sub(&sum.log()?)
+    } else {
+        diff.exp()?.broadcast_div(&sum)
+    }
+}
+
+/// Reshapes `xs` to 2D, the first dim being the product of the dims before `axis`. The axis can
+/// be equal to the rank of `xs`.
+fn flatten(xs: &Tensor, axis: i64) -> Result<Tensor> {
+    let axis = if axis == xs.rank() as i64 {
+        xs.rank()
+    } else {
+        normalize_axis(axis, xs.rank())?
+    };
+    let d0: usize = xs.dims()[..axis].iter().product();
+    let d1: usize = xs.dims()[axis..].iter().product();
+    xs.reshape((d0, d1))
+}
+
+fn reduce(node: &NodeProto, xs: &Tensor, axes: Option<Vec<i64>>, mean: bool) -> Result<Tensor> {
+    let keepdims = attr_i(node, "keepdims", 1) == 1;
+    let axes = match axes {
+        Some(axes) if !axes.is_empty() => normalize_axes(&axes, xs.rank())?,
+        _ if attr_i(node, "noop_with_empty_axes", 0) == 1 => return Ok(xs.clone()),
+        _ => (0..xs.rank()).collect(),
+    };
+    match (mean, keepdims) {
+        (true, true) => xs.mean_keepdim(axes),
+        (true, false) => xs.mean(axes),
+        (false, true) => xs.sum_keepdim(axes),
+        (false, false) => xs.sum(axes),
+    }
+}
+
+fn conv(node: &NodeProto, xs: &Tensor, ws: &Tensor, bias: Option<&Tensor>) -> Result<Tensor> {
+    if let Some(auto_pad) = attr_s(node, "auto_pad") {
+        if auto_pad != b"NOTSET" {
+            crate::bail!("onnx: Conv only supports explicit padding")
+        }
+    }
+    // The convolutions use the same padding, stride and dilation for all the spatial dims.
+    let non_negative = |name: &str, v: i64| -> Result<usize> {
+        match usize::try_from(v) {
+            Ok(v) => Ok(v),
+            Err(_) => crate::bail!("onnx: Conv {name} cannot be negative, got {v}"),
+        }
+    };
+    let uniform = |name: &str, default: i64| -> Result<usize> {
+        match attr_ints(node, name) {
+            None | Some([]) => non_negative(name, default),
+            Some(vs) if vs.iter().all(|&v| v == vs[0]) => non_negative(name, vs[0]),
+            Some(vs) => crate::bail!("onnx: Conv only supports uniform {name}, got {vs:?}"),
+        }
+    };
+    let padding = uniform("pads", 0)?;
+    let stride = uniform("strides", 1)?;
+    let dilation = uniform("dilations", 1)?;
+    let groups = non_negative("group", attr_i(node, "group", 1))?;
+    let ys = match xs.rank() {
+        3 => xs.conv1d(ws, padding, stride, dilation, groups)?,
+        4 => xs.conv2d(ws, padding, stride, dilation, groups)?,
+        rank => crate::bail!("onnx: Conv is only supported for 1d and 2d inputs, got rank {rank}"),
+    };
+    match bias {
+        None => Ok(ys),
+        Some(bias) => {
+            let mut bias_dims = vec![1; ys.rank()];
+            bias_dims[1] = bias.elem_count();
+            ys.broadcast_add(&bias.reshape(bias_dims)?)
+        }
+    }
+}
+
+fn slice(xs: &Tensor, starts: &[i64], ends: &[i64], axes: &[i64], steps: &[i64]) -> Result<Tensor> {
+    let mut xs = xs.clone();
+    for (i, (&start, &end)) in starts.iter().zip(ends.iter()).enumerate() {
+        let dim = normalize_axis(axes[i], xs.rank())?;
+        let step = steps[i];
+        let dim_len = xs.dim(dim)? as i64;
+        let resolve = |v: i64| if v < 0 { v + dim_len } else { v };
+        if step > 0 {
+            let start = resolve(start).clamp(0, dim_len);
+            let end = resolve(end).clamp(0, dim_len);
+            if step == 1 {
+                xs = xs.narrow(dim, start as usize, (end - start).max(0) as usize)?;
+                continue;
+            }
+            let idxs: Vec<u32> = (start..end)
+                .step_by(step as usize)
+                .map(|i| i as u32)
+                .collect();
+            let idxs = Tensor::new(idxs.as_slice(), &Device::Cpu)?;
+            xs = xs.index_select(&idxs, dim)?
+        } else if step < 0 {
+            let start = resolve(start).clamp(-1, dim_len - 1);
+            let end = resolve(end).clamp(-1, dim_len - 1);
+            let mut idxs = vec![];
+            let mut i = start;
+            while i > end {
+                idxs.push(i as u32);
+                i += step
+            }
+            let idxs = Tensor::new(idxs.as_slice(), &Device::Cpu)?;
+            xs = xs.index_select(&idxs, dim)?
+        } else {
+            crate::bail!("onnx: Slice step cannot be 0")
+        }
+    }
+    Ok(xs)
+}
+
+// `opset` is the version of the default domain imported by the model.
+fn eval_node(
+    node: &NodeProto,
+    values: &HashMap<String, Tensor>,
+    opset: i64,
+) -> Result<Vec<Tensor>> {
+    let input_opt = |i: usize| -> Result<Option<&Tensor>> {
+        match node.input.get(i) {
+            None => Ok(None),
+            Some(name) if name.is_empty() => Ok(None),
+            Some(name) => match values.get(name) {
+                Some(t) => Ok(Some(t)),
+                None => crate::bail!("onnx: cannot find {name} for op {}", node.name),
+            },
+        }
+    };
+    let input = |i: usize| -> Result<&Tensor> {
+        match input_opt(i)? {
+            Some(t) => Ok(t),
+            None => crate::bail!("onnx: missing input {i} for op {}", node.name),
+        }
+    };
+    let ys = match node.op_type.as_str() {
+        "Add" => input(0)?.broadcast_add(input(1)?)?,
+        "Sub" => input(0)?.broadcast_sub(input(1)?)?,
+        "Mul" => input(0)?.broadcast_mul(input(1)?)?,
+        "Div" => input(0)?.broadcast_div(input(1)?)?,
+        "Pow" => {
+            let xs = input(0)?;
+            xs.broadcast_pow(&input(1)?.to_dtype(xs.dtype())?)?
+        }
+        "Equal" => input(0)?.broadcast_eq(input(1)?)?,
+        "Greater" => input(0)?.broadcast_gt(input(1)?)?,
+        "GreaterOrEqual" => input(0)?.broadcast_ge(input(1)?)?,
+        "Less" => input(0)?.broadcast_lt(input(1)?)?,
+        "LessOrEqual" => input(0)?.broadcast_le(input(1)?)?,
+        "Where" => {
+            let (cond, on_true, on_false) = (input(0)?, input(1)?, input(2)?);
+            let dims = broadcast_shapes(&[cond, on_true, on_false])?;
+            cond.broadcast_as(dims.as_slice())?.where_cond(
+                &on_true.broadcast_as(dims.as_slice())?,
+                &on_false.broadcast_as(dims.as_slice())?,
+            )?
+        }
+        "MatMul" => input(0)?.matmul(input(1)?)?,
+        "Gemm" => {
+            let mut a = input(0)?.clone();
+            let mut b = input(1)?.clone();
+            if attr_i(node, "transA", 0) == 1 {
+                a = a.t()?
+            }
+            if attr_i(node, "transB", 0) == 1 {
+                b = b.t()?
+            }
+            let alpha = attr_f(node, "alpha", 1.) as f64;
+            let beta = attr_f(node, "beta", 1.) as f64;
+            let ys = a.matmul(&b)?.affine(alpha, 0.)?;
+            match input_opt(2)? {
+                None => ys,
+                Some(c) => ys.broadcast_add(&c.affine(beta, 0.)?)?,
+            }
+        }
+        "Conv" => conv(node, input(0)?, input(1)?, input_opt(2)?)?,
+        "Relu" => input(0)?.relu()?,
+        "Sigmoid" => input(0)?.sigmoid()?,
+        "Tanh" => input(0)?.tanh()?,
+        "Exp" => input(0)?.exp()?,
+        "Log" => input(0)?.log()?,
+        "Sqrt" => input(0)?.sqrt()?,
+        "Neg" => input(0)?.neg()?,
+        "Abs" => input(0)?.abs()?,
+        "Erf" => input(0)?.erf()?,
+        "Reciprocal" => input(0)?.recip()?,
+        "Floor" => input(0)?.floor()?,
+        "Ceil" => input(0)?.ceil()?,
+        "Sin" => input(0)?.sin()?,
+        "Cos" => input(0)?.cos()?,
+        "Gelu" => match attr_s(node, "approximate") {
+            Some(b"tanh") => input(0)?.gelu()?,
+            _ => input(0)?.gelu_erf()?,
+        },
+        "Softmax" | "LogSoftmax" if opset < 13 => {
+            // Before opset 13 the input is coerced to 2D at `axis` and the softmax is computed
+            // over all the trailing dims.
+            let xs = input(0)?;
+            let ys = flatten(xs, attr_i(node, "axis", 1))?;
+            let ys = if node.op_type == "LogSoftmax" {
+                ys.log_softmax_last_dim()?
+            } else {
+                ys.softmax_last_dim()?
+            };
+            ys.reshape(xs.shape())?
+        }
+        "Softmax" | "LogSoftmax" => {
+            let xs = input(0)?;
+            let dim = normalize_axis(attr_i(node, "axis", -1), xs.rank())?;
+            let log = node.op_type == "LogSoftmax";
+            match (dim + 1 == xs.rank(), log) {
+                (true, false) => xs.softmax_last_dim()?,
+                (true, true) => xs.log_softmax_last_dim()?,
+                (false, log) => softmax(xs, dim, log)?,
+            }
+        }
+        "LayerNormalization" => {
+            let xs = input(0)?;
+            let axis = normalize_axis(attr_i(node, "axis", -1), xs.rank())?;
+            let eps = attr_f(node, "epsilon", 1e-5) as f64;
+            let dims: Vec<usize> = (axis..xs.rank()).collect();
+            let mean = xs.mean_keepdim(dims.as_slice())?;
+            let xs = xs.broadcast_sub(&mean)?;
+            let var = xs.sqr()?.mean_keepdim(dims.as_slice())?;
+            let ys = xs
+                .broadcast_div(&(var + eps)?.sqrt()?)?
+                .broadcast_mul(input(1)?)?;
+            match input_opt(2)? {
+                None => ys,
+                Some(bias) => ys.broadcast_add(bias)?,
+            }
+        }
+        "Reshape" => {
+            let xs = input(0)?;
+            let allow_zero = attr_i(node, "allowzero", 0) == 1;
+            let shape = to_i64s(input(1)?)?;
+            let mut dims = Vec::with_capacity(shape.len());
+            let mut infer_dim = None;
+            for (i, &d) in shape.iter().enumerate() {
+                match d {
+                    0 if !allow_zero => dims.push(xs.dim(i)?),
+                    -1 if infer_dim.is_none() => {
+                        infer_dim = Some(i);
+                        dims.push(1)
+                    }
+                    d if d >= 0 => dims.push(d as usize),
+                    d => crate::bail!("onnx: invalid Reshape target {shape:?}, dim {d}"),
+                }
+            }
+            if let Some(i) = infer_dim {
+                let known: usize = dims.iter().product();
+                if known == 0 || xs.elem_count() % known != 0 {
+                    crate::bail!("onnx: cannot reshape {:?} to {shape:?}", xs.dims())
+                }
+                dims[i] = xs.elem_count() / known
+            }
+            xs.reshape(dims)?
+        }
+        "Transpose" => {
+            let xs = input(0)?;
+            let perm = match attr_ints(node, "perm") {
+                Some(perm) => perm
+                    .iter()
+                    .map(|&p| {
+                        if p < 0 {
+                            crate::bail!("onnx: Transpose perm {perm:?} has a negative value")
+                        }
+                        Ok(p as usize)
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+                None => (0..xs.rank()).rev().collect::<Vec<_>>(),
+            };
+            xs.permute(perm)?
+        }
+        "Gather" => {
+            let (xs, indices) = (input(0)?, input(1)?);
+            let axis = normalize_axis(attr_i(node, "axis", 0), xs.rank())?;
+            let dim_len = xs.dim(axis)? as i64;
+            let idxs = to_i64s(indices)?
+                .into_iter()
+                .map(|i| {
+                    if i < -dim_len || i >= dim_len {
+                        crate::bail!("onnx: Gather index {i} is out of range for dim {dim_len}")
+                    }
+                    Ok(if i < 0 { i + dim_len } else { i } as u32)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let idxs = Tensor::new(idxs.as_slice(), &Device::Cpu)?;
+            let mut dims = xs.dims()[..axis].to_vec();
+            dims.extend_from_slice(indices.dims());
+            dims.extend_from_slice(&xs.dims()[axis + 1..]);
+            xs.index_select(&idxs, axis)?.reshape(dims)?
+        }
+        "Concat" => {
+            let xs = (0..node.input.len())
+                .map(input)
+                .collect::<Result<Vec<_>>>()?;
+            let axis = normalize_axis(attr_i(node, "axis", 0), xs[0].rank())?;
+            Tensor::cat(&xs, axis)?
+        }
+        "Flatten" => flatten(input(0)?, attr_i(node, "axis", 1))?,
+        "Unsqueeze" => {
+            let xs = input(0)?;
+            let axes = match input_opt(1)? {
+                Some(axes) => to_i64s(axes)?,
+                None => attr_ints(node, "axes").unwrap_or_default().to_vec(),
+            };
+            let mut ys = xs.clone();
+            for axis in normalize_axes(&axes, xs.rank() + axes.len())? {
+                ys = ys.unsqueeze(axis)?
+            }
+            ys
+        }
+        "Squeeze" => {
+            let xs = input(0)?;
+            let axes = match input_opt(1)? {
+                Some(axes) => Some(to_i64s(axes)?),
+                None => attr_ints(node, "axes").map(|axes| axes.to_vec()),
+            };
+            let axes = match axes {
+                Some(axes) => normalize_axes(&axes, xs.rank())?,
+                None => (0..xs.rank()).filter(|&i| xs.dims()[i] == 1).collect(),
+            };
+            if let Some(&axis) = axes.iter().find(|&&axis| xs.dims()[axis] != 1) {
+                crate::bail!("onnx: cannot squeeze axis {axis} of {:?}", xs.dims())
+            }
+            let mut ys = xs.clone();
+            for &axis in axes.iter().rev() {
+                ys = ys.squeeze(axis)?
+            }
+            ys
+        }
+        "Shape" => {
+            let dims: Vec<i64> = input(0)?.dims().iter().map(|&d| d as i64).collect();
+            Tensor::new(dims.as_slice(), &Device::Cpu)?
+        }
+        "Identity" | "Dropout" => input(0)?.clone(),
+        "Cast" => {
+            let dtype = proto::dtype_from_onnx(attr_i(node, "to", 0) as i32)?;
+            input(0)?.to_dtype(dtype)?
+        }
+        "Constant" => {
+            let value = CONSTANT_VALUE_ATTRS
+                .iter()
+                .find_map(|name| get_attr(node, name));
+            match value {
+                Some(attr) if attr.name == "value" => match &attr.t {
+                    Some(t) => t.to_tensor()?,
+                    None => crate::bail!("onnx: Constant {} has no value", node.name),
+                },
+                Some(attr) if attr.name == "value_float" => Tensor::new(attr.f, &Device::Cpu)?,
+                Some(attr) if attr.name == "value_int" => Tensor::new(attr.i, &Device::Cpu)?,
+                Some(attr) if attr.name == "value_floats" => {
+                    Tensor::new(attr.floats.as_slice(), &Device::Cpu)?
+                }
+                Some(attr) => Tensor::new(attr.ints.as_slice(), &Device::Cpu)?,
+                None => crate::bail!("onnx: unsupported attributes for Constant {}", node.name),
+            }
+        }
+        "ReduceMean" | "ReduceSum" => {
+            let axes = match input_opt(1)? {
+                Some(axes) => Some(to_i64s(axes)?),
+                None => attr_ints(node, "axes").map(|axes| axes.to_vec()),
+            };
+            reduce(node, input(0)?, axes, node.op_type == "ReduceMean")?
+        }
+        "Slice" => {
+            let xs = input(0)?;
+            let starts = to_i64s(input(1)?)?;
+            let ends = to_i64s(input(2)?)?;
+            let axes = match input_opt(3)? {
+                Some(axes) => to_i64s(axes)?,
+                None => (0..starts.len() as i64).collect(),
+            };
+            let steps = match input_opt(4)? {
+                Some(steps) => to_i64s(steps)?,
+                None => vec![1; starts.len()],
+            };
+            if [ends.len(), axes.len(), steps.len()]
+                .iter()
+                .any(|&l| l != starts.len())
+            {
+                crate::bail!(
+                    "onnx: mismatched starts, ends, axes and steps for Slice {}",
+                    node.name
+                )
+            }
+            slice(xs, &starts, &ends, &axes, &steps)?
+        }
+        "Clip" => {
+            let xs = input(0)?;
+            let (min, max) = match (input_opt(1)?, input_opt(2)?) {
+                (None, None)
+                    if get_attr(node, "min").is_some() || get_attr(node, "max").is_some() =>
+                {
+                    // Before opset 11 the bounds were given as attributes.
+                    let min = attr_f(node, "min", f32::MIN);
+                    let max = attr_f(node, "max", f32::MAX);
+                    let dev = &Device::Cpu;
+                    (Some(Tensor::new(min, dev)?), Some(Tensor::new(max, dev)?))
+                }
+                (min, max) => (min.cloned(), max.cloned()),
+            };
+            let mut ys = xs.clone();
+            if let Some(min) = min {
+                ys = ys.broadcast_maximum(&min.to_dtype(xs.dtype())?)?
+            }
+            if let Some(max) = max {
+                ys = ys.broadcast_minimum(&max.to_dtype(xs.dtype())?)?
+            }
+            ys
+        }
+        op_type => crate::bail!("onnx: unsupported op {op_type}"),
+    };
+    Ok(vec![ys])
+}
+
+/// Evaluates the graph of an ONNX model on the cpu.
+///
+/// The `inputs` map the names of the graph inputs to their values, the initializers of the graph
+/// do not have to be provided. The graph outputs are returned by name. An error listing all the
+/// unsupported operators is returned before evaluating any node if the graph uses some of them.
+pub fn simple_eval(
+    model: &ModelProto,
+    inputs: HashMap<String, Tensor>,
+) -> Result<HashMap<String, Tensor>> {
+    let graph = match &model.graph {
+        None => crate::bail!("onnx: the model has no graph"),
+        Some(graph) => graph,
+    };
+    let mut unsupported = vec![];
+    for node in graph.node.iter().filter(|node| !is_supported(node)) {
+        let op_type = if node.domain.is_empty() {
+            node.op_type.clone()
+        } else {
+            format!("{}.{}", node.domain, node.op_type)
+        };
+        if !unsupported.contains(&op_type) {
+            unsupported.push(op_type)
+        }
+    }
+    if !unsupported.is_empty() {
+        crate::bail!("onnx: unsupported ops {}", unsupported.join(", "))
+    }
+
+    // Models without an opset import for the default domain are evaluated with the latest
+    // semantics.
+    let opset = model
+        .opset_import
+        .iter()
+        .find(|o| o.domain.is_empty() || o.domain == "ai.onnx")
+        .map_or(i64::MAX, |o| o.version);
+
+    // An initializer that is also a graph input only provides a default value, the values
+    // passed by the caller take precedence and get their shape checked.
+    let provided: HashSet<String> = inputs.keys().cloned().collect();
+    let mut values = inputs;
+    for t in graph.initializer.iter() {
+        if let Entry::Vacant(entry) = values.entry(t.name.clone()) {
+            entry.insert(t.to_tensor()?);
+        }
+    }
+    for input in graph.input.iter() {
+        if !values.contains_key(&input.name) {
+            crate::bail!("onnx: missing value for input {}", input.name)
+        }
+        if !provided.contains(&input.name) {
+            continue;
+        }
+        let dims = values[&input.name].dims();
+        let shape_matches = dims.len() == input.shape.len()
+            && dims
+                .iter()
+                .zip(input.shape.iter())
+                .all(|(&d, expected)| expected.map_or(true, |e| e == d));
+        if !input.shape.is_empty() && !shape_matches {
+            crate::bail!(
+                "onnx: unexpected shape {dims:?} for input {}, expected {:?}",
+                input.name,
+                input.shape
+            )
+        }
+    }
+
+    for node in graph.node.iter() {
+        let ys = eval_node(node, &values, opset)?;
+        for (name, ys) in node.output.iter().zip(ys) {
+            values.insert(name.clone(), ys);
+        }
+    }
+    graph
+        .output
+        .iter()
+        .map(|output| match values.remove(&output.name) {
+            Some(ys) => Ok((output.name.clone(), ys)),
+            None => crate::bail!("onnx: cannot find output {}", output.name),
+        })
+        .collect()
+}
@@ -0,0 +1,562 @@
+//! Import of ONNX models for inference.
+//!
+//! The protobuf messages of an `.onnx` file are decoded by the [`proto`] module, the initializers
+//! of the graph are converted to tensors and the nodes are evaluated on the cpu using the tensor
+//! ops. Only the operators listed in [`SUPPORTED_OPS`] can be evaluated, `simple_eval` returns an
+//! error listing all the unsupported operators of a graph before running any of its nodes.
+//!
+//! ```no_run
+//! use my_candle_core::{onnx, Device, Tensor};
+//! use std::collections::HashMap;
+//! let model = onnx::read_file("model.onnx")?;
+//! let xs = Tensor::zeros((1, 3, 224, 224), my_candle_core::DType::F32, &Device::Cpu)?;
+//! let inputs = HashMap::from([("input".to_string(), xs)]);
+//! let outputs = onnx::simple_eval(&model, inputs)?;
+//! # Ok::<(), my_candle_core::Error>(())
+//! ```
+use crate::error::Result;
+use std::path::Path;
+
+mod eval;
+pub mod proto;
+
+pub use eval::{simple_eval, SUPPORTED_OPS};
+
+/// Reads and decodes an ONNX model file, tensors using external data are not supported.
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<proto::ModelProto> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|e| crate::Error::from(e).with_path(path))?;
+    proto::ModelProto::decode(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Device, Tensor};
+    use std::collections::HashMap;
+
+    fn varint(mut v: u64, dst: &mut Vec<u8>) {
+        while v >= 0x80 {
+            dst.push((v as u8) | 0x80);
+            v >>= 7
+        }
+        dst.push(v as u8)
+    }
+
+    fn field_varint(field: u64, v: u64, dst: &mut Vec<u8>) {
+        varint(field << 3, dst);
+        varint(v, dst)
+    }
+
+    fn field_bytes(field: u64, bytes: &[u8], dst: &mut Vec<u8>) {
+        varint((field << 3) | 2, dst);
+        varint(bytes.len() as u64, dst);
+        dst.extend_from_slice(bytes)
+    }
+
+    fn node(op_type: &str, inputs: &[&str], output: &str) -> Vec<u8> {
+        let mut node = vec![];
+        for input in inputs {
+            field_bytes(1, input.as_bytes(), &mut node)
+        }
+        field_bytes(2, output.as_bytes(), &mut node);
+        field_bytes(4, op_type.as_bytes(), &mut node);
+        node
+    }
+
+    fn value_info(name: &str) -> Vec<u8> {
+        let mut value_info = vec![];
+        field_bytes(1, name.as_bytes(), &mut value_info);
+        value_info
+    }
+
+    // y = relu(x @ w) with w a 2x2 float initializer stored as raw data.
+    fn encode_model(op_type: &str) -> Vec<u8> {
+        let mut w = vec![];
+        field_varint(1, 2, &mut w);
+        field_varint(1, 2, &mut w);
+        field_varint(2, 1, &mut w);
+        field_bytes(8, b"w", &mut w);
+        let raw: Vec<u8> = [1f32, -1., 2., 0.5]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        field_bytes(9, &raw, &mut w);
+
+        let mut graph = vec![];
+        field_bytes(1, &node("MatMul", &["x", "w"], "xw"), &mut graph);
+        field_bytes(1, &node(op_type, &["xw"], "y"), &mut graph);
+        field_bytes(5, &w, &mut graph);
+        field_bytes(11, &value_info("x"), &mut graph);
+        field_bytes(12, &value_info("y"), &mut graph);
+
+        let mut model = vec![];
+        field_varint(1, 8, &mut model);
+        field_bytes(7, &graph, &mut model);
+        model
+    }
+
+    #[test]
+    fn onnx_eval() -> Result<()> {
+        let model = proto::ModelProto::decode(&encode_model("Relu"))?;
+        let graph = model.graph.as_ref().unwrap();
+        assert_eq!(model.ir_version, 8);
+        assert_eq!(graph.initializer[0].dims, [2, 2]);
+
+        let xs = Tensor::new(&[[1f32, 1.], [0., -1.]], &Device::Cpu)?;
+        let inputs = HashMap::from([("x".to_string(), xs)]);
+        let outputs = simple_eval(&model, inputs)?;
+        assert_eq!(outputs["y"].to_vec2::<f32>()?, [[3., 0.], [0., 0.]]);
+
+        let inputs = HashMap::new();
+        let err = simple_eval(&model, inputs).unwrap_err();
+        assert!(err.to_string().contains("missing value for input x"));
+
+        let model = proto::ModelProto::decode(&encode_model("Resize"))?;
+        let err = simple_eval(&model, HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("unsupported ops Resize"));
+        Ok(())
+    }
+
+    fn single_node_model(op_type: &str, axis: i64, opset: i64) -> proto::ModelProto {
+        let attr = proto::AttributeProto {
+            name: "axis".to_string(),
+            i: axis,
+            ..Default::default()
+        };
+        let node = proto::NodeProto {
+            op_type: op_type.to_string(),
+            input: vec!["x".to_string()],
+            output: vec!["y".to_string()],
+            attribute: vec![attr],
+            ..Default::default()
+        };
+        let value_info = |name: &str| proto::ValueInfoProto {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let graph = proto::GraphProto {
+            node: vec![node],
+            input: vec![value_info("x")],
+            output: vec![value_info("y")],
+            ..Default::default()
+        };
+        proto::ModelProto {
+            opset_import: vec![proto::OperatorSetIdProto {
+                domain: String::new(),
+                version: opset,
+            }],
+            graph: Some(graph),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn onnx_invalid_inputs() -> Result<()> {
+        // A length delimited field whose length overflows the position.
+        let mut data = vec![];
+        field_varint(1, 8, &mut data);
+        varint((7 << 3) | 2, &mut data);
+        varint(u64::MAX, &mut data);
+        let err = proto::ModelProto::decode(&data).unwrap_err();
+        assert!(err.to_string().contains("unexpected end of protobuf data"));
+
+        let mut t = proto::TensorProto {
+            data_type: proto::data_type::FLOAT,
+            dims: vec![2, -1],
+            raw_data: vec![0; 8],
+            ..Default::default()
+        };
+        let err = t.to_tensor().unwrap_err();
+        assert!(err.to_string().contains("negative dimension -1"));
+        t.dims = vec![1 << 40, 1 << 40];
+        let err = t.to_tensor().unwrap_err();
+        assert!(err.to_string().contains("too many elements"));
+
+        let xs = Tensor::arange(0f32, 8., &Device::Cpu)?.reshape((2, 2, 2))?;
+        let inputs = || HashMap::from([("x".to_string(), xs.clone())]);
+        let ys = simple_eval(&single_node_model("Flatten", 3, 13), inputs())?;
+        assert_eq!(ys["y"].dims(), [8, 1]);
+        let ys = simple_eval(&single_node_model("Flatten", -2, 13), inputs())?;
+        assert_eq!(ys["y"].dims(), [2, 4]);
+        let err = simple_eval(&single_node_model("Flatten", 4, 13), inputs()).unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+        Ok(())
+    }
+
+    #[test]
+    fn onnx_softmax_opset() -> Result<()> {
+        let xs = Tensor::arange(0f32, 8., &Device::Cpu)?.reshape((2, 2, 2))?;
+        let inputs = || HashMap::from([("x".to_string(), xs.clone())]);
+        // Since opset 13 the softmax is computed over the axis only.
+        let ys = simple_eval(&single_node_model("Softmax", 1, 13), inputs())?;
+        let sums = ys["y"].sum_keepdim(1)?.flatten_all()?.to_vec1::<f32>()?;
+        assert!(sums.iter().all(|s| (s - 1.).abs() < 1e-5), "{sums:?}");
+        // Before opset 13 it is computed over all the dims starting at the axis.
+        let ys = simple_eval(&single_node_model("Softmax", 1, 11), inputs())?;
+        assert_eq!(ys["y"].dims(), [2, 2, 2]);
+        let sums = ys["y"].sum((1, 2))?.to_vec1::<f32>()?;
+        assert!(sums.iter().all(|s| (s - 1.).abs() < 1e-5), "{sums:?}");
+        let ys = simple_eval(&single_node_model("LogSoftmax", 1, 11), inputs())?;
+        let sums = ys["y"].exp()?.sum((1, 2))?.to_vec1::<f32>()?;
+        assert!(sums.iter().all(|s| (s - 1.).abs() < 1e-5), "{sums:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn onnx_initializer_inputs() -> Result<()> {
+        let w = proto::TensorProto {
+            name: "w".to_string(),
+            dims: vec![2, 2],
+            data_type: proto::data_type::FLOAT,
+            float_data: vec![1., -1., 2., 0.5],
+            ..Default::default()
+        };
+        let node = |op_type: &str, inputs: &[&str], attribute: Vec<proto::AttributeProto>| {
+            proto::NodeProto {
+                op_type: op_type.to_string(),
+                input: inputs.iter().map(|s| s.to_string()).collect(),
+                output: vec!["y".to_string()],
+                attribute,
+                ..Default::default()
+            }
+        };
+        let value_info = |name: &str, shape: Vec<Option<usize>>| proto::ValueInfoProto {
+            name: name.to_string(),
+            shape,
+            ..Default::default()
+        };
+        let model = |node: proto::NodeProto| proto::ModelProto {
+            graph: Some(proto::GraphProto {
+                node: vec![node],
+                initializer: vec![w.clone()],
+                input: vec![
+                    value_info("x", vec![]),
+                    value_info("w", vec![Some(2), Some(2)]),
+                ],
+                output: vec![value_info("y", vec![])],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let dev = &Device::Cpu;
+        let xs = Tensor::new(&[[1f32, 1.], [0., -1.]], dev)?;
+
+        // The initializer is the default value of the w input, the caller can override it.
+        let matmul = model(node("MatMul", &["x", "w"], vec![]));
+        let inputs = HashMap::from([("x".to_string(), xs.clone())]);
+        let ys = simple_eval(&matmul, inputs)?;
+        assert_eq!(ys["y"].to_vec2::<f32>()?, [[3., -0.5], [-2., -0.5]]);
+        let eye = Tensor::new(&[[1f32, 0.], [0., 1.]], dev)?;
+        let inputs = HashMap::from([("x".to_string(), xs.clone()), ("w".to_string(), eye)]);
+        let ys = simple_eval(&matmul, inputs)?;
+        assert_eq!(ys["y"].to_vec2::<f32>()?, [[1., 1.], [0., -1.]]);
+        let w3 = Tensor::zeros((2, 3), crate::DType::F32, dev)?;
+        let inputs = HashMap::from([("x".to_string(), xs.clone()), ("w".to_string(), w3)]);
+        let err = simple_eval(&matmul, inputs).unwrap_err();
+        assert!(err.to_string().contains("unexpected shape"));
+
+        let ids = Tensor::new(&[-1i64, 1], dev)?;
+        let gather = model(node("Gather", &["w", "x"], vec![]));
+        let ys = simple_eval(&gather, HashMap::from([("x".to_string(), ids)]))?;
+        assert_eq!(ys["y"].to_vec2::<f32>()?, [[2., 0.5], [2., 0.5]]);
+        let ids = Tensor::new(&[-3i64], dev)?;
+        let err = simple_eval(&gather, HashMap::from([("x".to_string(), ids)])).unwrap_err();
+        assert!(err.to_string().contains("Gather index -3 is out of range"));
+
+        let group = proto::AttributeProto {
+            name: "group".to_string(),
+            i: -1,
+            ..Default::default()
+        };
+        let mut conv = model(node("Conv", &["x", "w"], vec![group]));
+        conv.graph.as_mut().unwrap().input[1].shape.clear();
+        let xs = Tensor::zeros((1, 2, 4), crate::DType::F32, dev)?;
+        let w = Tensor::zeros((2, 2, 1), crate::DType::F32, dev)?;
+        let inputs = HashMap::from([("x".to_string(), xs), ("w".to_string(), w)]);
+        let err = simple_eval(&conv, inputs).unwrap_err();
+        assert!(err.to_string().contains("group cannot be negative"));
+        Ok(())
+    }
+
+    fn int_attr(name: &str, i: i64) -> proto::AttributeProto {
+        proto::AttributeProto {
+            name: name.to_string(),
+            i,
+            ..Default::default()
+        }
+    }
+
+    fn float_attr(name: &str, f: f32) -> proto::AttributeProto {
+        proto::AttributeProto {
+            name: name.to_string(),
+            f,
+            ..Default::default()
+        }
+    }
+
+    fn ints_attr(name: &str, ints: &[i64]) -> proto::AttributeProto {
+        proto::AttributeProto {
+            name: name.to_string(),
+            ints: ints.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    // Evaluates a graph made of a single node, the inputs are named after their position and
+    // `None` leaves an optional input out.
+    fn eval_op(
+        op_type: &str,
+        inputs: &[Option<&Tensor>],
+        attribute: Vec<proto::AttributeProto>,
+    ) -> Result<Tensor> {
+        let names: Vec<String> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, t)| t.map_or(String::new(), |_| format!("x{i}")))
+            .collect();
+        let value_info = |name: &str| proto::ValueInfoProto {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let node = proto::NodeProto {
+            op_type: op_type.to_string(),
+            input: names.clone(),
+            output: vec!["y".to_string()],
+            attribute,
+            ..Default::default()
+        };
+        let model = proto::ModelProto {
+            graph: Some(proto::GraphProto {
+                node: vec![node],
+                input: names
+                    .iter()
+                    .filter(|name| !name.is_empty())
+                    .map(|name| value_info(name))
+                    .collect(),
+                output: vec![value_info("y")],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let inputs = names
+            .into_iter()
+            .zip(inputs.iter())
+            .filter_map(|(name, t)| t.map(|t| (name, t.clone())))
+            .collect();
+        let mut ys = simple_eval(&model, inputs)?;
+        Ok(ys.remove("y").unwrap())
+    }
+
+    fn max_diff(xs: &Tensor, ys: &[f32]) -> Result<f32> {
+        let xs = xs.flatten_all()?.to_vec1::<f32>()?;
+        assert_eq!(xs.len(), ys.len());
+        Ok(xs
+            .iter()
+            .zip(ys)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0., f32::max))
+    }
+
+    #[test]
+    fn onnx_gemm_conv_layer_norm() -> Result<()> {
+        let dev = &Device::Cpu;
+        // (a^T b^T) * alpha + c * beta with c broadcast over the rows.
+        let a = Tensor::new(&[[1f32, 2.], [3., 4.], [5., 6.]], dev)?;
+        let b = Tensor::new(&[[1f32, 0., 1.], [0., 1., 0.]], dev)?;
+        let c = Tensor::new(&[1f32, 2.], dev)?;
+        let attrs = vec![
+            int_attr("transA", 1),
+            int_attr("transB", 1),
+            float_attr("alpha", 0.5),
+            float_attr("beta", 2.),
+        ];
+        let ys = eval_op("Gemm", &[Some(&a), Some(&b), Some(&c)], attrs)?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[5., 5.5], [6., 6.]]);
+        let ys = eval_op("Gemm", &[Some(&a.t()?), Some(&b.t()?)], vec![])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[6., 3.], [8., 4.]]);
+
+        let xs = Tensor::arange(0f32, 9., dev)?.reshape((1, 1, 3, 3))?;
+        let ws = Tensor::ones((1, 1, 2, 2), crate::DType::F32, dev)?;
+        let bias = Tensor::new(&[1f32], dev)?;
+        let ys = eval_op("Conv", &[Some(&xs), Some(&ws), Some(&bias)], vec![])?;
+        assert_eq!(ys.dims(), [1, 1, 2, 2]);
+        assert_eq!(ys.flatten_all()?.to_vec1::<f32>()?, [9., 13., 21., 25.]);
+        let attrs = vec![
+            ints_attr("pads", &[1, 1, 1, 1]),
+            ints_attr("strides", &[2, 2]),
+        ];
+        let ys = eval_op("Conv", &[Some(&xs), Some(&ws), Some(&bias)], attrs)?;
+        assert_eq!(ys.flatten_all()?.to_vec1::<f32>()?, [1., 4., 10., 25.]);
+        let attrs = vec![ints_attr("strides", &[1, 2])];
+        let err = eval_op("Conv", &[Some(&xs), Some(&ws)], attrs).unwrap_err();
+        assert!(err.to_string().contains("uniform strides"));
+
+        let xs = Tensor::new(&[[1f32, 2., 3.], [2., 2., 2.]], dev)?;
+        let scale = Tensor::new(&[1f32, 1., 2.], dev)?;
+        let bias = Tensor::new(&[0f32, 0., 1.], dev)?;
+        let attrs = vec![float_attr("epsilon", 1e-6)];
+        let inputs = [Some(&xs), Some(&scale), Some(&bias)];
+        let ys = eval_op("LayerNormalization", &inputs, attrs)?;
+        let s = 1.5f32.sqrt();
+        assert!(max_diff(&ys, &[-s, 0., 2. * s + 1., 0., 0., 1.])? < 1e-4);
+        // Normalizing over all the dims.
+        let ys = eval_op(
+            "LayerNormalization",
+            &inputs[..2],
+            vec![int_attr("axis", 0)],
+        )?;
+        let s = 3f32.sqrt();
+        assert!(max_diff(&ys, &[-s, 0., 2. * s, 0., 0., 0.])? < 1e-4);
+        Ok(())
+    }
+
+    #[test]
+    fn onnx_shape_ops() -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = Tensor::arange(0f32, 6., dev)?.reshape((2, 3))?;
+        let shape = |dims: &[i64]| Tensor::new(dims, dev);
+
+        // A 0 copies the input dim unless allowzero is set, -1 is inferred.
+        let ys = eval_op("Reshape", &[Some(&xs), Some(&shape(&[0, -1, 1])?)], vec![])?;
+        assert_eq!(ys.dims(), [2, 3, 1]);
+        let ys = eval_op("Reshape", &[Some(&xs), Some(&shape(&[-1, 2])?)], vec![])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[0., 1.], [2., 3.], [4., 5.]]);
+        let empty = Tensor::zeros((0, 3), crate::DType::F32, dev)?;
+        let attrs = vec![int_attr("allowzero", 1)];
+        let ys = eval_op("Reshape", &[Some(&empty), Some(&shape(&[3, 0])?)], attrs)?;
+        assert_eq!(ys.dims(), [3, 0]);
+        let err = eval_op("Reshape", &[Some(&empty), Some(&shape(&[3, 0])?)], vec![]);
+        assert!(err.is_err());
+        let err = eval_op("Reshape", &[Some(&xs), Some(&shape(&[-1, -1])?)], vec![]);
+        assert!(err.is_err());
+
+        let ys = eval_op("Transpose", &[Some(&xs)], vec![])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[0., 3.], [1., 4.], [2., 5.]]);
+        let xs3 = Tensor::arange(0f32, 6., dev)?.reshape((1, 2, 3))?;
+        let ys = eval_op(
+            "Transpose",
+            &[Some(&xs3)],
+            vec![ints_attr("perm", &[2, 0, 1])],
+        )?;
+        assert_eq!(ys.dims(), [3, 1, 2]);
+        assert_eq!(
+            ys.flatten_all()?.to_vec1::<f32>()?,
+            [0., 3., 1., 4., 2., 5.]
+        );
+
+        let zs = Tensor::new(&[[-1f32], [-2.]], dev)?;
+        let ys = eval_op(
+            "Concat",
+            &[Some(&zs), Some(&xs)],
+            vec![int_attr("axis", -1)],
+        )?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[-1., 0., 1., 2.], [-2., 3., 4., 5.]]);
+        let ys = eval_op("Concat", &[Some(&xs), Some(&xs)], vec![int_attr("axis", 0)])?;
+        assert_eq!(ys.dims(), [4, 3]);
+
+        // Negative steps go backward from start, the end is excluded and clamped to -1.
+        let ids = |vs: &[i64]| Tensor::new(vs, dev);
+        let slice = |starts: &[i64], ends: &[i64], axes: &[i64], steps: &[i64]| {
+            let (starts, ends, axes, steps) = (ids(starts)?, ids(ends)?, ids(axes)?, ids(steps)?);
+            let inputs = [
+                Some(&xs),
+                Some(&starts),
+                Some(&ends),
+                Some(&axes),
+                Some(&steps),
+            ];
+            eval_op("Slice", &inputs, vec![])
+        };
+        let ys = slice(&[-1], &[i64::MIN], &[1], &[-1])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[2., 1., 0.], [5., 4., 3.]]);
+        let ys = slice(&[i64::MAX, 0], &[-4, 2], &[1, 0], &[-2, 1])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[2., 0.], [5., 3.]]);
+        let ys = slice(&[-1, 0], &[0, 1], &[-1, 0], &[-1, 1])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[2., 1.]]);
+        let ys = slice(&[0], &[10], &[1], &[2])?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[0., 2.], [3., 5.]]);
+        assert!(slice(&[0], &[3], &[1], &[0]).is_err());
+
+        // Since opset 13 the axes are an input, they were an attribute before.
+        let ys = eval_op("Unsqueeze", &[Some(&xs), Some(&ids(&[-1, 0])?)], vec![])?;
+        assert_eq!(ys.dims(), [1, 2, 3, 1]);
+        let ys = eval_op("Unsqueeze", &[Some(&xs)], vec![ints_attr("axes", &[1])])?;
+        assert_eq!(ys.dims(), [2, 1, 3]);
+        let xs4 = xs.reshape((1, 2, 1, 3))?;
+        let ys = eval_op("Squeeze", &[Some(&xs4)], vec![])?;
+        assert_eq!(ys.to_vec2::<f32>()?, xs.to_vec2::<f32>()?);
+        let ys = eval_op("Squeeze", &[Some(&xs4), Some(&ids(&[-2])?)], vec![])?;
+        assert_eq!(ys.dims(), [1, 2, 3]);
+        let ys = eval_op("Squeeze", &[Some(&xs4)], vec![ints_attr("axes", &[0])])?;
+        assert_eq!(ys.dims(), [2, 1, 3]);
+        assert!(eval_op("Squeeze", &[Some(&xs4), Some(&ids(&[1])?)], vec![]).is_err());
+        let err = eval_op("Transpose", &[Some(&xs)], vec![ints_attr("perm", &[-1, 0])]);
+        assert!(err.unwrap_err().to_string().contains("negative value"));
+        Ok(())
+    }
+
+    #[test]
+    fn onnx_bool_cast_constant() -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = Tensor::new(&[0f32, -2., 0.5], dev)?;
+        let ys = eval_op("Cast", &[Some(&xs)], vec![int_attr("to", 9)])?;
+        assert_eq!(ys.dtype(), crate::DType::Bool);
+        assert_eq!(ys.to_dtype(crate::DType::U8)?.to_vec1::<u8>()?, [0, 1, 1]);
+        let t = proto::TensorProto {
+            dims: vec![2],
+            data_type: proto::data_type::BOOL,
+            raw_data: vec![1, 0],
+            ..Default::default()
+        };
+        assert_eq!(t.to_tensor()?.dtype(), crate::DType::Bool);
+
+        // The value attribute does not have to come first.
+        let attrs = vec![int_attr("unrelated", 3), float_attr("value_float", 2.5)];
+        let ys = eval_op("Constant", &[], attrs)?;
+        assert_eq!(ys.to_scalar::<f32>()?, 2.5);
+        let ys = eval_op("Constant", &[], vec![ints_attr("value_ints", &[1, 2])])?;
+        assert_eq!(ys.to_vec1::<i64>()?, [1, 2]);
+        assert!(eval_op("Constant", &[], vec![int_attr("unrelated", 3)]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn onnx_where_clip() -> Result<()> {
+        let dev = &Device::Cpu;
+        // The three inputs are broadcast together.
+        let cond = Tensor::new(&[[1u8], [0]], dev)?;
+        let on_true = Tensor::new(&[[1f32, 2.], [3., 4.]], dev)?;
+        let on_false = Tensor::new(-1f32, dev)?;
+        let ys = eval_op(
+            "Where",
+            &[Some(&cond), Some(&on_true), Some(&on_false)],
+            vec![],
+        )?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[1., 2.], [-1., -1.]]);
+        let cond = Tensor::new(&[0u8, 1], dev)?;
+        let ys = eval_op(
+            "Where",
+            &[Some(&cond), Some(&on_true), Some(&on_false)],
+            vec![],
+        )?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[-1., 2.], [-1., 4.]]);
+
+        let xs = Tensor::new(&[-2f32, 0.5, 7.], dev)?;
+        let (min, max) = (Tensor::new(0f32, dev)?, Tensor::new(6f32, dev)?);
+        let ys = eval_op("Clip", &[Some(&xs), Some(&min), Some(&max)], vec![])?;
+        assert_eq!(ys.to_vec1::<f32>()?, [0., 0.5, 6.]);
+        let ys = eval_op("Clip", &[Some(&xs), None, Some(&max)], vec![])?;
+        assert_eq!(ys.to_vec1::<f32>()?, [-2., 0.5, 6.]);
+        let ys = eval_op("Clip", &[Some(&xs)], vec![])?;
+        assert_eq!(ys.to_vec1::<f32>()?, [-2., 0.5, 7.]);
+        // Before opset 11 the bounds are attributes.
+        let ys = eval_op("Clip", &[Some(&xs)], vec![float_attr("min", -1.)])?;
+        assert_eq!(ys.to_vec1::<f32>()?, [-1., 0.5, 7.]);
+        let xs = Tensor::new(&[-2i64, 3, 9], dev)?;
+        let (min, max) = (Tensor::new(0i64, dev)?, Tensor::new(5i64, dev)?);
+        let ys = eval_op("Clip", &[Some(&xs), Some(&min), Some(&max)], vec![])?;
+        assert_eq!(ys.to_vec1::<i64>()?, [0, 3, 5]);
+        Ok(())
+    }
+}
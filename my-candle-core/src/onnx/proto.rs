@@ -0,0 +1,537 @@
+//! Decoding of the ONNX protobuf messages.
+//!
+//! Only the messages and fields needed for inference are decoded, the other fields are skipped.
+//! The field numbers come from the ONNX spec:
+//! https://github.com/onnx/onnx/blob/main/onnx/onnx.proto
+use crate::error::Result;
+use crate::{DType, Device, Tensor};
+use byteorder::{ByteOrder, LittleEndian};
+use half::{bf16, f16};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WireType {
+    Varint,
+    Fixed64,
+    LengthDelimited,
+    Fixed32,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = match self.pos.checked_add(len) {
+            Some(end) if end <= self.data.len() => end,
+            _ => crate::bail!("onnx: unexpected end of protobuf data"),
+        };
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        crate::bail!("onnx: invalid protobuf varint")
+    }
+
+    fn key(&mut self) -> Result<(u64, WireType)> {
+        let key = self.varint()?;
+        let wire_type = match key & 7 {
+            0 => WireType::Varint,
+            1 => WireType::Fixed64,
+            2 => WireType::LengthDelimited,
+            5 => WireType::Fixed32,
+            w => crate::bail!("onnx: unsupported protobuf wire type {w}"),
+        };
+        Ok((key >> 3, wire_type))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = self.bytes()?;
+        match std::str::from_utf8(bytes) {
+            Ok(s) => Ok(s.to_string()),
+            Err(_) => crate::bail!("onnx: invalid utf8 string"),
+        }
+    }
+
+    fn message<M: Message>(&mut self) -> Result<M> {
+        M::decode(self.bytes()?)
+    }
+
+    fn skip(&mut self, wire_type: WireType) -> Result<()> {
+        match wire_type {
+            WireType::Varint => {
+                self.varint()?;
+            }
+            WireType::Fixed64 => {
+                self.take(8)?;
+            }
+            WireType::LengthDelimited => {
+                self.bytes()?;
+            }
+            WireType::Fixed32 => {
+                self.take(4)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Repeated scalar fields can either be packed in a single length delimited record or be
+    // written as one record per value.
+    fn repeated_varint(&mut self, wire_type: WireType, dst: &mut Vec<i64>) -> Result<()> {
+        if wire_type == WireType::LengthDelimited {
+            let mut packed = Reader::new(self.bytes()?);
+            while !packed.is_empty() {
+                dst.push(packed.varint()? as i64)
+            }
+        } else {
+            dst.push(self.varint()? as i64)
+        }
+        Ok(())
+    }
+
+    fn repeated_f32(&mut self, wire_type: WireType, dst: &mut Vec<f32>) -> Result<()> {
+        if wire_type == WireType::LengthDelimited {
+            let packed = self.bytes()?;
+            dst.extend(packed.chunks_exact(4).map(LittleEndian::read_f32))
+        } else {
+            dst.push(LittleEndian::read_f32(self.take(4)?))
+        }
+        Ok(())
+    }
+
+    fn repeated_f64(&mut self, wire_type: WireType, dst: &mut Vec<f64>) -> Result<()> {
+        if wire_type == WireType::LengthDelimited {
+            let packed = self.bytes()?;
+            dst.extend(packed.chunks_exact(8).map(LittleEndian::read_f64))
+        } else {
+            dst.push(LittleEndian::read_f64(self.take(8)?))
+        }
+        Ok(())
+    }
+}
+
+trait Message: Default {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()>;
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        let mut r = Reader::new(data);
+        while !r.is_empty() {
+            let (field, wire_type) = r.key()?;
+            msg.merge_field(field, wire_type, &mut r)?;
+        }
+        Ok(msg)
+    }
+}
+
+/// The element types from the `TensorProto.DataType` enum.
+pub mod data_type {
+    pub const FLOAT: i32 = 1;
+    pub const UINT8: i32 = 2;
+    pub const INT8: i32 = 3;
+    pub const UINT16: i32 = 4;
+    pub const INT16: i32 = 5;
+    pub const INT32: i32 = 6;
+    pub const INT64: i32 = 7;
+    pub const STRING: i32 = 8;
+    pub const BOOL: i32 = 9;
+    pub const FLOAT16: i32 = 10;
+    pub const DOUBLE: i32 = 11;
+    pub const UINT32: i32 = 12;
+    pub const UINT64: i32 = 13;
+    pub const BFLOAT16: i32 = 16;
+}
+
+/// The dtype used to represent an ONNX element type.
+pub fn dtype_from_onnx(data_type: i32) -> Result<DType> {
+    let dtype = match data_type {
+        data_type::FLOAT => DType::F32,
+        data_type::BOOL => DType::Bool,
+        data_type::UINT8 => DType::U8,
+        data_type::INT8 => DType::I8,
+        data_type::INT16 => DType::I16,
+        data_type::INT32 => DType::I32,
+        data_type::INT64 => DType::I64,
+        data_type::FLOAT16 => DType::F16,
+        data_type::DOUBLE => DType::F64,
+        data_type::UINT32 => DType::U32,
+        data_type::BFLOAT16 => DType::BF16,
+        _ => crate::bail!("onnx: unsupported element type {data_type}"),
+    };
+    Ok(dtype)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelProto {
+    pub ir_version: i64,
+    pub producer_name: String,
+    pub producer_version: String,
+    pub opset_import: Vec<OperatorSetIdProto>,
+    pub graph: Option<GraphProto>,
+}
+
+impl Message for ModelProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.ir_version = r.varint()? as i64,
+            2 => self.producer_name = r.string()?,
+            3 => self.producer_version = r.string()?,
+            7 => self.graph = Some(r.message()?),
+            8 => self.opset_import.push(r.message()?),
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+impl ModelProto {
+    /// Decodes a model from the content of an `.onnx` file.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        <Self as Message>::decode(data)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OperatorSetIdProto {
+    pub domain: String,
+    pub version: i64,
+}
+
+impl Message for OperatorSetIdProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.domain = r.string()?,
+            2 => self.version = r.varint()? as i64,
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GraphProto {
+    pub name: String,
+    pub node: Vec<NodeProto>,
+    pub initializer: Vec<TensorProto>,
+    pub input: Vec<ValueInfoProto>,
+    pub output: Vec<ValueInfoProto>,
+}
+
+impl Message for GraphProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.node.push(r.message()?),
+            2 => self.name = r.string()?,
+            5 => self.initializer.push(r.message()?),
+            11 => self.input.push(r.message()?),
+            12 => self.output.push(r.message()?),
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NodeProto {
+    pub name: String,
+    pub op_type: String,
+    pub domain: String,
+    pub input: Vec<String>,
+    pub output: Vec<String>,
+    pub attribute: Vec<AttributeProto>,
+}
+
+impl Message for NodeProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.input.push(r.string()?),
+            2 => self.output.push(r.string()?),
+            3 => self.name = r.string()?,
+            4 => self.op_type = r.string()?,
+            5 => self.attribute.push(r.message()?),
+            7 => self.domain = r.string()?,
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AttributeProto {
+    pub name: String,
+    pub f: f32,
+    pub i: i64,
+    pub s: Vec<u8>,
+    pub t: Option<TensorProto>,
+    pub floats: Vec<f32>,
+    pub ints: Vec<i64>,
+    pub strings: Vec<Vec<u8>>,
+}
+
+impl Message for AttributeProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.name = r.string()?,
+            2 => self.f = LittleEndian::read_f32(r.take(4)?),
+            3 => self.i = r.varint()? as i64,
+            4 => self.s = r.bytes()?.to_vec(),
+            5 => self.t = Some(r.message()?),
+            7 => r.repeated_f32(wire_type, &mut self.floats)?,
+            8 => r.repeated_varint(wire_type, &mut self.ints)?,
+            9 => self.strings.push(r.bytes()?.to_vec()),
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TensorProto {
+    pub name: String,
+    pub dims: Vec<i64>,
+    pub data_type: i32,
+    pub raw_data: Vec<u8>,
+    pub float_data: Vec<f32>,
+    pub int32_data: Vec<i64>,
+    pub int64_data: Vec<i64>,
+    pub double_data: Vec<f64>,
+    pub uint64_data: Vec<i64>,
+    /// Set when the data is stored in an external file, this is not supported.
+    pub has_external_data: bool,
+}
+
+impl Message for TensorProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => r.repeated_varint(wire_type, &mut self.dims)?,
+            2 => self.data_type = r.varint()? as i32,
+            4 => r.repeated_f32(wire_type, &mut self.float_data)?,
+            5 => r.repeated_varint(wire_type, &mut self.int32_data)?,
+            7 => r.repeated_varint(wire_type, &mut self.int64_data)?,
+            8 => self.name = r.string()?,
+            9 => self.raw_data = r.bytes()?.to_vec(),
+            10 => r.repeated_f64(wire_type, &mut self.double_data)?,
+            11 => r.repeated_varint(wire_type, &mut self.uint64_data)?,
+            13 => {
+                self.has_external_data = true;
+                r.skip(wire_type)?
+            }
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+impl TensorProto {
+    /// Converts the tensor to a `Tensor` on the cpu.
+    pub fn to_tensor(&self) -> Result<Tensor> {
+        use data_type as dt;
+        let name = &self.name;
+        if self.has_external_data {
+            crate::bail!("onnx: tensor {name} uses external data, this is not supported")
+        }
+        let mut dims = Vec::with_capacity(self.dims.len());
+        for &d in self.dims.iter() {
+            match usize::try_from(d) {
+                Ok(d) => dims.push(d),
+                Err(_) => crate::bail!("onnx: tensor {name} has a negative dimension {d}"),
+            }
+        }
+        let elem_count = match dims.iter().try_fold(1usize, |acc, &d| acc.checked_mul(d)) {
+            Some(elem_count) => elem_count,
+            None => crate::bail!("onnx: tensor {name} has too many elements for shape {dims:?}"),
+        };
+        let dev = &Device::Cpu;
+        let raw = self.raw_data.as_slice();
+        if !raw.is_empty() {
+            let size_in_bytes = match self.data_type {
+                dt::UINT8 | dt::INT8 | dt::BOOL => 1,
+                dt::INT16 | dt::FLOAT16 | dt::BFLOAT16 => 2,
+                dt::FLOAT | dt::INT32 | dt::UINT32 => 4,
+                dt::INT64 | dt::DOUBLE => 8,
+                _ => dtype_from_onnx(self.data_type)?.size_in_bytes(),
+            };
+            if elem_count.checked_mul(size_in_bytes) != Some(raw.len()) {
+                let len = raw.len();
+                crate::bail!("onnx: tensor {name} has {len} bytes of data for shape {dims:?}")
+            }
+        }
+        macro_rules! from_raw {
+            ($ty:ty, $read_into:ident) => {{
+                let mut vs = vec![<$ty>::default(); raw.len() / std::mem::size_of::<$ty>()];
+                LittleEndian::$read_into(raw, &mut vs);
+                vs
+            }};
+        }
+        let tensor = match self.data_type {
+            dt::FLOAT if !raw.is_empty() => {
+                Tensor::from_vec(from_raw!(f32, read_f32_into), dims, dev)?
+            }
+            dt::FLOAT => Tensor::from_slice(&self.float_data, dims, dev)?,
+            dt::DOUBLE if !raw.is_empty() => {
+                Tensor::from_vec(from_raw!(f64, read_f64_into), dims, dev)?
+            }
+            dt::DOUBLE => Tensor::from_slice(&self.double_data, dims, dev)?,
+            dt::INT64 if !raw.is_empty() => {
+                Tensor::from_vec(from_raw!(i64, read_i64_into), dims, dev)?
+            }
+            dt::INT64 => Tensor::from_slice(&self.int64_data, dims, dev)?,
+            dt::INT32 if !raw.is_empty() => {
+                Tensor::from_vec(from_raw!(i32, read_i32_into), dims, dev)?
+            }
+            dt::INT16 if !raw.is_empty() => {
+                Tensor::from_vec(from_raw!(i16, read_i16_into), dims, dev)?
+            }
+            dt::UINT32 if !raw.is_empty() => {
+                Tensor::from_vec(from_raw!(u32, read_u32_into), dims, dev)?
+            }
+            dt::UINT32 => {
+                let vs: Vec<u32> = self.uint64_data.iter().map(|&v| v as u32).collect();
+                Tensor::from_vec(vs, dims, dev)?
+            }
+            dt::UINT8 if !raw.is_empty() => Tensor::from_slice(raw, dims, dev)?,
+            dt::BOOL if !raw.is_empty() => {
+                Tensor::from_slice(raw, dims, dev)?.to_dtype(DType::Bool)?
+            }
+            dt::INT8 if !raw.is_empty() => {
+                let vs: Vec<i8> = raw.iter().map(|&v| v as i8).collect();
+                Tensor::from_vec(vs, dims, dev)?
+            }
+            dt::FLOAT16 | dt::BFLOAT16 => {
+                let bits: Vec<u16> = if raw.is_empty() {
+                    self.int32_data.iter().map(|&v| v as u16).collect()
+                } else {
+                    from_raw!(u16, read_u16_into)
+                };
+                if self.data_type == dt::FLOAT16 {
+                    let vs: Vec<f16> = bits.into_iter().map(f16::from_bits).collect();
+                    Tensor::from_vec(vs, dims, dev)?
+                } else {
+                    let vs: Vec<bf16> = bits.into_iter().map(bf16::from_bits).collect();
+                    Tensor::from_vec(vs, dims, dev)?
+                }
+            }
+            // The small integer types are stored in int32_data when there is no raw data.
+            dt::INT32 | dt::INT16 | dt::INT8 | dt::UINT8 | dt::BOOL => {
+                let vs: Vec<i64> = self.int32_data.iter().map(|&v| v as i32 as i64).collect();
+                let dtype = dtype_from_onnx(self.data_type)?;
+                Tensor::from_vec(vs, dims, dev)?.to_dtype(dtype)?
+            }
+            data_type => {
+                crate::bail!("onnx: unsupported element type {data_type} for tensor {name}")
+            }
+        };
+        Ok(tensor)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValueInfoProto {
+    pub name: String,
+    /// The element type when the value is a tensor.
+    pub elem_type: i32,
+    /// The tensor dimensions, `None` for symbolic dimensions such as the batch size.
+    pub shape: Vec<Option<usize>>,
+}
+
+// TypeProto and its nested messages are flattened into `ValueInfoProto`.
+#[derive(Default)]
+struct TypeProto(ValueInfoProto);
+
+#[derive(Default)]
+struct TypeProtoTensor(ValueInfoProto);
+
+#[derive(Default)]
+struct TensorShapeProto(Vec<Option<usize>>);
+
+#[derive(Default)]
+struct Dimension(Option<usize>);
+
+impl Message for ValueInfoProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.name = r.string()?,
+            2 => {
+                let TypeProto(tp) = r.message()?;
+                self.elem_type = tp.elem_type;
+                self.shape = tp.shape;
+            }
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+impl Message for TypeProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => {
+                let TypeProtoTensor(t) = r.message()?;
+                self.0 = t
+            }
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+impl Message for TypeProtoTensor {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.0.elem_type = r.varint()? as i32,
+            2 => {
+                let TensorShapeProto(shape) = r.message()?;
+                self.0.shape = shape
+            }
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+impl Message for TensorShapeProto {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => {
+                let Dimension(dim) = r.message()?;
+                self.0.push(dim)
+            }
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+impl Message for Dimension {
+    fn merge_field(&mut self, field: u64, wire_type: WireType, r: &mut Reader) -> Result<()> {
+        match field {
+            1 => self.0 = Some(r.varint()? as usize),
+            _ => r.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
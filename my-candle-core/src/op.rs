@@ -5,6 +5,7 @@ use half::{bf16, f16};
 use num_traits::float::Float;
 use crate::layout::Layout;
 use crate::shape::Shape;
+use crate::dtype::DType;
 use crate::error::{Error, Result};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -17,10 +18,24 @@ pub enum CmpOp {
     Gt,
 }
 
+impl CmpOp {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Eq => "eq",
+            Self::Ne => "ne",
+            Self::Le => "le",
+            Self::Ge => "ge",
+            Self::Lt => "lt",
+            Self::Gt => "gt",
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReduceOp {
     Sum,
+    Prod,
     Min,
     Max,
     ArgMin,
@@ -35,6 +50,7 @@ impl ReduceOp {
             Self::Min => "min",
             Self::Max => "max",
             Self::Sum => "sum",
+            Self::Prod => "prod",
         }
     }
 }
@@ -45,6 +61,23 @@ pub enum BinaryOp {
     Mul,
     Sub,
     Div,
+    Pow,
+    Maximum,
+    Minimum,
+}
+
+impl BinaryOp {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Add => Add::NAME,
+            Self::Mul => Mul::NAME,
+            Self::Sub => Sub::NAME,
+            Self::Div => Div::NAME,
+            Self::Pow => Pow::NAME,
+            Self::Maximum => Maximum::NAME,
+            Self::Minimum => Minimum::NAME,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -59,7 +92,43 @@ pub enum UnaryOp {
     Sqr,
     Sqrt,
     Gelu,
+    GeluErf,
     Relu,
+    Tanh,
+    Sigmoid,
+    Silu,
+    Erf,
+    Floor,
+    Ceil,
+    Round,
+    Sign,
+}
+
+impl UnaryOp {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Exp => Exp::NAME,
+            Self::Log => Log::NAME,
+            Self::Sin => Sin::NAME,
+            Self::Cos => Cos::NAME,
+            Self::Abs => Abs::NAME,
+            Self::Neg => Neg::NAME,
+            Self::Recip => Recip::NAME,
+            Self::Sqr => Sqr::NAME,
+            Self::Sqrt => Sqrt::NAME,
+            Self::Gelu => Gelu::NAME,
+            Self::GeluErf => GeluErf::NAME,
+            Self::Relu => Relu::NAME,
+            Self::Tanh => Tanh::NAME,
+            Self::Sigmoid => Sigmoid::NAME,
+            Self::Silu => Silu::NAME,
+            Self::Erf => Erf::NAME,
+            Self::Floor => Floor::NAME,
+            Self::Ceil => Ceil::NAME,
+            Self::Round => Round::NAME,
+            Self::Sign => Sign::NAME,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -82,6 +151,8 @@ pub enum Op {
         kernel: Tensor,
         padding: usize,
         stride: usize,
+        dilation: usize,
+        groups: usize,
     },
 
     #[allow(dead_code)]
@@ -90,6 +161,28 @@ pub enum Op {
         kernel: Tensor,
         padding: usize,
         stride: usize,
+        dilation: usize,
+        groups: usize,
+    },
+
+    #[allow(dead_code)]
+    ConvTranspose1D {
+        arg: Tensor,
+        kernel: Tensor,
+        padding: usize,
+        output_padding: usize,
+        stride: usize,
+        dilation: usize,
+    },
+
+    #[allow(dead_code)]
+    ConvTranspose2D {
+        arg: Tensor,
+        kernel: Tensor,
+        padding: usize,
+        output_padding: usize,
+        stride: usize,
+        dilation: usize,
     },
 
     AvgPool2D {
@@ -121,12 +214,26 @@ pub enum Op {
     Reshape(Tensor),
     ToDevice(Tensor),
     Transpose(Tensor, usize, usize),
+    Permute(Tensor, Vec<usize>),
+    Unfold(Tensor, usize, usize, usize),
     Elu(Tensor, f64),
+    Powf(Tensor, f64),
+    Clamp(Tensor, f64, f64),
+    Cumulative(Tensor, ReduceOp, usize),
+    SoftmaxLastDim(Tensor),
+    LogSoftmaxLastDim(Tensor),
     CustomOp1(Tensor, std::sync::Arc<Box<dyn CustomOp1>>),
     CustomOp2(Tensor, Tensor, std::sync::Arc<Box<dyn CustomOp2>>),
     CustomOp3(Tensor, Tensor, Tensor, std::sync::Arc<Box<dyn CustomOp3>>),
+    // The inputs of a checkpointed function, the function is run again during the backward pass
+    // to recompute the intermediate values.
+    Checkpoint(Vec<Tensor>, CheckpointFn),
 }
 
+/// A function whose intermediate values are recomputed during the backward pass, see
+/// `Tensor::checkpoint`.
+pub(crate) type CheckpointFn = std::sync::Arc<dyn Fn(&[Tensor]) -> Result<Tensor> + Send + Sync>;
+
 pub trait CustomOp1: Send + Sync {
     // Box<dyn> does not support const yet, so use a function to get the name.
     fn name(&self) -> &'static str;
@@ -242,6 +349,16 @@ pub trait UnaryOpT {
     fn f64(v1: f64) -> f64;
     fn u8(v1: u8) -> u8;
     fn u32(v1: u32) -> u32;
+    fn i8(v1: i8) -> i8;
+    fn i16(v1: i16) -> i16;
+    fn i32(v1: i32) -> i32;
+    fn i64(v1: i64) -> i64;
+
+    /// Whether the op has a kernel for `dtype`, the storage returns an `UnsupportedDTypeForOp`
+    /// error for the other dtypes.
+    fn supports_dtype(_dtype: DType) -> bool {
+        true
+    }
 
     // There is no very good way to represent optional function in traits so we go for an explicit
     // boolean flag to mark the function as existing.
@@ -265,6 +382,10 @@ pub trait BinaryOpT {
     fn f64(v1: f64, v2: f64) -> f64;
     fn u8(v1: u8, v2: u8) -> u8;
     fn u32(v1: u32, v2: u32) -> u32;
+    fn i8(v1: i8, v2: i8) -> i8;
+    fn i16(v1: i16, v2: i16) -> i16;
+    fn i32(v1: i32, v2: i32) -> i32;
+    fn i64(v1: i64, v2: i64) -> i64;
 
     const BF16_VEC: bool = false;
     fn bf16_vec(_xs1: &[bf16], _xs2: &[bf16], _ys: &mut [bf16]) {}
@@ -278,12 +399,23 @@ pub trait BinaryOpT {
     fn u8_vec(_xs1: &[u8], _xs2: &[u8], _ys: &mut [u8]) {}
     const U32_VEC: bool = false;
     fn u32_vec(_xs1: &[u32], _xs2: &[u32], _ys: &mut [u32]) {}
+    const I8_VEC: bool = false;
+    fn i8_vec(_xs1: &[i8], _xs2: &[i8], _ys: &mut [i8]) {}
+    const I16_VEC: bool = false;
+    fn i16_vec(_xs1: &[i16], _xs2: &[i16], _ys: &mut [i16]) {}
+    const I32_VEC: bool = false;
+    fn i32_vec(_xs1: &[i32], _xs2: &[i32], _ys: &mut [i32]) {}
+    const I64_VEC: bool = false;
+    fn i64_vec(_xs1: &[i64], _xs2: &[i64], _ys: &mut [i64]) {}
 }
 
 pub(crate) struct Add;
 pub(crate) struct Div;
 pub(crate) struct Mul;
 pub(crate) struct Sub;
+pub(crate) struct Pow;
+pub(crate) struct Maximum;
+pub(crate) struct Minimum;
 pub(crate) struct Exp;
 pub(crate) struct Log;
 pub(crate) struct Sin;
@@ -296,9 +428,20 @@ pub(crate) struct Sqrt;
 pub(crate) struct Gelu;
 pub(crate) struct Relu;
 pub(crate) struct GeluErf;
+pub(crate) struct Tanh;
+pub(crate) struct Sigmoid;
+pub(crate) struct Silu;
+pub(crate) struct Erf;
+pub(crate) struct Floor;
+pub(crate) struct Ceil;
+pub(crate) struct Round;
+pub(crate) struct Sign;
 
 macro_rules! bin_op {
     ($op:ident, $name: literal, $e: expr, $f32_vec: ident, $f64_vec: ident) => {
+        bin_op!($op, $name, $e, $e, $f32_vec, $f64_vec);
+    };
+    ($op:ident, $name: literal, $e: expr, $int_e: expr, $f32_vec: ident, $f64_vec: ident) => {
         impl BinaryOpT for $op {
             const NAME: &'static str = $name;
             const KERNEL: &'static str = concat!("b", $name);
@@ -321,11 +464,27 @@ macro_rules! bin_op {
             }
             #[inline(always)]
             fn u8(v1: u8, v2: u8) -> u8 {
-                $e(v1, v2)
+                $int_e(v1, v2)
             }
             #[inline(always)]
             fn u32(v1: u32, v2: u32) -> u32 {
-                $e(v1, v2)
+                $int_e(v1, v2)
+            }
+            #[inline(always)]
+            fn i8(v1: i8, v2: i8) -> i8 {
+                $int_e(v1, v2)
+            }
+            #[inline(always)]
+            fn i16(v1: i16, v2: i16) -> i16 {
+                $int_e(v1, v2)
+            }
+            #[inline(always)]
+            fn i32(v1: i32, v2: i32) -> i32 {
+                $int_e(v1, v2)
+            }
+            #[inline(always)]
+            fn i64(v1: i64, v2: i64) -> i64 {
+                $int_e(v1, v2)
             }
 
             #[cfg(feature = "mkl")]
@@ -364,7 +523,162 @@ macro_rules! bin_op {
 bin_op!(Add, "add", |v1, v2| v1 + v2, vs_add, vd_add);
 bin_op!(Sub, "sub", |v1, v2| v1 - v2, vs_sub, vd_sub);
 bin_op!(Mul, "mul", |v1, v2| v1 * v2, vs_mul, vd_mul);
-bin_op!(Div, "div", |v1, v2| v1 / v2, vs_div, vd_div);
+bin_op!(Div, "div", |v1, v2| v1 / v2, int_div, vs_div, vd_div);
+bin_op!(Minimum, "minimum", minimum, vs_min, vd_min);
+bin_op!(Maximum, "maximum", maximum, vs_max, vd_max);
+
+// Integer division by zero returns zero as in NumPy and `MIN / -1` wraps around to `MIN`, rather
+// than panicking.
+#[inline(always)]
+fn int_div<T: num_traits::PrimInt>(v1: T, v2: T) -> T {
+    match v1.checked_div(&v2) {
+        Some(v) => v,
+        None if v2.is_zero() => T::zero(),
+        None => v1,
+    }
+}
+
+// NaNs are propagated as in PyTorch whatever their position, `partial_cmp` only fails when
+// comparing a NaN. This has no effect on integers.
+#[inline(always)]
+fn minimum<T: PartialOrd>(v1: T, v2: T) -> T {
+    if v1 > v2 || v2.partial_cmp(&v2).is_none() {
+        v2
+    } else {
+        v1
+    }
+}
+
+#[inline(always)]
+fn maximum<T: PartialOrd>(v1: T, v2: T) -> T {
+    if v1 < v2 || v2.partial_cmp(&v2).is_none() {
+        v2
+    } else {
+        v1
+    }
+}
+
+// Integer kernels of the float only unary ops, `UnaryOpT::supports_dtype` rejects these dtypes
+// before any kernel runs so the bodies are never reached.
+macro_rules! unary_op_no_int {
+    () => {
+        fn supports_dtype(dtype: DType) -> bool {
+            dtype.is_float()
+        }
+        #[inline(always)]
+        fn u8(_: u8) -> u8 {
+            unreachable!("no unary function for u8")
+        }
+        #[inline(always)]
+        fn u32(_: u32) -> u32 {
+            unreachable!("no unary function for u32")
+        }
+        #[inline(always)]
+        fn i8(_: i8) -> i8 {
+            unreachable!("no unary function for i8")
+        }
+        #[inline(always)]
+        fn i16(_: i16) -> i16 {
+            unreachable!("no unary function for i16")
+        }
+        #[inline(always)]
+        fn i32(_: i32) -> i32 {
+            unreachable!("no unary function for i32")
+        }
+        #[inline(always)]
+        fn i64(_: i64) -> i64 {
+            unreachable!("no unary function for i64")
+        }
+    };
+}
+
+// Integer kernels of the ops that only make sense on signed integers, e.g. `neg` or `abs`.
+macro_rules! unary_op_signed_int {
+    ($a: ident, $int_e: expr) => {
+        fn supports_dtype(dtype: DType) -> bool {
+            !matches!(dtype, DType::Bool | DType::U8 | DType::U32)
+        }
+        #[inline(always)]
+        fn u8(_: u8) -> u8 {
+            unreachable!("no unary function for u8")
+        }
+        #[inline(always)]
+        fn u32(_: u32) -> u32 {
+            unreachable!("no unary function for u32")
+        }
+        #[inline(always)]
+        fn i8($a: i8) -> i8 {
+            $int_e
+        }
+        #[inline(always)]
+        fn i16($a: i16) -> i16 {
+            $int_e
+        }
+        #[inline(always)]
+        fn i32($a: i32) -> i32 {
+            $int_e
+        }
+        #[inline(always)]
+        fn i64($a: i64) -> i64 {
+            $int_e
+        }
+    };
+}
+
+macro_rules! unary_op_float {
+    ($a: ident, $e: expr) => {
+        #[inline(always)]
+        fn bf16($a: bf16) -> bf16 {
+            $e
+        }
+        #[inline(always)]
+        fn f16($a: f16) -> f16 {
+            $e
+        }
+        #[inline(always)]
+        fn f32($a: f32) -> f32 {
+            $e
+        }
+        #[inline(always)]
+        fn f64($a: f64) -> f64 {
+            $e
+        }
+    };
+}
+
+macro_rules! unary_op_vec {
+    ($f32_vec:ident, $f64_vec:ident) => {
+        #[cfg(feature = "mkl")]
+        const F32_VEC: bool = true;
+        #[cfg(feature = "mkl")]
+        const F64_VEC: bool = true;
+        #[cfg(feature = "mkl")]
+        #[inline(always)]
+        fn f32_vec(xs: &[f32], ys: &mut [f32]) {
+            crate::mkl::$f32_vec(xs, ys)
+        }
+        #[cfg(feature = "mkl")]
+        #[inline(always)]
+        fn f64_vec(xs: &[f64], ys: &mut [f64]) {
+            crate::mkl::$f64_vec(xs, ys)
+        }
+
+        #[cfg(feature = "accelerate")]
+        const F32_VEC: bool = true;
+        #[cfg(feature = "accelerate")]
+        const F64_VEC: bool = true;
+        #[cfg(feature = "accelerate")]
+        #[inline(always)]
+        fn f32_vec(xs: &[f32], ys: &mut [f32]) {
+            crate::accelerate::$f32_vec(xs, ys)
+        }
+        #[cfg(feature = "accelerate")]
+        #[inline(always)]
+        fn f64_vec(xs: &[f64], ys: &mut [f64]) {
+            crate::accelerate::$f64_vec(xs, ys)
+        }
+    };
+}
 
 macro_rules! unary_op {
     ($op: ident, $name: literal, $a: ident, $e: expr) => {
@@ -372,34 +686,67 @@ macro_rules! unary_op {
             const NAME: &'static str = $name;
             const KERNEL: &'static str = concat!("u", $name);
             const V: Self = $op;
-            #[inline(always)]
-            fn bf16($a: bf16) -> bf16 {
-                $e
-            }
-            #[inline(always)]
-            fn f16($a: f16) -> f16 {
-                $e
-            }
-            #[inline(always)]
-            fn f32($a: f32) -> f32 {
-                $e
-            }
-            #[inline(always)]
-            fn f64($a: f64) -> f64 {
-                $e
-            }
-            #[inline(always)]
-            fn u8(_: u8) -> u8 {
-                todo!("no unary function for u8")
-            }
-            #[inline(always)]
-            fn u32(_: u32) -> u32 {
-                todo!("no unary function for u32")
-            }
+            unary_op_float!($a, $e);
+            unary_op_no_int!();
+        }
+    };
+
+    ($op: ident, $name: literal, $a: ident, $e: expr, signed: $int_e: expr) => {
+        impl UnaryOpT for $op {
+            const NAME: &'static str = $name;
+            const KERNEL: &'static str = concat!("u", $name);
+            const V: Self = $op;
+            unary_op_float!($a, $e);
+            unary_op_signed_int!($a, $int_e);
         }
     };
 
     ($op: ident, $name: literal, $a: ident, $e: expr, $f32_vec:ident, $f64_vec:ident) => {
+        impl UnaryOpT for $op {
+            const NAME: &'static str = $name;
+            const KERNEL: &'static str = concat!("u", $name);
+            const V: Self = $op;
+            unary_op_float!($a, $e);
+            unary_op_no_int!();
+            unary_op_vec!($f32_vec, $f64_vec);
+        }
+    };
+
+    (
+        $op: ident,
+        $name: literal,
+        $a: ident,
+        $e: expr,
+        signed: $int_e: expr,
+        $f32_vec:ident,
+        $f64_vec:ident
+    ) => {
+        impl UnaryOpT for $op {
+            const NAME: &'static str = $name;
+            const KERNEL: &'static str = concat!("u", $name);
+            const V: Self = $op;
+            unary_op_float!($a, $e);
+            unary_op_signed_int!($a, $int_e);
+            unary_op_vec!($f32_vec, $f64_vec);
+        }
+    };
+}
+
+unary_op!(Exp, "exp", v, v.exp(), vs_exp, vd_exp);
+unary_op!(Log, "log", v, v.ln(), vs_ln, vd_ln);
+unary_op!(Sin, "sin", v, v.sin(), vs_sin, vd_sin);
+unary_op!(Cos, "cos", v, v.cos(), vs_cos, vd_cos);
+// Signed integers wrap around on overflow, e.g. `abs(i8::MIN)` is `i8::MIN`.
+unary_op!(Abs, "abs", v, v.abs(), signed: v.wrapping_abs());
+unary_op!(Neg, "neg", v, -v, signed: v.wrapping_neg());
+unary_op!(Recip, "recip", v, v.recip());
+unary_op!(Sqr, "sqr", v, v * v, signed: v.wrapping_mul(v), vs_sqr, vd_sqr);
+unary_op!(Sqrt, "sqrt", v, v.sqrt(), vs_sqrt, vd_sqrt);
+
+unary_op!(Tanh, "tanh", v, v.tanh(), vs_tanh, vd_tanh);
+
+macro_rules! unary_op_int {
+    ($op: ident, $name: literal, $a: ident, $e: expr, $uint_e: expr, $int_e: expr) => {
         impl UnaryOpT for $op {
             const NAME: &'static str = $name;
             const KERNEL: &'static str = concat!("u", $name);
@@ -421,56 +768,260 @@ macro_rules! unary_op {
                 $e
             }
             #[inline(always)]
-            fn u8(_: u8) -> u8 {
-                todo!("no unary function for u8")
+            fn u8($a: u8) -> u8 {
+                $uint_e
             }
             #[inline(always)]
-            fn u32(_: u32) -> u32 {
-                todo!("no unary function for u32")
+            fn u32($a: u32) -> u32 {
+                $uint_e
             }
-
-            #[cfg(feature = "mkl")]
-            const F32_VEC: bool = true;
-            #[cfg(feature = "mkl")]
-            const F64_VEC: bool = true;
-            #[cfg(feature = "mkl")]
             #[inline(always)]
-            fn f32_vec(xs: &[f32], ys: &mut [f32]) {
-                crate::mkl::$f32_vec(xs, ys)
+            fn i8($a: i8) -> i8 {
+                $int_e
             }
-            #[cfg(feature = "mkl")]
             #[inline(always)]
-            fn f64_vec(xs: &[f64], ys: &mut [f64]) {
-                crate::mkl::$f64_vec(xs, ys)
+            fn i16($a: i16) -> i16 {
+                $int_e
             }
-
-            #[cfg(feature = "accelerate")]
-            const F32_VEC: bool = true;
-            #[cfg(feature = "accelerate")]
-            const F64_VEC: bool = true;
-            #[cfg(feature = "accelerate")]
             #[inline(always)]
-            fn f32_vec(xs: &[f32], ys: &mut [f32]) {
-                crate::accelerate::$f32_vec(xs, ys)
+            fn i32($a: i32) -> i32 {
+                $int_e
             }
-            #[cfg(feature = "accelerate")]
             #[inline(always)]
-            fn f64_vec(xs: &[f64], ys: &mut [f64]) {
-                crate::accelerate::$f64_vec(xs, ys)
+            fn i64($a: i64) -> i64 {
+                $int_e
             }
         }
     };
 }
 
-unary_op!(Exp, "exp", v, v.exp(), vs_exp, vd_exp);
-unary_op!(Log, "log", v, v.ln(), vs_ln, vd_ln);
-unary_op!(Sin, "sin", v, v.sin(), vs_sin, vd_sin);
-unary_op!(Cos, "cos", v, v.cos(), vs_cos, vd_cos);
-unary_op!(Abs, "abs", v, v.abs());
-unary_op!(Neg, "neg", v, -v);
-unary_op!(Recip, "recip", v, v.recip());
-unary_op!(Sqr, "sqr", v, v * v, vs_sqr, vd_sqr);
-unary_op!(Sqrt, "sqrt", v, v.sqrt(), vs_sqrt, vd_sqrt);
+// Rounding is a no-op on integers. `round` rounds half-way cases away from zero.
+unary_op_int!(Floor, "floor", v, v.floor(), v, v);
+unary_op_int!(Ceil, "ceil", v, v.ceil(), v, v);
+unary_op_int!(Round, "round", v, v.round(), v, v);
+// `signum` returns 1 on +0. and -1 on -0. so zeros are kept as is.
+unary_op_int!(
+    Sign,
+    "sign",
+    v,
+    if num_traits::Zero::is_zero(&v) {
+        v
+    } else {
+        v.signum()
+    },
+    v.min(1),
+    v.signum()
+);
+
+/// `sigmoid` operation, `1 / (1 + exp(-x))`.
+impl UnaryOpT for Sigmoid {
+    const NAME: &'static str = "sigmoid";
+    const KERNEL: &'static str = "usigmoid";
+    const V: Self = Sigmoid;
+    #[inline(always)]
+    fn bf16(v: bf16) -> bf16 {
+        bf16::from_f32(Self::f32(v.to_f32()))
+    }
+    #[inline(always)]
+    fn f16(v: f16) -> f16 {
+        f16::from_f32(Self::f32(v.to_f32()))
+    }
+    #[inline(always)]
+    fn f32(v: f32) -> f32 {
+        1. / (1. + (-v).exp())
+    }
+    #[inline(always)]
+    fn f64(v: f64) -> f64 {
+        1. / (1. + (-v).exp())
+    }
+    unary_op_no_int!();
+
+    #[cfg(feature = "mkl")]
+    const F32_VEC: bool = true;
+    #[cfg(feature = "mkl")]
+    const F64_VEC: bool = true;
+    #[cfg(feature = "mkl")]
+    #[inline(always)]
+    fn f32_vec(xs: &[f32], ys: &mut [f32]) {
+        crate::mkl::vs_sigmoid(xs, ys)
+    }
+    #[cfg(feature = "mkl")]
+    #[inline(always)]
+    fn f64_vec(xs: &[f64], ys: &mut [f64]) {
+        crate::mkl::vd_sigmoid(xs, ys)
+    }
+
+    #[cfg(feature = "accelerate")]
+    const F32_VEC: bool = true;
+    #[cfg(feature = "accelerate")]
+    const F64_VEC: bool = true;
+    #[cfg(feature = "accelerate")]
+    #[inline(always)]
+    fn f32_vec(xs: &[f32], ys: &mut [f32]) {
+        crate::accelerate::vs_sigmoid(xs, ys)
+    }
+    #[cfg(feature = "accelerate")]
+    #[inline(always)]
+    fn f64_vec(xs: &[f64], ys: &mut [f64]) {
+        crate::accelerate::vd_sigmoid(xs, ys)
+    }
+}
+
+/// `silu` operation, `x * sigmoid(x)`.
+impl UnaryOpT for Silu {
+    const NAME: &'static str = "silu";
+    const KERNEL: &'static str = "usilu";
+    const V: Self = Silu;
+    #[inline(always)]
+    fn bf16(v: bf16) -> bf16 {
+        bf16::from_f32(Self::f32(v.to_f32()))
+    }
+    #[inline(always)]
+    fn f16(v: f16) -> f16 {
+        f16::from_f32(Self::f32(v.to_f32()))
+    }
+    #[inline(always)]
+    fn f32(v: f32) -> f32 {
+        v / (1. + (-v).exp())
+    }
+    #[inline(always)]
+    fn f64(v: f64) -> f64 {
+        v / (1. + (-v).exp())
+    }
+    unary_op_no_int!();
+
+    #[cfg(feature = "mkl")]
+    const F32_VEC: bool = true;
+    #[cfg(feature = "mkl")]
+    const F64_VEC: bool = true;
+    #[cfg(feature = "mkl")]
+    #[inline(always)]
+    fn f32_vec(xs: &[f32], ys: &mut [f32]) {
+        crate::mkl::vs_silu(xs, ys)
+    }
+    #[cfg(feature = "mkl")]
+    #[inline(always)]
+    fn f64_vec(xs: &[f64], ys: &mut [f64]) {
+        crate::mkl::vd_silu(xs, ys)
+    }
+
+    #[cfg(feature = "accelerate")]
+    const F32_VEC: bool = true;
+    #[cfg(feature = "accelerate")]
+    const F64_VEC: bool = true;
+    #[cfg(feature = "accelerate")]
+    #[inline(always)]
+    fn f32_vec(xs: &[f32], ys: &mut [f32]) {
+        crate::accelerate::vs_silu(xs, ys)
+    }
+    #[cfg(feature = "accelerate")]
+    #[inline(always)]
+    fn f64_vec(xs: &[f64], ys: &mut [f64]) {
+        crate::accelerate::vd_silu(xs, ys)
+    }
+}
+
+impl UnaryOpT for Erf {
+    const NAME: &'static str = "erf";
+    const KERNEL: &'static str = "uerf";
+    const V: Self = Erf;
+    #[inline(always)]
+    fn bf16(v: bf16) -> bf16 {
+        bf16::from_f64(Self::f64(v.to_f64()))
+    }
+    #[inline(always)]
+    fn f16(v: f16) -> f16 {
+        f16::from_f64(Self::f64(v.to_f64()))
+    }
+    #[inline(always)]
+    fn f32(v: f32) -> f32 {
+        Self::f64(v as f64) as f32
+    }
+    #[inline(always)]
+    fn f64(v: f64) -> f64 {
+        crate::cpu::erf::erf(v)
+    }
+    unary_op_no_int!();
+}
+
+/// Elementwise power, integer tensors use wrapping arithmetic. `Tensor::pow` rejects negative
+/// exponents on signed integers before this kernel runs.
+impl BinaryOpT for Pow {
+    const NAME: &'static str = "pow";
+    const KERNEL: &'static str = "bpow";
+    const V: Self = Pow;
+    #[inline(always)]
+    fn bf16(v1: bf16, v2: bf16) -> bf16 {
+        v1.powf(v2)
+    }
+    #[inline(always)]
+    fn f16(v1: f16, v2: f16) -> f16 {
+        v1.powf(v2)
+    }
+    #[inline(always)]
+    fn f32(v1: f32, v2: f32) -> f32 {
+        v1.powf(v2)
+    }
+    #[inline(always)]
+    fn f64(v1: f64, v2: f64) -> f64 {
+        v1.powf(v2)
+    }
+    #[inline(always)]
+    fn u8(v1: u8, v2: u8) -> u8 {
+        v1.wrapping_pow(v2 as u32)
+    }
+    #[inline(always)]
+    fn u32(v1: u32, v2: u32) -> u32 {
+        v1.wrapping_pow(v2)
+    }
+    #[inline(always)]
+    fn i8(v1: i8, v2: i8) -> i8 {
+        // `Tensor::pow` rejects the negative exponents and the ones that do not fit in a u32.
+        v1.wrapping_pow(v2 as u32)
+    }
+    #[inline(always)]
+    fn i16(v1: i16, v2: i16) -> i16 {
+        v1.wrapping_pow(v2 as u32)
+    }
+    #[inline(always)]
+    fn i32(v1: i32, v2: i32) -> i32 {
+        v1.wrapping_pow(v2 as u32)
+    }
+    #[inline(always)]
+    fn i64(v1: i64, v2: i64) -> i64 {
+        v1.wrapping_pow(v2 as u32)
+    }
+
+    #[cfg(feature = "mkl")]
+    const F32_VEC: bool = true;
+    #[cfg(feature = "mkl")]
+    const F64_VEC: bool = true;
+    #[cfg(feature = "mkl")]
+    #[inline(always)]
+    fn f32_vec(xs1: &[f32], xs2: &[f32], ys: &mut [f32]) {
+        crate::mkl::vs_pow(xs1, xs2, ys)
+    }
+    #[cfg(feature = "mkl")]
+    #[inline(always)]
+    fn f64_vec(xs1: &[f64], xs2: &[f64], ys: &mut [f64]) {
+        crate::mkl::vd_pow(xs1, xs2, ys)
+    }
+
+    #[cfg(feature = "accelerate")]
+    const F32_VEC: bool = true;
+    #[cfg(feature = "accelerate")]
+    const F64_VEC: bool = true;
+    #[cfg(feature = "accelerate")]
+    #[inline(always)]
+    fn f32_vec(xs1: &[f32], xs2: &[f32], ys: &mut [f32]) {
+        crate::accelerate::vs_pow(xs1, xs2, ys)
+    }
+    #[cfg(feature = "accelerate")]
+    #[inline(always)]
+    fn f64_vec(xs1: &[f64], xs2: &[f64], ys: &mut [f64]) {
+        crate::accelerate::vd_pow(xs1, xs2, ys)
+    }
+}
 
 /// `gelu` operation
 /// <https://en.wikipedia.org/wiki/Activation_function#Comparison_of_activation_functions>
@@ -511,14 +1062,7 @@ impl UnaryOpT for Gelu {
             * (1.0
             + f64::tanh((2.0f64 / std::f64::consts::PI).sqrt() * v * (1.0 + 0.044715 * v * v)))
     }
-    #[inline(always)]
-    fn u8(_: u8) -> u8 {
-        0
-    }
-    #[inline(always)]
-    fn u32(_: u32) -> u32 {
-        0
-    }
+    unary_op_no_int!();
     const KERNEL: &'static str = "ugelu";
 
     #[cfg(feature = "mkl")]
@@ -568,6 +1112,22 @@ impl UnaryOpT for Relu {
     fn u32(v: u32) -> u32 {
         v
     }
+    #[inline(always)]
+    fn i8(v: i8) -> i8 {
+        v.max(0)
+    }
+    #[inline(always)]
+    fn i16(v: i16) -> i16 {
+        v.max(0)
+    }
+    #[inline(always)]
+    fn i32(v: i32) -> i32 {
+        v.max(0)
+    }
+    #[inline(always)]
+    fn i64(v: i64) -> i64 {
+        v.max(0)
+    }
 }
 
 /// `BackpropOp` is a wrapper around `Option<Op>`. The main goal is to ensure that dependencies are
@@ -621,6 +1181,18 @@ impl BackpropOp {
         };
         Self(op)
     }
+
+    // The op is also recorded when none of the arguments are tracked but the function has used
+    // some variables or tracked values that are not part of its arguments.
+    pub(crate) fn checkpoint(args: Vec<Tensor>, f: CheckpointFn, uses_vars: bool) -> Self {
+        let tracked = crate::backprop::record_op(uses_vars) || args.iter().any(|a| a.track_op());
+        let op = if tracked {
+            Some(Op::Checkpoint(args, f))
+        } else {
+            None
+        };
+        Self(op)
+    }
 }
 
 impl UnaryOpT for GeluErf {
@@ -644,18 +1216,7 @@ impl UnaryOpT for GeluErf {
     fn f64(v: f64) -> f64 {
         (crate::cpu::erf::erf(v / 2f64.sqrt()) + 1.) * 0.5 * v
     }
-    #[inline(always)]
-    fn u8(_: u8) -> u8 {
-        0
-    }
-    #[inline(always)]
-    fn u32(_: u32) -> u32 {
-        0
-    }
-    #[inline(always)]
-    fn i64(_: i64) -> i64 {
-        0
-    }
+    unary_op_no_int!();
 }
 
 impl std::ops::Deref for BackpropOp {
@@ -664,3 +1225,109 @@ impl std::ops::Deref for BackpropOp {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{DType, Device, Result, Tensor};
+
+    #[test]
+    fn rounding_ops() -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = Tensor::new(&[-2.5f32, -1.5, -0.5, -0.2, 0., 0.5, 1.5, 2.5, 3.7], dev)?;
+        let floor = [-3f32, -2., -1., -1., 0., 0., 1., 2., 3.];
+        let ceil = [-2f32, -1., -0., -0., 0., 1., 2., 3., 4.];
+        // Half-way cases are rounded away from zero.
+        let round = [-3f32, -2., -1., -0., 0., 1., 2., 3., 4.];
+        let sign = [-1f32, -1., -1., -1., 0., 1., 1., 1., 1.];
+        assert_eq!(xs.floor()?.to_vec1::<f32>()?, floor);
+        assert_eq!(xs.ceil()?.to_vec1::<f32>()?, ceil);
+        assert_eq!(xs.round()?.to_vec1::<f32>()?, round);
+        assert_eq!(xs.sign()?.to_vec1::<f32>()?, sign);
+        let xs = xs.to_dtype(DType::F64)?;
+        assert_eq!(xs.round()?.to_dtype(DType::F32)?.to_vec1::<f32>()?, round);
+        assert_eq!(xs.sign()?.to_dtype(DType::F32)?.to_vec1::<f32>()?, sign);
+
+        // The rounding ops are no-ops on integers.
+        let xs = Tensor::new(&[-3i64, 0, 2], dev)?;
+        assert_eq!(xs.floor()?.to_vec1::<i64>()?, [-3, 0, 2]);
+        assert_eq!(xs.round()?.to_vec1::<i64>()?, [-3, 0, 2]);
+        assert_eq!(xs.sign()?.to_vec1::<i64>()?, [-1, 0, 1]);
+        let xs = Tensor::new(&[0u32, 5], dev)?;
+        assert_eq!(xs.sign()?.to_vec1::<u32>()?, [0, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn int_pow() -> Result<()> {
+        let dev = &Device::Cpu;
+        let base = Tensor::new(&[1i64, -1, 0, 1, -1, 0, -3], dev)?;
+        let exp = Tensor::new(&[0i64, 0, 0, 5, 3, 2, 3], dev)?;
+        assert_eq!(base.pow(&exp)?.to_vec1::<i64>()?, [1, 1, 1, 1, -1, 0, -27]);
+        let base = base.to_dtype(DType::I8)?;
+        let exp = exp.to_dtype(DType::I8)?;
+        assert_eq!(base.pow(&exp)?.to_vec1::<i8>()?, [1, 1, 1, 1, -1, 0, -27]);
+
+        // Negative exponents are rejected whatever the base, including 1, -1 and 0.
+        for base in [1i32, -1, 0, 2] {
+            let base = Tensor::new(&[base, base], dev)?;
+            let exp = Tensor::new(&[2i32, -1], dev)?;
+            let err = base.pow(&exp).unwrap_err().to_string();
+            assert!(err.contains("negative integer powers are not allowed"), "{err}");
+        }
+        let base = Tensor::new(&[1i64], dev)?;
+        let exp = Tensor::new(&[u32::MAX as i64 + 1], dev)?;
+        assert!(base.pow(&exp).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn maximum_minimum_nan() -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = Tensor::new(&[f32::NAN, 1., f32::NAN, -2.], dev)?;
+        let ys = Tensor::new(&[1f32, f32::NAN, f32::NAN, 3.], dev)?;
+        for (lhs, rhs) in [(&xs, &ys), (&ys, &xs)] {
+            for zs in [lhs.maximum(rhs)?, lhs.minimum(rhs)?] {
+                let zs = zs.to_vec1::<f32>()?;
+                assert!(zs[..3].iter().all(|z| z.is_nan()), "{zs:?}");
+            }
+            assert_eq!(lhs.maximum(rhs)?.to_vec1::<f32>()?[3], 3.);
+            assert_eq!(lhs.minimum(rhs)?.to_vec1::<f32>()?[3], -2.);
+        }
+        let xs = xs.to_dtype(DType::F64)?;
+        assert!(xs.maximum(1.)?.to_vec1::<f64>()?[0].is_nan());
+        assert!(xs.minimum(1.)?.to_vec1::<f64>()?[0].is_nan());
+        Ok(())
+    }
+
+    #[test]
+    fn int_div() -> Result<()> {
+        let dev = &Device::Cpu;
+        let xs = Tensor::new(&[7i8, -7, i8::MIN, 3], dev)?;
+        let ys = Tensor::new(&[2i8, 2, -1, 0], dev)?;
+        assert_eq!((&xs / &ys)?.to_vec1::<i8>()?, [3, -3, i8::MIN, 0]);
+        let xs = Tensor::new(&[i64::MIN, 5], dev)?;
+        let ys = Tensor::new(&[-1i64, 0], dev)?;
+        assert_eq!(xs.div(&ys)?.to_vec1::<i64>()?, [i64::MIN, 0]);
+        let xs = Tensor::new(&[9u32, 9], dev)?;
+        let ys = Tensor::new(&[0u32, 4], dev)?;
+        assert_eq!(xs.div(&ys)?.to_vec1::<u32>()?, [0, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn float_only_ops_on_ints() -> Result<()> {
+        let xs = Tensor::new(&[-1i64, 2], &Device::Cpu)?;
+        for (name, ys) in [
+            ("sigmoid", xs.sigmoid()),
+            ("silu", xs.silu()),
+            ("erf", xs.erf()),
+            ("tanh", xs.tanh()),
+            ("gelu", xs.gelu()),
+            ("gelu_erf", xs.gelu_erf()),
+        ] {
+            let err = ys.unwrap_err().to_string();
+            assert!(err.contains(&format!("unsupported dtype I64 for op {name}")), "{err}");
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 use super::{k_quants, GgmlDType};
 use crate::error::{Result, Error};
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::Seek;
+use std::sync::Arc;
 
 // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/llama.h#L37
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -148,14 +150,62 @@ pub fn qtensor_from_ggml(
         GgmlDType::Q4K => from_raw_data::<k_quants::BlockQ4K>(raw_data, size_in_bytes, dims),
         GgmlDType::Q5K => from_raw_data::<k_quants::BlockQ5K>(raw_data, size_in_bytes, dims),
         GgmlDType::Q6K => from_raw_data::<k_quants::BlockQ6K>(raw_data, size_in_bytes, dims),
-        _ => crate::bail!("quantized type {ggml_dtype:?} is not supported yet"),
+        GgmlDType::Q8_1 => from_raw_data::<k_quants::BlockQ8_1>(raw_data, size_in_bytes, dims),
+        GgmlDType::Q8K => from_raw_data::<k_quants::BlockQ8K>(raw_data, size_in_bytes, dims),
     }
 }
 
-fn read_one_tensor<R: std::io::Seek + std::io::Read>(
+fn from_mmaped_data<T: super::GgmlType + Send + Sync + 'static>(
+    mmap: &Arc<memmap2::Mmap>,
+    offset: usize,
+    size_in_bytes: usize,
+    dims: Vec<usize>,
+) -> Result<super::QTensor> {
+    let n_blocks = size_in_bytes / std::mem::size_of::<T>();
+    super::QTensor::from_mmap::<_, T>(mmap.clone(), offset, n_blocks, dims)
+}
+
+/// Creates a [super::QTensor] that points directly at the raw GGML tensor data stored at
+/// `offset` in the memory mapped file, without copying it.
+pub fn qtensor_from_mmap(
+    ggml_dtype: GgmlDType,
+    mmap: &Arc<memmap2::Mmap>,
+    offset: usize,
+    dims: Vec<usize>,
+) -> Result<super::QTensor> {
+    let tensor_elems = dims.iter().product::<usize>();
+    let size_in_bytes = tensor_elems * ggml_dtype.type_size() / ggml_dtype.blck_size();
+
+    match ggml_dtype {
+        GgmlDType::F32 => from_mmaped_data::<f32>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::F16 => from_mmaped_data::<half::f16>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q4_0 => from_mmaped_data::<k_quants::BlockQ4_0>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q4_1 => from_mmaped_data::<k_quants::BlockQ4_1>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q5_0 => from_mmaped_data::<k_quants::BlockQ5_0>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q5_1 => from_mmaped_data::<k_quants::BlockQ5_1>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q8_0 => from_mmaped_data::<k_quants::BlockQ8_0>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q2K => from_mmaped_data::<k_quants::BlockQ2K>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q3K => from_mmaped_data::<k_quants::BlockQ3K>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q4K => from_mmaped_data::<k_quants::BlockQ4K>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q5K => from_mmaped_data::<k_quants::BlockQ5K>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q6K => from_mmaped_data::<k_quants::BlockQ6K>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q8_1 => from_mmaped_data::<k_quants::BlockQ8_1>(mmap, offset, size_in_bytes, dims),
+        GgmlDType::Q8K => from_mmaped_data::<k_quants::BlockQ8K>(mmap, offset, size_in_bytes, dims),
+    }
+}
+
+struct TensorHeader {
+    name: String,
+    ggml_dtype: GgmlDType,
+    dims: Vec<usize>,
+    size_in_bytes: usize,
+}
+
+// Reads the tensor header and leaves the reader at the start of the tensor data.
+fn read_tensor_header<R: std::io::Seek + std::io::Read>(
     reader: &mut R,
     magic: VersionedMagic,
-) -> Result<(String, super::QTensor)> {
+) -> Result<TensorHeader> {
     let n_dims = reader.read_u32::<LittleEndian>()?;
     let name_len = reader.read_u32::<LittleEndian>()?;
     let ggml_dtype = reader.read_u32::<LittleEndian>()?;
@@ -170,19 +220,60 @@ fn read_one_tensor<R: std::io::Seek + std::io::Read>(
         let pos = reader.stream_position()?;
         reader.seek(std::io::SeekFrom::Current(((32 - pos % 32) % 32) as i64))?;
     }
-    let dims = dims.iter().map(|&u| u as usize).collect::<Vec<_>>();
+    // The dimensions are stored with the fastest varying one first, reverse them so that the
+    // shape is row-major like the data, e.g. a weight matrix has the shape (n, k).
+    let dims = dims.iter().rev().map(|&u| u as usize).collect::<Vec<_>>();
     let tensor_elems = dims.iter().product::<usize>();
     let size_in_bytes = tensor_elems * ggml_dtype.type_size() / ggml_dtype.blck_size();
-    println!("{name} {ggml_dtype:?} {dims:?}");
-    // TODO: Mmap version to avoid copying the data around?
-    let mut raw_data = vec![0u8; size_in_bytes];
+    Ok(TensorHeader {
+        name,
+        ggml_dtype,
+        dims,
+        size_in_bytes,
+    })
+}
+
+fn read_one_tensor<R: std::io::Seek + std::io::Read>(
+    reader: &mut R,
+    magic: VersionedMagic,
+) -> Result<(String, super::QTensor)> {
+    let header = read_tensor_header(reader, magic)?;
+    let mut raw_data = vec![0u8; header.size_in_bytes];
     reader.read_exact(&mut raw_data)?;
-    match qtensor_from_ggml(ggml_dtype, &raw_data, dims) {
-        Ok(tensor) => Ok((name, tensor)),
-        Err(e) => crate::bail!("Error creating tensor {name}: {e}"),
+    match qtensor_from_ggml(header.ggml_dtype, &raw_data, header.dims) {
+        Ok(tensor) => Ok((header.name, tensor)),
+        Err(e) => crate::bail!("Error creating tensor {}: {e}", header.name),
     }
 }
 
+fn mmap_one_tensor(
+    reader: &mut std::io::Cursor<&[u8]>,
+    mmap: &Arc<memmap2::Mmap>,
+    magic: VersionedMagic,
+) -> Result<(String, super::QTensor)> {
+    let header = read_tensor_header(reader, magic)?;
+    let offset = reader.position() as usize;
+    reader.seek(std::io::SeekFrom::Current(header.size_in_bytes as i64))?;
+    let tensor = if magic.align32() {
+        qtensor_from_mmap(header.ggml_dtype, mmap, offset, header.dims)
+    } else {
+        // The unversioned formats do not pad the tensor data so it may not be suitably aligned
+        // to be used in place, the data gets copied in this case.
+        match mmap.get(offset..offset + header.size_in_bytes) {
+            Some(raw_data) => qtensor_from_ggml(header.ggml_dtype, raw_data, header.dims),
+            None => Err(Error::Msg(format!(
+                "tensor data {offset}..{} is out of the file bounds",
+                offset + header.size_in_bytes
+            ))),
+        }
+    };
+    let tensor = match tensor {
+        Ok(tensor) => tensor,
+        Err(e) => crate::bail!("Error creating tensor {}: {e}", header.name),
+    };
+    Ok((header.name, tensor))
+}
+
 pub struct Content {
     pub magic: VersionedMagic,
     pub hparams: HParams,
@@ -211,4 +302,154 @@ impl Content {
             tensors,
         })
     }
+
+    /// Memory maps the file and loads the tensors without copying their data, the quantized
+    /// tensors point directly at the mapped pages so only the parts that are actually used get
+    /// loaded in memory.
+    ///
+    /// # Safety
+    ///
+    /// The unsafe is inherited from [`memmap2::MmapOptions`], the file must not be modified
+    /// while the returned tensors are alive.
+    pub unsafe fn read_mmap<P: AsRef<std::path::Path>>(p: P) -> Result<Content> {
+        let p = p.as_ref();
+        let file = std::fs::File::open(p).map_err(|e| Error::from(e).with_path(p))?;
+        let mmap = memmap2::MmapOptions::new()
+            .map(&file)
+            .map_err(|e| Error::from(e).with_path(p))?;
+        let mmap = Arc::new(mmap);
+        let mut reader = std::io::Cursor::new(&mmap[..]);
+        let magic = VersionedMagic::read(&mut reader)?;
+        let hparams = HParams::read(&mut reader)?;
+        let vocab = Vocab::read(&mut reader, hparams.n_vocab as usize)?;
+        let mut tensors = vec![];
+
+        while (reader.position() as usize) < mmap.len() {
+            let (name, tensor) = mmap_one_tensor(&mut reader, &mmap, magic)?;
+            tensors.push((name, tensor))
+        }
+        Ok(Self {
+            magic,
+            hparams,
+            vocab,
+            tensors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantized::k_quants::{quantize, BlockQ8_0};
+    use crate::quantized::QTensor;
+    use crate::{Device, Tensor};
+    use byteorder::WriteBytesExt;
+    use std::io::Write;
+
+    // Writes a ggml file with two tokens and the given tensors, `magic` being either the
+    // unversioned ggml magic or ggjt v3.
+    fn encode(magic: VersionedMagic, tensors: &[(&str, &QTensor)]) -> Vec<u8> {
+        let mut w = vec![];
+        match magic {
+            VersionedMagic::GgmlUnversioned => w.write_u32::<LittleEndian>(0x67676d6c).unwrap(),
+            _ => {
+                w.write_u32::<LittleEndian>(0x67676a74).unwrap();
+                w.write_u32::<LittleEndian>(3).unwrap()
+            }
+        }
+        for v in [2, 256, 1, 1, 1, 1, 0] {
+            w.write_u32::<LittleEndian>(v).unwrap()
+        }
+        for (token, score) in [("a", 0.5f32), ("bc", -1.)] {
+            w.write_u32::<LittleEndian>(token.len() as u32).unwrap();
+            w.write_all(token.as_bytes()).unwrap();
+            w.write_f32::<LittleEndian>(score).unwrap()
+        }
+        for (name, tensor) in tensors.iter() {
+            let dims = tensor.shape().dims();
+            w.write_u32::<LittleEndian>(dims.len() as u32).unwrap();
+            w.write_u32::<LittleEndian>(name.len() as u32).unwrap();
+            w.write_u32::<LittleEndian>(tensor.dtype().to_u32()).unwrap();
+            for &d in dims.iter().rev() {
+                w.write_u32::<LittleEndian>(d as u32).unwrap()
+            }
+            w.write_all(name.as_bytes()).unwrap();
+            if magic.align32() {
+                w.resize(w.len() + (32 - w.len() % 32) % 32, 0)
+            }
+            w.write_all(tensor.data()).unwrap()
+        }
+        w
+    }
+
+    // Removes the file when dropped so that it does not outlive a failed assertion.
+    struct TempFile(std::path::PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn read_mmap_matches_read() -> Result<()> {
+        let xs: Vec<f32> = (0..2 * 256).map(|i| (i as f32 * 0.1).sin()).collect();
+        let q8 = QTensor::new(quantize::<BlockQ8_0>(&xs)?, (2, 256));
+        let f32 = QTensor::new(quantize::<f32>(&xs[..10])?, (2, 5));
+        for magic in [VersionedMagic::GgjtV3, VersionedMagic::GgmlUnversioned] {
+            let name = format!("my-candle-read-mmap-{}-{magic:?}.ggml", std::process::id());
+            let tmp = TempFile(std::env::temp_dir().join(name));
+            std::fs::write(&tmp.0, encode(magic, &[("q8", &q8), ("f32", &f32)]))?;
+            let mut file = std::fs::File::open(&tmp.0)?;
+            let content = Content::read(&mut file)?;
+            let mmaped = unsafe { Content::read_mmap(&tmp.0)? };
+            assert_eq!(mmaped.magic, magic);
+            assert_eq!(mmaped.hparams, content.hparams);
+            assert_eq!(mmaped.vocab, content.vocab);
+            assert_eq!(mmaped.tensors.len(), 2);
+            for ((name, read), (mmaped_name, mmaped)) in
+                content.tensors.iter().zip(mmaped.tensors.iter())
+            {
+                assert_eq!(name, mmaped_name);
+                assert_eq!(read.dtype(), mmaped.dtype());
+                assert_eq!(read.shape(), mmaped.shape());
+                assert_eq!(read.data(), mmaped.data());
+                let read = read.dequantize(&Device::Cpu)?.flatten_all()?.to_vec1::<f32>()?;
+                let mmaped = mmaped.dequantize(&Device::Cpu)?.flatten_all()?.to_vec1::<f32>()?;
+                assert_eq!(read, mmaped);
+            }
+            assert_eq!(content.tensors[0].1.shape().dims(), [2, 256]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_row_major_shape() -> Result<()> {
+        // An unversioned ggml file holding a 2x3 f32 matrix, the dims are written with the
+        // fastest varying one first.
+        let mut w = vec![];
+        w.write_u32::<LittleEndian>(0x67676d6c)?;
+        for v in [0, 3, 1, 1, 1, 1, 0] {
+            w.write_u32::<LittleEndian>(v)?
+        }
+        // The rank, the name length, the f32 dtype and the dims.
+        for v in [2, 1, 0, 3, 2] {
+            w.write_u32::<LittleEndian>(v)?
+        }
+        w.write_all(b"w")?;
+        for v in [1f32, 2., 3., 4., 5., 6.] {
+            w.write_f32::<LittleEndian>(v)?
+        }
+        let content = Content::read(&mut std::io::Cursor::new(w))?;
+        let (name, ws) = content.tensors.into_iter().next().unwrap();
+        assert_eq!(name, "w");
+        assert_eq!(ws.shape().dims(), [2, 3]);
+        let dense = ws.dequantize(&Device::Cpu)?;
+        assert_eq!(dense.to_vec2::<f32>()?, [[1., 2., 3.], [4., 5., 6.]]);
+        // The weights are used transposed, `xs @ ws^T`.
+        let xs = Tensor::new(&[[1f32, 0., -1.]], &Device::Cpu)?;
+        let ys = xs.custom_op1(super::super::QMatMul::new(std::sync::Arc::new(ws)))?;
+        assert_eq!(ys.to_vec2::<f32>()?, [[-2., -2.]]);
+        Ok(())
+    }
 }
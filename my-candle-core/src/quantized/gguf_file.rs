@@ -0,0 +1,586 @@
+//! Support for the GGUF file format.
+//!
+//! Spec: https://github.com/ggerganov/ggml/blob/master/docs/gguf.md
+use super::{GgmlDType, QTensor};
+use crate::error::Result;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::HashMap;
+
+pub const DEFAULT_ALIGNMENT: u64 = 32;
+// The maximum number of dimensions of a ggml tensor.
+const MAX_DIMS: u32 = 4;
+// Arrays of arrays are allowed, deeper nesting is rejected to bound the recursion when reading.
+const MAX_ARRAY_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Magic {
+    Gguf,
+}
+
+impl TryFrom<u32> for Magic {
+    type Error = crate::Error;
+    fn try_from(value: u32) -> Result<Self> {
+        let magic = match value {
+            0x46554747 => Self::Gguf,
+            0x47475546 => crate::bail!("big-endian gguf files are not supported"),
+            _ => crate::bail!("unknown magic 0x{value:08x}"),
+        };
+        Ok(magic)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionedMagic {
+    GgufV1,
+    GgufV2,
+    GgufV3,
+}
+
+impl VersionedMagic {
+    fn read<R: std::io::Read>(reader: &mut R) -> Result<Self> {
+        let magic = reader.read_u32::<LittleEndian>()?;
+        let magic = Magic::try_from(magic)?;
+        let version = reader.read_u32::<LittleEndian>()?;
+        let versioned_magic = match (magic, version) {
+            (Magic::Gguf, 1) => Self::GgufV1,
+            (Magic::Gguf, 2) => Self::GgufV2,
+            (Magic::Gguf, 3) => Self::GgufV3,
+            _ => crate::bail!("gguf: unsupported magic/version {magic:?}/{version}"),
+        };
+        Ok(versioned_magic)
+    }
+
+    // Lengths and counts are stored as u32 in v1 and as u64 from v2 onwards. They are checked
+    // against `max_len`, usually the file size, before being used to allocate anything.
+    fn read_len<R: std::io::Read>(&self, reader: &mut R, max_len: u64) -> Result<usize> {
+        let len = match self {
+            Self::GgufV1 => reader.read_u32::<LittleEndian>()? as u64,
+            Self::GgufV2 | Self::GgufV3 => reader.read_u64::<LittleEndian>()?,
+        };
+        if len > max_len {
+            crate::bail!("gguf: length {len} is larger than the file size {max_len}")
+        }
+        Ok(len as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TensorInfo {
+    pub ggml_dtype: GgmlDType,
+    pub shape: crate::Shape,
+    /// Offset of the tensor data relative to the start of the tensor data section.
+    pub offset: u64,
+}
+
+impl TensorInfo {
+    fn size_in_bytes(&self) -> Result<usize> {
+        let shape = &self.shape;
+        let tensor_elems = match shape.dims().iter().try_fold(1usize, |p, &d| p.checked_mul(d)) {
+            Some(tensor_elems) => tensor_elems,
+            None => crate::bail!("gguf: too many elements in tensor of shape {shape:?}"),
+        };
+        let blck_size = self.ggml_dtype.blck_size();
+        if tensor_elems % blck_size != 0 {
+            crate::bail!(
+                "the number of elements {tensor_elems} is not divisible by the block size {blck_size}"
+            )
+        }
+        Ok(tensor_elems / blck_size * self.ggml_dtype.type_size())
+    }
+
+    pub fn read<R: std::io::Seek + std::io::Read>(
+        &self,
+        reader: &mut R,
+        tensor_data_offset: u64,
+    ) -> Result<QTensor> {
+        let size_in_bytes = self.size_in_bytes()?;
+        let file_size = reader.seek(std::io::SeekFrom::End(0))?;
+        let start = tensor_data_offset.saturating_add(self.offset);
+        if start.saturating_add(size_in_bytes as u64) > file_size {
+            crate::bail!("gguf: tensor data at {start} ({size_in_bytes} bytes) is out of the file")
+        }
+        let mut raw_data = vec![0u8; size_in_bytes];
+        reader.seek(std::io::SeekFrom::Start(start))?;
+        reader.read_exact(&mut raw_data)?;
+        super::ggml_file::qtensor_from_ggml(self.ggml_dtype, &raw_data, self.shape.dims().to_vec())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+    Bool,
+    String,
+    Array,
+}
+
+impl ValueType {
+    fn from_u32(v: u32) -> Result<Self> {
+        let v = match v {
+            0 => Self::U8,
+            1 => Self::I8,
+            2 => Self::U16,
+            3 => Self::I16,
+            4 => Self::U32,
+            5 => Self::I32,
+            6 => Self::F32,
+            7 => Self::Bool,
+            8 => Self::String,
+            9 => Self::Array,
+            10 => Self::U64,
+            11 => Self::I64,
+            12 => Self::F64,
+            v => crate::bail!("unrecognized value-type {v:#08x}"),
+        };
+        Ok(v)
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            Self::U8 => 0,
+            Self::I8 => 1,
+            Self::U16 => 2,
+            Self::I16 => 3,
+            Self::U32 => 4,
+            Self::I32 => 5,
+            Self::F32 => 6,
+            Self::Bool => 7,
+            Self::String => 8,
+            Self::Array => 9,
+            Self::U64 => 10,
+            Self::I64 => 11,
+            Self::F64 => 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+}
+
+macro_rules! value_getter {
+    ($fn_name:ident, $variant:ident, $ty:ty) => {
+        pub fn $fn_name(&self) -> Result<$ty> {
+            match self {
+                Self::$variant(v) => Ok(*v),
+                v => crate::bail!("not a {} {v:?}", stringify!($ty)),
+            }
+        }
+    };
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Self::U8(_) => ValueType::U8,
+            Self::I8(_) => ValueType::I8,
+            Self::U16(_) => ValueType::U16,
+            Self::I16(_) => ValueType::I16,
+            Self::U32(_) => ValueType::U32,
+            Self::I32(_) => ValueType::I32,
+            Self::U64(_) => ValueType::U64,
+            Self::I64(_) => ValueType::I64,
+            Self::F32(_) => ValueType::F32,
+            Self::F64(_) => ValueType::F64,
+            Self::Bool(_) => ValueType::Bool,
+            Self::String(_) => ValueType::String,
+            Self::Array(_) => ValueType::Array,
+        }
+    }
+
+    value_getter!(to_u8, U8, u8);
+    value_getter!(to_i8, I8, i8);
+    value_getter!(to_u16, U16, u16);
+    value_getter!(to_i16, I16, i16);
+    value_getter!(to_u32, U32, u32);
+    value_getter!(to_i32, I32, i32);
+    value_getter!(to_i64, I64, i64);
+    value_getter!(to_f32, F32, f32);
+    value_getter!(to_f64, F64, f64);
+    value_getter!(to_bool, Bool, bool);
+
+    /// Returns the value as a u64, any unsigned integer type is accepted as some writers use
+    /// u32 for values that other writers store as u64.
+    pub fn to_u64(&self) -> Result<u64> {
+        match self {
+            Self::U8(v) => Ok(*v as u64),
+            Self::U16(v) => Ok(*v as u64),
+            Self::U32(v) => Ok(*v as u64),
+            Self::U64(v) => Ok(*v),
+            v => crate::bail!("not a u64 {v:?}"),
+        }
+    }
+
+    pub fn to_vec(&self) -> Result<&Vec<Value>> {
+        match self {
+            Self::Array(v) => Ok(v),
+            v => crate::bail!("not a vec {v:?}"),
+        }
+    }
+
+    pub fn to_string(&self) -> Result<&String> {
+        match self {
+            Self::String(v) => Ok(v),
+            v => crate::bail!("not a string {v:?}"),
+        }
+    }
+
+    // `depth` is the number of arrays this value is nested in.
+    fn read<R: std::io::Seek + std::io::Read>(
+        reader: &mut R,
+        value_type: ValueType,
+        magic: &VersionedMagic,
+        max_len: u64,
+        depth: usize,
+    ) -> Result<Self> {
+        let v = match value_type {
+            ValueType::U8 => Self::U8(reader.read_u8()?),
+            ValueType::I8 => Self::I8(reader.read_i8()?),
+            ValueType::U16 => Self::U16(reader.read_u16::<LittleEndian>()?),
+            ValueType::I16 => Self::I16(reader.read_i16::<LittleEndian>()?),
+            ValueType::U32 => Self::U32(reader.read_u32::<LittleEndian>()?),
+            ValueType::I32 => Self::I32(reader.read_i32::<LittleEndian>()?),
+            ValueType::U64 => Self::U64(reader.read_u64::<LittleEndian>()?),
+            ValueType::I64 => Self::I64(reader.read_i64::<LittleEndian>()?),
+            ValueType::F32 => Self::F32(reader.read_f32::<LittleEndian>()?),
+            ValueType::F64 => Self::F64(reader.read_f64::<LittleEndian>()?),
+            ValueType::Bool => match reader.read_u8()? {
+                0 => Self::Bool(false),
+                1 => Self::Bool(true),
+                b => crate::bail!("unexpected bool value {b}"),
+            },
+            ValueType::String => Self::String(read_string(reader, magic, max_len)?),
+            ValueType::Array => {
+                if depth >= MAX_ARRAY_DEPTH {
+                    crate::bail!("gguf: arrays cannot be nested more than {MAX_ARRAY_DEPTH} deep")
+                }
+                let value_type = reader.read_u32::<LittleEndian>()?;
+                let value_type = ValueType::from_u32(value_type)?;
+                let len = magic.read_len(reader, max_len)?;
+                // The length has not been checked against the actual elements yet, the initial
+                // allocation is bounded by the bytes left in the file.
+                let remaining = max_len.saturating_sub(reader.stream_position()?) as usize;
+                let mut vs = Vec::with_capacity(len.min(remaining / std::mem::size_of::<Value>()));
+                for _ in 0..len {
+                    vs.push(Value::read(reader, value_type, magic, max_len, depth + 1)?)
+                }
+                Self::Array(vs)
+            }
+        };
+        Ok(v)
+    }
+
+    fn write<W: std::io::Write>(&self, w: &mut W) -> Result<()> {
+        match self {
+            &Self::U8(v) => w.write_u8(v)?,
+            &Self::I8(v) => w.write_i8(v)?,
+            &Self::U16(v) => w.write_u16::<LittleEndian>(v)?,
+            &Self::I16(v) => w.write_i16::<LittleEndian>(v)?,
+            &Self::U32(v) => w.write_u32::<LittleEndian>(v)?,
+            &Self::I32(v) => w.write_i32::<LittleEndian>(v)?,
+            &Self::U64(v) => w.write_u64::<LittleEndian>(v)?,
+            &Self::I64(v) => w.write_i64::<LittleEndian>(v)?,
+            &Self::F32(v) => w.write_f32::<LittleEndian>(v)?,
+            &Self::F64(v) => w.write_f64::<LittleEndian>(v)?,
+            &Self::Bool(v) => w.write_u8(u8::from(v))?,
+            Self::String(v) => write_string(w, v.as_str())?,
+            Self::Array(v) => {
+                // The `Value` type does not enforce that all the values in an array have the
+                // same type so this is checked here.
+                let value_type = match v.first() {
+                    // The type does not matter for empty arrays.
+                    None => ValueType::U32,
+                    Some(first) => first.value_type(),
+                };
+                if let Some(elem) = v.iter().find(|elem| elem.value_type() != value_type) {
+                    crate::bail!("multiple value-types in the same array {value_type:?} {elem:?}")
+                }
+                w.write_u32::<LittleEndian>(value_type.to_u32())?;
+                w.write_u64::<LittleEndian>(v.len() as u64)?;
+                for elem in v.iter() {
+                    elem.write(w)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_string<R: std::io::Read>(
+    reader: &mut R,
+    magic: &VersionedMagic,
+    max_len: u64,
+) -> Result<String> {
+    let len = magic.read_len(reader, max_len)?;
+    let mut v = vec![0u8; len];
+    reader.read_exact(&mut v)?;
+    // GGUF strings are not supposed to be null terminated but some writers do it anyway.
+    while let Some(0) = v.last() {
+        v.pop();
+    }
+    // GGUF strings are supposed to be utf8 but this is not always the case in practice.
+    Ok(String::from_utf8_lossy(&v).into_owned())
+}
+
+fn write_string<W: std::io::Write>(w: &mut W, str: &str) -> Result<()> {
+    let bytes = str.as_bytes();
+    w.write_u64::<LittleEndian>(bytes.len() as u64)?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
+fn alignment(metadata: &HashMap<String, Value>) -> Result<u64> {
+    let alignment = match metadata.get("general.alignment") {
+        None => return Ok(DEFAULT_ALIGNMENT),
+        Some(Value::U8(v)) => *v as u64,
+        Some(Value::U16(v)) => *v as u64,
+        Some(Value::U32(v)) => *v as u64,
+        Some(Value::U64(v)) => *v,
+        Some(Value::I8(v)) => u64::try_from(*v).unwrap_or(0),
+        Some(Value::I16(v)) => u64::try_from(*v).unwrap_or(0),
+        Some(Value::I32(v)) => u64::try_from(*v).unwrap_or(0),
+        Some(Value::I64(v)) => u64::try_from(*v).unwrap_or(0),
+        Some(v) => crate::bail!("gguf: unexpected general.alignment {v:?}"),
+    };
+    // This also rejects zero which would otherwise result in a division by zero.
+    if !alignment.is_power_of_two() {
+        crate::bail!("gguf: general.alignment {alignment} is not a power of two")
+    }
+    Ok(alignment)
+}
+
+#[derive(Debug)]
+pub struct Content {
+    pub magic: VersionedMagic,
+    pub metadata: HashMap<String, Value>,
+    pub tensor_infos: HashMap<String, TensorInfo>,
+    /// Absolute position of the tensor data section in the file.
+    pub tensor_data_offset: u64,
+}
+
+impl Content {
+    /// Reads the header, metadata and tensor infos, the tensor data is only loaded on demand
+    /// via [Content::tensor].
+    pub fn read<R: std::io::Seek + std::io::Read>(reader: &mut R) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let file_size = reader.seek(std::io::SeekFrom::End(0))?;
+        reader.seek(std::io::SeekFrom::Start(start))?;
+        let magic = VersionedMagic::read(reader)?;
+
+        let tensor_count = magic.read_len(reader, file_size)?;
+        let metadata_kv_count = magic.read_len(reader, file_size)?;
+
+        let mut metadata = HashMap::new();
+        for _idx in 0..metadata_kv_count {
+            let key = read_string(reader, &magic, file_size)?;
+            let value_type = reader.read_u32::<LittleEndian>()?;
+            let value_type = ValueType::from_u32(value_type)?;
+            let value = Value::read(reader, value_type, &magic, file_size, 0)?;
+            metadata.insert(key, value);
+        }
+        let mut tensor_infos = HashMap::new();
+        for _idx in 0..tensor_count {
+            let tensor_name = read_string(reader, &magic, file_size)?;
+            let n_dimensions = reader.read_u32::<LittleEndian>()?;
+            if n_dimensions > MAX_DIMS {
+                crate::bail!("gguf: {tensor_name} has {n_dimensions} dimensions, max is {MAX_DIMS}")
+            }
+            let mut dimensions = Vec::with_capacity(n_dimensions as usize);
+            for _ in 0..n_dimensions {
+                // The dimensions are not used for allocations, the element count is checked
+                // against the file size when reading the tensor data.
+                dimensions.push(magic.read_len(reader, u64::MAX)?)
+            }
+            // GGUF stores the dimensions with the fastest varying one first.
+            dimensions.reverse();
+            let ggml_dtype = reader.read_u32::<LittleEndian>()?;
+            let ggml_dtype = GgmlDType::from_u32(ggml_dtype)?;
+            let offset = reader.read_u64::<LittleEndian>()?;
+            tensor_infos.insert(
+                tensor_name,
+                TensorInfo {
+                    shape: crate::Shape::from(dimensions),
+                    offset,
+                    ggml_dtype,
+                },
+            );
+        }
+        let position = reader.stream_position()?;
+        let alignment = alignment(&metadata)?;
+        let tensor_data_offset = (position + alignment - 1) / alignment * alignment;
+        Ok(Self {
+            magic,
+            metadata,
+            tensor_infos,
+            tensor_data_offset,
+        })
+    }
+
+    pub fn tensor<R: std::io::Seek + std::io::Read>(
+        &self,
+        reader: &mut R,
+        name: &str,
+    ) -> Result<QTensor> {
+        let tensor_info = match self.tensor_infos.get(name) {
+            Some(tensor_info) => tensor_info,
+            None => crate::bail!("cannot find tensor-info for {name}"),
+        };
+        tensor_info.read(reader, self.tensor_data_offset)
+    }
+}
+
+/// Writes a GGUF v3 file with the given metadata and tensors. The tensor data is aligned on the
+/// `general.alignment` metadata value if present, and on [DEFAULT_ALIGNMENT] otherwise.
+pub fn write<W: std::io::Seek + std::io::Write>(
+    w: &mut W,
+    metadata: &[(&str, &Value)],
+    tensors: &[(&str, &QTensor)],
+) -> Result<()> {
+    let alignment = {
+        let metadata = metadata
+            .iter()
+            .filter(|(k, _)| *k == "general.alignment")
+            .map(|(k, v)| (k.to_string(), (*v).clone()))
+            .collect::<HashMap<_, _>>();
+        alignment(&metadata)? as usize
+    };
+    let padding = |size: usize| (alignment - size % alignment) % alignment;
+
+    w.write_u32::<LittleEndian>(0x46554747)?;
+    w.write_u32::<LittleEndian>(3)?;
+    w.write_u64::<LittleEndian>(tensors.len() as u64)?;
+    w.write_u64::<LittleEndian>(metadata.len() as u64)?;
+    for (name, value) in metadata.iter() {
+        write_string(w, name)?;
+        w.write_u32::<LittleEndian>(value.value_type().to_u32())?;
+        value.write(w)?;
+    }
+    let mut offset = 0usize;
+    let mut offsets = Vec::with_capacity(tensors.len());
+    for (name, tensor) in tensors.iter() {
+        write_string(w, name)?;
+        let dims = tensor.shape().dims();
+        w.write_u32::<LittleEndian>(dims.len() as u32)?;
+        for &dim in dims.iter().rev() {
+            w.write_u64::<LittleEndian>(dim as u64)?;
+        }
+        w.write_u32::<LittleEndian>(tensor.dtype().to_u32())?;
+        w.write_u64::<LittleEndian>(offset as u64)?;
+        offsets.push(offset);
+        let size_in_bytes = tensor.storage_size_in_bytes();
+        offset += size_in_bytes + padding(size_in_bytes);
+    }
+    let pos = w.stream_position()? as usize;
+    w.write_all(&vec![0u8; padding(pos)])?;
+    let tensor_start_pos = w.stream_position()? as usize;
+    for (offset, (name, tensor)) in offsets.iter().zip(tensors.iter()) {
+        let pos = w.stream_position()? as usize;
+        if tensor_start_pos + offset != pos {
+            crate::bail!("internal error writing {name}, unexpected position {pos}")
+        }
+        let data = tensor.data();
+        w.write_all(data)?;
+        w.write_all(&vec![0u8; padding(data.len())])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantized::k_quants::{quantize, BlockQ8_0};
+    use std::io::Cursor;
+
+    #[test]
+    fn write_read_round_trip() -> Result<()> {
+        let xs: Vec<f32> = (0..2 * 256).map(|i| (i as f32 * 0.1).sin()).collect();
+        let q8 = QTensor::new(quantize::<BlockQ8_0>(&xs)?, (2, 256));
+        let f32 = QTensor::new(quantize::<f32>(&xs[..6])?, (2, 3));
+        let name = Value::String("test".to_string());
+        let alignment = Value::U32(64);
+        let metadata = [("general.name", &name), ("general.alignment", &alignment)];
+        let mut buffer = Cursor::new(vec![]);
+        write(&mut buffer, &metadata, &[("q8", &q8), ("f32", &f32)])?;
+
+        let content = Content::read(&mut buffer)?;
+        assert_eq!(content.magic, VersionedMagic::GgufV3);
+        assert_eq!(content.metadata["general.name"].to_string()?, "test");
+        assert_eq!(content.tensor_data_offset % 64, 0);
+        assert_eq!(content.tensor_infos["q8"].shape.dims(), [2, 256]);
+        for (name, tensor) in [("q8", &q8), ("f32", &f32)] {
+            let read = content.tensor(&mut buffer, name)?;
+            assert_eq!(read.dtype(), tensor.dtype());
+            assert_eq!(read.data(), tensor.data());
+        }
+
+        // Zero is not a valid alignment.
+        let alignment = Value::U8(0);
+        let metadata = [("general.alignment", &alignment)];
+        let mut buffer = Cursor::new(vec![]);
+        assert!(write(&mut buffer, &metadata, &[("q8", &q8)]).is_err());
+
+        // A truncated file or a corrupted string length are reported as errors rather than
+        // triggering huge allocations.
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&0x46554747u32.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Content::read(&mut Cursor::new(bytes)).is_err());
+
+        // A metadata value made of arrays nested three times, the innermost one being an array
+        // of u8 whose length exceeds its data.
+        let header = |bytes: &mut Vec<u8>| {
+            bytes.extend_from_slice(&0x46554747u32.to_le_bytes());
+            bytes.extend_from_slice(&3u32.to_le_bytes());
+            bytes.extend_from_slice(&0u64.to_le_bytes());
+            bytes.extend_from_slice(&1u64.to_le_bytes());
+            bytes.extend_from_slice(&1u64.to_le_bytes());
+            bytes.push(b'k');
+        };
+        let array = |bytes: &mut Vec<u8>, value_type: u32, len: u64| {
+            bytes.extend_from_slice(&value_type.to_le_bytes());
+            bytes.extend_from_slice(&len.to_le_bytes());
+        };
+        let mut bytes = vec![];
+        header(&mut bytes);
+        bytes.extend_from_slice(&9u32.to_le_bytes());
+        array(&mut bytes, 9, 1);
+        array(&mut bytes, 9, 1);
+        array(&mut bytes, 0, 1);
+        bytes.push(1);
+        let err = Content::read(&mut Cursor::new(bytes)).unwrap_err();
+        assert!(err.to_string().contains("arrays cannot be nested"), "{err}");
+
+        let mut bytes = vec![];
+        header(&mut bytes);
+        bytes.extend_from_slice(&9u32.to_le_bytes());
+        array(&mut bytes, 0, 64);
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(Content::read(&mut Cursor::new(bytes)).is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+use super::utils::{make_q3_quants, make_qkx1_quants, make_qx_quants, nearest_int};
 use super::GgmlDType;
 use crate::error::{Error,Result};
 use half::f16;
+use rayon::prelude::*;
 use crate::quantized::GgmlDType::Q4_1;
 
 pub const QK_K:usize = 256;
@@ -12,7 +14,7 @@ pub const QK5_1: usize = 32;
 pub const QK8_0: usize = 32;
 pub const QK8_1: usize = 32;
 
-pub trait GgmlType:Sized + Clone {
+pub trait GgmlType: Sized + Clone + Send + Sync {
     const DTYPE: GgmlDType;
     const BLCK_SIZE: usize;
 
@@ -26,79 +28,101 @@ pub trait GgmlType:Sized + Clone {
     fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()>;
 
     fn vec_dot(n: usize, xs:&[Self], ys: &[Self::VecDotType]) -> Result<f32>;
+
+    /// The scalar version of `vec_dot`, for types that have a simd implementation this is the
+    /// reference used when testing and benchmarking it.
+    fn vec_dot_unopt(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        Self::vec_dot(n, xs, ys)
+    }
+}
+
+/// Quantizes `xs` to blocks of type `T`, the number of values has to be a multiple of the block
+/// size.
+pub fn quantize<T: GgmlType>(xs: &[f32]) -> Result<Vec<T>> {
+    if xs.len() % T::BLCK_SIZE != 0 {
+        crate::bail!(
+            "quantize {:?}: {} values are not divisible by the block size {}",
+            T::DTYPE,
+            xs.len(),
+            T::BLCK_SIZE
+        )
+    }
+    let mut ys = vec![T::zeros(); xs.len() / T::BLCK_SIZE];
+    T::from_float(xs, &mut ys)?;
+    Ok(ys)
 }
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ4_0 {
-    d: f16,
-    qs:[u8; QK4_0 / 2],
+    pub(crate) d: f16,
+    pub(crate) qs:[u8; QK4_0 / 2],
 }
 
-const _:() = assert!(std::mem::size_of()::<BlockQ4_0>() == 18);
+const _: () = assert!(std::mem::size_of::<BlockQ4_0>() == 18);
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ4_1 {
-    d: f16,
-    m: f16,
-    qs: [u8; QK4_1 / 2],
+    pub(crate) d: f16,
+    pub(crate) m: f16,
+    pub(crate) qs: [u8; QK4_1 / 2],
 }
 const _: () = assert!(std::mem::size_of::<BlockQ4_1>() == 20);
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ5_0 {
-    d: f16,
-    qh: [u8; 4],
-    qs: [u8; QK5_0 / 2],
+    pub(crate) d: f16,
+    pub(crate) qh: [u8; 4],
+    pub(crate) qs: [u8; QK5_0 / 2],
 }
 const _: () = assert!(std::mem::size_of::<BlockQ5_0>() == 22);
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ5_1 {
-    d: f16,
-    m: f16,
-    qh: [u8; 4],
-    qs: [u8; QK5_1 / 2],
+    pub(crate) d: f16,
+    pub(crate) m: f16,
+    pub(crate) qh: [u8; 4],
+    pub(crate) qs: [u8; QK5_1 / 2],
 }
 const _: () = assert!(std::mem::size_of::<BlockQ5_1>() == 24);
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ8_0 {
-    d: f16,
-    qs: [u8; QK8_0],
+    pub(crate) d: f16,
+    pub(crate) qs: [i8; QK8_0],
 }
 const _: () = assert!(std::mem::size_of::<BlockQ8_0>() == 34);
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ8_1 {
-    d: f16,
-    s: f16,
-    qs: [u8; QK8_1],
+    pub(crate) d: f16,
+    pub(crate) s: f16,
+    pub(crate) qs: [i8; QK8_1],
 }
 const _: () = assert!(std::mem::size_of::<BlockQ8_1>() == 36);
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ2K {
-    scales: [u8; QK_K / 16],
-    qs: [u8; QK_K / 4],
-    d: f16,
-    dmin: f16,
+    pub(crate) scales: [u8; QK_K / 16],
+    pub(crate) qs: [u8; QK_K / 4],
+    pub(crate) d: f16,
+    pub(crate) dmin: f16,
 }
 const _: () = assert!(QK_K / 16 + QK_K / 4 + 2 * 2 == std::mem::size_of::<BlockQ2K>());
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ3K {
-    hmask: [u8; QK_K / 8],
-    qs: [u8; QK_K / 4],
-    scales: [u8; 12],
-    d: f16,
+    pub(crate) hmask: [u8; QK_K / 8],
+    pub(crate) qs: [u8; QK_K / 4],
+    pub(crate) scales: [u8; 12],
+    pub(crate) d: f16,
 }
 const _: () = assert!(QK_K / 8 + QK_K / 4 + 12 + 2 == std::mem::size_of::<BlockQ3K>());
 
@@ -106,21 +130,21 @@ const _: () = assert!(QK_K / 8 + QK_K / 4 + 12 + 2 == std::mem::size_of::<BlockQ
 // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/k_quants.h#L82
 #[repr(C)]
 pub struct BlockQ4K {
-    d: f16,
-    dmin: f16,
-    scales: [u8; K_SCALE_SIZE],
-    qs: [u8; QK_K / 2],
+    pub(crate) d: f16,
+    pub(crate) dmin: f16,
+    pub(crate) scales: [u8; K_SCALE_SIZE],
+    pub(crate) qs: [u8; QK_K / 2],
 }
 const _: () = assert!(QK_K / 2 + K_SCALE_SIZE + 2 * 2 == std::mem::size_of::<BlockQ4K>());
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ5K {
-    d: f16,
-    dmin: f16,
-    scales: [u8; K_SCALE_SIZE],
-    qh: [u8; QK_K / 8],
-    qs: [u8; QK_K / 2],
+    pub(crate) d: f16,
+    pub(crate) dmin: f16,
+    pub(crate) scales: [u8; K_SCALE_SIZE],
+    pub(crate) qh: [u8; QK_K / 8],
+    pub(crate) qs: [u8; QK_K / 2],
 }
 const _: () =
     assert!(QK_K / 8 + QK_K / 2 + 2 * 2 + K_SCALE_SIZE == std::mem::size_of::<BlockQ5K>());
@@ -128,19 +152,19 @@ const _: () =
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ6K {
-    ql: [u8; QK_K / 2],
-    qh: [u8; QK_K / 4],
-    scales: [i8; QK_K / 16],
-    d: f16,
+    pub(crate) ql: [u8; QK_K / 2],
+    pub(crate) qh: [u8; QK_K / 4],
+    pub(crate) scales: [i8; QK_K / 16],
+    pub(crate) d: f16,
 }
 const _: () = assert!(3 * QK_K / 4 + QK_K / 16 + 2 == std::mem::size_of::<BlockQ6K>());
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct BlockQ8K {
-    d: f32,
-    qs: [i8; QK_K],
-    bsums: [i16; QK_K / 16],
+    pub(crate) d: f32,
+    pub(crate) qs: [i8; QK_K],
+    pub(crate) bsums: [i16; QK_K / 16],
 }
 const _: () = assert!(4 + QK_K + QK_K / 16 * 2 == std::mem::size_of::<BlockQ8K>());
 
@@ -152,12 +176,51 @@ impl GgmlType for BlockQ4_1 {
 
     type VecDotType =BlockQ8_1;
 
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L2721
     fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+        let qk = QK4_1;
+        if n % qk != 0 {
+            crate::bail!("vec_dot_q4_1_q8_1: {n} is not divisible by {qk}")
+        }
+        let nb = n / qk;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let mut sum_i = 0i32;
+            for j in 0..qk / 2 {
+                let v0 = (x.qs[j] & 0x0F) as i32;
+                let v1 = (x.qs[j] >> 4) as i32;
+                sum_i += v0 * y.qs[j] as i32 + v1 * y.qs[j + qk / 2] as i32
+            }
+            sumf += sum_i as f32 * f16::to_f32(x.d) * f16::to_f32(y.d)
+                + f16::to_f32(x.m) * f16::to_f32(y.s)
+        }
+        Ok(sumf)
     }
 
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L1226
     fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
-        todo!()
+        // quantize_row_q4_1
+        let qk = Self::BLCK_SIZE;
+        if xs.len() != ys.len() * qk {
+            crate::bail!("size mismatch {} {} {}", xs.len(), ys.len(), qk)
+        }
+        for (ys, xs) in ys.iter_mut().zip(xs.chunks_exact(qk)) {
+            let min = xs.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = xs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let d = (max - min) / ((1 << 4) - 1) as f32;
+            let id = if d != 0f32 { 1. / d } else { 0. };
+            ys.d = f16::from_f32(d);
+            ys.m = f16::from_f32(min);
+
+            for (j, q) in ys.qs.iter_mut().enumerate() {
+                let x0 = (xs[j] - min) * id;
+                let x1 = (xs[qk / 2 + j] - min) * id;
+                let xi0 = u8::min(15, (x0 + 0.5) as u8);
+                let xi1 = u8::min(15, (x1 + 0.5) as u8);
+                *q = xi0 | (xi1 << 4)
+            }
+        }
+        Ok(())
     }
 
     fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
@@ -188,12 +251,61 @@ impl GgmlType for BlockQ5_0 {
     const BLCK_SIZE: usize = QK5_0;
     type VecDotType = BlockQ8_0;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L2938
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        let qk = QK5_0;
+        if n % qk != 0 {
+            crate::bail!("vec_dot_q5_0_q8_0: {n} is not divisible by {qk}")
+        }
+        let nb = n / qk;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let qh: u32 = unsafe { std::mem::transmute_copy(&x.qh) };
+            let mut sum_i = 0i32;
+            for j in 0..qk / 2 {
+                let xh_0 = (((qh >> j) << 4) & 0x10) as u8;
+                let xh_1 = ((qh >> (j + 12)) & 0x10) as u8;
+                let v0 = ((x.qs[j] & 0x0F) | xh_0) as i32 - 16;
+                let v1 = ((x.qs[j] >> 4) | xh_1) as i32 - 16;
+                sum_i += v0 * y.qs[j] as i32 + v1 * y.qs[j + qk / 2] as i32
+            }
+            sumf += sum_i as f32 * f16::to_f32(x.d) * f16::to_f32(y.d)
+        }
+        Ok(sumf)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L1256
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        // quantize_row_q5_0
+        let qk = Self::BLCK_SIZE;
+        if xs.len() != ys.len() * qk {
+            crate::bail!("size mismatch {} {} {}", xs.len(), ys.len(), qk)
+        }
+        for (ys, xs) in ys.iter_mut().zip(xs.chunks_exact(qk)) {
+            let mut amax = 0f32;
+            let mut max = 0f32;
+            for &x in xs.iter() {
+                if amax < x.abs() {
+                    amax = x.abs();
+                    max = x;
+                }
+            }
+            let d = max / -16.;
+            let id = if d != 0f32 { 1. / d } else { 0. };
+            ys.d = f16::from_f32(d);
+            let mut qh = 0u32;
+            for j in 0..qk / 2 {
+                let x0 = xs[j] * id;
+                let x1 = xs[j + qk / 2] * id;
+                let xi0 = u8::min(31, (x0 + 16.5) as u8);
+                let xi1 = u8::min(31, (x1 + 16.5) as u8);
+                ys.qs[j] = (xi0 & 0x0F) | ((xi1 & 0x0F) << 4);
+                qh |= ((xi0 as u32 & 0x10) >> 4) << j;
+                qh |= ((xi1 as u32 & 0x10) >> 4) << (j + qk / 2);
+            }
+            ys.qh = qh.to_le_bytes();
+        }
+        Ok(())
     }
 
     // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L1566
@@ -228,12 +340,57 @@ impl GgmlType for BlockQ5_1 {
     const BLCK_SIZE: usize = QK5_1;
     type VecDotType = BlockQ8_1;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L3246
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        let qk = QK5_1;
+        if n % qk != 0 {
+            crate::bail!("vec_dot_q5_1_q8_1: {n} is not divisible by {qk}")
+        }
+        let nb = n / qk;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let qh: u32 = unsafe { std::mem::transmute_copy(&x.qh) };
+            let mut sum_i = 0i32;
+            for j in 0..qk / 2 {
+                let xh_0 = (((qh >> j) << 4) & 0x10) as u8;
+                let xh_1 = ((qh >> (j + 12)) & 0x10) as u8;
+                let v0 = ((x.qs[j] & 0x0F) | xh_0) as i32;
+                let v1 = ((x.qs[j] >> 4) | xh_1) as i32;
+                sum_i += v0 * y.qs[j] as i32 + v1 * y.qs[j + qk / 2] as i32
+            }
+            sumf += sum_i as f32 * f16::to_f32(x.d) * f16::to_f32(y.d)
+                + f16::to_f32(x.m) * f16::to_f32(y.s)
+        }
+        Ok(sumf)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L1304
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        // quantize_row_q5_1
+        let qk = Self::BLCK_SIZE;
+        if xs.len() != ys.len() * qk {
+            crate::bail!("size mismatch {} {} {}", xs.len(), ys.len(), qk)
+        }
+        for (ys, xs) in ys.iter_mut().zip(xs.chunks_exact(qk)) {
+            let min = xs.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = xs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let d = (max - min) / ((1 << 5) - 1) as f32;
+            let id = if d != 0f32 { 1. / d } else { 0. };
+            ys.d = f16::from_f32(d);
+            ys.m = f16::from_f32(min);
+            let mut qh = 0u32;
+            for j in 0..qk / 2 {
+                let x0 = (xs[j] - min) * id;
+                let x1 = (xs[j + qk / 2] - min) * id;
+                let xi0 = u8::min(31, (x0 + 0.5) as u8);
+                let xi1 = u8::min(31, (x1 + 0.5) as u8);
+                ys.qs[j] = (xi0 & 0x0F) | ((xi1 & 0x0F) << 4);
+                qh |= ((xi0 as u32 & 0x10) >> 4) << j;
+                qh |= ((xi1 as u32 & 0x10) >> 4) << (j + qk / 2);
+            }
+            ys.qh = qh.to_le_bytes();
+        }
+        Ok(())
     }
 
     // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L1592
@@ -264,18 +421,98 @@ impl GgmlType for BlockQ5_1 {
     }
 }
 
+/// Scalar dot product for the k-quants that do not have a dedicated kernel: each block of `xs` is
+/// dequantized and multiplied with the matching `BlockQ8K` values.
+fn vec_dot_dequantized<T: GgmlType>(
+    name: &str,
+    n: usize,
+    xs: &[T],
+    ys: &[BlockQ8K],
+) -> Result<f32> {
+    if n % QK_K != 0 {
+        crate::bail!("{name}: {n} is not divisible by {QK_K}")
+    }
+    let nb = n / QK_K;
+    let mut block = vec![0f32; QK_K];
+    let mut sumf = 0f32;
+    for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+        T::to_float(std::slice::from_ref(x), &mut block)?;
+        let sum = block
+            .iter()
+            .zip(y.qs.iter())
+            .map(|(&x, &y)| x * y as f32)
+            .sum::<f32>();
+        sumf += sum * y.d
+    }
+    Ok(sumf)
+}
+
 impl GgmlType for BlockQ2K {
     const DTYPE: GgmlDType = GgmlDType::Q2K;
     const BLCK_SIZE: usize = QK_K;
     type VecDotType = BlockQ8K;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        vec_dot_dequantized("vec_dot_q2k_q8k", n, xs, ys)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L279
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        if xs.len() != ys.len() * QK_K {
+            crate::bail!("quantize_row_q2k: size mismatch {} {} {QK_K}", xs.len(), ys.len())
+        }
+        const Q4SCALE: f32 = 15.0;
+        for (y, x) in ys.iter_mut().zip(xs.chunks_exact(QK_K)) {
+            let mut ls = [0u8; QK_K];
+            let mut mins = [0f32; QK_K / 16];
+            let mut scales = [0f32; QK_K / 16];
+            for (j, x) in x.chunks_exact(16).enumerate() {
+                (scales[j], mins[j]) = make_qkx1_quants(3, 5, x, &mut ls[j * 16..(j + 1) * 16]);
+            }
+            let max_scale = scales.iter().copied().fold(0f32, f32::max);
+            let max_min = mins.iter().copied().fold(0f32, f32::max);
+
+            if max_scale > 0. {
+                let iscale = Q4SCALE / max_scale;
+                for (y_scale, &scale) in y.scales.iter_mut().zip(scales.iter()) {
+                    *y_scale = nearest_int(iscale * scale) as u8;
+                }
+                y.d = f16::from_f32(max_scale / Q4SCALE);
+            } else {
+                y.scales.fill(0);
+                y.d = f16::from_f32(0.);
+            }
+            if max_min > 0. {
+                let iscale = Q4SCALE / max_min;
+                for (y_scale, &min) in y.scales.iter_mut().zip(mins.iter()) {
+                    *y_scale |= (nearest_int(iscale * min) as u8) << 4;
+                }
+                y.dmin = f16::from_f32(max_min / Q4SCALE);
+            } else {
+                y.dmin = f16::from_f32(0.);
+            }
+            for (j, x) in x.chunks_exact(16).enumerate() {
+                let d = y.d.to_f32() * (y.scales[j] & 0xF) as f32;
+                if d == 0. {
+                    continue;
+                }
+                let dm = y.dmin.to_f32() * (y.scales[j] >> 4) as f32;
+                for (l, &x) in ls[j * 16..(j + 1) * 16].iter_mut().zip(x.iter()) {
+                    *l = nearest_int((x + dm) / d).clamp(0, 3) as u8;
+                }
+            }
+            for j in (0..QK_K).step_by(128) {
+                for l in 0..32 {
+                    y.qs[j / 4 + l] = ls[j + l]
+                        | (ls[j + l + 32] << 2)
+                        | (ls[j + l + 64] << 4)
+                        | (ls[j + l + 96] << 6);
+                }
+            }
+        }
+        Ok(())
     }
+
     // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L354
     fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
         let k = ys.len();
@@ -334,18 +571,126 @@ fn get_scale_min_k4(j: usize, q: &[u8]) -> (u8, u8) {
     }
 }
 
+// Shared by q4k and q5k: quantizes the 8 sub-blocks of 32 values on `[0, nmax]`, packs the 6-bit
+// scales and mins in `scales` and writes the quantized values in `ls`.
+fn quantize_scale_min_k4(
+    x: &[f32],
+    nmax: i32,
+    scales: &mut [u8; K_SCALE_SIZE],
+    ls: &mut [u8; QK_K],
+) -> (f16, f16) {
+    let mut sub_scales = [0f32; QK_K / 32];
+    let mut sub_mins = [0f32; QK_K / 32];
+    for (j, x) in x.chunks_exact(32).enumerate() {
+        (sub_scales[j], sub_mins[j]) =
+            make_qkx1_quants(nmax, 5, x, &mut ls[j * 32..(j + 1) * 32]);
+    }
+    let max_scale = sub_scales.iter().copied().fold(0f32, f32::max);
+    let max_min = sub_mins.iter().copied().fold(0f32, f32::max);
+    let inv_scale = if max_scale > 0. { 63. / max_scale } else { 0. };
+    let inv_min = if max_min > 0. { 63. / max_min } else { 0. };
+    scales.fill(0);
+    for j in 0..QK_K / 32 {
+        let l_scale = nearest_int(inv_scale * sub_scales[j]).min(63) as u8;
+        let l_min = nearest_int(inv_min * sub_mins[j]).min(63) as u8;
+        if j < 4 {
+            scales[j] = l_scale;
+            scales[j + 4] = l_min;
+        } else {
+            scales[j + 4] = (l_scale & 0xF) | ((l_min & 0xF) << 4);
+            scales[j - 4] |= (l_scale >> 4) << 6;
+            scales[j] |= (l_min >> 4) << 6;
+        }
+    }
+    let d = f16::from_f32(max_scale / 63.);
+    let dmin = f16::from_f32(max_min / 63.);
+    for (j, x) in x.chunks_exact(32).enumerate() {
+        let (sc, m) = get_scale_min_k4(j, &scales[..]);
+        let d = d.to_f32() * sc as f32;
+        if d == 0. {
+            continue;
+        }
+        let dm = dmin.to_f32() * m as f32;
+        for (l, &x) in ls[j * 32..(j + 1) * 32].iter_mut().zip(x.iter()) {
+            *l = nearest_int((x + dm) / d).clamp(0, nmax) as u8;
+        }
+    }
+    (d, dmin)
+}
+
+// The 8 6-bit scales and mins of a q4k/q5k block.
+pub(crate) fn q4k_scales_mins(scales: &[u8; K_SCALE_SIZE]) -> ([u8; 8], [u8; 8]) {
+    let mut sc = [0u8; 8];
+    let mut mins = [0u8; 8];
+    for j in 0..8 {
+        (sc[j], mins[j]) = get_scale_min_k4(j, &scales[..]);
+    }
+    (sc, mins)
+}
+
 impl GgmlType for BlockQ4K {
     const DTYPE: GgmlDType = GgmlDType::Q4K;
     const BLCK_SIZE: usize = QK_K;
     type VecDotType = BlockQ8K;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    #[allow(unreachable_code)]
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        #[cfg(target_feature = "avx2")]
+        return crate::cpu::k_quants_avx::vec_dot_q4k_q8k(n, xs, ys);
+
+        #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+        return crate::cpu::k_quants_neon::vec_dot_q4k_q8k(n, xs, ys);
+
+        Self::vec_dot_unopt(n, xs, ys)
+    }
+
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L1971
+    fn vec_dot_unopt(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        if n % QK_K != 0 {
+            crate::bail!("vec_dot_q4k_q8k: {n} is not divisible by {QK_K}")
+        }
+        let nb = n / QK_K;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let (scales, mins) = q4k_scales_mins(&x.scales);
+            let mut sumi = 0i32;
+            let mut sum_mins = 0i32;
+            for j in 0..QK_K / 32 {
+                let q4 = &x.qs[(j / 2) * 32..(j / 2) * 32 + 32];
+                let q8 = &y.qs[j * 32..(j + 1) * 32];
+                let shift = if j % 2 == 0 { 0 } else { 4 };
+                let dot = q4
+                    .iter()
+                    .zip(q8.iter())
+                    .map(|(&q4, &q8)| ((q4 >> shift) & 0xF) as i32 * q8 as i32)
+                    .sum::<i32>();
+                sumi += scales[j] as i32 * dot;
+                sum_mins += mins[j] as i32 * (y.bsums[2 * j] as i32 + y.bsums[2 * j + 1] as i32);
+            }
+            sumf += y.d * (x.d.to_f32() * sumi as f32 - x.dmin.to_f32() * sum_mins as f32)
+        }
+        Ok(sumf)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L652
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        if xs.len() != ys.len() * QK_K {
+            crate::bail!("quantize_row_q4k: size mismatch {} {} {QK_K}", xs.len(), ys.len())
+        }
+        for (y, x) in ys.iter_mut().zip(xs.chunks_exact(QK_K)) {
+            let mut ls = [0u8; QK_K];
+            let (d, dmin) = quantize_scale_min_k4(x, 15, &mut y.scales, &mut ls);
+            y.d = d;
+            y.dmin = dmin;
+            for j in (0..QK_K).step_by(64) {
+                for l in 0..32 {
+                    y.qs[j / 2 + l] = ls[j + l] | (ls[j + l + 32] << 4);
+                }
+            }
+        }
+        Ok(())
     }
+
     // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L735
     fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
         let k = ys.len();
@@ -388,18 +733,132 @@ impl GgmlType for BlockQ3K {
     const BLCK_SIZE: usize = QK_K;
     type VecDotType = BlockQ8K;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        vec_dot_dequantized("vec_dot_q3k_q8k", n, xs, ys)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L454
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        if xs.len() != ys.len() * QK_K {
+            crate::bail!("quantize_row_q3k: size mismatch {} {} {QK_K}", xs.len(), ys.len())
+        }
+        for (y, x) in ys.iter_mut().zip(xs.chunks_exact(QK_K)) {
+            let mut ls = [0i8; QK_K];
+            let mut scales = [0f32; QK_K / 16];
+            let mut max_scale = 0f32;
+            let mut amax = 0f32;
+            for (j, x) in x.chunks_exact(16).enumerate() {
+                scales[j] = make_q3_quants(4, x, &mut ls[j * 16..(j + 1) * 16]);
+                if scales[j].abs() > amax {
+                    amax = scales[j].abs();
+                    max_scale = scales[j];
+                }
+            }
+
+            y.scales.fill(0);
+            if max_scale != 0. {
+                let iscale = -32. / max_scale;
+                for (j, &scale) in scales.iter().enumerate() {
+                    let l = (nearest_int(iscale * scale).clamp(-32, 31) + 32) as u8;
+                    if j < 8 {
+                        y.scales[j] = l & 0xF;
+                    } else {
+                        y.scales[j - 8] |= (l & 0xF) << 4;
+                    }
+                    y.scales[j % 4 + 8] |= (l >> 4) << (2 * (j / 4));
+                }
+                y.d = f16::from_f32(1. / iscale);
+            } else {
+                y.d = f16::from_f32(0.);
+            }
+
+            let q3_scales = unpack_q3k_scales(&y.scales);
+            for (j, x) in x.chunks_exact(16).enumerate() {
+                let d = y.d.to_f32() * q3_scales[j] as f32;
+                if d == 0. {
+                    continue;
+                }
+                for (l, &x) in ls[j * 16..(j + 1) * 16].iter_mut().zip(x.iter()) {
+                    *l = (nearest_int(x / d).clamp(-4, 3) + 4) as i8;
+                }
+            }
+
+            y.hmask.fill(0);
+            let mut m = 0;
+            let mut hm = 1u8;
+            for l in ls.iter_mut() {
+                if *l > 3 {
+                    y.hmask[m] |= hm;
+                    *l -= 4;
+                }
+                m += 1;
+                if m == QK_K / 8 {
+                    m = 0;
+                    hm <<= 1;
+                }
+            }
+            let ls = ls.map(|l| l as u8);
+            for j in (0..QK_K).step_by(128) {
+                for l in 0..32 {
+                    y.qs[j / 4 + l] = ls[j + l]
+                        | (ls[j + l + 32] << 2)
+                        | (ls[j + l + 64] << 4)
+                        | (ls[j + l + 96] << 6);
+                }
+            }
+        }
+        Ok(())
     }
 
     // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L533
-    fn to_float(_xs: &[Self], _ys: &mut [f32]) -> Result<()> {
-        todo!()
+    fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
+        let k = ys.len();
+        if k % QK_K != 0 {
+            crate::bail!("dequantize_row_q3k: {k} is not divisible by {QK_K}")
+        }
+        let mut ys_index = 0;
+        for x in xs.iter() {
+            let d_all = x.d.to_f32();
+            let scales = unpack_q3k_scales(&x.scales);
+            let mut m = 1u8;
+            let mut is = 0;
+            for n in (0..QK_K).step_by(128) {
+                let q = &x.qs[n / 4..n / 4 + 32];
+                let mut shift = 0;
+                for _j in 0..4 {
+                    for half in [0..16, 16..32] {
+                        let dl = d_all * scales[is] as f32;
+                        is += 1;
+                        for l in half {
+                            let hbit = if x.hmask[l] & m != 0 { 0 } else { 4 };
+                            let q = ((q[l] >> shift) & 3) as i8 - hbit;
+                            ys[ys_index] = dl * q as f32;
+                            ys_index += 1;
+                        }
+                    }
+                    shift += 2;
+                    m <<= 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Unpacks the 16 6-bit scales of a q3k block, the low 4 bits are stored in the first 8 bytes and
+// the high 2 bits in the last 4 bytes.
+fn unpack_q3k_scales(scales: &[u8; 12]) -> [i8; QK_K / 16] {
+    let mut res = [0i8; QK_K / 16];
+    for (j, r) in res.iter_mut().enumerate() {
+        let low = if j < 8 {
+            scales[j] & 0xF
+        } else {
+            scales[j - 8] >> 4
+        };
+        let high = (scales[8 + j % 4] >> (2 * (j / 4))) & 3;
+        *r = (low | (high << 4)) as i8 - 32;
     }
+    res
 }
 
 // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L928
@@ -408,13 +867,44 @@ impl GgmlType for BlockQ5K {
     const BLCK_SIZE: usize = QK_K;
     type VecDotType = BlockQ8K;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        vec_dot_dequantized("vec_dot_q5k_q8k", n, xs, ys)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L838
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        if xs.len() != ys.len() * QK_K {
+            crate::bail!("quantize_row_q5k: size mismatch {} {} {QK_K}", xs.len(), ys.len())
+        }
+        for (y, x) in ys.iter_mut().zip(xs.chunks_exact(QK_K)) {
+            let mut ls = [0u8; QK_K];
+            let (d, dmin) = quantize_scale_min_k4(x, 31, &mut y.scales, &mut ls);
+            y.d = d;
+            y.dmin = dmin;
+            y.qh.fill(0);
+            let mut m1 = 1u8;
+            let mut m2 = 2u8;
+            for n in (0..QK_K).step_by(64) {
+                for j in 0..32 {
+                    let mut l1 = ls[n + j];
+                    if l1 > 15 {
+                        l1 -= 16;
+                        y.qh[j] |= m1;
+                    }
+                    let mut l2 = ls[n + j + 32];
+                    if l2 > 15 {
+                        l2 -= 16;
+                        y.qh[j] |= m2;
+                    }
+                    y.qs[n / 2 + j] = l1 | (l2 << 4);
+                }
+                m1 <<= 2;
+                m2 <<= 2;
+            }
+        }
+        Ok(())
     }
+
     fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
         let k = ys.len();
         if k % QK_K != 0 {
@@ -438,13 +928,13 @@ impl GgmlType for BlockQ5K {
                 let d2 = d * sc as f32;
                 let m2 = min * m as f32;
                 for (ql, qh) in ql.iter().zip(qh) {
-                    let to_add = if qh & u1 != 0 { 16 } else { 1 };
+                    let to_add = if qh & u1 != 0 { 16 } else { 0 };
                     let y = d1 * ((ql & 0xF) + to_add) as f32 - m1;
                     ys[ys_index] = y;
                     ys_index += 1;
                 }
                 for (ql, qh) in ql.iter().zip(qh) {
-                    let to_add = if qh & u2 != 0 { 16 } else { 1 };
+                    let to_add = if qh & u2 != 0 { 16 } else { 0 };
                     let y = d2 * ((ql >> 4) + to_add) as f32 - m2;
                     ys[ys_index] = y;
                     ys_index += 1;
@@ -463,12 +953,103 @@ impl GgmlType for BlockQ6K {
     const BLCK_SIZE: usize = QK_K;
     type VecDotType = BlockQ8K;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    #[allow(unreachable_code)]
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        #[cfg(target_feature = "avx2")]
+        return crate::cpu::k_quants_avx::vec_dot_q6k_q8k(n, xs, ys);
+
+        #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+        return crate::cpu::k_quants_neon::vec_dot_q6k_q8k(n, xs, ys);
+
+        Self::vec_dot_unopt(n, xs, ys)
+    }
+
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L3453
+    fn vec_dot_unopt(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        if n % QK_K != 0 {
+            crate::bail!("vec_dot_q6k_q8k: {n} is not divisible by {QK_K}")
+        }
+        let nb = n / QK_K;
+        let mut aux8 = [0i8; QK_K];
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            for j in (0..QK_K).step_by(128) {
+                let ql = &x.ql[j / 2..];
+                let qh = &x.qh[j / 4..];
+                let a = &mut aux8[j..];
+                for l in 0..32 {
+                    a[l] = ((ql[l] & 0xF) | ((qh[l] & 3) << 4)) as i8 - 32;
+                    a[l + 32] = ((ql[l + 32] & 0xF) | (((qh[l] >> 2) & 3) << 4)) as i8 - 32;
+                    a[l + 64] = ((ql[l] >> 4) | (((qh[l] >> 4) & 3) << 4)) as i8 - 32;
+                    a[l + 96] = ((ql[l + 32] >> 4) | (((qh[l] >> 6) & 3) << 4)) as i8 - 32;
+                }
+            }
+            let mut sumi = 0i32;
+            for (j, (a, q8)) in aux8.chunks_exact(16).zip(y.qs.chunks_exact(16)).enumerate() {
+                let dot = a
+                    .iter()
+                    .zip(q8.iter())
+                    .map(|(&a, &q8)| a as i32 * q8 as i32)
+                    .sum::<i32>();
+                sumi += x.scales[j] as i32 * dot;
+            }
+            sumf += x.d.to_f32() * y.d * sumi as f32
+        }
+        Ok(sumf)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L1002
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        if xs.len() != ys.len() * QK_K {
+            crate::bail!("quantize_row_q6k: size mismatch {} {} {QK_K}", xs.len(), ys.len())
+        }
+        for (y, x) in ys.iter_mut().zip(xs.chunks_exact(QK_K)) {
+            let mut ls = [0i8; QK_K];
+            let mut scales = [0f32; QK_K / 16];
+            let mut max_scale = 0f32;
+            let mut max_abs_scale = 0f32;
+            for (ib, x) in x.chunks_exact(16).enumerate() {
+                scales[ib] = make_qx_quants(32, x, &mut ls[ib * 16..(ib + 1) * 16]);
+                if scales[ib].abs() > max_abs_scale {
+                    max_abs_scale = scales[ib].abs();
+                    max_scale = scales[ib];
+                }
+            }
+            if max_abs_scale == 0. {
+                *y = Self::zeros();
+                continue;
+            }
+            let iscale = -128. / max_scale;
+            y.d = f16::from_f32(1. / iscale);
+            for (y_scale, &scale) in y.scales.iter_mut().zip(scales.iter()) {
+                *y_scale = nearest_int(iscale * scale).min(127) as i8;
+            }
+            for (j, x) in x.chunks_exact(16).enumerate() {
+                let d = y.d.to_f32() * y.scales[j] as f32;
+                if d == 0. {
+                    continue;
+                }
+                for (l, &x) in ls[j * 16..(j + 1) * 16].iter_mut().zip(x.iter()) {
+                    *l = (nearest_int(x / d).clamp(-32, 31) + 32) as i8;
+                }
+            }
+            let ls = ls.map(|l| l as u8);
+            for j in (0..QK_K).step_by(128) {
+                for l in 0..32 {
+                    let q1 = ls[j + l] & 0xF;
+                    let q2 = ls[j + l + 32] & 0xF;
+                    let q3 = ls[j + l + 64] & 0xF;
+                    let q4 = ls[j + l + 96] & 0xF;
+                    y.ql[j / 2 + l] = q1 | (q3 << 4);
+                    y.ql[j / 2 + l + 32] = q2 | (q4 << 4);
+                    y.qh[j / 4 + l] = (ls[j + l] >> 4)
+                        | ((ls[j + l + 32] >> 4) << 2)
+                        | ((ls[j + l + 64] >> 4) << 4)
+                        | ((ls[j + l + 96] >> 4) << 6);
+                }
+            }
+        }
+        Ok(())
     }
 
     // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L1067
@@ -477,14 +1058,14 @@ impl GgmlType for BlockQ6K {
         if k % QK_K != 0 {
             crate::bail!("dequantize_row_q6k: {k} is not divisible by {QK_K}")
         }
-        for x in xs.iter() {
+        for (idx_x, x) in xs.iter().enumerate() {
             let d = x.d.to_f32();
             let ql = &x.ql;
             let qh = &x.qh;
             let sc = &x.scales;
             for n in (0..QK_K).step_by(128) {
                 let idx = n / 128;
-                let ys = &mut ys[n..];
+                let ys = &mut ys[idx_x * QK_K + n..];
                 let sc = &sc[8 * idx..];
                 let ql = &ql[64 * idx..];
                 let qh = &qh[32 * idx..];
@@ -510,17 +1091,66 @@ impl GgmlType for BlockQ8K {
     const BLCK_SIZE: usize = QK_K;
     type VecDotType = BlockQ8K;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        if n % QK_K != 0 {
+            crate::bail!("vec_dot_q8k_q8k: {n} is not divisible by {QK_K}")
+        }
+        let nb = n / QK_K;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let sum_i = x
+                .qs
+                .iter()
+                .zip(y.qs.iter())
+                .map(|(&x, &y)| x as i32 * y as i32)
+                .sum::<i32>();
+            sumf += sum_i as f32 * x.d * y.d
+        }
+        Ok(sumf)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L1138
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        if xs.len() != ys.len() * QK_K {
+            crate::bail!("quantize_row_q8k: size mismatch {} {} {QK_K}", xs.len(), ys.len())
+        }
+        for (y, x) in ys.iter_mut().zip(xs.chunks_exact(QK_K)) {
+            let mut max = 0f32;
+            let mut amax = 0f32;
+            for &x in x.iter() {
+                if amax < x.abs() {
+                    amax = x.abs();
+                    max = x;
+                }
+            }
+            if amax == 0. {
+                *y = Self::zeros();
+                continue;
+            }
+            let iscale = -128. / max;
+            for (q, &x) in y.qs.iter_mut().zip(x.iter()) {
+                *q = nearest_int(iscale * x).min(127) as i8
+            }
+            for (bsum, qs) in y.bsums.iter_mut().zip(y.qs.chunks_exact(16)) {
+                *bsum = qs.iter().map(|&q| q as i16).sum();
+            }
+            y.d = 1. / iscale;
+        }
+        Ok(())
     }
 
-    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L533
-    fn to_float(_xs: &[Self], _ys: &mut [f32]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L1173
+    fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
+        let k = ys.len();
+        if k % QK_K != 0 {
+            crate::bail!("dequantize_row_q8k: {k} is not divisible by {QK_K}")
+        }
+        for (x, ys) in xs.iter().zip(ys.chunks_exact_mut(QK_K)) {
+            for (y, &q) in ys.iter_mut().zip(x.qs.iter()) {
+                *y = x.d * q as f32
+            }
+        }
+        Ok(())
     }
 }
 
@@ -588,8 +1218,19 @@ impl GgmlType for BlockQ4_0 {
         Ok(())
     }
 
-    // https://github.com/ggerganov/llama.cpp/blob/b5ffb2849d23afe73647f68eec7b68187af09be6/ggml.c#L2361C10-L2361C122
+    #[allow(unreachable_code)]
     fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        #[cfg(target_feature = "avx2")]
+        return crate::cpu::k_quants_avx::vec_dot_q4_0_q8_0(n, xs, ys);
+
+        #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+        return crate::cpu::k_quants_neon::vec_dot_q4_0_q8_0(n, xs, ys);
+
+        Self::vec_dot_unopt(n, xs, ys)
+    }
+
+    // https://github.com/ggerganov/llama.cpp/blob/b5ffb2849d23afe73647f68eec7b68187af09be6/ggml.c#L2361C10-L2361C122
+    fn vec_dot_unopt(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
         let qk = QK8_0;
         let nb = n / qk;
         if n % QK8_0 != 0 {
@@ -663,36 +1304,96 @@ impl GgmlType for BlockQ8_0 {
             let id = if d != 0f32 { 1. / d } else { 0. };
             ys.d = f16::from_f32(d);
             for (y, &x) in ys.qs.iter_mut().zip(xs.iter()) {
-                *y = f32::round(x * id) as u8
+                *y = f32::round(x * id) as i8
             }
         }
         Ok(())
     }
 
-    fn vec_dot(_: usize, _: &[Self], _: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        let qk = QK8_0;
+        if n % qk != 0 {
+            crate::bail!("vec_dot_q8_0_q8_0: {n} is not divisible by {qk}")
+        }
+        let nb = n / qk;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let sum_i = x
+                .qs
+                .iter()
+                .zip(y.qs.iter())
+                .map(|(&x, &y)| x as i32 * y as i32)
+                .sum::<i32>();
+            sumf += sum_i as f32 * f16::to_f32(x.d) * f16::to_f32(y.d)
+        }
+        Ok(sumf)
     }
 }
 
 impl GgmlType for BlockQ8_1 {
-    const DTYPE: GgmlDType = GgmlDType::Q3K;
-    const BLCK_SIZE: usize = QK_K;
+    const DTYPE: GgmlDType = GgmlDType::Q8_1;
+    const BLCK_SIZE: usize = QK8_1;
     type VecDotType = BlockQ8_1;
 
-    fn vec_dot(_n: usize, _xs: &[Self], _ys: &[Self::VecDotType]) -> Result<f32> {
-        todo!()
+    fn vec_dot(n: usize, xs: &[Self], ys: &[Self::VecDotType]) -> Result<f32> {
+        let qk = QK8_1;
+        if n % qk != 0 {
+            crate::bail!("vec_dot_q8_1_q8_1: {n} is not divisible by {qk}")
+        }
+        let nb = n / qk;
+        let mut sumf = 0f32;
+        for (x, y) in xs[..nb].iter().zip(ys[..nb].iter()) {
+            let sum_i = x
+                .qs
+                .iter()
+                .zip(y.qs.iter())
+                .map(|(&x, &y)| x as i32 * y as i32)
+                .sum::<i32>();
+            sumf += sum_i as f32 * f16::to_f32(x.d) * f16::to_f32(y.d)
+        }
+        Ok(sumf)
     }
 
-    fn from_float(_xs: &[f32], _ys: &mut [Self]) -> Result<()> {
-        todo!()
+    // https://github.com/ggerganov/llama.cpp/blob/468ea24fb4633a0d681f7ac84089566c1c6190cb/ggml.c#L1432
+    fn from_float(xs: &[f32], ys: &mut [Self]) -> Result<()> {
+        // quantize_row_q8_1
+        let qk = Self::BLCK_SIZE;
+        if xs.len() != ys.len() * qk {
+            crate::bail!("size mismatch {} {} {}", xs.len(), ys.len(), qk)
+        }
+        for (ys, xs) in ys.iter_mut().zip(xs.chunks_exact(qk)) {
+            let amax = xs.iter().fold(0f32, |acc, x| acc.max(x.abs()));
+            let d = amax / ((1 << 7) - 1) as f32;
+            let id = if d != 0f32 { 1. / d } else { 0. };
+            ys.d = f16::from_f32(d);
+            let mut sum = 0i32;
+            for (q, &x) in ys.qs.iter_mut().zip(xs.iter()) {
+                *q = f32::round(x * id) as i8;
+                sum += *q as i32;
+            }
+            ys.s = f16::from_f32(sum as f32 * d);
+        }
+        Ok(())
     }
 
-    // https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L533
-    fn to_float(_xs: &[Self], _ys: &mut [f32]) -> Result<()> {
-        todo!()
+    fn to_float(xs: &[Self], ys: &mut [f32]) -> Result<()> {
+        let k = ys.len();
+        if k % QK8_1 != 0 {
+            crate::bail!("dequantize_row_q8_1: {k} is not divisible by {QK8_1}");
+        }
+        for (x, ys) in xs.iter().zip(ys.chunks_exact_mut(QK8_1)) {
+            let d = x.d.to_f32();
+            for (y, &q) in ys.iter_mut().zip(x.qs.iter()) {
+                *y = q as f32 * d
+            }
+        }
+        Ok(())
     }
 }
 
+// The number of output columns computed by each task in `matmul`.
+const MATMUL_COL_BLOCK: usize = 64;
+
 // https://github.com/ggerganov/llama.cpp/blob/b5ffb2849d23afe73647f68eec7b68187af09be6/ggml.c#L10605
 pub fn matmul<T: GgmlType>(
     mkn: (usize, usize, usize),
@@ -704,25 +1405,86 @@ pub fn matmul<T: GgmlType>(
     if m * k != lhs.len() {
         crate::bail!("unexpected lhs length {} {mkn:?}", lhs.len());
     }
+    if m * n != dst.len() {
+        crate::bail!("unexpected dst length {} {mkn:?}", dst.len());
+    }
 
-    let k_in_lhs_blocks = (k + T::BLCK_SIZE - 1) / T::BLCK_SIZE;
-    let k_in_rhs_blocks = (k + T::VecDotType::BLCK_SIZE - 1) / T::VecDotType::BLCK_SIZE;
+    let k_in_lhs_blocks = (k + T::VecDotType::BLCK_SIZE - 1) / T::VecDotType::BLCK_SIZE;
+    let k_in_rhs_blocks = (k + T::BLCK_SIZE - 1) / T::BLCK_SIZE;
+    if n * k_in_rhs_blocks > rhs_t.len() {
+        crate::bail!("unexpected rhs length {} {mkn:?}", rhs_t.len());
+    }
+    if dst.is_empty() || k == 0 {
+        dst.fill(0.);
+        return Ok(());
+    }
+    // The lhs is quantized once, each of its rows then gets reused for all the output columns.
     // TODO: Do not make this copy if the DotType is f32.
-    // TODO: Pre-allocate this.
     let mut lhs_b = vec![T::VecDotType::zeros(); m * k_in_lhs_blocks];
-    for row_idx in 0..m {
-        let lhs_b = &mut lhs_b[row_idx * k_in_lhs_blocks..(row_idx + 1) * k_in_lhs_blocks];
-        let lhs = &lhs[row_idx * k..(row_idx + 1) * k];
-        T::VecDotType::from_float(lhs, lhs_b)?
-    }
+    lhs_b
+        .par_chunks_mut(k_in_lhs_blocks)
+        .zip(lhs.par_chunks(k))
+        .try_for_each(|(lhs_b, lhs)| T::VecDotType::from_float(lhs, lhs_b))?;
     let lhs_b = lhs_b.as_slice();
 
-    for row_idx in 0..m {
-        let lhs_row = &lhs_b[row_idx * k_in_lhs_blocks..(row_idx + 1) * k_in_lhs_blocks];
-        let dst_row = &mut dst[row_idx * n..(row_idx + 1) * n];
-        for (col_idx, dst) in dst_row.iter_mut().enumerate() {
-            let rhs_col = &rhs_t[col_idx * k_in_rhs_blocks..(col_idx + 1) * k_in_rhs_blocks];
-            *dst = T::vec_dot(k, rhs_col, lhs_row)?;
+    // Each task handles a block of columns within an output row rather than the whole row, so
+    // that all the cores are used when processing a single token (m = 1).
+    dst.par_chunks_mut(n)
+        .zip(lhs_b.par_chunks(k_in_lhs_blocks))
+        .try_for_each(|(dst_row, lhs_row)| {
+            dst_row
+                .par_chunks_mut(MATMUL_COL_BLOCK)
+                .enumerate()
+                .try_for_each(|(block_idx, dst)| {
+                    for (i, dst) in dst.iter_mut().enumerate() {
+                        let col_idx = block_idx * MATMUL_COL_BLOCK + i;
+                        let rhs_col =
+                            &rhs_t[col_idx * k_in_rhs_blocks..(col_idx + 1) * k_in_rhs_blocks];
+                        *dst = T::vec_dot(k, rhs_col, lhs_row)?;
+                    }
+                    Ok::<(), Error>(())
+                })
+        })
+}
+
+// The gradient of `matmul` with respect to its lhs: computes `dst = grad @ rhs` where `grad` has
+// shape `(m, n)` and `rhs_t` holds the `n` quantized rows of `k` values. The rows are dequantized
+// one at a time so that the dense weight matrix never gets materialized.
+pub fn matmul_grad<T: GgmlType>(
+    mkn: (usize, usize, usize),
+    grad: &[f32],
+    rhs_t: &[T],
+    dst: &mut [f32],
+) -> Result<()> {
+    let (m, k, n) = mkn;
+    if m * n != grad.len() {
+        crate::bail!("unexpected grad length {} {mkn:?}", grad.len());
+    }
+    if m * k != dst.len() {
+        crate::bail!("unexpected dst length {} {mkn:?}", dst.len());
+    }
+    if k % T::BLCK_SIZE != 0 {
+        crate::bail!("{k} is not divisible by the block size {}", T::BLCK_SIZE);
+    }
+    let k_in_rhs_blocks = k / T::BLCK_SIZE;
+    if n * k_in_rhs_blocks > rhs_t.len() {
+        crate::bail!("unexpected rhs length {} {mkn:?}", rhs_t.len());
+    }
+    dst.fill(0.);
+    if dst.is_empty() || n == 0 {
+        return Ok(());
+    }
+    let mut rhs_row = vec![0f32; k];
+    for (row_idx, rhs_blocks) in rhs_t.chunks_exact(k_in_rhs_blocks).take(n).enumerate() {
+        T::to_float(rhs_blocks, &mut rhs_row)?;
+        for (dst_row, grad_row) in dst.chunks_exact_mut(k).zip(grad.chunks_exact(n)) {
+            let g = grad_row[row_idx];
+            if g == 0. {
+                continue;
+            }
+            for (d, &r) in dst_row.iter_mut().zip(rhs_row.iter()) {
+                *d += g * r
+            }
         }
     }
     Ok(())
@@ -7,6 +7,8 @@ use crate::error::{Result,Error};
 
 pub mod k_quants;
 pub mod ggml_file;
+pub mod gguf_file;
+mod utils;
 
 pub use k_quants::GgmlType;
 
@@ -56,6 +58,25 @@ impl GgmlDType {
         Ok(dtype)
     }
 
+    pub(crate) fn to_u32(self) -> u32 {
+        match self {
+            Self::F32 => 0,
+            Self::F16 => 1,
+            Self::Q4_0 => 2,
+            Self::Q4_1 => 3,
+            Self::Q5_0 => 6,
+            Self::Q5_1 => 7,
+            Self::Q8_0 => 8,
+            Self::Q8_1 => 9,
+            Self::Q2K => 10,
+            Self::Q3K => 11,
+            Self::Q4K => 12,
+            Self::Q5K => 13,
+            Self::Q6K => 14,
+            Self::Q8K => 15,
+        }
+    }
+
     fn type_size(&self) -> usize {
         use k_quants::*;
         match self {
@@ -95,7 +116,10 @@ impl GgmlDType {
 pub trait QuantizedType: Send + Sync {
     fn dtype(&self) -> GgmlDType;
     fn matmul_t(&self, mkn: (usize, usize, usize), lhs: &[f32], dst: &mut [f32]) -> Result<()>;
+    fn matmul_grad(&self, mkn: (usize, usize, usize), grad: &[f32], dst: &mut [f32]) -> Result<()>;
     fn to_float(&self, ys: &mut [f32]) -> Result<()>;
+    fn storage_size_in_bytes(&self) -> usize;
+    fn as_ptr(&self) -> *const u8;
 }
 
 impl<T: k_quants::GgmlType + Send + Sync> QuantizedType for Vec<T> {
@@ -103,6 +127,55 @@ impl<T: k_quants::GgmlType + Send + Sync> QuantizedType for Vec<T> {
         k_quants::matmul(mkn, lhs, self.as_slice(), dst)
     }
 
+    fn matmul_grad(&self, mkn: (usize, usize, usize), grad: &[f32], dst: &mut [f32]) -> Result<()> {
+        k_quants::matmul_grad(mkn, grad, self.as_slice(), dst)
+    }
+
+    fn dtype(&self) -> GgmlDType {
+        T::DTYPE
+    }
+
+    fn to_float(&self, ys: &mut [f32]) -> Result<()> {
+        T::to_float(self.as_slice(), ys)
+    }
+
+    fn storage_size_in_bytes(&self) -> usize {
+        self.len() * std::mem::size_of::<T>()
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.as_slice().as_ptr() as *const u8
+    }
+}
+
+/// Quantized blocks living directly in a memory mapped file, no copy of the data is made and
+/// the pages are only loaded when they get accessed.
+struct MmapedBlocks<T> {
+    mmap: std::sync::Arc<memmap2::Mmap>,
+    offset: usize,
+    n_blocks: usize,
+    phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> MmapedBlocks<T> {
+    fn as_slice(&self) -> &[T] {
+        // The bounds and the alignment have been checked in `QTensor::from_mmap`.
+        unsafe {
+            let ptr = self.mmap.as_ptr().add(self.offset) as *const T;
+            std::slice::from_raw_parts(ptr, self.n_blocks)
+        }
+    }
+}
+
+impl<T: k_quants::GgmlType + Send + Sync> QuantizedType for MmapedBlocks<T> {
+    fn matmul_t(&self, mkn: (usize, usize, usize), lhs: &[f32], dst: &mut [f32]) -> Result<()> {
+        k_quants::matmul(mkn, lhs, self.as_slice(), dst)
+    }
+
+    fn matmul_grad(&self, mkn: (usize, usize, usize), grad: &[f32], dst: &mut [f32]) -> Result<()> {
+        k_quants::matmul_grad(mkn, grad, self.as_slice(), dst)
+    }
+
     fn dtype(&self) -> GgmlDType {
         T::DTYPE
     }
@@ -110,6 +183,14 @@ impl<T: k_quants::GgmlType + Send + Sync> QuantizedType for Vec<T> {
     fn to_float(&self, ys: &mut [f32]) -> Result<()> {
         T::to_float(self.as_slice(), ys)
     }
+
+    fn storage_size_in_bytes(&self) -> usize {
+        self.n_blocks * std::mem::size_of::<T>()
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.as_slice().as_ptr() as *const u8
+    }
 }
 
 impl std::fmt::Debug for QTensor {
@@ -129,6 +210,79 @@ impl QTensor {
         }
     }
 
+    /// Quantizes a tensor to the given ggml dtype, the tensor is converted to f32 first and its
+    /// last dimension has to be a multiple of the dtype block size.
+    pub fn quantize(src: &Tensor, dtype: GgmlDType) -> Result<Self> {
+        use k_quants::*;
+        match dtype {
+            GgmlDType::F32 => Self::quantize_as::<f32>(src),
+            GgmlDType::F16 => Self::quantize_as::<half::f16>(src),
+            GgmlDType::Q4_0 => Self::quantize_as::<BlockQ4_0>(src),
+            GgmlDType::Q4_1 => Self::quantize_as::<BlockQ4_1>(src),
+            GgmlDType::Q5_0 => Self::quantize_as::<BlockQ5_0>(src),
+            GgmlDType::Q5_1 => Self::quantize_as::<BlockQ5_1>(src),
+            GgmlDType::Q8_0 => Self::quantize_as::<BlockQ8_0>(src),
+            GgmlDType::Q8_1 => Self::quantize_as::<BlockQ8_1>(src),
+            GgmlDType::Q2K => Self::quantize_as::<BlockQ2K>(src),
+            GgmlDType::Q3K => Self::quantize_as::<BlockQ3K>(src),
+            GgmlDType::Q4K => Self::quantize_as::<BlockQ4K>(src),
+            GgmlDType::Q5K => Self::quantize_as::<BlockQ5K>(src),
+            GgmlDType::Q6K => Self::quantize_as::<BlockQ6K>(src),
+            GgmlDType::Q8K => Self::quantize_as::<BlockQ8K>(src),
+        }
+    }
+
+    fn quantize_as<T: k_quants::GgmlType + Send + Sync + 'static>(src: &Tensor) -> Result<Self> {
+        let shape = src.shape();
+        let last_dim = match shape.dims().last() {
+            Some(&last_dim) => last_dim,
+            None => crate::bail!("cannot quantize a scalar tensor to {:?}", T::DTYPE),
+        };
+        if last_dim % T::BLCK_SIZE != 0 {
+            crate::bail!(
+                "quantize {:?}: last dim {last_dim} of {shape:?} is not divisible by the block size {}",
+                T::DTYPE,
+                T::BLCK_SIZE
+            )
+        }
+        let src = src
+            .to_dtype(crate::DType::F32)?
+            .flatten_all()?
+            .to_vec1::<f32>()?;
+        Ok(Self::new(k_quants::quantize::<T>(&src)?, shape.clone()))
+    }
+
+    /// Creates a quantized tensor backed by `n_blocks` blocks of type `T` starting at `offset`
+    /// in the memory mapped file.
+    pub(crate) fn from_mmap<S: Into<Shape>, T: k_quants::GgmlType + Send + Sync + 'static>(
+        mmap: std::sync::Arc<memmap2::Mmap>,
+        offset: usize,
+        n_blocks: usize,
+        shape: S,
+    ) -> Result<Self> {
+        let size_in_bytes = n_blocks * std::mem::size_of::<T>();
+        if offset + size_in_bytes > mmap.len() {
+            crate::bail!(
+                "tensor data {offset}..{} is out of the mapped file bounds {}",
+                offset + size_in_bytes,
+                mmap.len()
+            )
+        }
+        if (mmap.as_ptr() as usize + offset) % std::mem::align_of::<T>() != 0 {
+            crate::bail!("tensor data at offset {offset} is not aligned for {:?}", T::DTYPE)
+        }
+        let data = MmapedBlocks::<T> {
+            mmap,
+            offset,
+            n_blocks,
+            phantom: std::marker::PhantomData,
+        };
+        Ok(Self {
+            data: Box::new(data),
+            shape: shape.into(),
+        })
+    }
+
     pub fn dtype(&self) -> GgmlDType {
         self.data.dtype()
     }
@@ -146,6 +300,25 @@ impl QTensor {
     pub fn matmul_t(&self, mkn: (usize, usize, usize), lhs: &[f32], dst: &mut [f32]) -> Result<()> {
         self.data.matmul_t(mkn, lhs, dst)
     }
+
+    pub fn matmul_grad(
+        &self,
+        mkn: (usize, usize, usize),
+        grad: &[f32],
+        dst: &mut [f32],
+    ) -> Result<()> {
+        self.data.matmul_grad(mkn, grad, dst)
+    }
+
+    pub fn storage_size_in_bytes(&self) -> usize {
+        self.data.storage_size_in_bytes()
+    }
+
+    /// The raw quantized blocks, in the same layout as in ggml/gguf files.
+    pub(crate) fn data(&self) -> &[u8] {
+        let size_in_bytes = self.storage_size_in_bytes();
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr(), size_in_bytes) }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -155,6 +328,11 @@ impl QMatMul {
     pub fn new(qtensor: std::sync::Arc<QTensor>) -> Self {
         Self(qtensor)
     }
+
+    /// Computes `xs @ w.t()` where `w` is the quantized `(n, k)` weight matrix.
+    pub fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        xs.custom_op1(self.clone())
+    }
 }
 
 impl crate::CustomOp1 for QMatMul {
@@ -171,7 +349,8 @@ impl crate::CustomOp1 for QMatMul {
             crate::bail!("input tensor is not contiguous {layout:?}")
         }
         let src_shape = layout.shape();
-        let (k, n) = self.0.shape.dims2()?;
+        // The weights are stored as `n` rows of `k` values, i.e. already transposed.
+        let (n, k) = self.0.shape.dims2()?;
         if src_shape.rank() < 2 {
             crate::bail!("input tensor has only one dimension {layout:?}")
         }
@@ -196,5 +375,312 @@ impl crate::CustomOp1 for QMatMul {
         )?;
         Ok((crate::CpuStorage::F32(dst_storage), dst_shape))
     }
+
+    fn bwd(&self, _arg: &Tensor, _res: &Tensor, grad_res: &Tensor) -> Result<Option<Tensor>> {
+        let grad_arg = grad_res
+            .contiguous()?
+            .custom_op1(QMatMulGrad(self.0.clone()))?;
+        Ok(Some(grad_arg))
+    }
+}
+
+// The gradient of `QMatMul` with respect to its input, i.e. `grad_res @ w`.
+struct QMatMulGrad(std::sync::Arc<QTensor>);
+
+impl crate::CustomOp1 for QMatMulGrad {
+    fn name(&self) -> &'static str {
+        "qmatmul-grad"
+    }
+
+    fn cpu_fwd(
+        &self,
+        storage: &crate::CpuStorage,
+        layout: &crate::Layout,
+    ) -> Result<(crate::CpuStorage, Shape)> {
+        if !layout.is_contiguous() {
+            crate::bail!("grad tensor is not contiguous {layout:?}")
+        }
+        let src_shape = layout.shape();
+        let (n, k) = self.0.shape.dims2()?;
+        let mut dst_shape = src_shape.dims().to_vec();
+        match dst_shape.pop() {
+            Some(last_n) if last_n == n => dst_shape.push(k),
+            _ => crate::bail!(
+                "grad tensor {layout:?} incompatible with {:?}",
+                self.0.shape
+            ),
+        }
+        let dst_shape = Shape::from(dst_shape);
+        let storage = storage.as_slice::<f32>()?;
+        let storage =
+            &storage[layout.start_offset()..layout.start_offset() + src_shape.elem_count()];
+        let mut dst_storage = vec![0f32; dst_shape.elem_count()];
+        self.0.matmul_grad(
+            (dst_shape.elem_count() / k, k, n),
+            storage,
+            &mut dst_storage,
+        )?;
+        Ok((crate::CpuStorage::F32(dst_storage), dst_shape))
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Quantizes a smooth-ish signal in [-2, 2] and checks the root mean square error of the
+    // round trip against a per-format bound.
+    fn round_trip_rmse(dtype: GgmlDType) -> f32 {
+        let n = 4 * k_quants::QK_K;
+        let xs: Vec<f32> = (0..n)
+            .map(|i| (i as f32 * 0.37).sin() * (1. + (i % 7) as f32 / 7.))
+            .collect();
+        let src = Tensor::from_vec(xs.clone(), (4, k_quants::QK_K), &Device::Cpu).unwrap();
+        let qtensor = QTensor::quantize(&src, dtype).unwrap();
+        assert_eq!(qtensor.dtype(), dtype);
+        assert_eq!(qtensor.shape().dims(), &[4, k_quants::QK_K]);
+        let ys = qtensor
+            .dequantize(&Device::Cpu)
+            .unwrap()
+            .flatten_all()
+            .unwrap()
+            .to_vec1::<f32>()
+            .unwrap();
+        let sum_sq: f32 = xs.iter().zip(ys.iter()).map(|(x, y)| (x - y) * (x - y)).sum();
+        (sum_sq / n as f32).sqrt()
+    }
+
+    #[test]
+    fn quantize_round_trip() {
+        for (dtype, bound) in [
+            (GgmlDType::F32, 0.),
+            (GgmlDType::F16, 1e-3),
+            (GgmlDType::Q4_0, 0.12),
+            (GgmlDType::Q4_1, 0.12),
+            (GgmlDType::Q5_0, 0.06),
+            (GgmlDType::Q5_1, 0.06),
+            (GgmlDType::Q8_0, 0.01),
+            (GgmlDType::Q8_1, 0.01),
+            (GgmlDType::Q8K, 0.01),
+            (GgmlDType::Q2K, 0.5),
+            (GgmlDType::Q3K, 0.25),
+            (GgmlDType::Q4K, 0.12),
+            (GgmlDType::Q5K, 0.06),
+            (GgmlDType::Q6K, 0.03),
+        ] {
+            let rmse = round_trip_rmse(dtype);
+            assert!(rmse <= bound, "{dtype:?}: rmse {rmse} > {bound}")
+        }
+    }
+
+    #[test]
+    fn quantize_bad_shape() {
+        let src = Tensor::zeros((2, 48), crate::DType::F32, &Device::Cpu).unwrap();
+        assert!(QTensor::quantize(&src, GgmlDType::Q4_0).is_err());
+        assert!(QTensor::quantize(&src, GgmlDType::Q4K).is_err());
+    }
+
+    #[test]
+    fn qmatmul_q8() {
+        let (m, k, n) = (3, k_quants::QK_K, 4);
+        let xs: Vec<f32> = (0..m * k).map(|i| (i as f32 * 0.11).cos()).collect();
+        let ws: Vec<f32> = (0..n * k).map(|i| (i as f32 * 0.23).sin()).collect();
+        let xs = Tensor::from_vec(xs, (m, k), &Device::Cpu).unwrap();
+        let ws = Tensor::from_vec(ws, (n, k), &Device::Cpu).unwrap();
+        let expected = xs.matmul(&ws.t().unwrap()).unwrap().to_vec2::<f32>().unwrap();
+        for dtype in [GgmlDType::Q8_1, GgmlDType::Q8K] {
+            let qws = QTensor::quantize(&ws, dtype).unwrap();
+            let ys = QMatMul::new(std::sync::Arc::new(qws))
+                .forward(&xs)
+                .unwrap()
+                .to_vec2::<f32>()
+                .unwrap();
+            for (ys, expected) in ys.iter().zip(expected.iter()) {
+                for (y, e) in ys.iter().zip(expected.iter()) {
+                    assert!((y - e).abs() < 0.1, "{dtype:?}: {y} {e}")
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn qmatmul_all_dtypes() {
+        let (m, k, n) = (3, 2 * k_quants::QK_K, 4);
+        let xs: Vec<f32> = (0..m * k).map(|i| (i as f32 * 0.11).cos()).collect();
+        let ws: Vec<f32> = (0..n * k).map(|i| (i as f32 * 0.23).sin()).collect();
+        let xs = Tensor::from_vec(xs, (m, k), &Device::Cpu).unwrap();
+        let ws = Tensor::from_vec(ws, (n, k), &Device::Cpu).unwrap();
+        for dtype in [
+            GgmlDType::F32,
+            GgmlDType::F16,
+            GgmlDType::Q4_0,
+            GgmlDType::Q4_1,
+            GgmlDType::Q5_0,
+            GgmlDType::Q5_1,
+            GgmlDType::Q8_0,
+            GgmlDType::Q8_1,
+            GgmlDType::Q8K,
+            GgmlDType::Q2K,
+            GgmlDType::Q3K,
+            GgmlDType::Q4K,
+            GgmlDType::Q5K,
+            GgmlDType::Q6K,
+        ] {
+            let qws = QTensor::quantize(&ws, dtype).unwrap();
+            // Only the quantization of the lhs to the vec-dot type differs from a float matmul
+            // with the dequantized weights.
+            let expected = xs
+                .matmul(&qws.dequantize(&Device::Cpu).unwrap().t().unwrap())
+                .unwrap()
+                .to_vec2::<f32>()
+                .unwrap();
+            let ys = QMatMul::new(std::sync::Arc::new(qws))
+                .forward(&xs)
+                .unwrap()
+                .to_vec2::<f32>()
+                .unwrap();
+            for (ys, expected) in ys.iter().zip(expected.iter()) {
+                for (y, e) in ys.iter().zip(expected.iter()) {
+                    assert!((y - e).abs() < 0.1, "{dtype:?}: {y} {e}")
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matmul_shapes() {
+        // Several column blocks per row, the last one being partial.
+        let (m, k, n) = (2, 32, 150);
+        let lhs: Vec<f32> = (0..m * k).map(|i| (i as f32 * 0.3).sin()).collect();
+        let rhs_t: Vec<f32> = (0..n * k).map(|i| (i as f32 * 0.7).cos()).collect();
+        let mut dst = vec![0f32; m * n];
+        k_quants::matmul((m, k, n), &lhs, &rhs_t, &mut dst).unwrap();
+        for (dst_idx, &d) in dst.iter().enumerate() {
+            let (row, col) = (dst_idx / n, dst_idx % n);
+            let lhs_row = &lhs[row * k..(row + 1) * k];
+            let rhs_row = &rhs_t[col * k..(col + 1) * k];
+            let expected: f32 = lhs_row.iter().zip(rhs_row).map(|(l, r)| l * r).sum();
+            assert!((d - expected).abs() < 1e-4, "{dst_idx}: {d} {expected}")
+        }
+
+        // Empty dims produce an empty or zero filled output.
+        let mut dst = vec![1f32; 6];
+        k_quants::matmul::<f32>((2, 0, 3), &[], &[], &mut dst).unwrap();
+        assert_eq!(dst, [0.; 6]);
+        let rhs_t = vec![k_quants::BlockQ4_0::zeros(); 3];
+        k_quants::matmul((0, 32, 3), &[], &rhs_t, &mut []).unwrap();
+        k_quants::matmul::<f32>((2, 4, 0), &[0.; 8], &[], &mut []).unwrap();
+        k_quants::matmul_grad::<f32>((2, 0, 3), &[0.; 6], &[], &mut []).unwrap();
+        let mut dst = vec![1f32; 8];
+        k_quants::matmul_grad::<f32>((2, 4, 0), &[], &[], &mut dst).unwrap();
+        assert_eq!(dst, [0.; 8]);
+    }
+
+    #[test]
+    fn qmatmul_adapter_training() {
+        let (m, k, n, r) = (8, 64, 16, 4);
+        let dev = &Device::Cpu;
+        let values = |len: usize, freq: f32, scale: f32| -> Vec<f32> {
+            (0..len).map(|i| (i as f32 * freq).sin() * scale).collect()
+        };
+        let xs = Tensor::from_vec(values(m * k, 0.7, 1.), (m, k), dev).unwrap();
+        let ws = Tensor::from_vec(values(n * k, 0.31, 0.125), (n, k), dev).unwrap();
+        let qws = QTensor::quantize(&ws, GgmlDType::Q4_0).unwrap();
+        let dense_ws = qws.dequantize(dev).unwrap();
+        let qmatmul = QMatMul::new(std::sync::Arc::new(qws));
+
+        // The input gradient matches the one obtained with the dequantized weights.
+        let xs_var = crate::Var::from_tensor(&xs).unwrap();
+        let gs = Tensor::from_vec(values(m * n, 0.19, 1.), (m, n), dev).unwrap();
+        let loss = qmatmul
+            .forward(&xs_var)
+            .unwrap()
+            .mul(&gs)
+            .unwrap()
+            .sum_all()
+            .unwrap();
+        let grads = loss.backward().unwrap();
+        let grad_xs = grads.get(&xs_var).unwrap().to_vec2::<f32>().unwrap();
+        let expected = gs.matmul(&dense_ws).unwrap().to_vec2::<f32>().unwrap();
+        for (g, e) in grad_xs.iter().flatten().zip(expected.iter().flatten()) {
+            assert!((g - e).abs() < 1e-4, "{g} {e}")
+        }
+
+        // Trains a low-rank adapter `xs + xs @ a.t() @ b.t()` in front of the frozen weights.
+        let a = crate::Var::from_tensor(
+            &Tensor::from_vec(values(r * k, 0.53, 0.1), (r, k), dev).unwrap(),
+        )
+        .unwrap();
+        let b = crate::Var::zeros((k, r), crate::DType::F32, dev).unwrap();
+        let b_target = Tensor::from_vec(values(k * r, 0.41, 0.3), (k, r), dev).unwrap();
+        let adapter = |a: &Tensor, b: &Tensor| -> Result<Tensor> {
+            let hs = xs.add(&xs.matmul(&a.t()?)?.matmul(&b.t()?)?)?;
+            qmatmul.forward(&hs)
+        };
+        let target = adapter(&a, &b_target).unwrap();
+        let loss_fn = || -> Result<Tensor> { adapter(&a, &b)?.sub(&target)?.sqr()?.sum_all() };
+        let initial_loss = loss_fn().unwrap().to_scalar::<f32>().unwrap();
+        for _step in 0..100 {
+            let grads = loss_fn().unwrap().backward().unwrap();
+            for var in [&a, &b] {
+                let grad = grads.get(var).unwrap();
+                var.set(&var.sub(&grad.affine(0.05, 0.).unwrap()).unwrap())
+                    .unwrap();
+            }
+        }
+        let final_loss = loss_fn().unwrap().to_scalar::<f32>().unwrap();
+        assert!(
+            final_loss < initial_loss * 0.5,
+            "{initial_loss} {final_loss}"
+        )
+    }
+
+    // Checks the simd versions of vec_dot, when enabled, against the scalar ones.
+    fn check_vec_dot<T: k_quants::GgmlType>(k: usize) {
+        let xs: Vec<f32> = (0..k).map(|i| (i as f32 * 0.13).sin()).collect();
+        let ys: Vec<f32> = (0..k).map(|i| (i as f32 * 0.29).cos() * 2.).collect();
+        let mut xs_b = vec![T::zeros(); k / T::BLCK_SIZE];
+        let mut ys_b = vec![T::VecDotType::zeros(); k / T::VecDotType::BLCK_SIZE];
+        T::from_float(&xs, &mut xs_b).unwrap();
+        T::VecDotType::from_float(&ys, &mut ys_b).unwrap();
+        let dot = T::vec_dot(k, &xs_b, &ys_b).unwrap();
+        let dot_unopt = T::vec_dot_unopt(k, &xs_b, &ys_b).unwrap();
+        assert!((dot - dot_unopt).abs() < 1e-3, "{:?}: {dot} {dot_unopt}", T::DTYPE);
+        let exact: f32 = xs.iter().zip(ys.iter()).map(|(x, y)| x * y).sum();
+        assert!((dot - exact).abs() < 0.01 * k as f32, "{:?}: {dot} {exact}", T::DTYPE);
+    }
+
+    #[test]
+    fn vec_dot_simd() {
+        check_vec_dot::<k_quants::BlockQ4_0>(4 * k_quants::QK_K);
+        check_vec_dot::<k_quants::BlockQ4K>(4 * k_quants::QK_K);
+        check_vec_dot::<k_quants::BlockQ6K>(4 * k_quants::QK_K);
+    }
+
+    // Checks vec_dot against a float dot product of the dequantized operands.
+    fn check_vec_dot_dequantized<T: k_quants::GgmlType>(k: usize) {
+        let xs: Vec<f32> = (0..k).map(|i| (i as f32 * 0.13).sin()).collect();
+        let ys: Vec<f32> = (0..k).map(|i| (i as f32 * 0.29).cos() * 2.).collect();
+        let mut xs_b = vec![T::zeros(); k / T::BLCK_SIZE];
+        let mut ys_b = vec![T::VecDotType::zeros(); k / T::VecDotType::BLCK_SIZE];
+        T::from_float(&xs, &mut xs_b).unwrap();
+        T::VecDotType::from_float(&ys, &mut ys_b).unwrap();
+        let (mut xs, mut ys) = (vec![0f32; k], vec![0f32; k]);
+        T::to_float(&xs_b, &mut xs).unwrap();
+        T::VecDotType::to_float(&ys_b, &mut ys).unwrap();
+        let dot = T::vec_dot(k, &xs_b, &ys_b).unwrap();
+        let expected: f32 = xs.iter().zip(ys.iter()).map(|(x, y)| x * y).sum();
+        assert!((dot - expected).abs() < 1e-3 * k as f32, "{:?}: {dot} {expected}", T::DTYPE);
+    }
+
+    #[test]
+    fn vec_dot_scalar() {
+        check_vec_dot_dequantized::<k_quants::BlockQ4_1>(4 * k_quants::QK_K);
+        check_vec_dot_dequantized::<k_quants::BlockQ5_0>(4 * k_quants::QK_K);
+        check_vec_dot_dequantized::<k_quants::BlockQ5_1>(4 * k_quants::QK_K);
+        check_vec_dot_dequantized::<k_quants::BlockQ8_0>(4 * k_quants::QK_K);
+        check_vec_dot_dequantized::<k_quants::BlockQ2K>(4 * k_quants::QK_K);
+        check_vec_dot_dequantized::<k_quants::BlockQ3K>(4 * k_quants::QK_K);
+        check_vec_dot_dequantized::<k_quants::BlockQ5K>(4 * k_quants::QK_K);
+    }
+}
@@ -0,0 +1,164 @@
+// Helpers used when quantizing blocks, these follow the reference implementations from
+// https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c
+
+pub(super) fn nearest_int(v: f32) -> i32 {
+    v.round() as i32
+}
+
+// https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L43
+// Quantizes `xs` symmetrically on `[-nmax, nmax)`, searching for the scale that minimizes the
+// weighted squared error. The quantized values are written in `ls` shifted by `nmax`.
+pub(super) fn make_qx_quants(nmax: i32, xs: &[f32], ls: &mut [i8]) -> f32 {
+    let mut max = 0f32;
+    let mut amax = 0f32;
+    for &x in xs.iter() {
+        if x.abs() > amax {
+            amax = x.abs();
+            max = x;
+        }
+    }
+    if amax == 0. {
+        ls.fill(0);
+        return 0.;
+    }
+    let quantize = |iscale: f32, x: f32| nearest_int(iscale * x).clamp(-nmax, nmax - 1);
+    let sums = |iscale: f32| {
+        let mut sumlx = 0f32;
+        let mut suml2 = 0f32;
+        for &x in xs.iter() {
+            let l = quantize(iscale, x) as f32;
+            let w = x * x;
+            sumlx += w * x * l;
+            suml2 += w * l * l;
+        }
+        (sumlx, suml2)
+    };
+
+    let mut iscale = -(nmax as f32) / max;
+    let (sumlx, suml2) = sums(iscale);
+    let mut scale = sumlx / suml2;
+    let mut best = scale * sumlx;
+    for itry in -9..=9 {
+        if itry == 0 {
+            continue;
+        }
+        let this_iscale = -(nmax as f32 + 0.1 * itry as f32) / max;
+        let (sumlx, suml2) = sums(this_iscale);
+        if suml2 > 0. && sumlx * sumlx > best * suml2 {
+            iscale = this_iscale;
+            scale = sumlx / suml2;
+            best = scale * sumlx;
+        }
+    }
+    for (l, &x) in ls.iter_mut().zip(xs.iter()) {
+        *l = (quantize(iscale, x) + nmax) as i8
+    }
+    scale
+}
+
+// https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L160
+// Quantizes `xs` on `[0, nmax]` with a scale and a min, returns `(scale, -min)`.
+pub(super) fn make_qkx1_quants(nmax: i32, ntry: usize, xs: &[f32], ls: &mut [u8]) -> (f32, f32) {
+    let n = xs.len();
+    let mut min = xs.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = xs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if max == min {
+        ls.fill(0);
+        return (0., 0.);
+    }
+    if min > 0. {
+        min = 0.
+    }
+    let mut iscale = nmax as f32 / (max - min);
+    let mut scale = 1. / iscale;
+    for _ in 0..ntry {
+        let mut sumlx = 0f32;
+        let mut suml2 = 0i32;
+        let mut did_change = false;
+        for (l, &x) in ls.iter_mut().zip(xs.iter()) {
+            let new_l = nearest_int(iscale * (x - min)).clamp(0, nmax);
+            if new_l as u8 != *l {
+                *l = new_l as u8;
+                did_change = true;
+            }
+            sumlx += (x - min) * new_l as f32;
+            suml2 += new_l * new_l;
+        }
+        scale = sumlx / suml2 as f32;
+        let sum: f32 = ls
+            .iter()
+            .zip(xs.iter())
+            .map(|(&l, &x)| x - scale * l as f32)
+            .sum();
+        min = sum / n as f32;
+        if min > 0. {
+            min = 0.
+        }
+        iscale = 1. / scale;
+        if !did_change {
+            break;
+        }
+    }
+    (scale, -min)
+}
+
+// https://github.com/ggerganov/llama.cpp/blob/8183159cf3def112f6d1fe94815fce70e1bffa12/k_quants.c#L82
+// Symmetric quantization on `[-nmax, nmax)` refined by a few passes that greedily update the
+// values reducing the weighted error. The quantized values are shifted by `nmax` in `ls`.
+pub(super) fn make_q3_quants(nmax: i32, xs: &[f32], ls: &mut [i8]) -> f32 {
+    let mut max = 0f32;
+    let mut amax = 0f32;
+    for &x in xs.iter() {
+        if x.abs() > amax {
+            amax = x.abs();
+            max = x;
+        }
+    }
+    if amax == 0. {
+        ls.fill(0);
+        return 0.;
+    }
+    let iscale = -(nmax as f32) / max;
+    let mut sumlx = 0f32;
+    let mut suml2 = 0f32;
+    for (l, &x) in ls.iter_mut().zip(xs.iter()) {
+        let v = nearest_int(iscale * x).clamp(-nmax, nmax - 1);
+        *l = v as i8;
+        let w = x * x;
+        sumlx += w * x * v as f32;
+        suml2 += w * (v * v) as f32;
+    }
+    for _ in 0..5 {
+        let mut n_changed = 0;
+        for (l, &x) in ls.iter_mut().zip(xs.iter()) {
+            let w = x * x;
+            let cur = *l as f32;
+            let mut slx = sumlx - w * x * cur;
+            if slx > 0. {
+                let mut sl2 = suml2 - w * cur * cur;
+                let new_l = nearest_int(x * sl2 / slx).clamp(-nmax, nmax - 1);
+                if new_l != *l as i32 {
+                    slx += w * x * new_l as f32;
+                    sl2 += w * (new_l * new_l) as f32;
+                    if sl2 > 0. && slx * slx * suml2 > sumlx * sumlx * sl2 {
+                        *l = new_l as i8;
+                        sumlx = slx;
+                        suml2 = sl2;
+                        n_changed += 1;
+                    }
+                }
+            }
+        }
+        if n_changed == 0 {
+            break;
+        }
+    }
+    for l in ls.iter_mut() {
+        *l += nmax as i8
+    }
+    if suml2 > 0. {
+        sumlx / suml2
+    } else {
+        0.
+    }
+}
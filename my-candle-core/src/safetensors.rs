@@ -8,8 +8,13 @@ use std::path::Path;
 impl From<DType> for st::Dtype {
     fn from(value: DType) -> Self {
         match value {
+            DType::Bool => st::Dtype::BOOL,
             DType::U8 => st::Dtype::U8,
             DType::U32 => st::Dtype::U32,
+            DType::I8 => st::Dtype::I8,
+            DType::I16 => st::Dtype::I16,
+            DType::I32 => st::Dtype::I32,
+            DType::I64 => st::Dtype::I64,
             DType::BF16 => st::Dtype::BF16,
             DType::F16 => st::Dtype::F16,
             DType::F32 => st::Dtype::F32,
@@ -22,8 +27,13 @@ impl TryFrom<st::Dtype> for DType {
     type Error = Error;
     fn try_from(value: st::Dtype) -> Result<Self> {
         match value {
+            st::Dtype::BOOL => Ok(DType::Bool),
             st::Dtype::U8 => Ok(DType::U8),
             st::Dtype::U32 => Ok(DType::U32),
+            st::Dtype::I8 => Ok(DType::I8),
+            st::Dtype::I16 => Ok(DType::I16),
+            st::Dtype::I32 => Ok(DType::I32),
+            st::Dtype::I64 => Ok(DType::I64),
             st::Dtype::BF16 => Ok(DType::BF16),
             st::Dtype::F16 => Ok(DType::F16),
             st::Dtype::F32 => Ok(DType::F32),
@@ -187,8 +197,13 @@ impl Tensor {
         device: &Device,
     ) -> Result<Self> {
         match dtype {
+            DType::Bool => convert_slice::<u8>(data, shape, device)?.to_dtype(DType::Bool),
             DType::U8 => convert_slice::<u8>(data, shape, device),
             DType::U32 => convert_slice::<u32>(data, shape, device),
+            DType::I8 => convert_slice::<i8>(data, shape, device),
+            DType::I16 => convert_slice::<i16>(data, shape, device),
+            DType::I32 => convert_slice::<i32>(data, shape, device),
+            DType::I64 => convert_slice::<i64>(data, shape, device),
             DType::BF16 => convert_slice::<half::bf16>(data, shape, device),
             DType::F16 => convert_slice::<half::f16>(data, shape, device),
             DType::F32 => convert_slice::<f32>(data, shape, device),
@@ -209,20 +224,11 @@ fn convert(view: &st::TensorView<'_>, device: &Device) -> Result<Tensor> {
         st::Dtype::F16 => convert_::<half::f16>(view, device),
         st::Dtype::F32 => convert_::<f32>(view, device),
         st::Dtype::F64 => convert_::<f64>(view, device),
-        st::Dtype::I32 => {
-            let conv = |x| {
-                u32::try_from(x)
-                    .map_err(|_| Error::Msg(format!("out of bounds value for u32: {x}")))
-            };
-            convert_with_cast_::<i32, u32, _>(view, device, conv)
-        }
-        st::Dtype::I64 => {
-            let conv = |x| {
-                u32::try_from(x)
-                    .map_err(|_| Error::Msg(format!("out of bounds value for u32: {x}")))
-            };
-            convert_with_cast_::<i64, u32, _>(view, device, conv)
-        }
+        st::Dtype::I8 => convert_::<i8>(view, device),
+        st::Dtype::I16 => convert_::<i16>(view, device),
+        st::Dtype::I32 => convert_::<i32>(view, device),
+        st::Dtype::I64 => convert_::<i64>(view, device),
+        st::Dtype::BOOL => convert_::<u8>(view, device)?.to_dtype(DType::Bool),
         dtype => Err(Error::UnsupportedSafeTensorDtype(dtype)),
     }
 }
@@ -231,8 +237,13 @@ fn convert_back(tensor: &Tensor) -> Result<Vec<u8>> {
     // TODO: This makes an unnecessary copy when the tensor is on the cpu.
     let tensor = tensor.flatten_all()?;
     match tensor.dtype() {
+        DType::Bool => Ok(convert_back_::<u8>(tensor.to_dtype(DType::U8)?.to_vec1()?)),
         DType::U8 => Ok(convert_back_::<u8>(tensor.to_vec1()?)),
         DType::U32 => Ok(convert_back_::<u32>(tensor.to_vec1()?)),
+        DType::I8 => Ok(convert_back_::<i8>(tensor.to_vec1()?)),
+        DType::I16 => Ok(convert_back_::<i16>(tensor.to_vec1()?)),
+        DType::I32 => Ok(convert_back_::<i32>(tensor.to_vec1()?)),
+        DType::I64 => Ok(convert_back_::<i64>(tensor.to_vec1()?)),
         DType::F16 => Ok(convert_back_::<half::f16>(tensor.to_vec1()?)),
         DType::BF16 => Ok(convert_back_::<half::bf16>(tensor.to_vec1()?)),
         DType::F32 => Ok(convert_back_::<f32>(tensor.to_vec1()?)),
@@ -319,4 +330,31 @@ mod tests {
         assert_eq!(bytes, b"x\0\0\0\0\0\0\0{\"t\":{\"dtype\":\"F32\",\"shape\":[2,2],\"data_offsets\":[0,16]},\"u\":{\"dtype\":\"F32\",\"shape\":[1,2],\"data_offsets\":[16,24]}}      \0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
         std::fs::remove_file("multi.safetensors").unwrap();
     }
+
+    #[test]
+    fn save_load_signed_int() {
+        let t = Tensor::new(&[-3i64, 0, 1 << 40], &Device::Cpu).unwrap();
+        let u = Tensor::new(&[-3i16, 0, 7], &Device::Cpu).unwrap();
+        let map: HashMap<_, _> = [("t", t), ("u", u)].into_iter().collect();
+        save(&map, "signed.safetensors").unwrap();
+        let weights = load("signed.safetensors", &Device::Cpu).unwrap();
+        assert_eq!(weights["t"].dtype(), DType::I64);
+        assert_eq!(weights["t"].to_vec1::<i64>().unwrap(), [-3, 0, 1 << 40]);
+        assert_eq!(weights["u"].dtype(), DType::I16);
+        assert_eq!(weights["u"].to_vec1::<i16>().unwrap(), [-3, 0, 7]);
+        std::fs::remove_file("signed.safetensors").unwrap();
+    }
+
+    #[test]
+    fn save_load_bool() {
+        let t = Tensor::new(&[1u8, 0, 2], &Device::Cpu).unwrap();
+        let t = t.to_dtype(DType::Bool).unwrap();
+        t.save_safetensors("t", "bool.safetensors").unwrap();
+        let weights = load("bool.safetensors", &Device::Cpu).unwrap();
+        let t = weights.get("t").unwrap();
+        assert_eq!(t.dtype(), DType::Bool);
+        let t = t.to_dtype(DType::U8).unwrap();
+        assert_eq!(t.to_vec1::<u8>().unwrap(), [1, 0, 1]);
+        std::fs::remove_file("bool.safetensors").unwrap();
+    }
 }
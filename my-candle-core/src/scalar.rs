@@ -0,0 +1,28 @@
+//! Support for operations that accept either a tensor or a scalar value as their right hand side.
+use crate::{Device, Result, Tensor, WithDType};
+
+pub enum TensorScalar {
+    Tensor(Tensor),
+    Scalar(Tensor),
+}
+
+/// Values that can be used as the right hand side of comparisons and of `maximum`/`minimum`.
+///
+/// Scalars are converted to a single element tensor on the cpu, this tensor gets converted to the
+/// appropriate dtype and device and broadcasted by the operation.
+pub trait TensorOrScalar {
+    fn to_tensor_scalar(self) -> Result<TensorScalar>;
+}
+
+impl TensorOrScalar for &Tensor {
+    fn to_tensor_scalar(self) -> Result<TensorScalar> {
+        Ok(TensorScalar::Tensor(self.clone()))
+    }
+}
+
+impl<T: WithDType> TensorOrScalar for T {
+    fn to_tensor_scalar(self) -> Result<TensorScalar> {
+        let scalar = Tensor::new(self, &Device::Cpu)?;
+        Ok(TensorScalar::Scalar(scalar))
+    }
+}
@@ -0,0 +1,178 @@
+//! Sorting along a dimension, the sort itself is implemented as a custom op returning the
+//! indexes, the values are then obtained with `gather`.
+use crate::{CpuStorage, Layout, Result, Shape, Tensor, WithDType};
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+struct ArgSort {
+    asc: bool,
+    last_dim: usize,
+}
+
+// A total order that puts the NaN values last so that the sort is well defined.
+#[inline(always)]
+#[allow(clippy::eq_op)]
+fn total_cmp<T: WithDType>(a: T, b: T) -> Ordering {
+    match a.partial_cmp(&b) {
+        Some(ord) => ord,
+        None => match (a != a, b != b) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, _) => Ordering::Less,
+        },
+    }
+}
+
+impl ArgSort {
+    fn asort<T: WithDType>(&self, vs: &[T], layout: &Layout) -> Result<Vec<u32>> {
+        let vs = match layout.contiguous_offsets() {
+            Some((o1, o2)) => &vs[o1..o2],
+            None => crate::bail!("arg-sort: input has to be contiguous"),
+        };
+        let mut sort_indexes = vec![0u32; vs.len()];
+        // Each row is sorted independently, the sort is stable so equal values keep their order.
+        sort_indexes
+            .par_chunks_exact_mut(self.last_dim)
+            .zip(vs.par_chunks_exact(self.last_dim))
+            .for_each(|(indexes, vs)| {
+                indexes
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, v)| *v = i as u32);
+                indexes.sort_by(|&i, &j| {
+                    let ord = total_cmp(vs[i as usize], vs[j as usize]);
+                    if self.asc {
+                        ord
+                    } else {
+                        ord.reverse()
+                    }
+                })
+            });
+        Ok(sort_indexes)
+    }
+}
+
+impl crate::CustomOp1 for ArgSort {
+    fn name(&self) -> &'static str {
+        "arg-sort"
+    }
+
+    fn cpu_fwd(&self, storage: &CpuStorage, layout: &Layout) -> Result<(CpuStorage, Shape)> {
+        let sort_indexes = match storage {
+            CpuStorage::Bool(_) => crate::bail!("arg-sort is not supported for bool tensors"),
+            CpuStorage::U8(vs) => self.asort(vs, layout)?,
+            CpuStorage::U32(vs) => self.asort(vs, layout)?,
+            CpuStorage::I8(vs) => self.asort(vs, layout)?,
+            CpuStorage::I16(vs) => self.asort(vs, layout)?,
+            CpuStorage::I32(vs) => self.asort(vs, layout)?,
+            CpuStorage::I64(vs) => self.asort(vs, layout)?,
+            CpuStorage::BF16(vs) => self.asort(vs, layout)?,
+            CpuStorage::F16(vs) => self.asort(vs, layout)?,
+            CpuStorage::F32(vs) => self.asort(vs, layout)?,
+            CpuStorage::F64(vs) => self.asort(vs, layout)?,
+        };
+        Ok((CpuStorage::U32(sort_indexes), layout.shape().clone()))
+    }
+
+    // The indexes are not differentiable, the gradient flows through the gather op instead.
+    fn bwd(&self, _arg: &Tensor, _res: &Tensor, _grad_res: &Tensor) -> Result<Option<Tensor>> {
+        Ok(None)
+    }
+}
+
+impl Tensor {
+    /// Returns the indexes that sort the tensor along the last dimension.
+    ///
+    /// If `asc` is `true`, sorting is in ascending order, otherwise it is in descending order.
+    /// The sort is stable and NaN values are considered larger than any other value.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[3f32, 1., 2.], [0., 5., 4.]], &Device::Cpu)?;
+    /// let idx = a.arg_sort_last_dim(true)?;
+    /// assert_eq!(idx.to_vec2::<u32>()?, &[[1, 2, 0], [0, 2, 1]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn arg_sort_last_dim(&self, asc: bool) -> Result<Tensor> {
+        if self.rank() == 0 {
+            crate::bail!("arg_sort_last_dim is not supported on scalars")
+        }
+        let last_dim = self.dims()[self.rank() - 1];
+        if last_dim == 0 {
+            return Tensor::zeros(self.dims(), crate::DType::U32, self.device());
+        }
+        self.contiguous()?.custom_op1(ArgSort { asc, last_dim })
+    }
+
+    /// Sorts the tensor along the last dimension, returns the sorted values and the indexes
+    /// these values had in the original tensor.
+    pub fn sort_last_dim(&self, asc: bool) -> Result<(Tensor, Tensor)> {
+        let indexes = self.arg_sort_last_dim(asc)?;
+        let sorted = self.gather(&indexes, crate::D::Minus1)?;
+        Ok((sorted, indexes))
+    }
+
+    /// Returns the indexes that sort the tensor along dimension `dim`.
+    pub fn argsort<D: crate::shape::Dim>(&self, dim: D, asc: bool) -> Result<Tensor> {
+        let dim = dim.to_index(self.shape(), "argsort")?;
+        let last_dim = self.rank() - 1;
+        if dim == last_dim {
+            self.arg_sort_last_dim(asc)
+        } else {
+            self.transpose(dim, last_dim)?
+                .arg_sort_last_dim(asc)?
+                .transpose(dim, last_dim)?
+                .contiguous()
+        }
+    }
+
+    /// Returns the `k` largest values along dimension `dim` in descending order together with
+    /// their indexes.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[3f32, 1., 2., 7.], [0., 5., 4., 6.]], &Device::Cpu)?;
+    /// let (values, indexes) = a.topk(2, 1)?;
+    /// assert_eq!(values.to_vec2::<f32>()?, &[[7., 3.], [6., 5.]]);
+    /// assert_eq!(indexes.to_vec2::<u32>()?, &[[3, 0], [3, 1]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn topk<D: crate::shape::Dim>(&self, k: usize, dim: D) -> Result<(Tensor, Tensor)> {
+        let dim = dim.to_index(self.shape(), "topk")?;
+        let dim_size = self.dims()[dim];
+        if k > dim_size {
+            crate::bail!("topk: k ({k}) is larger than the size of dim {dim} ({dim_size})")
+        }
+        let indexes = self.argsort(dim, false)?.narrow(dim, 0, k)?.contiguous()?;
+        let values = self.gather(&indexes, dim)?;
+        Ok((values, indexes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, Result, Tensor, Var};
+
+    #[test]
+    fn sort_and_topk() -> Result<()> {
+        let dev = &Device::Cpu;
+        let t = Tensor::new(&[[1f32, f32::NAN, -2., 1.], [4., 0., 3., -1.]], dev)?;
+        let (sorted, indexes) = t.sort_last_dim(true)?;
+        assert_eq!(indexes.to_vec2::<u32>()?, [[2, 0, 3, 1], [3, 1, 2, 0]]);
+        let sorted = sorted.to_vec2::<f32>()?;
+        assert_eq!(sorted[0][..3], [-2., 1., 1.]);
+        assert!(sorted[0][3].is_nan());
+        assert_eq!(sorted[1], [-1., 0., 3., 4.]);
+
+        let t = Tensor::new(&[[3i64, 1], [-2, 5], [0, 4]], dev)?;
+        assert_eq!(t.argsort(0, false)?.to_vec2::<u32>()?, [[0, 1], [2, 2], [1, 0]]);
+
+        // The gradient flows back to the selected positions.
+        let x = Var::new(&[[0.5f64, 2., -1., 3.], [1., -4., 6., 0.]], dev)?;
+        let (values, _) = x.topk(2, 1)?;
+        let grads = values.sum_all()?.backward()?;
+        let grad = grads.get(&x).unwrap().to_vec2::<f64>()?;
+        assert_eq!(grad, [[0., 1., 0., 1.], [1., 0., 1., 0.]]);
+        Ok(())
+    }
+}
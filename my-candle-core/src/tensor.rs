@@ -16,6 +16,7 @@ use crate::dtype::{DType,FloatDType,WithDType};
 use crate::device::{Device, NdArray};
 use crate::error::{Error, Result};
 use crate::layout::Layout;
+use crate::scalar::{TensorOrScalar, TensorScalar};
 use std::sync::{Arc, atomic, RwLock};
 use crate::cpu_backend::CpuStorage;
 use crate::strided_index::{StridedBlocks, StridedIndex};
@@ -106,6 +107,22 @@ macro_rules! broadcast_binary_op {
     };
 }
 
+macro_rules! binary_op_scalar {
+    ($fn_name:ident, $op_name:ident) => {
+        pub fn $fn_name<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
+            let rhs = self.tensor_or_scalar(rhs)?;
+            let shape = self.same_shape_binary_op(&rhs, stringify!($fn_name))?;
+            let storage = self.storage().binary_impl::<crate::op::$op_name>(
+                &*rhs.storage(),
+                self.layout(),
+                rhs.layout(),
+            )?;
+            let op = BackpropOp::new2(self, &rhs, |t1, t2| Op::Binary(t1, t2, BinaryOp::$op_name));
+            Ok(from_storage(storage, shape.clone(), op, false))
+        }
+    };
+}
+
 fn from_storage<S:Into<Shape>>(
     storage: Storage,
     shape:S,
@@ -329,6 +346,36 @@ impl Tensor {
         Self::from_vec_impl(data, len, device, false)
     }
 
+    // Mask for the lower (resp. upper) triangle of a `(n, m)` matrix, the diagonal is offset by
+    // `diagonal`, positive values moving it above the main diagonal.
+    fn tri_mask(n: usize, m: usize, diagonal: i64, lower: bool, device: &Device) -> Result<Self> {
+        let rows = Tensor::arange(diagonal, n as i64 + diagonal, device)?.reshape((n, 1))?;
+        let cols = Tensor::arange(0i64, m as i64, device)?.reshape((1, m))?;
+        if lower {
+            cols.broadcast_le(&rows)
+        } else {
+            cols.broadcast_ge(&rows)
+        }
+    }
+
+    /// Creates a `(n, n)` matrix with ones on and below the diagonal and zeros above, e.g. a
+    /// causal attention mask.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, DType, Device};
+    /// let t = Tensor::tril2(3, DType::F32, &Device::Cpu)?;
+    /// assert_eq!(t.to_vec2::<f32>()?, &[[1., 0., 0.], [1., 1., 0.], [1., 1., 1.]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn tril2(n: usize, dtype: DType, device: &Device) -> Result<Self> {
+        Self::tri_mask(n, n, 0, true, device)?.to_dtype(dtype)
+    }
+
+    /// Creates a `(n, n)` matrix with ones on and above the diagonal and zeros below.
+    pub fn triu2(n: usize, dtype: DType, device: &Device) -> Result<Self> {
+        Self::tri_mask(n, n, 0, false, device)?.to_dtype(dtype)
+    }
+
     pub(crate) fn from_vec_impl<S: Into<Shape>, D: WithDType>(
         data: Vec<D>,
         shape: S,
@@ -424,9 +471,10 @@ impl Tensor {
     }
 
     /// Returns true if the computation graph should track this op, that is if it is
-    /// a variable or if it has some variable as dependencies.
+    /// a variable or if it has some variable as dependencies. Ops are never tracked while a
+    /// checkpointed function runs in the forward pass.
     pub(crate) fn track_op(&self) -> bool {
-        self.is_variable || self.op.is_some()
+        crate::backprop::record_op(self.is_variable || self.op.is_some())
     }
 
     // TODO: Also make an inplace version or a pre-allocated? This could be tricky
@@ -435,10 +483,15 @@ impl Tensor {
     binary_op!(mul, Mul);
     binary_op!(sub, Sub);
     binary_op!(div, Div);
+    binary_op_scalar!(maximum, Maximum);
+    binary_op_scalar!(minimum, Minimum);
     broadcast_binary_op!(broadcast_add, add);
     broadcast_binary_op!(broadcast_mul, mul);
     broadcast_binary_op!(broadcast_sub, sub);
     broadcast_binary_op!(broadcast_div, div);
+    broadcast_binary_op!(broadcast_pow, pow);
+    broadcast_binary_op!(broadcast_maximum, maximum);
+    broadcast_binary_op!(broadcast_minimum, minimum);
 
     unary_op!(recip, Recip);
     unary_op!(neg, Neg);
@@ -452,6 +505,14 @@ impl Tensor {
     unary_op!(gelu, Gelu);
     unary_op!(relu, Relu);
     unary_op!(gelu_erf, GeluErf);
+    unary_op!(tanh, Tanh);
+    unary_op!(sigmoid, Sigmoid);
+    unary_op!(silu, Silu);
+    unary_op!(erf, Erf);
+    unary_op!(floor, Floor);
+    unary_op!(ceil, Ceil);
+    unary_op!(round, Round);
+    unary_op!(sign, Sign);
 
     /// Retrieves the single scalar value hold in the tensor. If the tensor contains multiple
     /// dimensions, an error is returned instead.
@@ -493,6 +554,85 @@ impl Tensor {
         Ok(from_storage(storage, self.shape(), op, false))
     }
 
+    /// Raises each element of `self` to the power given by the matching element of `rhs`.
+    ///
+    /// On signed integers, negative exponents are rejected as the results are not integers
+    /// (except for bases of 1 or -1), this is the same as PyTorch. Exponents are also limited to
+    /// `u32::MAX`.
+    pub fn pow(&self, rhs: &Self) -> Result<Self> {
+        let shape = self.same_shape_binary_op(rhs, "pow")?;
+        if matches!(rhs.dtype(), DType::I8 | DType::I16 | DType::I32 | DType::I64) {
+            let any = |mask: Tensor| -> Result<bool> {
+                Ok(mask.to_dtype(DType::U32)?.sum_all()?.to_scalar::<u32>()? > 0)
+            };
+            if any(rhs.lt(0i64)?)? {
+                crate::bail!("pow: integers to negative integer powers are not allowed")
+            }
+            if rhs.dtype() == DType::I64 && any(rhs.gt(u32::MAX as i64)?)? {
+                crate::bail!("pow: integer exponents larger than {} are not supported", u32::MAX)
+            }
+        }
+        let storage = self.storage().binary_impl::<crate::op::Pow>(
+            &*rhs.storage(),
+            self.layout(),
+            rhs.layout(),
+        )?;
+        let op = BackpropOp::new2(self, rhs, |t1, t2| Op::Binary(t1, t2, BinaryOp::Pow));
+        Ok(from_storage(storage, shape.clone(), op, false))
+    }
+
+    /// Raises each element of the input tensor to the power `e`.
+    pub fn powf(&self, e: f64) -> Result<Self> {
+        let storage = self.storage().powf(self.layout(), e)?;
+        let op = BackpropOp::new1(self, |t| Op::Powf(t, e));
+        Ok(from_storage(storage, self.shape(), op, false))
+    }
+
+    /// Clamps each element of the input tensor between `min` and `max`.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[-2f32, 0.5, 7.], &Device::Cpu)?;
+    /// let a = a.clamp(0., 6.)?;
+    /// assert_eq!(a.to_vec1::<f32>()?, &[0., 0.5, 6.]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn clamp(&self, min: f64, max: f64) -> Result<Self> {
+        if min > max {
+            crate::bail!("clamp: min {min} is larger than max {max}")
+        }
+        let storage = self.storage().clamp(self.layout(), min, max)?;
+        let op = BackpropOp::new1(self, |t| Op::Clamp(t, min, max));
+        Ok(from_storage(storage, self.shape(), op, false))
+    }
+
+    /// Applies the softmax function over the last dimension of the input tensor, this uses a
+    /// single fused kernel rather than composing the max, exp, sum and div operations.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[0f32, 1., 0., 1.], [-2., 2., 3., -3.]], &Device::Cpu)?;
+    /// let a = a.softmax_last_dim()?;
+    /// let sum = a.sum_keepdim(1)?.to_vec2::<f32>()?;
+    /// assert!((sum[0][0] - 1.).abs() < 1e-6);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn softmax_last_dim(&self) -> Result<Self> {
+        let xs = self.contiguous()?;
+        let storage = xs.storage().softmax_last_dim(xs.layout(), false)?;
+        let op = BackpropOp::new1(&xs, Op::SoftmaxLastDim);
+        Ok(from_storage(storage, xs.shape(), op, false))
+    }
+
+    /// Applies the log-softmax function over the last dimension of the input tensor using a
+    /// single fused kernel.
+    pub fn log_softmax_last_dim(&self) -> Result<Self> {
+        let xs = self.contiguous()?;
+        let storage = xs.storage().softmax_last_dim(xs.layout(), true)?;
+        let op = BackpropOp::new1(&xs, Op::LogSoftmaxLastDim);
+        Ok(from_storage(storage, xs.shape(), op, false))
+    }
+
     fn check_dim(&self, dim: usize, op: &'static str) -> Result<()> {
         if dim >= self.dims().len() {
             Err(Error::DimOutOfRange {
@@ -602,20 +742,30 @@ impl Tensor {
     }
 
     fn sum_impl<D: Dims>(&self, sum_dims: D, keepdim: bool) -> Result<Self> {
-        let sum_dims = sum_dims.to_indexes(self.shape(), "sum")?;
+        self.reduce_dims_impl(sum_dims, keepdim, ReduceOp::Sum)
+    }
+
+    // Reduction over multiple dimensions, argmin and argmax only support a single one.
+    fn reduce_dims_impl<D: Dims>(
+        &self,
+        reduce_dims: D,
+        keepdim: bool,
+        op: ReduceOp,
+    ) -> Result<Self> {
+        let reduce_dims = reduce_dims.to_indexes(self.shape(), op.name())?;
         let storage = self
             .storage()
-            .reduce_op(ReduceOp::Sum, self.layout(), &sum_dims)?;
+            .reduce_op(op, self.layout(), &reduce_dims)?;
         let mut dims = self.dims().to_vec();
-        for &sum_dim in sum_dims.iter() {
-            dims[sum_dim] = 1
+        for &reduce_dim in reduce_dims.iter() {
+            dims[reduce_dim] = 1
         }
-        let op = BackpropOp::new1(self, |a| Op::Reduce(a, ReduceOp::Sum, dims.to_vec()));
-        let sum = from_storage(storage, dims, op, false);
+        let backprop_op = BackpropOp::new1(self, |a| Op::Reduce(a, op, dims.to_vec()));
+        let res = from_storage(storage, dims, backprop_op, false);
         if keepdim {
-            Ok(sum)
+            Ok(res)
         } else {
-            sum.squeeze_dims(&sum_dims)
+            res.squeeze_dims(&reduce_dims)
         }
     }
 
@@ -630,20 +780,205 @@ impl Tensor {
         self.sum_impl(sum_dims, false)
     }
 
-    pub fn max_keepdim<D: Dim>(&self, dim: D) -> Result<Self> {
-        self.reduce_impl(dim, true, ReduceOp::Max)
+    /// The product of the elements over the given dimensions, these dimensions are kept with a
+    /// size of one.
+    pub fn prod_keepdim<D: Dims>(&self, prod_dims: D) -> Result<Self> {
+        self.reduce_dims_impl(prod_dims, true, ReduceOp::Prod)
+    }
+
+    /// The product of the elements over the given dimensions, these dimensions are squeezed.
+    pub fn prod<D: Dims>(&self, prod_dims: D) -> Result<Self> {
+        self.reduce_dims_impl(prod_dims, false, ReduceOp::Prod)
+    }
+
+    /// The mean of the elements over the given dimensions, these dimensions are kept with a size
+    /// of one. This is only supported for floating point dtypes.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[0f32, 1.], [2., 3.], [4., 5.]], &Device::Cpu)?;
+    /// let m = a.mean_keepdim(0)?;
+    /// assert_eq!(m.to_vec2::<f32>()?, &[[2., 3.]]);
+    /// let m = a.mean((0, 1))?;
+    /// assert_eq!(m.to_scalar::<f32>()?, 2.5);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn mean_keepdim<D: Dims>(&self, mean_dims: D) -> Result<Self> {
+        let mean_dims = mean_dims.to_indexes(self.shape(), "mean")?;
+        // The scale would be truncated to zero on integer dtypes.
+        if !self.dtype().is_float() {
+            Err(Error::UnsupportedDTypeForOp(self.dtype(), "mean").bt())?
+        }
+        let reduced_dim: usize = mean_dims.iter().map(|i| self.dims()[*i]).product();
+        let scale = 1f64 / (reduced_dim as f64);
+        self.sum_impl(mean_dims, true)? * scale
+    }
+
+    /// The mean of the elements over the given dimensions, these dimensions are squeezed.
+    pub fn mean<D: Dims>(&self, mean_dims: D) -> Result<Self> {
+        let mean_dims = mean_dims.to_indexes(self.shape(), "mean")?;
+        self.mean_keepdim(mean_dims.as_slice())?.squeeze_dims(&mean_dims)
+    }
+
+    /// The variance of the elements over the given dimensions, these dimensions are kept with a
+    /// size of one.
+    ///
+    /// The mean is subtracted before squaring the values, this is more precise than computing
+    /// `mean(x^2) - mean(x)^2`. When `unbiased` is set the sum of squares is divided by `n - 1`
+    /// rather than `n`.
+    pub fn var_keepdim<D: Dims>(&self, var_dims: D, unbiased: bool) -> Result<Self> {
+        let var_dims = var_dims.to_indexes(self.shape(), "var")?;
+        let n: usize = var_dims.iter().map(|i| self.dims()[*i]).product();
+        let n = if unbiased { n.saturating_sub(1) } else { n };
+        let mean = self.mean_keepdim(var_dims.as_slice())?;
+        let squares = self.broadcast_sub(&mean)?.sqr()?;
+        squares.sum_impl(var_dims, true)? / n as f64
     }
 
-    pub fn max<D: Dim>(&self, dim: D) -> Result<Self> {
-        self.reduce_impl(dim, false, ReduceOp::Max)
+    /// The variance of the elements over the given dimensions, these dimensions are squeezed.
+    pub fn var<D: Dims>(&self, var_dims: D, unbiased: bool) -> Result<Self> {
+        let var_dims = var_dims.to_indexes(self.shape(), "var")?;
+        self.var_keepdim(var_dims.as_slice(), unbiased)?.squeeze_dims(&var_dims)
     }
 
-    pub fn min_keepdim<D: Dim>(&self, dim: D) -> Result<Self> {
-        self.reduce_impl(dim, true, ReduceOp::Min)
+    /// The standard deviation of the elements over the given dimensions, these dimensions are
+    /// kept with a size of one. See `var_keepdim` for the meaning of `unbiased`.
+    pub fn std_keepdim<D: Dims>(&self, std_dims: D, unbiased: bool) -> Result<Self> {
+        self.var_keepdim(std_dims, unbiased)?.sqrt()
     }
 
-    pub fn min<D: Dim>(&self, dim: D) -> Result<Self> {
-        self.reduce_impl(dim, false, ReduceOp::Min)
+    /// The standard deviation of the elements over the given dimensions, these dimensions are
+    /// squeezed.
+    pub fn std<D: Dims>(&self, std_dims: D, unbiased: bool) -> Result<Self> {
+        self.var(std_dims, unbiased)?.sqrt()
+    }
+
+    /// Computes `log(sum(exp(x)))` over the given dimensions, these dimensions are kept with a
+    /// size of one.
+    ///
+    /// The maximum is subtracted before taking the exponential so that large values do not
+    /// overflow.
+    pub fn logsumexp_keepdim<D: Dims>(&self, lse_dims: D) -> Result<Self> {
+        let lse_dims = lse_dims.to_indexes(self.shape(), "logsumexp")?;
+        if lse_dims.is_empty() {
+            return Ok(self.clone());
+        }
+        // The gradient of the result with respect to the maximum is zero so the maximum does
+        // not have to be tracked.
+        let max = self.detach()?.max_keepdim(lse_dims.as_slice())?;
+        // The maximum of a fully masked slice is -inf and subtracting it would result in NaNs,
+        // infinite maximums are replaced with zeros as in PyTorch.
+        let max = max.abs()?.eq(f64::INFINITY)?.where_cond(&max.zeros_like()?, &max)?;
+        let sum = self.broadcast_sub(&max)?.exp()?.sum_impl(lse_dims, true)?;
+        // A fully masked slice sums to zero and its result is -inf. The log is taken on a sum of
+        // one for these slices so that their gradient is zero rather than NaN.
+        let masked = sum.eq(0.)?;
+        let log_sum = masked.where_cond(&sum.ones_like()?, &sum)?.log()?;
+        let neg_inf = log_sum.ones_like()?.affine(f64::NEG_INFINITY, 0.)?;
+        masked.where_cond(&neg_inf, &log_sum)?.broadcast_add(&max)
+    }
+
+    /// Computes `log(sum(exp(x)))` over the given dimensions, these dimensions are squeezed.
+    pub fn logsumexp<D: Dims>(&self, lse_dims: D) -> Result<Self> {
+        let lse_dims = lse_dims.to_indexes(self.shape(), "logsumexp")?;
+        self.logsumexp_keepdim(lse_dims.as_slice())?.squeeze_dims(&lse_dims)
+    }
+
+    fn cumulative_impl<D: Dim>(&self, dim: D, op: ReduceOp) -> Result<Self> {
+        let dim = dim.to_index(self.shape(), op.name())?;
+        let storage = self.storage().cumulative(op, self.layout(), dim)?;
+        let backprop_op = BackpropOp::new1(self, |t| Op::Cumulative(t, op, dim));
+        Ok(from_storage(storage, self.shape().clone(), backprop_op, false))
+    }
+
+    /// The cumulative sum along dimension `dim`, the element at index `i` is the sum of the
+    /// elements at indexes `0..=i`.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[1f32, 2., 3.], [4., 5., 6.]], &Device::Cpu)?;
+    /// assert_eq!(a.cumsum(1)?.to_vec2::<f32>()?, &[[1., 3., 6.], [4., 9., 15.]]);
+    /// assert_eq!(a.cumprod(0)?.to_vec2::<f32>()?, &[[1., 2., 3.], [4., 10., 18.]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn cumsum<D: Dim>(&self, dim: D) -> Result<Self> {
+        self.cumulative_impl(dim, ReduceOp::Sum)
+    }
+
+    /// The cumulative product along dimension `dim`, the element at index `i` is the product of
+    /// the elements at indexes `0..=i`.
+    pub fn cumprod<D: Dim>(&self, dim: D) -> Result<Self> {
+        self.cumulative_impl(dim, ReduceOp::Prod)
+    }
+
+    fn tri_impl(&self, diagonal: i64, lower: bool) -> Result<Self> {
+        let (n, m) = match self.dims() {
+            [.., n, m] => (*n, *m),
+            _ => crate::bail!("tril/triu expect at least two dims, got {:?}", self.shape()),
+        };
+        let mask = Self::tri_mask(n, m, diagonal, lower, self.device())?;
+        mask.broadcast_as(self.shape())?.where_cond(self, &self.zeros_like()?)
+    }
+
+    /// Keeps the lower triangle of the matrices formed by the last two dimensions, the other
+    /// values are set to zero. The elements on and below the `diagonal`-th diagonal are kept,
+    /// `0` is the main diagonal and positive values select diagonals above it.
+    pub fn tril(&self, diagonal: i64) -> Result<Self> {
+        self.tri_impl(diagonal, true)
+    }
+
+    /// Keeps the upper triangle of the matrices formed by the last two dimensions, the other
+    /// values are set to zero. The elements on and above the `diagonal`-th diagonal are kept.
+    pub fn triu(&self, diagonal: i64) -> Result<Self> {
+        self.tri_impl(diagonal, false)
+    }
+
+    /// The maximum over the given dimensions, these dimensions are kept with a size of one.
+    ///
+    /// When several values are equal to the maximum, the gradient is split evenly between them.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[[[0f32, 7.], [2., 3.]], [[4., 1.], [5., 6.]]], &Device::Cpu)?;
+    /// let m = a.max_keepdim((0, 2))?;
+    /// assert_eq!(m.to_vec3::<f32>()?, &[[[7.], [6.]]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn max_keepdim<D: Dims>(&self, max_dims: D) -> Result<Self> {
+        self.reduce_dims_impl(max_dims, true, ReduceOp::Max)
+    }
+
+    /// The maximum over the given dimensions, these dimensions are squeezed.
+    pub fn max<D: Dims>(&self, max_dims: D) -> Result<Self> {
+        self.reduce_dims_impl(max_dims, false, ReduceOp::Max)
+    }
+
+    /// The minimum over the given dimensions, these dimensions are kept with a size of one.
+    pub fn min_keepdim<D: Dims>(&self, min_dims: D) -> Result<Self> {
+        self.reduce_dims_impl(min_dims, true, ReduceOp::Min)
+    }
+
+    /// The minimum over the given dimensions, these dimensions are squeezed.
+    pub fn min<D: Dims>(&self, min_dims: D) -> Result<Self> {
+        self.reduce_dims_impl(min_dims, false, ReduceOp::Min)
+    }
+
+    /// The maximum over all the elements of the tensor, a rank 0 tensor is returned as is.
+    pub fn max_all(&self) -> Result<Tensor> {
+        if self.rank() == 0 {
+            return Ok(self.clone());
+        }
+        let dims: Vec<_> = (0..self.rank()).collect();
+        self.max(dims)
+    }
+
+    /// The minimum over all the elements of the tensor, a rank 0 tensor is returned as is.
+    pub fn min_all(&self) -> Result<Tensor> {
+        if self.rank() == 0 {
+            return Ok(self.clone());
+        }
+        let dims: Vec<_> = (0..self.rank()).collect();
+        self.min(dims)
     }
 
     pub fn argmax_keepdim<D: Dim>(&self, dim: D) -> Result<Self> {
@@ -662,8 +997,43 @@ impl Tensor {
         self.reduce_impl(dim, false, ReduceOp::ArgMin)
     }
 
-    pub fn cmp(&self, rhs: &Self, op: CmpOp) -> Result<Self> {
-        let shape = self.same_shape_binary_op(rhs, "cmp")?;
+    // Scalars are converted to the dtype and device of self and broadcasted to its shape. The
+    // scalars that cannot be represented exactly in the dtype of self are rejected, e.g. comparing
+    // an integer tensor with 2.5 would otherwise compare it with 2.
+    fn tensor_or_scalar<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
+        match rhs.to_tensor_scalar()? {
+            TensorScalar::Tensor(rhs) => Ok(rhs),
+            TensorScalar::Scalar(rhs) => {
+                let converted = rhs.to_dtype(self.dtype())?;
+                if self.dtype().is_int() {
+                    let v = rhs.to_dtype(DType::F64)?.to_scalar::<f64>()?;
+                    if converted.to_dtype(DType::F64)?.to_scalar::<f64>()? != v {
+                        crate::bail!("scalar {v} cannot be represented as {:?}", self.dtype())
+                    }
+                }
+                converted.to_device(self.device())?.broadcast_as(self.shape())
+            }
+        }
+    }
+
+    /// Elementwise comparison, the result is a `u8` tensor with ones where the comparison holds.
+    ///
+    /// The right hand side can either be a tensor with the same shape as `self` or a scalar.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::new(&[1f32, 3., 2.], &Device::Cpu)?;
+    /// let b = Tensor::new(&[2f32, 2., 2.], &Device::Cpu)?;
+    /// assert_eq!(a.ge(&b)?.to_vec1::<u8>()?, &[0, 1, 1]);
+    /// assert_eq!(a.lt(2.5)?.to_vec1::<u8>()?, &[1, 0, 1]);
+    /// let c = Tensor::new(&[1u8, 3, 2], &Device::Cpu)?;
+    /// assert_eq!(c.lt(2.)?.to_vec1::<u8>()?, &[1, 0, 0]);
+    /// assert!(c.lt(2.5).is_err());
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn cmp<T: TensorOrScalar>(&self, rhs: T, op: CmpOp) -> Result<Self> {
+        let rhs = self.tensor_or_scalar(rhs)?;
+        let shape = self.same_shape_binary_op(&rhs, "cmp")?;
         let storage = self
             .storage()
             .cmp(op, &rhs.storage(), self.layout(), rhs.layout())?;
@@ -671,43 +1041,100 @@ impl Tensor {
         Ok(from_storage(storage, shape.dims(), op, false))
     }
 
-    pub fn eq(&self, rhs: &Self) -> Result<Self> {
+    pub fn eq<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
         self.cmp(rhs, CmpOp::Eq)
     }
 
-    pub fn ne(&self, rhs: &Self) -> Result<Self> {
+    pub fn ne<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
         self.cmp(rhs, CmpOp::Ne)
     }
 
-    pub fn lt(&self, rhs: &Self) -> Result<Self> {
+    pub fn lt<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
         self.cmp(rhs, CmpOp::Lt)
     }
 
-    pub fn gt(&self, rhs: &Self) -> Result<Self> {
+    pub fn gt<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
         self.cmp(rhs, CmpOp::Gt)
     }
 
-    pub fn ge(&self, rhs: &Self) -> Result<Self> {
+    pub fn ge<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
         self.cmp(rhs, CmpOp::Ge)
     }
 
-    pub fn le(&self, rhs: &Self) -> Result<Self> {
+    pub fn le<T: TensorOrScalar>(&self, rhs: T) -> Result<Self> {
         self.cmp(rhs, CmpOp::Le)
     }
 
+    pub fn broadcast_eq(&self, rhs: &Self) -> Result<Self> {
+        self.broadcast_cmp(rhs, CmpOp::Eq)
+    }
+
+    pub fn broadcast_ne(&self, rhs: &Self) -> Result<Self> {
+        self.broadcast_cmp(rhs, CmpOp::Ne)
+    }
+
+    pub fn broadcast_lt(&self, rhs: &Self) -> Result<Self> {
+        self.broadcast_cmp(rhs, CmpOp::Lt)
+    }
+
+    pub fn broadcast_gt(&self, rhs: &Self) -> Result<Self> {
+        self.broadcast_cmp(rhs, CmpOp::Gt)
+    }
+
+    pub fn broadcast_ge(&self, rhs: &Self) -> Result<Self> {
+        self.broadcast_cmp(rhs, CmpOp::Ge)
+    }
+
+    pub fn broadcast_le(&self, rhs: &Self) -> Result<Self> {
+        self.broadcast_cmp(rhs, CmpOp::Le)
+    }
+
+    fn broadcast_cmp(&self, rhs: &Self, op: CmpOp) -> Result<Self> {
+        let shape = self.broadcast_shape_binary_op(rhs, "broadcast_cmp")?;
+        self.broadcast_as(&shape)?.cmp(&rhs.broadcast_as(&shape)?, op)
+    }
+
     /// Applies a 1D convolution over the input tensor.
-    pub fn conv1d(&self, kernel: &Self, padding: usize, stride: usize) -> Result<Self> {
+    ///
+    /// The input has shape `(b_size, c_in, l_in)` and the kernel `(c_out, c_in / groups, k_size)`,
+    /// both `c_in` and `c_out` have to be divisible by `groups`.
+    pub fn conv1d(
+        &self,
+        kernel: &Self,
+        padding: usize,
+        stride: usize,
+        dilation: usize,
+        groups: usize,
+    ) -> Result<Self> {
         let (c_out, c_in_k, k_size) = kernel.dims3()?;
         let (b_size, c_in, l_in) = self.dims3()?;
-        if c_in != c_in_k {
+        let invalid_args = |msg: &'static str| -> Result<()> {
             Err(Error::Conv1dInvalidArgs {
                 inp_shape: self.shape().clone(),
                 k_shape: kernel.shape().clone(),
                 padding,
                 stride,
-                msg: "the number of in-channels on the input doesn't match the kernel size",
+                msg,
             }
-                .bt())?
+            .bt())
+        };
+        if groups == 0 {
+            invalid_args("the number of groups has to be positive")?
+        }
+        if c_in % groups != 0 {
+            invalid_args("the number of in-channels is not divisible by the number of groups")?
+        }
+        if c_out % groups != 0 {
+            invalid_args("the number of out-channels is not divisible by the number of groups")?
+        }
+        if c_in / groups != c_in_k {
+            invalid_args("the number of in-channels on the input doesn't match the kernel size")?
+        }
+        if stride == 0 || dilation == 0 {
+            invalid_args("the stride and dilation have to be positive")?
+        }
+        if k_size == 0 || l_in + 2 * padding < dilation * (k_size - 1) + 1 {
+            invalid_args("the dilated kernel is larger than the padded input")?
         }
         let params = crate::conv::ParamsConv1D {
             b_size,
@@ -717,6 +1144,8 @@ impl Tensor {
             k_size,
             padding,
             stride,
+            dilation,
+            groups,
         };
         let storage =
             self.storage()
@@ -726,16 +1155,49 @@ impl Tensor {
             kernel,
             padding,
             stride,
+            dilation,
+            groups,
         });
         let out_dims = params.out_dims();
         Ok(from_storage(storage, out_dims, op, false))
     }
 
-    pub fn conv2d(&self, kernel: &Self, padding: usize, stride: usize) -> Result<Self> {
+    /// Applies a 2D convolution over the input tensor.
+    ///
+    /// The input has shape `(b_size, c_in, h, w)` and the kernel `(c_out, c_in / groups, k_h, k_w)`,
+    /// both `c_in` and `c_out` have to be divisible by `groups`.
+    pub fn conv2d(
+        &self,
+        kernel: &Self,
+        padding: usize,
+        stride: usize,
+        dilation: usize,
+        groups: usize,
+    ) -> Result<Self> {
         let (b_size, c_in, i_h, i_w) = self.dims4()?;
         let (c_out, c_in_k, k_h, k_w) = kernel.dims4()?;
-        if c_in != c_in_k {
-            crate::bail!("in_channel mismatch between input ({c_in}) and kernel ({c_in_k})")
+        if groups == 0 {
+            crate::bail!("conv2d: the number of groups has to be positive")
+        }
+        if c_in % groups != 0 {
+            crate::bail!("conv2d: in_channels ({c_in}) is not divisible by groups ({groups})")
+        }
+        if c_out % groups != 0 {
+            crate::bail!("conv2d: out_channels ({c_out}) is not divisible by groups ({groups})")
+        }
+        if c_in / groups != c_in_k {
+            crate::bail!(
+                "in_channel mismatch between input ({c_in}, groups {groups}) and kernel ({c_in_k})"
+            )
+        }
+        if stride == 0 || dilation == 0 {
+            crate::bail!("conv2d: stride ({stride}) and dilation ({dilation}) have to be positive")
+        }
+        let fits = |i: usize, k: usize| k > 0 && i + 2 * padding >= dilation * (k - 1) + 1;
+        if !fits(i_h, k_h) || !fits(i_w, k_w) {
+            crate::bail!(
+                "conv2d: the dilated kernel ({k_h}, {k_w}) is larger than the padded input ({i_h}, {i_w})"
+            )
         }
         let params = crate::conv::ParamsConv2D {
             b_size,
@@ -747,6 +1209,8 @@ impl Tensor {
             c_in,
             padding,
             stride,
+            dilation,
+            groups,
         };
         let storage =
             self.storage()
@@ -756,6 +1220,134 @@ impl Tensor {
             kernel,
             padding,
             stride,
+            dilation,
+            groups,
+        });
+        let out_dims = params.out_dims();
+        Ok(from_storage(storage, out_dims, op, false))
+    }
+
+    /// Applies a 1D transposed convolution over the input tensor.
+    ///
+    /// The input has shape `(b_size, c_in, l_in)` and the kernel `(c_in, c_out, k_size)`.
+    pub fn conv_transpose1d(
+        &self,
+        kernel: &Self,
+        padding: usize,
+        output_padding: usize,
+        stride: usize,
+        dilation: usize,
+    ) -> Result<Self> {
+        let (b_size, c_in, l_in) = self.dims3()?;
+        let (c_in_k, c_out, k_size) = kernel.dims3()?;
+        if c_in != c_in_k {
+            crate::bail!("in_channel mismatch between input ({c_in}) and kernel ({c_in_k})")
+        }
+        if l_in == 0 || k_size == 0 {
+            crate::bail!("conv_transpose1d: empty input ({l_in}) or kernel ({k_size})")
+        }
+        if stride == 0 || dilation == 0 {
+            crate::bail!(
+                "conv_transpose1d: stride ({stride}) and dilation ({dilation}) have to be positive"
+            )
+        }
+        if output_padding >= stride && output_padding >= dilation {
+            crate::bail!(
+                "conv_transpose1d: output_padding ({output_padding}) has to be smaller than stride ({stride}) or dilation ({dilation})"
+            )
+        }
+        let params = crate::conv::ParamsConvTranspose1D {
+            b_size,
+            l_in,
+            c_out,
+            c_in,
+            k_size,
+            padding,
+            output_padding,
+            stride,
+            dilation,
+        };
+        if (l_in - 1) * stride + dilation * (k_size - 1) + output_padding + 1 <= 2 * padding {
+            crate::bail!("conv_transpose1d: padding {padding} is too large for {params:?}")
+        }
+        let storage = self.storage().conv_transpose1d(
+            self.layout(),
+            &kernel.storage(),
+            kernel.layout(),
+            &params,
+        )?;
+        let op = BackpropOp::new2(self, kernel, |arg, kernel| Op::ConvTranspose1D {
+            arg,
+            kernel,
+            padding,
+            output_padding,
+            stride,
+            dilation,
+        });
+        let out_dims = params.out_dims();
+        Ok(from_storage(storage, out_dims, op, false))
+    }
+
+    /// Applies a 2D transposed convolution over the input tensor.
+    ///
+    /// The input has shape `(b_size, c_in, h, w)` and the kernel `(c_in, c_out, k_h, k_w)`.
+    pub fn conv_transpose2d(
+        &self,
+        kernel: &Self,
+        padding: usize,
+        output_padding: usize,
+        stride: usize,
+        dilation: usize,
+    ) -> Result<Self> {
+        let (b_size, c_in, i_h, i_w) = self.dims4()?;
+        let (c_in_k, c_out, k_h, k_w) = kernel.dims4()?;
+        if c_in != c_in_k {
+            crate::bail!("in_channel mismatch between input ({c_in}) and kernel ({c_in_k})")
+        }
+        if i_h == 0 || i_w == 0 || k_h == 0 || k_w == 0 {
+            crate::bail!("conv_transpose2d: empty input ({i_h}, {i_w}) or kernel ({k_h}, {k_w})")
+        }
+        if stride == 0 || dilation == 0 {
+            crate::bail!(
+                "conv_transpose2d: stride ({stride}) and dilation ({dilation}) have to be positive"
+            )
+        }
+        if output_padding >= stride && output_padding >= dilation {
+            crate::bail!(
+                "conv_transpose2d: output_padding ({output_padding}) has to be smaller than stride ({stride}) or dilation ({dilation})"
+            )
+        }
+        let params = crate::conv::ParamsConvTranspose2D {
+            b_size,
+            i_h,
+            i_w,
+            k_h,
+            k_w,
+            c_out,
+            c_in,
+            padding,
+            output_padding,
+            stride,
+            dilation,
+        };
+        let unpadded_size =
+            |i: usize, k: usize| (i - 1) * stride + dilation * (k - 1) + output_padding + 1;
+        if unpadded_size(i_h, k_h) <= 2 * padding || unpadded_size(i_w, k_w) <= 2 * padding {
+            crate::bail!("conv_transpose2d: padding {padding} is too large for {params:?}")
+        }
+        let storage = self.storage().conv_transpose2d(
+            self.layout(),
+            &kernel.storage(),
+            kernel.layout(),
+            &params,
+        )?;
+        let op = BackpropOp::new2(self, kernel, |arg, kernel| Op::ConvTranspose2D {
+            arg,
+            kernel,
+            padding,
+            output_padding,
+            stride,
+            dilation,
         });
         let out_dims = params.out_dims();
         Ok(from_storage(storage, out_dims, op, false))
@@ -809,8 +1401,33 @@ impl Tensor {
     /// * `self` - A tensor with dimensions `b1, b2, ..., bi, m, k`.
     /// * `rhs` - A tensor with dimensions `b1, b2, ..., bi, k, n`.
     ///
-    /// The resulting tensor has dimensions `b1, b2, ..., bi, m, n`.
+    /// The resulting tensor has dimensions `b1, b2, ..., bi, m, n`. When the batch dimensions of
+    /// the operands differ, they are broadcasted as in `broadcast_matmul`. A 1D `self` is used
+    /// as a row vector and a 1D `rhs` as a column vector, the corresponding dimension being
+    /// removed from the result.
     pub fn matmul(&self, rhs: &Self) -> Result<Self> {
+        // As in NumPy, a 1D lhs is a row vector and a 1D rhs is a column vector, the added
+        // dimension is removed from the result.
+        match (self.rank(), rhs.rank()) {
+            (1, 1) => {
+                return self
+                    .unsqueeze(0)?
+                    .matmul(&rhs.unsqueeze(1)?)?
+                    .reshape(())
+            }
+            (1, r) if r >= 2 => {
+                let res = self.unsqueeze(0)?.matmul(rhs)?;
+                return res.squeeze(res.rank() - 2);
+            }
+            (r, 1) if r >= 2 => {
+                let res = self.matmul(&rhs.unsqueeze(1)?)?;
+                return res.squeeze(res.rank() - 1);
+            }
+            (r1, r2) if r1 >= 2 && r2 >= 2 && self.dims()[..r1 - 2] != rhs.dims()[..r2 - 2] => {
+                return self.broadcast_matmul(rhs)
+            }
+            _ => {}
+        }
         let a_dims = self.shape().dims();
         let b_dims = rhs.shape().dims();
 
@@ -852,9 +1469,70 @@ impl Tensor {
         Ok(from_storage(storage, c_shape, op, false))
     }
 
+    /// Matrix-multiplication with broadcasting over the batch dimensions, i.e. all the
+    /// dimensions but the last two, following the NumPy rules. `matmul` uses this when the batch
+    /// dimensions of the operands differ.
+    ///
+    /// The broadcasted operands are not copied when their batch dimensions can be flattened
+    /// with a single stride, e.g. when a weight matrix is shared by all the batch elements.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device, DType};
+    /// let xs = Tensor::ones((2, 3, 4, 5), DType::F32, &Device::Cpu)?;
+    /// let w = Tensor::ones((5, 6), DType::F32, &Device::Cpu)?;
+    /// let ys = xs.broadcast_matmul(&w)?;
+    /// assert_eq!(ys.dims(), &[2, 3, 4, 6]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn broadcast_matmul(&self, rhs: &Self) -> Result<Self> {
+        let lhs_dims = self.dims();
+        let rhs_dims = rhs.dims();
+        if lhs_dims.len() < 2 || rhs_dims.len() < 2 {
+            Err(Error::ShapeMismatchBinaryOp {
+                lhs: self.shape().clone(),
+                rhs: rhs.shape().clone(),
+                op: "broadcast_matmul",
+            }
+                .bt())?
+        }
+        let (lhs_batch, lhs_mat) = lhs_dims.split_at(lhs_dims.len() - 2);
+        let (rhs_batch, rhs_mat) = rhs_dims.split_at(rhs_dims.len() - 2);
+        let batch_rank = usize::max(lhs_batch.len(), rhs_batch.len());
+        let mut batch = Vec::with_capacity(batch_rank + 2);
+        for idx in 0..batch_rank {
+            let dim = |b: &[usize]| {
+                (idx + b.len())
+                    .checked_sub(batch_rank)
+                    .map_or(1, |i| b[i])
+            };
+            let (l, r) = (dim(lhs_batch), dim(rhs_batch));
+            if l != r && l != 1 && r != 1 {
+                Err(Error::ShapeMismatchBinaryOp {
+                    lhs: self.shape().clone(),
+                    rhs: rhs.shape().clone(),
+                    op: "broadcast_matmul",
+                }
+                    .bt())?
+            }
+            batch.push(usize::max(l, r))
+        }
+        let broadcast = |xs: &Tensor, mat: &[usize]| -> Result<Tensor> {
+            let xs = xs.broadcast_as([batch.as_slice(), mat].concat())?;
+            // The matmul kernels require the batch dimensions to be flattenable.
+            if xs.layout().matmul_batch_stride().is_some() {
+                Ok(xs)
+            } else {
+                xs.contiguous()
+            }
+        };
+        let lhs = broadcast(self, lhs_mat)?;
+        let rhs = broadcast(rhs, rhs_mat)?;
+        lhs.matmul(&rhs)
+    }
+
     /// Returns a tensor with the same shape as the input tensor, the values are taken from
-    /// `on_true` if the input tensor value is not zero, and `on_false` at the positions where the
-    /// input tensor is equal to zero.
+    /// `on_true` if the input tensor value is not zero (or true for a `bool` mask), and `on_false`
+    /// at the positions where the input tensor is equal to zero.
     pub fn where_cond(&self, on_true: &Self, on_false: &Self) -> Result<Self> {
         let _shap = self.same_shape_binary_op(on_true, "where_cond")?;
         let shape = self.same_shape_binary_op(on_false, "where_cond")?;
@@ -974,6 +1652,45 @@ impl Tensor {
         Ok(from_storage(storage, self.shape(), op, false))
     }
 
+    /// Returns a copy of `self` where the slices at positions `indexes` along `dim` have been
+    /// replaced by the slices of `source`, this is the out-of-place equivalent of
+    /// `self[indexes] = source` in PyTorch. An error is returned if `indexes` contains
+    /// duplicates as the written value would then be ambiguous.
+    pub fn index_put<D: Dim>(&self, indexes: &Self, source: &Self, dim: D) -> Result<Self> {
+        let dim = dim.to_index(self.shape(), "index-put")?;
+        let indexes_len = indexes.dims1()?;
+        let mut source_dims = self.dims().to_vec();
+        source_dims[dim] = indexes_len;
+        if source.dims() != source_dims.as_slice() {
+            Err(Error::ShapeMismatchBinaryOp {
+                op: "index-put",
+                lhs: self.shape().clone(),
+                rhs: source.shape().clone(),
+            })?
+        }
+        if indexes_len == 0 {
+            return Ok(self.clone());
+        }
+        // For each position along `dim`, the number of times it is written and the slice of
+        // `source` that is written there, both are computed exactly in u32.
+        let dim_size = self.dim(dim)?;
+        let device = self.device();
+        let ones = Self::ones(indexes_len, DType::U32, device)?;
+        let counts = Self::zeros(dim_size, DType::U32, device)?.index_add(indexes, &ones, 0)?;
+        if counts.max(0)?.to_scalar::<u32>()? > 1 {
+            crate::bail!("index-put: indexes must be unique")
+        }
+        let slots = Self::arange(0u32, indexes_len as u32, device)?;
+        let slots = Self::zeros(dim_size, DType::U32, device)?.index_add(indexes, &slots, 0)?;
+        // The source values are selected rather than added to zeros so that they are written
+        // exactly, e.g. -0.0 stays -0.0.
+        let source = source.index_select(&slots, dim)?;
+        let mut mask_dims = vec![1; self.rank()];
+        mask_dims[dim] = dim_size;
+        let mask = counts.gt(0u32)?.reshape(mask_dims)?;
+        mask.broadcast_as(self.shape())?.where_cond(&source, self)
+    }
+
     pub fn gather<D: Dim>(&self, indexes: &Self, dim: D) -> Result<Self> {
         let dim = dim.to_index(self.shape(), "gather")?;
         let self_dims = self.dims();
@@ -1292,6 +2009,157 @@ impl Tensor {
         Ok(Tensor(Arc::new(tensor_)))
     }
 
+    /// Returns a tensor with the same data as the input where the dimensions have been permuted,
+    /// dimension `i` of the result is dimension `dims[i]` of the input. No copy is made.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let tensor = Tensor::arange(0u32, 120u32, &Device::Cpu)?.reshape((2, 3, 4, 5))?;
+    /// let tensor = tensor.permute((2, 3, 1, 0))?;
+    /// assert_eq!(tensor.dims(), &[4, 5, 3, 2]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn permute<D: Dims>(&self, dims: D) -> Result<Tensor> {
+        let dims = dims.to_indexes(self.shape(), "permute")?;
+        let op = BackpropOp::new1(self, |t| Op::Permute(t, dims.clone()));
+        let tensor_ = Tensor_ {
+            id: TensorId::new(),
+            storage: self.storage.clone(),
+            layout: self.layout.permute(&dims)?,
+            op,
+            is_variable: false,
+            dtype: self.dtype,
+            device: self.device.clone(),
+        };
+        Ok(Tensor(Arc::new(tensor_)))
+    }
+
+    /// Moves dimension `src` to position `dst`, the other dimensions keep their relative order.
+    pub fn movedim<D1: Dim, D2: Dim>(&self, src: D1, dst: D2) -> Result<Tensor> {
+        let src = src.to_index(self.shape(), "movedim")?;
+        let dst = dst.to_index(self.shape(), "movedim")?;
+        if src == dst {
+            return Ok(self.clone());
+        }
+        let mut dims: Vec<usize> = (0..self.rank()).filter(|&d| d != src).collect();
+        dims.insert(dst, src);
+        self.permute(dims)
+    }
+
+    /// Reverses the order of the elements along the given dimensions.
+    ///
+    /// Strides cannot be negative so this copies the data using `index_select`.
+    pub fn flip<D: Dims>(&self, dims: D) -> Result<Tensor> {
+        let dims = dims.to_indexes(self.shape(), "flip")?;
+        let mut result = self.clone();
+        for dim in dims {
+            let size = self.dims()[dim];
+            let ids: Vec<u32> = (0..size as u32).rev().collect();
+            let ids = Tensor::from_vec(ids, size, self.device())?;
+            result = result.index_select(&ids, dim)?;
+        }
+        Ok(result)
+    }
+
+    /// Rolls the tensor along the given dimensions, the elements shifted beyond the last
+    /// position are re-introduced at the first position. Negative shifts roll the other way.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let tensor = Tensor::new(&[[0f32, 1.], [2., 3.], [4., 5.]], &Device::Cpu)?;
+    /// let tensor = tensor.roll(&[1, -1], (0, 1))?;
+    /// assert_eq!(tensor.to_vec2::<f32>()?, &[[5., 4.], [1., 0.], [3., 2.]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn roll<D: Dims>(&self, shifts: &[i64], dims: D) -> Result<Tensor> {
+        let dims = dims.to_indexes(self.shape(), "roll")?;
+        if shifts.len() != dims.len() {
+            crate::bail!(
+                "roll: got {} shifts for {} dims",
+                shifts.len(),
+                dims.len()
+            )
+        }
+        let mut result = self.clone();
+        for (&shift, dim) in shifts.iter().zip(dims) {
+            let size = self.dims()[dim];
+            if size == 0 {
+                continue;
+            }
+            let shift = shift.rem_euclid(size as i64) as usize;
+            if shift == 0 {
+                continue;
+            }
+            let head = result.narrow(dim, size - shift, shift)?;
+            let tail = result.narrow(dim, 0, size - shift)?;
+            result = Tensor::cat(&[head, tail], dim)?;
+        }
+        Ok(result)
+    }
+
+    /// Repeats the tensor `sizes[i]` times along dimension `i`. When there are more sizes than
+    /// dimensions, the tensor is first expanded with leading dimensions of size 1 as in PyTorch.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let tensor = Tensor::new(&[[0f32, 1.]], &Device::Cpu)?;
+    /// let tensor = tensor.repeat((2, 2))?;
+    /// assert_eq!(tensor.to_vec2::<f32>()?, &[[0., 1., 0., 1.], [0., 1., 0., 1.]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn repeat<S: Into<Shape>>(&self, sizes: S) -> Result<Tensor> {
+        let sizes = sizes.into();
+        let sizes = sizes.dims();
+        if sizes.len() < self.rank() {
+            crate::bail!(
+                "repeat: got {} sizes for a tensor of rank {}",
+                sizes.len(),
+                self.rank()
+            )
+        }
+        let mut dims = vec![1; sizes.len() - self.rank()];
+        dims.extend_from_slice(self.dims());
+        // Interleave a new dimension before each of the original ones and broadcast it to the
+        // number of repetitions, the final reshape is the only copy.
+        let mut interleaved = Vec::with_capacity(2 * dims.len());
+        let mut broadcasted = Vec::with_capacity(2 * dims.len());
+        for (&d, &r) in dims.iter().zip(sizes.iter()) {
+            interleaved.extend_from_slice(&[1, d]);
+            broadcasted.extend_from_slice(&[r, d]);
+        }
+        let out_dims: Vec<usize> = dims.iter().zip(sizes.iter()).map(|(d, r)| d * r).collect();
+        self.reshape(interleaved)?
+            .broadcast_as(broadcasted)?
+            .reshape(out_dims)
+    }
+
+    /// Returns a view of all the windows of size `size` along dimension `dim`, the start of two
+    /// consecutive windows are `step` apart. Dimension `dim` indexes the windows and a new last
+    /// dimension of size `size` indexes the elements within each window. No copy is made.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let tensor = Tensor::arange(0f32, 7., &Device::Cpu)?;
+    /// let tensor = tensor.unfold(0, 3, 2)?;
+    /// assert_eq!(tensor.to_vec2::<f32>()?, &[[0., 1., 2.], [2., 3., 4.], [4., 5., 6.]]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn unfold<D: Dim>(&self, dim: D, size: usize, step: usize) -> Result<Tensor> {
+        let dim = dim.to_index(self.shape(), "unfold")?;
+        let layout = self.layout.unfold(dim, size, step)?;
+        let op = BackpropOp::new1(self, |t| Op::Unfold(t, dim, size, step));
+        let tensor_ = Tensor_ {
+            id: TensorId::new(),
+            storage: self.storage.clone(),
+            layout,
+            op,
+            is_variable: false,
+            dtype: self.dtype,
+            device: self.device.clone(),
+        };
+        Ok(Tensor(Arc::new(tensor_)))
+    }
+
     /// Returns true if the data is stored in a C contiguous (aka row major) way.
     pub fn is_contiguous(&self) -> bool {
         self.layout.is_contiguous()
@@ -1333,6 +2201,50 @@ impl Tensor {
         Ok(Tensor(Arc::new(tensor_)))
     }
 
+    /// Applies `f` to `xs` without recording the intermediate ops, only the inputs are kept
+    /// alive and `f` is run again during the backward pass to compute the gradients. This trades
+    /// compute for memory when training deep models, e.g. by checkpointing each block.
+    ///
+    /// The gradients also flow to the variables used by `f` that are not part of `xs`, e.g. the
+    /// weights of a layer. `f` should be deterministic as the recomputed values are used for the
+    /// backward pass. The ops are only left unrecorded on the thread calling `checkpoint`, the
+    /// ops that `f` runs on other threads, e.g. through rayon, are still recorded and the
+    /// variables they use are not detected, so `f` should not spawn work that uses `xs` or
+    /// tracked tensors on other threads.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Device, Tensor, Var};
+    /// let w = Var::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+    /// let x = Tensor::new(&[[1f32, -1.]], &Device::Cpu)?;
+    /// let f = {
+    ///     let w = w.as_tensor().clone();
+    ///     move |xs: &[Tensor]| xs[0].matmul(&w)?.tanh()
+    /// };
+    /// let y = Tensor::checkpoint(&[&x], f)?;
+    /// let grads = y.sum_all()?.backward()?;
+    /// assert_eq!(grads.get(&w).unwrap().dims(), &[2, 2]);
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn checkpoint<F>(xs: &[&Tensor], f: F) -> Result<Tensor>
+    where
+        F: Fn(&[Tensor]) -> Result<Tensor> + Send + Sync + 'static,
+    {
+        let args: Vec<Tensor> = xs.iter().map(|&x| x.clone()).collect();
+        let (ys, uses_vars) = crate::backprop::run_untracked(|| f(&args));
+        let ys = ys?;
+        let op = BackpropOp::checkpoint(args, Arc::new(f), uses_vars);
+        let tensor_ = Tensor_ {
+            id: TensorId::new(),
+            storage: ys.storage.clone(),
+            layout: ys.layout.clone(),
+            op,
+            is_variable: false,
+            dtype: ys.dtype,
+            device: ys.device.clone(),
+        };
+        Ok(Tensor(Arc::new(tensor_)))
+    }
+
     /// If the target device is the same as the tensor device, only a shallow copy is performed.
     pub fn to_device(&self, device: &Device) -> Result<Tensor> {
         if self.device().same_device(device) {
@@ -1629,6 +2541,76 @@ impl Tensor {
         }
     }
 
+    /// Returns a copy of `self` where the values within `ranges` have been replaced with the
+    /// content of `src`, there must be one range per dimension.
+    ///
+    /// The result is computed from `src` padded with zeros and a mask so the gradient flows
+    /// back to both `self` (outside of the ranges) and `src`.
+    ///
+    /// ```rust
+    /// use my_candle_core::{Tensor, Device};
+    /// let a = Tensor::zeros((3, 4), my_candle_core::DType::F32, &Device::Cpu)?;
+    /// let src = Tensor::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+    /// let a = a.slice_assign(&[1..3, 2..4], &src)?;
+    /// assert_eq!(
+    ///     a.to_vec2::<f32>()?,
+    ///     &[[0., 0., 0., 0.], [0., 0., 1., 2.], [0., 0., 3., 4.]]
+    /// );
+    /// # Ok::<(), my_candle_core::Error>(())
+    /// ```
+    pub fn slice_assign<R: std::ops::RangeBounds<usize>>(
+        &self,
+        ranges: &[R],
+        src: &Tensor,
+    ) -> Result<Self> {
+        use std::ops::Bound;
+        let src_dims = src.dims();
+        let self_dims = self.dims();
+        if self_dims.len() != src_dims.len() {
+            crate::bail!(
+                "slice-assign requires input with the same rank {} <> {}",
+                self_dims.len(),
+                src_dims.len()
+            )
+        }
+        if self_dims.len() != ranges.len() {
+            crate::bail!(
+                "slice-assign requires input with the same rank as there are ranges {} <> {}",
+                self_dims.len(),
+                ranges.len()
+            )
+        }
+        let mut src = src.clone();
+        let mut mask = Self::ones(src.shape(), DType::U8, src.device())?;
+        for (i, range) in ranges.iter().enumerate() {
+            let start = match range.start_bound() {
+                Bound::Included(&n) => n,
+                Bound::Excluded(&n) => n + 1,
+                Bound::Unbounded => 0,
+            };
+            let end = match range.end_bound() {
+                Bound::Included(&n) => n + 1,
+                Bound::Excluded(&n) => n,
+                Bound::Unbounded => self_dims[i],
+            };
+            if end < start || end > self_dims[i] {
+                crate::bail!(
+                    "slice-assign: invalid range {start}..{end} for dim {i} of size {}",
+                    self_dims[i]
+                )
+            }
+            if end - start != src_dims[i] {
+                crate::bail!(
+                    "slice-assign: the range {start}..{end} for dim {i} does not match the src size {}",
+                    src_dims[i]
+                )
+            }
+            src = src.pad_with_zeros(i, start, self_dims[i] - end)?;
+            mask = mask.pad_with_zeros(i, start, self_dims[i] - end)?;
+        }
+        mask.where_cond(&src, self)
+    }
+
     fn storage(&self) -> std::sync::RwLockReadGuard<'_, Storage> {
         self.storage.read().unwrap()
     }
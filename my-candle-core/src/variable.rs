@@ -2,7 +2,8 @@ use crate::dtype::{DType, FloatDType, WithDType};
 use crate::tensor::Tensor;
 use crate::device::Device;
 use crate::error::{Result, Error};
-use crate::shape::Shape;
+use crate::layout::Layout;
+use crate::shape::{Dim, Shape};
 /// A variable is a wrapper around a tensor, however variables can have their content modified
 /// whereas tensors are immutable.
 #[derive(Clone, Debug)]
@@ -139,4 +140,60 @@ impl Var {
         src.copy_strided_src(&mut dst, layout.start_offset(), src_l)?;
         Ok(())
     }
+
+    /// Writes `src` in place into the variable, starting at position `offset` along dimension
+    /// `dim`. All the other dimensions of `src` must match the ones of the variable, this can be
+    /// used to append to a preallocated kv-cache without a copy of the full tensor.
+    ///
+    /// The operation is not tracked by the backprop graph, see `Tensor::slice_assign` for the
+    /// differentiable out-of-place version.
+    pub fn slice_set<D: Dim>(&self, src: &Tensor, dim: D, offset: usize) -> Result<()> {
+        let dim = dim.to_index(self.shape(), "slice-set")?;
+        if self.same_storage(src) {
+            let msg = "cannot set a variable to a tensor that is derived from its value";
+            Err(Error::CannotSetVar { msg }.bt())?
+        }
+        if self.dtype() != src.dtype() {
+            Err(Error::DTypeMismatchBinaryOp {
+                lhs: self.dtype(),
+                rhs: src.dtype(),
+                op: "slice-set",
+            }
+                .bt())?
+        }
+        let shape_mismatch = self.rank() != src.rank()
+            || self
+                .dims()
+                .iter()
+                .zip(src.dims().iter())
+                .enumerate()
+                .any(|(i, (&d1, &d2))| if i == dim { offset + d2 > d1 } else { d1 != d2 });
+        if shape_mismatch {
+            Err(Error::ShapeMismatchBinaryOp {
+                lhs: self.shape().clone(),
+                rhs: src.shape().clone(),
+                op: "slice-set",
+            }
+                .bt())?
+        }
+        let src = src.contiguous()?;
+        let (mut dst, layout) = self.storage_mut_and_layout();
+        if !layout.is_contiguous() {
+            let msg = "cannot set a non-contiguous variable";
+            Err(Error::CannotSetVar { msg }.bt())?
+        }
+        let (src, src_l) = src.storage_and_layout();
+        // The destination is made of `d1` blocks of `d2` contiguous elements, one per index of
+        // the dimensions before `dim`, each of these is copied separately.
+        let block_size: usize = layout.dims()[dim + 1..].iter().product();
+        let d1: usize = layout.dims()[..dim].iter().product();
+        let d2 = block_size * src_l.dims()[dim];
+        let dst_stride = block_size * layout.dims()[dim];
+        let dst_offset = layout.start_offset() + offset * block_size;
+        for i in 0..d1 {
+            let block_l = Layout::contiguous_with_offset(d2, src_l.start_offset() + i * d2);
+            src.copy_strided_src(&mut dst, dst_offset + i * dst_stride, &block_l)?;
+        }
+        Ok(())
+    }
 }
@@ -49,6 +49,39 @@ fast_sum(const size_t src_numel, const size_t el_to_sum_per_block,
     dst[dst_id] = shr[0];
 }
 
+// Same as fast_sum but multiplies the values together.
+template <typename T>
+__device__ void
+fast_prod(const size_t src_numel, const size_t el_to_sum_per_block,
+          const size_t num_dims, const size_t *info, const T *src, T *dst) {
+  const size_t *dims = info;
+  const size_t *strides = info + num_dims;
+
+  __shared__ T shr[BLOCK_SIZE];
+  size_t tid = threadIdx.x;
+  size_t dst_id = blockIdx.x;
+
+  shr[tid] = 1;
+  size_t start_idx = dst_id * el_to_sum_per_block;
+  size_t stop_idx = min(start_idx + el_to_sum_per_block, src_numel);
+  size_t idx = start_idx + tid;
+
+  while (idx < stop_idx) {
+    size_t strided_i = get_strided_index(idx, num_dims, dims, strides);
+    shr[tid] *= src[strided_i];
+    idx += blockDim.x;
+  }
+
+  for (int s = blockDim.x / 2; s > 0; s >>= 1) {
+    __syncthreads();
+    if (tid < s)
+      shr[tid] *= shr[tid + s];
+  }
+
+  if (tid == 0)
+    dst[dst_id] = shr[0];
+}
+
 template <typename T>
 __device__ void
 fast_max(const size_t src_numel, const size_t el_to_sum_per_block,
@@ -222,7 +255,8 @@ fast_argmax(const size_t src_numel, const size_t el_to_sum_per_block,
     dst[dst_id] = shr_index[0];
 }
 
-#define FAST_OP(TYPENAME, MIN_NAME, MAX_NAME, ARGMIN_NAME, ARGMAX_NAME, SUM_NAME) \
+#define FAST_OP(TYPENAME, MIN_NAME, MAX_NAME, ARGMIN_NAME, ARGMAX_NAME, SUM_NAME, \
+                PROD_NAME)                                                     \
   extern "C" __global__ void ARGMIN_NAME(                                      \
       const size_t src_numel, const size_t el_to_sum_per_block,                \
       const size_t num_dims, const size_t *info, const TYPENAME *src,          \
@@ -252,6 +286,12 @@ fast_argmax(const size_t src_numel, const size_t el_to_sum_per_block,
       const size_t num_dims, const size_t *info, const TYPENAME *src,          \
       TYPENAME *dst) {                                                         \
     fast_sum(src_numel, el_to_sum_per_block, num_dims, info, src, dst);        \
+  }                                                                            \
+  extern "C" __global__ void PROD_NAME(                                        \
+      const size_t src_numel, const size_t el_to_sum_per_block,                \
+      const size_t num_dims, const size_t *info, const TYPENAME *src,          \
+      TYPENAME *dst) {                                                         \
+    fast_prod(src_numel, el_to_sum_per_block, num_dims, info, src, dst);       \
   }
 
 #define SUM_OP(TYPENAME, FN_NAME)                                              \
@@ -292,19 +332,19 @@ fast_argmax(const size_t src_numel, const size_t el_to_sum_per_block,
 
 #if __CUDA_ARCH__ >= 800
 SUM_OP(__nv_bfloat16, sum_bf16)
-FAST_OP(__nv_bfloat16, fast_min_bf16, fast_max_bf16, fast_argmin_bf16, fast_argmax_bf16, fast_sum_bf16)
+FAST_OP(__nv_bfloat16, fast_min_bf16, fast_max_bf16, fast_argmin_bf16, fast_argmax_bf16, fast_sum_bf16, fast_prod_bf16)
 #endif
 
 #if __CUDA_ARCH__ >= 530
 SUM_OP(__half, sum_f16)
-FAST_OP(__half, fast_min_f16, fast_max_f16, fast_argmin_f16, fast_argmax_f16, fast_sum_f16)
+FAST_OP(__half, fast_min_f16, fast_max_f16, fast_argmin_f16, fast_argmax_f16, fast_sum_f16, fast_prod_f16)
 #endif
 
 SUM_OP(float, sum_f32)
 SUM_OP(double, sum_f64)
 SUM_OP(uint32_t, sum_u32)
 
-FAST_OP(float, fast_min_f32, fast_max_f32, fast_argmin_f32, fast_argmax_f32, fast_sum_f32)
-FAST_OP(double, fast_min_f64, fast_max_f64, fast_argmin_f64, fast_argmax_f64, fast_sum_f64)
-FAST_OP(uint32_t, fast_min_u32, fast_max_u32, fast_argmin_u32, fast_argmax_u32, fast_sum_u32)
-FAST_OP(uint8_t, fast_min_u8, fast_max_u8, fast_argmin_u8, fast_argmax_u8, fast_sum_u8)
+FAST_OP(float, fast_min_f32, fast_max_f32, fast_argmin_f32, fast_argmax_f32, fast_sum_f32, fast_prod_f32)
+FAST_OP(double, fast_min_f64, fast_max_f64, fast_argmin_f64, fast_argmax_f64, fast_sum_f64, fast_prod_f64)
+FAST_OP(uint32_t, fast_min_u32, fast_max_u32, fast_argmin_u32, fast_argmax_u32, fast_sum_u32, fast_prod_u32)
+FAST_OP(uint8_t, fast_min_u8, fast_max_u8, fast_argmin_u8, fast_argmax_u8, fast_sum_u8, fast_prod_u8)
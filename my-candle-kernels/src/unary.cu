@@ -50,6 +50,33 @@ __device__ __forceinline__ T relu_fwd(T x) {
     return maxg(x, zero);
 }
 
+template<typename T>
+__device__ __forceinline__ T gelu_erf_fwd(T x) {
+  return x * static_cast<T>(0.5) * (static_cast<T>(1.0) + erfg(x * static_cast<T>(M_SQRT1_2)));
+}
+
+template<typename T>
+__device__ __forceinline__ T sigmoid_fwd(T x) {
+    return recipg(static_cast<T>(1) + expg(-x));
+}
+
+template<typename T>
+__device__ __forceinline__ T silu_fwd(T x) {
+    return x * sigmoid_fwd(x);
+}
+
+template<typename T>
+__device__ __forceinline__ T sign_fwd(T x) {
+    T zero = 0.;
+    T one = 1.;
+    return x > zero ? one : (x < zero ? -one : x);
+}
+
+template<typename T>
+__device__ __forceinline__ T clamp_fwd(T x, T min, T max) {
+    return x < min ? min : (x > max ? max : x);
+}
+
 #define UNARY_OP1(TYPENAME, FN_NAME, FUNC) \
 extern "C" __global__ void FN_NAME( \
     const size_t numel, \
@@ -76,6 +103,32 @@ extern "C" __global__ void FN_NAME( \
     } \
 } \
 
+#define UNARY_OP2(TYPENAME, FN_NAME, FUNC) \
+extern "C" __global__ void FN_NAME( \
+    const size_t numel, \
+    const size_t num_dims, \
+    const size_t *info, \
+    const TYPENAME param1, \
+    const TYPENAME param2, \
+    const TYPENAME *inp, \
+    TYPENAME *out \
+) { \
+    const size_t *dims = info; \
+    const size_t *strides = info + num_dims; \
+    if (is_contiguous(num_dims, dims, strides)) { \
+        for (unsigned int i = blockIdx.x * blockDim.x + threadIdx.x; i < numel; i += blockDim.x * gridDim.x) { \
+            TYPENAME x = inp ? inp[i] : out[i]; \
+            out[i] = FUNC; \
+        } \
+    } \
+    else { \
+        for (unsigned int i = blockIdx.x * blockDim.x + threadIdx.x; i < numel; i += blockDim.x * gridDim.x) { \
+            unsigned strided_i = get_strided_index(i, num_dims, dims, strides); \
+            TYPENAME x = inp ? inp[strided_i] : out[i]; \
+            out[i] = FUNC; \
+        } \
+    } \
+} \
 
 #if __CUDA_ARCH__ >= 800
 UNARY_OP(__nv_bfloat16, ucopy_bf16, x)
@@ -91,6 +144,17 @@ UNARY_OP(__nv_bfloat16, usqrt_bf16, sqrtg(x))
 UNARY_OP(__nv_bfloat16, ugelu_bf16, gelu_fwd(x))
 UNARY_OP(__nv_bfloat16, urelu_bf16, relu_fwd(x))
 UNARY_OP1(__nv_bfloat16, uelu_bf16, elu_fwd(x, param))
+UNARY_OP(__nv_bfloat16, ugelu_erf_bf16, gelu_erf_fwd(x))
+UNARY_OP(__nv_bfloat16, utanh_bf16, tanhg(x))
+UNARY_OP(__nv_bfloat16, usigmoid_bf16, sigmoid_fwd(x))
+UNARY_OP(__nv_bfloat16, usilu_bf16, silu_fwd(x))
+UNARY_OP(__nv_bfloat16, uerf_bf16, erfg(x))
+UNARY_OP(__nv_bfloat16, ufloor_bf16, floorg(x))
+UNARY_OP(__nv_bfloat16, uceil_bf16, ceilg(x))
+UNARY_OP(__nv_bfloat16, uround_bf16, roundg(x))
+UNARY_OP(__nv_bfloat16, usign_bf16, sign_fwd(x))
+UNARY_OP1(__nv_bfloat16, upowf_bf16, powg(x, param))
+UNARY_OP2(__nv_bfloat16, uclamp_bf16, clamp_fwd(x, param1, param2))
 #endif
 
 #if __CUDA_ARCH__ >= 530
@@ -107,6 +171,17 @@ UNARY_OP(__half, usqrt_f16, sqrtg(x))
 UNARY_OP(__half, ugelu_f16, gelu_fwd(x))
 UNARY_OP(__half, urelu_f16, relu_fwd(x))
 UNARY_OP1(__half, uelu_f16, elu_fwd(x, param))
+UNARY_OP(__half, ugelu_erf_f16, gelu_erf_fwd(x))
+UNARY_OP(__half, utanh_f16, tanhg(x))
+UNARY_OP(__half, usigmoid_f16, sigmoid_fwd(x))
+UNARY_OP(__half, usilu_f16, silu_fwd(x))
+UNARY_OP(__half, uerf_f16, erfg(x))
+UNARY_OP(__half, ufloor_f16, floorg(x))
+UNARY_OP(__half, uceil_f16, ceilg(x))
+UNARY_OP(__half, uround_f16, roundg(x))
+UNARY_OP(__half, usign_f16, sign_fwd(x))
+UNARY_OP1(__half, upowf_f16, powg(x, param))
+UNARY_OP2(__half, uclamp_f16, clamp_fwd(x, param1, param2))
 #endif
 
 UNARY_OP(uint8_t, ucopy_u8, x)
@@ -137,3 +212,25 @@ UNARY_OP(float, urelu_f32, relu_fwd(x))
 UNARY_OP(double, urelu_f64, relu_fwd(x))
 UNARY_OP1(float, uelu_f32, elu_fwd(x, param))
 UNARY_OP1(double, uelu_f64, elu_fwd(x, param))
+UNARY_OP(float, ugelu_erf_f32, gelu_erf_fwd(x))
+UNARY_OP(float, utanh_f32, tanhg(x))
+UNARY_OP(float, usigmoid_f32, sigmoid_fwd(x))
+UNARY_OP(float, usilu_f32, silu_fwd(x))
+UNARY_OP(float, uerf_f32, erfg(x))
+UNARY_OP(float, ufloor_f32, floorg(x))
+UNARY_OP(float, uceil_f32, ceilg(x))
+UNARY_OP(float, uround_f32, roundg(x))
+UNARY_OP(float, usign_f32, sign_fwd(x))
+UNARY_OP1(float, upowf_f32, powg(x, param))
+UNARY_OP2(float, uclamp_f32, clamp_fwd(x, param1, param2))
+UNARY_OP(double, ugelu_erf_f64, gelu_erf_fwd(x))
+UNARY_OP(double, utanh_f64, tanhg(x))
+UNARY_OP(double, usigmoid_f64, sigmoid_fwd(x))
+UNARY_OP(double, usilu_f64, silu_fwd(x))
+UNARY_OP(double, uerf_f64, erfg(x))
+UNARY_OP(double, ufloor_f64, floorg(x))
+UNARY_OP(double, uceil_f64, ceilg(x))
+UNARY_OP(double, uround_f64, roundg(x))
+UNARY_OP(double, usign_f64, sign_fwd(x))
+UNARY_OP1(double, upowf_f64, powg(x, param))
+UNARY_OP2(double, uclamp_f64, clamp_fwd(x, param1, param2))
@@ -2,39 +2,51 @@ use my_candle_core::{bail, Result, Tensor};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Conv1dConfig {
-    pub padding:usize,
-    pub stride:usize,
+    pub padding: usize,
+    pub stride: usize,
+    pub dilation: usize,
+    pub groups: usize,
 }
 
 impl Default for Conv1dConfig {
     fn default() -> Self {
         Self {
-            padding:0,
-            stride:1,
+            padding: 0,
+            stride: 1,
+            dilation: 1,
+            groups: 1,
         }
     }
 }
 
 #[derive(Debug)]
 pub struct Conv1d {
-    weight:Tensor,
-    bias:Option<Tensor>,
-    config:Conv1dConfig,
+    weight: Tensor,
+    bias: Option<Tensor>,
+    config: Conv1dConfig,
 }
 
 impl Conv1d {
-    pub fn new(weight:Tensor, bias:Option<Tensor>, config: Conv1dConfig) -> Self {
-        Self{
+    pub fn new(weight: Tensor, bias: Option<Tensor>, config: Conv1dConfig) -> Self {
+        Self {
             weight,
             bias,
             config,
         }
     }
 
-    pub fn config(&self) -> &Conv1dConfig {&self.config}
+    pub fn config(&self) -> &Conv1dConfig {
+        &self.config
+    }
 
-    pub fn forward(&self, x:&Tensor) -> Result<Tensor> {
-        let x = x.conv1d(&self.weight, self.config.padding, self.config.stride)?;
+    pub fn forward(&self, x: &Tensor) -> Result<Tensor> {
+        let x = x.conv1d(
+            &self.weight,
+            self.config.padding,
+            self.config.stride,
+            self.config.dilation,
+            self.config.groups,
+        )?;
         match &self.bias {
             None => Ok(x),
             Some(bias) => {
@@ -49,7 +61,9 @@ impl Conv1d {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Conv2dConfig {
     pub padding: usize,
-    pub stride:usize,
+    pub stride: usize,
+    pub dilation: usize,
+    pub groups: usize,
 }
 
 impl Default for Conv2dConfig {
@@ -57,19 +71,21 @@ impl Default for Conv2dConfig {
         Self {
             padding: 0,
             stride: 1,
+            dilation: 1,
+            groups: 1,
         }
     }
 }
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Conv2d {
-    weight:Tensor,
-    bias:Option<Tensor>,
-    config:Conv2dConfig,
+    weight: Tensor,
+    bias: Option<Tensor>,
+    config: Conv2dConfig,
 }
 
 impl Conv2d {
-    pub fn new(weight:Tensor, bias:Option<Tensor>, config:Conv2dConfig) -> Self{
+    pub fn new(weight: Tensor, bias: Option<Tensor>, config: Conv2dConfig) -> Self {
         Self {
             weight,
             bias,
@@ -81,13 +97,135 @@ impl Conv2d {
         &self.config
     }
 
-    pub fn forward(&self, x:&Tensor) -> Result<Tensor> {
-        let x = x.conv2d(&self.weight, self.config.padding, self.config.stride)?;
+    pub fn forward(&self, x: &Tensor) -> Result<Tensor> {
+        let x = x.conv2d(
+            &self.weight,
+            self.config.padding,
+            self.config.stride,
+            self.config.dilation,
+            self.config.groups,
+        )?;
         match &self.bias {
             None => Ok(x),
             Some(bias) => {
                 let b = bias.dims1()?;
-                let bias = bias.reshape((1,b, 1, 1))?;
+                let bias = bias.reshape((1, b, 1, 1))?;
+                Ok(x.broadcast_add(&bias)?)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvTranspose1dConfig {
+    pub padding: usize,
+    pub output_padding: usize,
+    pub stride: usize,
+    pub dilation: usize,
+}
+
+impl Default for ConvTranspose1dConfig {
+    fn default() -> Self {
+        Self {
+            padding: 0,
+            output_padding: 0,
+            stride: 1,
+            dilation: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConvTranspose1d {
+    weight: Tensor,
+    bias: Option<Tensor>,
+    config: ConvTranspose1dConfig,
+}
+
+impl ConvTranspose1d {
+    pub fn new(weight: Tensor, bias: Option<Tensor>, config: ConvTranspose1dConfig) -> Self {
+        Self {
+            weight,
+            bias,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ConvTranspose1dConfig {
+        &self.config
+    }
+
+    pub fn forward(&self, x: &Tensor) -> Result<Tensor> {
+        let x = x.conv_transpose1d(
+            &self.weight,
+            self.config.padding,
+            self.config.output_padding,
+            self.config.stride,
+            self.config.dilation,
+        )?;
+        match &self.bias {
+            None => Ok(x),
+            Some(bias) => {
+                let b = bias.dims1()?;
+                let bias = bias.reshape((1, b, 1))?;
+                Ok(x.broadcast_add(&bias)?)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvTranspose2dConfig {
+    pub padding: usize,
+    pub output_padding: usize,
+    pub stride: usize,
+    pub dilation: usize,
+}
+
+impl Default for ConvTranspose2dConfig {
+    fn default() -> Self {
+        Self {
+            padding: 0,
+            output_padding: 0,
+            stride: 1,
+            dilation: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConvTranspose2d {
+    weight: Tensor,
+    bias: Option<Tensor>,
+    config: ConvTranspose2dConfig,
+}
+
+impl ConvTranspose2d {
+    pub fn new(weight: Tensor, bias: Option<Tensor>, config: ConvTranspose2dConfig) -> Self {
+        Self {
+            weight,
+            bias,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ConvTranspose2dConfig {
+        &self.config
+    }
+
+    pub fn forward(&self, x: &Tensor) -> Result<Tensor> {
+        let x = x.conv_transpose2d(
+            &self.weight,
+            self.config.padding,
+            self.config.output_padding,
+            self.config.stride,
+            self.config.dilation,
+        )?;
+        match &self.bias {
+            None => Ok(x),
+            Some(bias) => {
+                let b = bias.dims1()?;
+                let bias = bias.reshape((1, b, 1, 1))?;
                 Ok(x.broadcast_add(&bias)?)
             }
         }
@@ -98,13 +236,23 @@ pub fn conv1d(
     in_channels: usize,
     out_channels: usize,
     kernel_size: usize,
-    cfg:Conv1dConfig,
-    vb:crate::var_builder::VarBuilder,
+    cfg: Conv1dConfig,
+    vb: crate::var_builder::VarBuilder,
 ) -> Result<Conv1d> {
+    if cfg.groups == 0 || in_channels % cfg.groups != 0 || out_channels % cfg.groups != 0 {
+        bail!(
+            "conv1d: in_channels ({in_channels}) and out_channels ({out_channels}) must be divisible by groups ({})",
+            cfg.groups
+        )
+    }
     let init_ws = crate::init::DEFAULT_KAIMING_NORMAL;
-    let ws = vb.get_or_init((out_channels, in_channels, kernel_size), "weight", init_ws)?;
-    let bound = 1./(in_channels as f64).sqrt();
-    let init_bs =crate::init::Init::Uniform {
+    let ws = vb.get_or_init(
+        (out_channels, in_channels / cfg.groups, kernel_size),
+        "weight",
+        init_ws,
+    )?;
+    let bound = 1. / ((in_channels / cfg.groups) as f64).sqrt();
+    let init_bs = crate::init::Init::Uniform {
         lo: -bound,
         up: bound,
     };
@@ -113,21 +261,74 @@ pub fn conv1d(
 }
 
 pub fn conv2d(
-    in_channels:usize,
-    out_channels:usize,
-    kernel_size:usize,
+    in_channels: usize,
+    out_channels: usize,
+    kernel_size: usize,
     cfg: Conv2dConfig,
     vb: crate::var_builder::VarBuilder,
 ) -> Result<Conv2d> {
+    if cfg.groups == 0 || in_channels % cfg.groups != 0 || out_channels % cfg.groups != 0 {
+        bail!(
+            "conv2d: in_channels ({in_channels}) and out_channels ({out_channels}) must be divisible by groups ({})",
+            cfg.groups
+        )
+    }
     let init_ws = crate::init::DEFAULT_KAIMING_NORMAL;
     let ws = vb.get_or_init(
-        (out_channels, in_channels, kernel_size, kernel_size),
-    "weight", init_ws)?;
-    let bound = 1. / (in_channels as f64).sqrt();
+        (
+            out_channels,
+            in_channels / cfg.groups,
+            kernel_size,
+            kernel_size,
+        ),
+        "weight",
+        init_ws,
+    )?;
+    let bound = 1. / ((in_channels / cfg.groups) as f64).sqrt();
     let init_bs = crate::init::Init::Uniform {
         lo: -bound,
         up: bound,
     };
     let bs = vb.get_or_init(out_channels, "bias", init_bs)?;
     Ok(Conv2d::new(ws, Some(bs), cfg))
-}
\ No newline at end of file
+}
+
+pub fn conv_transpose1d(
+    in_channels: usize,
+    out_channels: usize,
+    kernel_size: usize,
+    cfg: ConvTranspose1dConfig,
+    vb: crate::var_builder::VarBuilder,
+) -> Result<ConvTranspose1d> {
+    let bound = 1. / (out_channels as f64 * kernel_size as f64).sqrt();
+    let init = crate::init::Init::Uniform {
+        lo: -bound,
+        up: bound,
+    };
+    // The weight layout is (in_channels, out_channels, kernel_size) as in PyTorch.
+    let ws = vb.get_or_init((in_channels, out_channels, kernel_size), "weight", init)?;
+    let bs = vb.get_or_init(out_channels, "bias", init)?;
+    Ok(ConvTranspose1d::new(ws, Some(bs), cfg))
+}
+
+pub fn conv_transpose2d(
+    in_channels: usize,
+    out_channels: usize,
+    kernel_size: usize,
+    cfg: ConvTranspose2dConfig,
+    vb: crate::var_builder::VarBuilder,
+) -> Result<ConvTranspose2d> {
+    let bound = 1. / (out_channels as f64 * (kernel_size * kernel_size) as f64).sqrt();
+    let init = crate::init::Init::Uniform {
+        lo: -bound,
+        up: bound,
+    };
+    // The weight layout is (in_channels, out_channels, kernel_size, kernel_size) as in PyTorch.
+    let ws = vb.get_or_init(
+        (in_channels, out_channels, kernel_size, kernel_size),
+        "weight",
+        init,
+    )?;
+    let bs = vb.get_or_init(out_channels, "bias", init)?;
+    Ok(ConvTranspose2d::new(ws, Some(bs), cfg))
+}
@@ -11,7 +11,10 @@ pub mod layer_norm;
 pub mod linear;
 
 pub use activation::Activation;
-pub use conv::{conv1d, conv2d, Conv1d, Conv2d, Conv1dConfig, Conv2dConfig};
+pub use conv::{
+    conv1d, conv2d, conv_transpose1d, conv_transpose2d, Conv1d, Conv1dConfig, Conv2d,
+    Conv2dConfig, ConvTranspose1d, ConvTranspose1dConfig, ConvTranspose2d, ConvTranspose2dConfig,
+};
 pub use embedding::{embedding, Embedding};
 pub use group_norm::{group_nore, GroupNorm};
 pub use init::Init;
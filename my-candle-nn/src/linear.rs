@@ -2,8 +2,8 @@
 //!
 //! This layer applies a linear transformation to the incoming data, `y = x@w.t() + b`.
 //! The bias is optional. The `forward` method can be used to apply the layer, it supports input
-//! with a batch dimension (so of shape `(b_sz, in_c)`) or without (of shape `(in_c,)`), the
-//! output has shape `(b_sz, out_c)` and `(out_c,)` respectively.
+//! with any number of batch dimensions (so of shape `(b_sz, .., in_c)`) or without (of shape
+//! `(in_c,)`), the output has shape `(b_sz, .., out_c)` and `(out_c,)` respectively.
 //!
 //! ```rust
 //! use my_candle_core::{Tensor, Device::Cpu};
@@ -35,13 +35,9 @@ impl Linear {
     }
 
     pub fn forward(&self, x:&Tensor) -> Result<Tensor> {
-        let w = match x.dims() {
-            &[bszie, _, _] => self.weight.broadcast_left(bszie)?.t()?,
-            _ => self.weight.t()?,
-        };
-
-        let x = x.matmul(&w)?;
-        match *self.bias {
+        // The weight is broadcasted over the leading dims of x without being copied.
+        let x = x.matmul(&self.weight.t()?)?;
+        match &self.bias {
             None => Ok(x),
             Some(bias) => x.broadcast_add(bias),
         }
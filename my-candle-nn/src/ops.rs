@@ -8,6 +8,9 @@ use my_candle_core::{Tensor, Result};
 ///
 pub fn log_softmax<D: my_candle_core::shape::Dim>(xs: &Tensor, d: D) -> Result<Tensor> {
     let d = d.to_index(xs.shape(), "log-softmax")?;
+    if d + 1 == xs.rank() {
+        return xs.log_softmax_last_dim();
+    }
     let max = xs.max_keepdim(d)?;
     let diff = xs.broadcast_sub(&max)?;
     let sum_exp = diff.exp()?.sum_keepdim(d)?;
@@ -17,6 +20,9 @@ pub fn log_softmax<D: my_candle_core::shape::Dim>(xs: &Tensor, d: D) -> Result<T
 
 pub fn softmax<D: my_candle_core::shape::Dim>(xs:&Tensor, dim: D) -> Result<Tensor> {
     let dim = dim.to_index(xs.shape(), "softmax")?;
+    if dim + 1 == xs.rank() {
+        return xs.softmax_last_dim();
+    }
     let max = xs.max_keepdim(dim)?;
     let diff = xs.broadcast_sub(&max)?;
     let num = diff.exp()?;
@@ -25,9 +31,9 @@ pub fn softmax<D: my_candle_core::shape::Dim>(xs:&Tensor, dim: D) -> Result<Tens
 }
 
 pub fn silu(xs:Tensor)-> Result<Tensor> {
-    xs / (xs.neg()?.exp()? + 1.0)?
+    xs.silu()
 }
 
 pub fn sigmoid(xs:&Tensor) -> Result<Tensor> {
-    (xs.neg()?.exp()? + 1.0)?.recip()
+    xs.sigmoid()
 }
\ No newline at end of file
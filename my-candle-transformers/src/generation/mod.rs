@@ -22,13 +22,7 @@ impl LogitsProcessor {
             let distr = rand::distributions::WeightedIndex::new(prs).map_err(Error::wrap)?;
             distr.sample(&mut self.rng) as u32
         } else {
-            let logits_v:Vec<f32> = logits.to_vec1()?;
-            logits_v
-                .iter()
-                .enumerate()
-                .max_by(|(_, u), (_, v)|u.total_cmp(v))
-                .map(|i, _| i as u32)
-                .unwrap()
+            logits.argmax(D::Minus1)?.to_scalar::<u32>()?
         };
         Ok(next_token)
     }
@@ -85,11 +85,11 @@ impl LayerNorm {
             d => d,
         };
 
-        let (_bsize, _seq_len, hidden_size) = x.dims3()?;
+        let (_bsize, _seq_len, _hidden_size) = x.dims3()?;
         let x = x.to_dtype(internal_dtype)?;
-        let mean_x = (x.sum_keepdim(2)? / hidden_size as f64)?;
+        let mean_x = x.mean_keepdim(2)?;
         let x = x.broadcast_sub(&mean_x)?;
-        let norm_x  = (x.sqr()?.sum_keepdim(2)? / hidden_size as f64)?;
+        let norm_x = x.sqr()?.mean_keepdim(2)?;
         let x_normed = x.broadcast_div(&(norm_x + self.eps)?.sqrt()?)?;
         let x = x_normed
             .to_dtype(x_dtype)?